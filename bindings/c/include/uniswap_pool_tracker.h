@@ -0,0 +1,40 @@
+/* C interface of the Uniswap V3 pool price tracker, see bindings/c/src/lib.rs.
+ * Link against libuniswap_pool_tracker_c (.so, .dylib or .a). */
+#ifndef UNISWAP_POOL_TRACKER_H
+#define UNISWAP_POOL_TRACKER_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Tracker Tracker;
+
+/* Connect to the websocket endpoint `rpc_url` and follow the Uniswap V3 pools of
+ * `pools_json`, a JSON array of addresses. NULL on failure, see tracker_last_error. */
+Tracker *tracker_start(const char *rpc_url, const char *pools_json);
+
+/* The next event as a JSON object whose "type" names it ("swap", "price_update", ...,
+ * "error" for listener errors), waiting up to `timeout_ms` (0 does not wait). NULL
+ * when there was none. Free it with tracker_free_string. */
+char *tracker_poll_event(Tracker *tracker, uint32_t timeout_ms);
+
+/* Events dropped since tracker_start because the queue was full, the host polling too
+ * slowly. 0 for NULL. */
+uint64_t tracker_dropped_events(const Tracker *tracker);
+
+void tracker_free_string(char *event);
+
+/* Stop following the pools and free the tracker, NULL is ignored. */
+void tracker_stop(Tracker *tracker);
+
+/* Why the last call on this thread returning NULL failed, NULL when none did. Owned
+ * by the library, valid until the next failing call on the same thread. */
+const char *tracker_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,247 @@
+// the tracker behind a C ABI, declared in include/uniswap_pool_tracker.h: a tracker runs
+// on a tokio runtime of its own and queues its events as JSON strings, in the shape of
+// PoolEvent, until the host polls them
+use alloy_primitives::Address;
+use serde_json::{Value, json};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use uniswapv3_pool_price_tracker::{
+    EVENTS_CAPACITY, ListenerError, PoolTracker, TrackerBuilder, TrackerError,
+};
+
+// pools followed in the background, owned by the host between tracker_start and
+// tracker_stop
+pub struct Tracker {
+    runtime: Runtime,
+    tracker: PoolTracker,
+    events: Receiver<String>,
+    // events dropped because the queue was full, see tracker_dropped_events
+    dropped: Arc<AtomicU64>,
+}
+
+thread_local! {
+    // why the last call on this thread failed, see tracker_last_error
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Connects to the websocket endpoint `rpc_url` and follows the Uniswap V3 pools of
+/// `pools_json`, a JSON array of addresses. Returns NULL on failure, see
+/// `tracker_last_error`.
+///
+/// # Safety
+///
+/// `rpc_url` and `pools_json` must be NUL terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tracker_start(
+    rpc_url: *const c_char,
+    pools_json: *const c_char,
+) -> *mut Tracker {
+    // SAFETY: the caller passes NUL terminated strings
+    let args = unsafe { (string(rpc_url), string(pools_json)) };
+    let (rpc_url, pools_json) = match args {
+        (Ok(rpc_url), Ok(pools_json)) => (rpc_url, pools_json),
+        (Err(e), _) | (_, Err(e)) => return fail(e),
+    };
+    let pools = match parse_pools(&pools_json) {
+        Ok(pools) => pools,
+        Err(e) => return fail(e),
+    };
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => return fail(format!("Failed to start the runtime: {}", e)),
+    };
+
+    let builder = pools
+        .into_iter()
+        .fold(TrackerBuilder::new().rpc(rpc_url), TrackerBuilder::pool);
+    let mut tracker = match runtime.block_on(builder.build()) {
+        Ok(tracker) => tracker,
+        Err(e) => return fail(report_message(&e)),
+    };
+
+    // the handlers are spawned on the tracker's runtime
+    let (tx, events) = sync_channel(EVENTS_CAPACITY);
+    let dropped = Arc::new(AtomicU64::new(0));
+    {
+        let _guard = runtime.enter();
+        let errors = tx.clone();
+        let dropped_events = dropped.clone();
+        let dropped_errors = dropped.clone();
+        tracker.on_event(move |event| {
+            match serde_json::to_string(&event) {
+                Ok(json) => queue(&tx, json, &dropped_events),
+                // the host never sees tracing output, so it gets an error event instead
+                Err(e) => {
+                    let error = ListenerError {
+                        pool: Some(event.pool()),
+                        message: format!("Failed to serialize {} event: {}", event.kind(), e),
+                        kind: None,
+                        retryable: false,
+                        stopped: false,
+                    };
+                    queue(&tx, error_json(&error).to_string(), &dropped_events);
+                }
+            }
+            async {}
+        });
+        tracker.on_error(move |error| {
+            queue(&errors, error_json(&error).to_string(), &dropped_errors);
+            async {}
+        });
+    }
+
+    Box::into_raw(Box::new(Tracker {
+        runtime,
+        tracker,
+        events,
+        dropped,
+    }))
+}
+
+/// Waits up to `timeout_ms` milliseconds (0 does not wait) for the next event and
+/// returns it as a JSON object whose `type` names it, "error" for the errors of the
+/// listeners. Returns NULL when there was none. Free the string with
+/// `tracker_free_string`.
+///
+/// # Safety
+///
+/// `tracker` must come from `tracker_start` and not be stopped yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tracker_poll_event(tracker: *mut Tracker, timeout_ms: u32) -> *mut c_char {
+    // SAFETY: the caller passes a tracker of tracker_start
+    let Some(tracker) = (unsafe { tracker.as_ref() }) else {
+        return fail("Tracker is NULL");
+    };
+    let event = match timeout_ms {
+        0 => tracker.events.try_recv().ok(),
+        ms => tracker
+            .events
+            .recv_timeout(Duration::from_millis(ms.into()))
+            .ok(),
+    };
+    match event.and_then(|json| CString::new(json).ok()) {
+        Some(json) => json.into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// How many events were dropped since `tracker_start` because the host polled too
+/// slowly and the queue was full. 0 for NULL.
+///
+/// # Safety
+///
+/// `tracker` must come from `tracker_start` and not be stopped yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tracker_dropped_events(tracker: *const Tracker) -> u64 {
+    // SAFETY: the caller passes a tracker of tracker_start
+    match unsafe { tracker.as_ref() } {
+        Some(tracker) => tracker.dropped.load(Ordering::Relaxed),
+        None => 0,
+    }
+}
+
+/// Frees a string returned by `tracker_poll_event`, NULL is ignored.
+///
+/// # Safety
+///
+/// `event` must come from `tracker_poll_event` and not be freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tracker_free_string(event: *mut c_char) {
+    if !event.is_null() {
+        // SAFETY: the caller passes a string of tracker_poll_event
+        drop(unsafe { CString::from_raw(event) });
+    }
+}
+
+/// Stops following the pools, writes out what the sinks still hold and frees the
+/// tracker; events not polled yet are dropped. NULL is ignored.
+///
+/// # Safety
+///
+/// `tracker` must come from `tracker_start` and not be stopped yet, it is invalid
+/// afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tracker_stop(tracker: *mut Tracker) {
+    if tracker.is_null() {
+        return;
+    }
+    // SAFETY: the caller passes a tracker of tracker_start and gives up its ownership
+    let Tracker {
+        runtime, tracker, ..
+    } = *unsafe { Box::from_raw(tracker) };
+    runtime.block_on(tracker.stop());
+}
+
+/// Why the last call on this thread that returned NULL failed, NULL when none did.
+/// The string belongs to the library and is valid until the next failing call on the
+/// same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn tracker_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+// drop the event when the host polls too slowly, like a subscriber falling behind, and
+// count it for tracker_dropped_events
+fn queue(tx: &SyncSender<String>, json: String, dropped: &AtomicU64) {
+    if let Err(TrySendError::Full(_)) = tx.try_send(json) {
+        dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// remember `message` for tracker_last_error and return NULL
+fn fail<T>(message: impl Into<String>) -> *mut T {
+    let message = CString::new(message.into().replace('\0', " ")).ok();
+    LAST_ERROR.with(|error| *error.borrow_mut() = message);
+    ptr::null_mut()
+}
+
+// SAFETY: `s` must be NULL or a NUL terminated string
+unsafe fn string(s: *const c_char) -> Result<String, String> {
+    if s.is_null() {
+        return Err("Argument is NULL".to_string());
+    }
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map(str::to_string)
+        .map_err(|e| format!("Argument is not UTF-8: {}", e))
+}
+
+fn parse_pools(pools_json: &str) -> Result<Vec<Address>, String> {
+    let pools: Vec<String> = serde_json::from_str(pools_json)
+        .map_err(|e| format!("Pools must be a JSON array of addresses: {}", e))?;
+    pools
+        .iter()
+        .map(|pool| {
+            pool.parse()
+                .map_err(|e| format!("Invalid pool address {}: {}", pool, e))
+        })
+        .collect()
+}
+
+fn error_json(error: &ListenerError) -> Value {
+    json!({
+        "type": "error",
+        "pool": error.pool,
+        "message": error.message,
+        "kind": error.kind.as_ref().map(ToString::to_string),
+        "retryable": error.retryable,
+        "stopped": error.stopped,
+    })
+}
+
+// the whole chain of causes, marked when the failure may go away when tried again
+fn report_message(e: &eyre::Report) -> String {
+    match TrackerError::of(e).is_some_and(TrackerError::is_retryable) {
+        true => format!("{:#} (retryable)", e),
+        false => format!("{:#}", e),
+    }
+}
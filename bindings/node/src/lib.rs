@@ -0,0 +1,176 @@
+// the tracker as a Node.js addon: `Tracker.start` follows pools in the background and
+// `tracker.on(name, cb)` calls back with their events as plain objects, in the JSON
+// shape of PoolEvent
+use alloy_primitives::Address;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use uniswapv3_pool_price_tracker::{
+    ListenerError, PoolEvent, PoolTracker, TrackerBuilder, TrackerError,
+};
+
+type Callback = ThreadsafeFunction<Value, ErrorStrategy::Fatal>;
+
+// event names `on` accepts besides the PoolEvent types, with the type they stand for
+const ALIASES: [(&str, &str); 2] = [("price", "price_update"), ("liquidity", "liquidity_update")];
+
+const EVENT_NAMES: [&str; 10] = [
+    "swap",
+    "mint",
+    "burn",
+    "collect",
+    "flash",
+    "initialize",
+    "price_update",
+    "liquidity_update",
+    // every PoolEvent
+    "event",
+    // ListenerErrors
+    "error",
+];
+
+// pools followed in the background
+#[napi]
+pub struct Tracker {
+    // None once stopped
+    tracker: Arc<Mutex<Option<PoolTracker>>>,
+    // "error" callbacks, also told about events that could not be handed to JS
+    errors: Arc<Mutex<Vec<Callback>>>,
+}
+
+#[napi]
+impl Tracker {
+    // connect to the websocket endpoint `rpc_url` and follow the Uniswap V3 `pools`,
+    // `confirmations` works like in TrackerBuilder
+    #[napi]
+    pub async fn start(
+        rpc_url: String,
+        pools: Vec<String>,
+        confirmations: Option<u32>,
+    ) -> Result<Tracker> {
+        let pools = pools
+            .iter()
+            .map(|pool| parse_address(pool))
+            .collect::<Result<Vec<_>>>()?;
+        let mut builder = pools
+            .into_iter()
+            .fold(TrackerBuilder::new().rpc(rpc_url), TrackerBuilder::pool);
+        if let Some(blocks) = confirmations {
+            builder = builder.confirmations(blocks.into());
+        }
+        let tracker = builder.build().await.map_err(to_js)?;
+        Ok(Tracker {
+            tracker: Arc::new(Mutex::new(Some(tracker))),
+            errors: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    // call `callback` with every event named `name`: a PoolEvent type like "swap" or
+    // "price_update" ("price" and "liquidity" for short), "event" for all of them or
+    // "error" for the errors of the listeners
+    #[napi(ts_args_type = "name: string, callback: (event: any) => void")]
+    pub fn on(&self, name: String, callback: Callback) -> Result<()> {
+        let kind = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map_or(name.as_str(), |(_, kind)| kind);
+        if !EVENT_NAMES.contains(&kind) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("Unknown event {}, expected one of {:?}", name, EVENT_NAMES),
+            ));
+        }
+        let mut tracker = self.tracker.lock().unwrap();
+        let Some(tracker) = tracker.as_mut() else {
+            return Err(Error::new(Status::GenericFailure, "Tracker is stopped"));
+        };
+
+        // the handlers run on the tracker's runtime, callbacks are queued to the JS thread
+        napi::bindgen_prelude::within_runtime_if_available(|| {
+            if kind == "error" {
+                self.errors.lock().unwrap().push(callback.clone());
+                tracker.on_error(move |error| {
+                    callback.call(error_json(&error), ThreadsafeFunctionCallMode::NonBlocking);
+                    async {}
+                });
+                return;
+            }
+            let all = kind == "event";
+            let kind = kind.to_string();
+            let errors = self.errors.clone();
+            tracker.on_event(move |event: PoolEvent| {
+                if all || event.kind() == kind {
+                    match serde_json::to_value(&event) {
+                        Ok(value) => {
+                            callback.call(value, ThreadsafeFunctionCallMode::NonBlocking);
+                        }
+                        Err(e) => {
+                            let error = ListenerError {
+                                pool: Some(event.pool()),
+                                message: format!(
+                                    "Failed to serialize {} event: {}",
+                                    event.kind(),
+                                    e
+                                ),
+                                kind: None,
+                                retryable: false,
+                                stopped: false,
+                            };
+                            for callback in errors.lock().unwrap().iter() {
+                                callback.call(
+                                    error_json(&error),
+                                    ThreadsafeFunctionCallMode::NonBlocking,
+                                );
+                            }
+                        }
+                    }
+                }
+                async {}
+            });
+        });
+        Ok(())
+    }
+
+    // stop following the pools and write out what the sinks still hold; the callbacks
+    // are released, so they no longer keep the process alive
+    #[napi]
+    pub async fn stop(&self) -> Result<()> {
+        let tracker = self.tracker.lock().unwrap().take();
+        self.errors.lock().unwrap().clear();
+        if let Some(tracker) = tracker {
+            tracker.stop().await;
+        }
+        Ok(())
+    }
+}
+
+fn error_json(error: &ListenerError) -> Value {
+    json!({
+        "pool": error.pool,
+        "message": error.message,
+        "kind": error.kind.as_ref().map(ToString::to_string),
+        "retryable": error.retryable,
+        "stopped": error.stopped,
+    })
+}
+
+fn parse_address(pool: &str) -> Result<Address> {
+    pool.parse().map_err(|e| {
+        Error::new(
+            Status::InvalidArg,
+            format!("Invalid pool address {}: {}", pool, e),
+        )
+    })
+}
+
+// the whole chain of causes, marked when the failure may go away when tried again
+fn to_js(e: eyre::Report) -> Error {
+    let retryable = TrackerError::of(&e).is_some_and(TrackerError::is_retryable);
+    let reason = match retryable {
+        true => format!("{:#} (retryable)", e),
+        false => format!("{:#}", e),
+    };
+    Error::new(Status::GenericFailure, reason)
+}
@@ -0,0 +1,176 @@
+// the tracker as the `uniswap_pool_tracker` Python module: a Tracker follows pools in
+// the background and is an async iterator of their price updates, get_price reads a
+// pool's current price once
+use alloy_primitives::Address;
+use futures_util::{Stream, StreamExt};
+use pyo3::exceptions::{PyConnectionError, PyRuntimeError, PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uniswapv3_pool_price_tracker::{PoolTracker, PriceUpdate, TrackerBuilder, TrackerError};
+
+type Updates = Pin<Box<dyn Stream<Item = PriceUpdate> + Send>>;
+
+// pools followed in the background, `async for price in tracker` gets their swaps'
+// prices until the tracker is stopped
+#[pyclass]
+struct Tracker {
+    // None once stopped
+    tracker: Arc<Mutex<Option<PoolTracker>>>,
+    updates: Arc<Mutex<Updates>>,
+}
+
+// a swap's price, see PriceUpdate
+#[pyclass(frozen, get_all)]
+struct Price {
+    pool: String,
+    chain_id: u64,
+    symbol0: String,
+    symbol1: String,
+    // fee tier in hundredths of a bip
+    fee: u32,
+    block: u64,
+    // unix timestamp of the block
+    timestamp: u64,
+    tx: Option<String>,
+    // token1 per token0
+    price: f64,
+    // token0 bought or sold
+    amount0: f64,
+    // USD price of token0, when a tracked pool prices it
+    usd_price0: Option<f64>,
+}
+
+#[pymethods]
+impl Tracker {
+    // connect to the websocket endpoint `rpc_url` and follow the Uniswap V3 `pools`,
+    // `confirmations` works like in TrackerBuilder
+    #[staticmethod]
+    #[pyo3(signature = (rpc_url, pools, confirmations = None))]
+    fn start(
+        py: Python<'_>,
+        rpc_url: String,
+        pools: Vec<String>,
+        confirmations: Option<u64>,
+    ) -> PyResult<Bound<'_, PyAny>> {
+        let pools = pools
+            .iter()
+            .map(|pool| parse_address(pool))
+            .collect::<PyResult<Vec<_>>>()?;
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let mut builder = pools
+                .into_iter()
+                .fold(TrackerBuilder::new().rpc(rpc_url), TrackerBuilder::pool);
+            if let Some(blocks) = confirmations {
+                builder = builder.confirmations(blocks);
+            }
+            let tracker = builder.build().await.map_err(to_py)?;
+            let updates: Updates = Box::pin(tracker.subscribe());
+            Ok(Tracker {
+                tracker: Arc::new(Mutex::new(Some(tracker))),
+                updates: Arc::new(Mutex::new(updates)),
+            })
+        })
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let updates = self.updates.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match updates.lock().await.next().await {
+                Some(update) => Ok(Price::from(update)),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+
+    // stop following the pools, iteration ends once the updates already received are
+    // taken
+    fn stop<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let tracker = self.tracker.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            if let Some(tracker) = tracker.lock().await.take() {
+                tracker.stop().await;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[pymethods]
+impl Price {
+    fn __repr__(&self) -> String {
+        format!(
+            "Price(pool={}, {}/{} {} at block {})",
+            self.pool, self.symbol0, self.symbol1, self.price, self.block
+        )
+    }
+}
+
+impl From<PriceUpdate> for Price {
+    fn from(update: PriceUpdate) -> Self {
+        Price {
+            pool: format!("{:?}", update.pool),
+            chain_id: update.chain_id,
+            symbol0: update.symbol0,
+            symbol1: update.symbol1,
+            fee: update.fee,
+            block: update.block,
+            timestamp: update.timestamp,
+            tx: update.tx.map(|tx| format!("{:?}", tx)),
+            price: update.price,
+            amount0: update.amount0,
+            usd_price0: update.usd_price0,
+        }
+    }
+}
+
+// the current price of a Uniswap V3 pool in token1 per token0, read once from
+// `rpc_url` or the RPC_URL environment variable
+#[pyfunction]
+#[pyo3(signature = (pool, rpc_url = None))]
+fn get_price(py: Python<'_>, pool: &str, rpc_url: Option<String>) -> PyResult<Option<f64>> {
+    let pool = parse_address(pool)?;
+    let rpc_url = match rpc_url.or_else(|| std::env::var("RPC_URL").ok()) {
+        Some(url) => url,
+        None => {
+            return Err(PyValueError::new_err(
+                "rpc_url not given and RPC_URL not set",
+            ));
+        }
+    };
+    let runtime = pyo3_async_runtimes::tokio::get_runtime();
+    let snapshot = py
+        .allow_threads(|| {
+            runtime.block_on(uniswapv3_pool_price_tracker::pool_snapshot(&rpc_url, pool))
+        })
+        .map_err(to_py)?;
+    Ok(snapshot.price)
+}
+
+fn parse_address(pool: &str) -> PyResult<Address> {
+    pool.parse()
+        .map_err(|e| PyValueError::new_err(format!("Invalid pool address {}: {}", pool, e)))
+}
+
+// failures that may go away when tried again raise ConnectionError, the others
+// RuntimeError; the message has the whole chain of causes
+fn to_py(e: eyre::Report) -> PyErr {
+    let message = format!("{:#}", e);
+    match TrackerError::of(&e) {
+        Some(error) if error.is_retryable() => PyConnectionError::new_err(message),
+        _ => PyRuntimeError::new_err(message),
+    }
+}
+
+#[pymodule]
+fn uniswap_pool_tracker(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Tracker>()?;
+    m.add_class::<Price>()?;
+    m.add_function(wrap_pyfunction!(get_price, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+from typing import List, Optional
+
+class Price:
+    pool: str
+    chain_id: int
+    symbol0: str
+    symbol1: str
+    fee: int
+    block: int
+    timestamp: int
+    tx: Optional[str]
+    price: float
+    amount0: float
+    usd_price0: Optional[float]
+
+class Tracker:
+    @staticmethod
+    async def start(rpc_url: str, pools: List[str], confirmations: Optional[int] = None) -> "Tracker": ...
+    def __aiter__(self) -> "Tracker": ...
+    async def __anext__(self) -> Price: ...
+    async def stop(self) -> None: ...
+
+def get_price(pool: str, rpc_url: Optional[str] = None) -> Optional[float]: ...
@@ -0,0 +1,55 @@
+// EIP-1193 providers for PoolWatcher: the wallet injected as window.ethereum, or a
+// JSON-RPC websocket such as a public endpoint for dashboards without a wallet
+
+export function browserProvider() {
+  if (typeof window === 'undefined' || !window.ethereum) {
+    throw new Error('No window.ethereum, install a wallet or pass a websocket url')
+  }
+  return window.ethereum
+}
+
+// a minimal provider over a websocket: requests are sent once the socket is open and
+// resolved by the id of their response
+export function wsProvider(url) {
+  const socket = new WebSocket(url)
+  const opened = new Promise((resolve, reject) => {
+    socket.addEventListener('open', resolve, { once: true })
+    socket.addEventListener('error', () => reject(new Error(`Failed to connect ${url}`)), { once: true })
+  })
+  const pending = new Map()
+  let nextId = 1
+
+  socket.addEventListener('message', (message) => {
+    const response = JSON.parse(message.data)
+    const request = pending.get(response.id)
+    if (!request) return
+    pending.delete(response.id)
+    if (response.error) request.reject(new Error(response.error.message))
+    else request.resolve(response.result)
+  })
+  socket.addEventListener('close', () => {
+    for (const request of pending.values()) request.reject(new Error(`Connection to ${url} closed`))
+    pending.clear()
+  })
+
+  return {
+    async request({ method, params = [] }) {
+      await opened
+      // `opened` stays resolved after the socket closed, sending would never be answered
+      if (socket.readyState !== WebSocket.OPEN) throw new Error(`Connection to ${url} closed`)
+      const id = nextId++
+      return new Promise((resolve, reject) => {
+        pending.set(id, { resolve, reject })
+        socket.send(JSON.stringify({ jsonrpc: '2.0', id, method, params }))
+      })
+    },
+    close() {
+      socket.close()
+    },
+  }
+}
+
+// the websocket at `url` when given, the browser wallet otherwise
+export function connect(url) {
+  return url ? wsProvider(url) : browserProvider()
+}
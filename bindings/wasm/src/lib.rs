@@ -0,0 +1,203 @@
+// the price engine in the browser: prices from sqrtPriceX96, swap decoding and candle
+// merging as plain functions, and a PoolWatcher polling a pool's swaps through any
+// EIP-1193 provider (window.ethereum, or the websocket shim of js/provider.js)
+use alloy_primitives::{Address, B256, Bytes};
+use alloy_sol_types::SolEvent;
+use js_sys::{Function, Promise, Reflect};
+use serde::Serialize;
+use serde_json::{Value, json};
+use serde_wasm_bindgen::Serializer;
+use std::cell::Cell;
+use std::rc::Rc;
+use uniswap_pool_core::calc::{calculate_prices, scaled_to_f64};
+use uniswap_pool_core::candle::{Candle, merge_candles};
+use uniswap_pool_core::swap::{Swap, decode_swap};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{JsFuture, future_to_promise};
+
+// public endpoints reject eth_getLogs over wider ranges, a watcher that fell further
+// behind (e.g. a background tab) skips to the latest blocks
+const MAX_BLOCKS: u64 = 1000;
+
+#[wasm_bindgen(module = "/js/provider.js")]
+extern "C" {
+    // the websocket at `url` when given, window.ethereum otherwise
+    #[wasm_bindgen(catch, js_name = connect)]
+    fn connect_provider(url: Option<String>) -> Result<JsValue, JsValue>;
+}
+
+// an EIP-1193 provider for PoolWatcher, see js/provider.js
+#[wasm_bindgen]
+pub fn connect(url: Option<String>) -> Result<JsValue, JsValue> {
+    connect_provider(url)
+}
+
+// token1 per token0 at a sqrtPriceX96 given as a decimal string
+#[wasm_bindgen(js_name = priceFromSqrtPriceX96)]
+pub fn price_from_sqrt_price_x96(
+    sqrt_price_x96: String,
+    decimals0: u32,
+    decimals1: u32,
+) -> Result<f64, JsError> {
+    price(sqrt_price_x96, decimals0, decimals1)
+}
+
+// the Uniswap V3 swap of a log's hex topics and data, undefined for other logs
+#[wasm_bindgen(js_name = decodeSwap)]
+pub fn decode_swap_log(topics: Vec<String>, data: String) -> Result<JsValue, JsError> {
+    let topics = topics
+        .iter()
+        .map(|topic| topic.parse())
+        .collect::<Result<Vec<B256>, _>>()
+        .map_err(|e| JsError::new(&format!("Invalid topic: {}", e)))?;
+    let data: Bytes = data
+        .parse()
+        .map_err(|e| JsError::new(&format!("Invalid data: {}", e)))?;
+    match decode_swap(&topics, &data) {
+        Some(swap) => to_js(&swap_json(&swap)),
+        None => Ok(JsValue::UNDEFINED),
+    }
+}
+
+// time ordered candles (or swaps as single swap candles) merged into candles of
+// `interval` seconds, like the API's /candles
+#[wasm_bindgen(js_name = mergeCandles)]
+pub fn merge_candles_js(candles: JsValue, interval: u32) -> Result<JsValue, JsError> {
+    if interval == 0 {
+        return Err(JsError::new("Interval must be at least one second"));
+    }
+    let candles: Vec<Candle> = serde_wasm_bindgen::from_value(candles)?;
+    to_js(&merge_candles(candles, interval.into()))
+}
+
+// the swaps of a Uniswap V3 pool, fetched with eth_getLogs whenever `poll` is called
+#[wasm_bindgen]
+pub struct PoolWatcher {
+    inner: Rc<Watcher>,
+}
+
+struct Watcher {
+    provider: JsValue,
+    pool: Address,
+    decimals0: u32,
+    decimals1: u32,
+    // first block the next poll asks for, None before the first poll
+    next_block: Cell<Option<u64>>,
+}
+
+#[wasm_bindgen]
+impl PoolWatcher {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        provider: JsValue,
+        pool: String,
+        decimals0: u32,
+        decimals1: u32,
+    ) -> Result<PoolWatcher, JsError> {
+        let pool = pool
+            .parse()
+            .map_err(|e| JsError::new(&format!("Invalid pool address {}: {}", pool, e)))?;
+        Ok(PoolWatcher {
+            inner: Rc::new(Watcher {
+                provider,
+                pool,
+                decimals0,
+                decimals1,
+                next_block: Cell::new(None),
+            }),
+        })
+    }
+
+    // resolves to the pool's swaps since the previous poll, each with its price; the
+    // first poll only reads the latest block
+    pub fn poll(&self) -> Promise {
+        let watcher = self.inner.clone();
+        future_to_promise(async move { watcher.poll().await })
+    }
+}
+
+impl Watcher {
+    async fn poll(&self) -> Result<JsValue, JsValue> {
+        let latest = quantity(&request(&self.provider, "eth_blockNumber", json!([])).await?)?;
+        let from = self
+            .next_block
+            .get()
+            .unwrap_or(latest)
+            .max(latest.saturating_sub(MAX_BLOCKS - 1));
+        if from > latest {
+            return Ok(to_js(&Vec::<Value>::new())?);
+        }
+        let filter = json!([{
+            "address": self.pool,
+            "topics": [Swap::SIGNATURE_HASH],
+            "fromBlock": format!("{:#x}", from),
+            "toBlock": format!("{:#x}", latest),
+        }]);
+        let logs = request(&self.provider, "eth_getLogs", filter).await?;
+        self.next_block.set(Some(latest + 1));
+
+        let mut swaps = Vec::new();
+        for log in logs.as_array().into_iter().flatten() {
+            let topics: Vec<B256> =
+                serde_json::from_value(log["topics"].clone()).unwrap_or_default();
+            let data: Bytes = serde_json::from_value(log["data"].clone()).unwrap_or_default();
+            let Some(swap) = decode_swap(&topics, &data) else {
+                continue;
+            };
+            let price = price(
+                swap.sqrtPriceX96.to_string(),
+                self.decimals0,
+                self.decimals1,
+            )
+            .map_err(JsValue::from)?;
+            let mut swap = swap_json(&swap);
+            swap["pool"] = json!(self.pool);
+            swap["block"] = json!(quantity(&log["blockNumber"]).ok());
+            swap["tx"] = log["transactionHash"].clone();
+            swap["price"] = json!(price);
+            swaps.push(swap);
+        }
+        Ok(to_js(&swaps)?)
+    }
+}
+
+// `method` through an EIP-1193 provider's request({ method, params })
+async fn request(provider: &JsValue, method: &str, params: Value) -> Result<Value, JsValue> {
+    let request: Function = Reflect::get(provider, &JsValue::from_str("request"))?.dyn_into()?;
+    let args = to_js(&json!({ "method": method, "params": params }))?;
+    let result = JsFuture::from(Promise::resolve(&request.call1(provider, &args)?)).await?;
+    serde_wasm_bindgen::from_value(result).map_err(JsValue::from)
+}
+
+fn price(sqrt_price_x96: String, decimals0: u32, decimals1: u32) -> Result<f64, JsError> {
+    let symbol0 = "token0".to_string();
+    let symbol1 = "token1".to_string();
+    let (price, _) = calculate_prices(sqrt_price_x96, decimals0, decimals1, &symbol0, &symbol1)?;
+    Ok(scaled_to_f64(&price))
+}
+
+// a swap with its big numbers as decimal strings, JS numbers would lose precision
+fn swap_json(swap: &Swap) -> Value {
+    json!({
+        "sender": swap.sender,
+        "recipient": swap.recipient,
+        "amount0": swap.amount0.to_string(),
+        "amount1": swap.amount1.to_string(),
+        "sqrtPriceX96": swap.sqrtPriceX96.to_string(),
+        "liquidity": swap.liquidity.to_string(),
+        "tick": swap.tick.as_i32(),
+    })
+}
+
+// a hex quantity of a JSON-RPC result, like eth_blockNumber's
+fn quantity(value: &Value) -> Result<u64, JsValue> {
+    value
+        .as_str()
+        .and_then(|hex| u64::from_str_radix(hex.trim_start_matches("0x"), 16).ok())
+        .ok_or_else(|| JsValue::from_str(&format!("Expected a hex quantity, got {}", value)))
+}
+
+// plain JS objects rather than Maps, as JSON.parse would give
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsError> {
+    Ok(value.serialize(&Serializer::json_compatible())?)
+}
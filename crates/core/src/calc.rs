@@ -0,0 +1,183 @@
+use crate::error::{PriceFailure, TrackerError};
+use alloy_primitives::aliases::U160;
+use alloy_primitives::ruint::UintTryFrom;
+use alloy_primitives::utils::format_units;
+use alloy_primitives::{U256, U512};
+use num_bigint::{BigInt, Sign};
+use num_rational::Ratio;
+use num_traits::{One, ToPrimitive};
+use tracing::debug;
+
+// sqrtPriceX96 = √(price token1/token0) * 2^96: https://docs.uniswap.org/contracts/v4/reference/core/libraries/liquidity-amounts
+// this function reverses the calculation to get the actual price from sqrtPriceX96
+pub fn calculate_prices(
+    sqrt_price_x96_str: String,
+    decimal_token0: u32,
+    decimal_token1: u32,
+    token0_symbol: &String,
+    token1_symbol: &String,
+) -> Result<(BigInt, BigInt), TrackerError> {
+    // parse the sqrtPriceX96 string into BigInt
+    let sqrt_price_x96 = match BigInt::parse_bytes(sqrt_price_x96_str.as_bytes(), 10) {
+        Some(v) => v,
+        None => {
+            tracing::error!(
+                "Failed to parse sqrt_price_x96 from string: {}",
+                sqrt_price_x96_str
+            );
+            return Err(TrackerError::new(PriceFailure::SqrtPriceParseFailed));
+        }
+    };
+    // a zero price has no inverse and a negative one is not a square root
+    if sqrt_price_x96.sign() != Sign::Plus {
+        tracing::error!("sqrt_price_x96 is not positive: {}", sqrt_price_x96_str);
+        return Err(TrackerError::new(PriceFailure::SqrtPriceParseFailed));
+    }
+
+    //2^96
+    let two_pow_96: BigInt = BigInt::one() << 96;
+
+    // (sqrtPriceX96 / 2^96)^2
+    let price_ratio = Ratio::new(sqrt_price_x96.clone(), two_pow_96.clone()).pow(2);
+
+    // 10^(dec1 - dec0)
+    let decimal_factor = Ratio::new(
+        BigInt::from(10).pow(decimal_token1),
+        BigInt::from(10).pow(decimal_token0),
+    );
+
+    let buy_one_token0_ratio: Ratio<BigInt> = price_ratio / decimal_factor;
+    let buy_one_token1_ratio: Ratio<BigInt> = Ratio::one() / &buy_one_token0_ratio;
+
+    let scale = BigInt::from(10u64.pow(18));
+
+    let buy_one_token0 = (buy_one_token0_ratio.clone() * &scale).to_integer();
+    let buy_one_token1 = (buy_one_token1_ratio.clone() * &scale).to_integer();
+
+    //convert type to U256 for format the price
+    let (Some(buy_one_token0_u256), Some(buy_one_token1_u256)) = (
+        U256::try_from_be_slice(&buy_one_token0.to_signed_bytes_be()),
+        U256::try_from_be_slice(&buy_one_token1.to_signed_bytes_be()),
+    ) else {
+        tracing::error!(
+            "Price of {}/{} does not fit in 256 bits",
+            token0_symbol,
+            token1_symbol
+        );
+        return Err(TrackerError::new(PriceFailure::FormatPriceFailed));
+    };
+
+    // format BigInt prices into human-readable strings
+    let formatted_token0_price = match format_units(buy_one_token0_u256, "ether") {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Failed to format token0 price: {}", e);
+            return Err(TrackerError::new(PriceFailure::FormatPriceFailed).with_source(e));
+        }
+    };
+
+    let formatted_token1_price = match format_units(buy_one_token1_u256, "ether") {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::error!("Failed to format token0 price: {}", e);
+            return Err(TrackerError::new(PriceFailure::FormatPriceFailed).with_source(e));
+        }
+    };
+
+    // logs token prices for both directions:
+    // 1 token0 = *price* token1
+    // 1 token1 = *price* token0
+    debug!(
+        "1 {:?} =  {:?} {:?}, 1 {:?} = {:?} {:?}",
+        token0_symbol,
+        formatted_token0_price,
+        token1_symbol,
+        token1_symbol,
+        formatted_token1_price,
+        token0_symbol
+    );
+
+    Ok((buy_one_token0, buy_one_token1))
+}
+
+// convert a price scaled by 10^18 (as returned by calculate_prices) into a float
+// for threshold comparisons and human readable messages
+pub fn scaled_to_f64(value: &BigInt) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN) / 1e18
+}
+
+// sqrtPriceX96 for an exchange rate of `amount1` raw token1 per `amount0` raw token0,
+// √(amount1/amount0) * 2^96, so pools that are not Uniswap V3 (constant product
+// reserves, quoted amounts) can go through the same pipeline as V3 pools
+pub fn sqrt_price_x96_from_amounts(amount0: U256, amount1: U256) -> Option<U160> {
+    if amount0.is_zero() {
+        return None;
+    }
+    let ratio: U512 = (U512::from(amount1) << 192) / U512::from(amount0);
+    U160::uint_try_from(ratio.root(2)).ok()
+}
+
+// liquidity of a constant product pool, √(reserve0 * reserve1)
+pub fn liquidity_from_reserves(reserve0: U256, reserve1: U256) -> u128 {
+    let product: U512 = U512::from(reserve0) * U512::from(reserve1);
+    u128::try_from(product.root(2)).unwrap_or(u128::MAX)
+}
+
+// tick whose price is closest below the given sqrtPriceX96, price = 1.0001^tick
+pub fn tick_from_sqrt_price(sqrt_price_x96: U160) -> i32 {
+    let sqrt_price = f64::from(sqrt_price_x96) / 2f64.powi(96);
+    (2.0 * sqrt_price.ln() / 1.0001f64.ln()).floor() as i32
+}
+
+// token1 per token0 at a (possibly fractional) tick, 1.0001^tick scaled by the decimals
+pub fn price_from_tick(tick: f64, decimal_token0: u8, decimal_token1: u8) -> f64 {
+    1.0001f64.powf(tick) * 10f64.powi(decimal_token0 as i32 - decimal_token1 as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(
+        sqrt_price_x96: &str,
+        decimals0: u32,
+        decimals1: u32,
+    ) -> Result<(BigInt, BigInt), TrackerError> {
+        let symbol = "T".to_string();
+        calculate_prices(
+            sqrt_price_x96.to_string(),
+            decimals0,
+            decimals1,
+            &symbol,
+            &symbol,
+        )
+    }
+
+    #[test]
+    fn prices_at_parity() {
+        let q96 = (BigInt::one() << 96u32).to_string();
+        let (price0, price1) = prices(&q96, 18, 18).unwrap();
+        assert_eq!(price0, BigInt::from(10u64.pow(18)));
+        assert_eq!(price1, BigInt::from(10u64.pow(18)));
+    }
+
+    #[test]
+    fn prices_with_more_than_19_decimals() {
+        // 10^24 does not fit in a u64
+        let q96 = (BigInt::one() << 96u32).to_string();
+        let (price0, price1) = prices(&q96, 24, 18).unwrap();
+        assert_eq!(price0, BigInt::from(10).pow(24));
+        assert_eq!(price1, BigInt::from(10u64.pow(12)));
+    }
+
+    #[test]
+    fn rejects_zero_and_negative_sqrt_price() {
+        assert!(prices("0", 18, 18).is_err());
+        assert!(prices("-1", 18, 18).is_err());
+    }
+
+    #[test]
+    fn rejects_prices_beyond_256_bits() {
+        assert!(prices("1", 0, 77).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+// OHLC of the swap prices within one interval, intervals without swaps are left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candle {
+    // unix timestamp of the interval start
+    pub time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    // token0 traded, in whole tokens
+    pub volume0: f64,
+    pub swaps: u64,
+}
+
+// combine time ordered candles (or swaps as single swap candles) into candles of `interval`
+pub fn merge_candles(pieces: Vec<Candle>, interval: u64) -> Vec<Candle> {
+    let mut candles: Vec<Candle> = Vec::new();
+    for piece in pieces {
+        let time = piece.time - piece.time % interval;
+        match candles.last_mut() {
+            Some(candle) if candle.time == time => {
+                candle.high = candle.high.max(piece.high);
+                candle.low = candle.low.min(piece.low);
+                candle.close = piece.close;
+                candle.volume0 += piece.volume0;
+                candle.swaps += piece.swaps;
+            }
+            _ => candles.push(Candle { time, ..piece }),
+        }
+    }
+    candles
+}
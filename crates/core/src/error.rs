@@ -0,0 +1,200 @@
+use alloy_primitives::Address;
+use std::error::Error;
+use std::fmt;
+use thiserror::Error;
+
+// a failure of the tracker: what failed as an ErrorKind, the pool and chain it
+// happened on when known, and the error that caused it as its `source()`; library
+// users get it inside eyre reports, see `TrackerError::of`
+#[derive(Debug)]
+pub struct TrackerError {
+    kind: ErrorKind,
+    pool: Option<Address>,
+    chain_id: Option<u64>,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+// what failed, by the part of the tracker it failed in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Provider(ProviderFailure),
+    Token(TokenFailure),
+    Log(LogFailure),
+    Price(PriceFailure),
+    Pool(PoolFailure),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ProviderFailure {
+    #[error("Failed to connect WS")]
+    WSConnectionFailed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TokenFailure {
+    #[error("Failed to fetch token from pool")]
+    TokenFetchFailed,
+
+    #[error("Failed to fetch token info from address")]
+    TokenInfoFetchFailed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LogFailure {
+    #[error("Failed to subscribe logs")]
+    LogSubscriptionFailed,
+
+    #[error("Failed to fetch sqrt price")]
+    SqrtPriceFetchFailed,
+}
+
+// the contract is not the kind of pool it was configured as, or the config
+// does not describe the pool
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PoolFailure {
+    #[error("Pool is not a {0} pool")]
+    Unsupported(&'static str),
+
+    #[error("Pool has no token at index {0}")]
+    NoSuchToken(usize),
+
+    #[error("Pool key does not identify a pool")]
+    InvalidKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PriceFailure {
+    #[error("Failed to calculate price for tokens {symbol0}/{symbol1}")]
+    CalculationFailed { symbol0: String, symbol1: String },
+
+    #[error("Failed to parse sqrt price")]
+    SqrtPriceParseFailed,
+
+    #[error("Failed to format price")]
+    FormatPriceFailed,
+
+    // window in seconds
+    #[error("Failed to get a TWAP over {0}s")]
+    TwapFailed(u64),
+
+    #[error("No price at block {0}")]
+    NoHistoricalPrice(u64),
+}
+
+impl TrackerError {
+    pub fn new(kind: impl Into<ErrorKind>) -> Self {
+        TrackerError {
+            kind: kind.into(),
+            pool: None,
+            chain_id: None,
+            source: None,
+        }
+    }
+
+    pub fn with_pool(mut self, pool: Address) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub fn with_chain(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    // the error this one was caused by, returned by `source()`
+    pub fn with_source(mut self, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    // whether the same operation may succeed when tried again later, e.g. after the
+    // endpoint dropped a connection; malformed logs and prices never will, and neither
+    // will a token that does not answer decimals() or symbol()
+    pub fn is_retryable(&self) -> bool {
+        match &self.kind {
+            ErrorKind::Provider(_) => true,
+            ErrorKind::Token(failure) => *failure == TokenFailure::TokenFetchFailed,
+            ErrorKind::Log(failure) => *failure == LogFailure::LogSubscriptionFailed,
+            ErrorKind::Price(_) | ErrorKind::Pool(_) => false,
+        }
+    }
+
+    // the TrackerError inside an eyre report, None for other errors
+    pub fn of(report: &eyre::Report) -> Option<&TrackerError> {
+        report.downcast_ref()
+    }
+}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        match (self.pool, self.chain_id) {
+            (Some(pool), Some(chain_id)) => write!(f, " (pool {:?} on chain {})", pool, chain_id),
+            (Some(pool), None) => write!(f, " (pool {:?})", pool),
+            (None, Some(chain_id)) => write!(f, " (chain {})", chain_id),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Error for TrackerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Provider(failure) => failure.fmt(f),
+            ErrorKind::Token(failure) => failure.fmt(f),
+            ErrorKind::Log(failure) => failure.fmt(f),
+            ErrorKind::Price(failure) => failure.fmt(f),
+            ErrorKind::Pool(failure) => failure.fmt(f),
+        }
+    }
+}
+
+impl From<ProviderFailure> for ErrorKind {
+    fn from(failure: ProviderFailure) -> Self {
+        ErrorKind::Provider(failure)
+    }
+}
+
+impl From<TokenFailure> for ErrorKind {
+    fn from(failure: TokenFailure) -> Self {
+        ErrorKind::Token(failure)
+    }
+}
+
+impl From<LogFailure> for ErrorKind {
+    fn from(failure: LogFailure) -> Self {
+        ErrorKind::Log(failure)
+    }
+}
+
+impl From<PriceFailure> for ErrorKind {
+    fn from(failure: PriceFailure) -> Self {
+        ErrorKind::Price(failure)
+    }
+}
+
+impl From<PoolFailure> for ErrorKind {
+    fn from(failure: PoolFailure) -> Self {
+        ErrorKind::Pool(failure)
+    }
+}
@@ -0,0 +1,7 @@
+// the parts of the tracker that only compute: prices from sqrtPriceX96, decoding of
+// swap logs and candle aggregation; the tracker re-exports them, the wasm bindings
+// run them in the browser
+pub mod calc;
+pub mod candle;
+pub mod error;
+pub mod swap;
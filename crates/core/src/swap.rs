@@ -0,0 +1,22 @@
+use alloy_primitives::B256;
+use alloy_sol_types::{SolEvent, sol};
+
+sol! {
+    event Swap(
+        address indexed sender,
+        address indexed recipient,
+        int256 amount0,
+        int256 amount1,
+        uint160 sqrtPriceX96,
+        uint128 liquidity,
+        int24 tick
+    );
+}
+
+// a Uniswap V3 swap from the topics and data of its log, None for other logs
+pub fn decode_swap(topics: &[B256], data: &[u8]) -> Option<Swap> {
+    if topics.first() != Some(&Swap::SIGNATURE_HASH) {
+        return None;
+    }
+    Swap::decode_raw_log(topics.iter().copied(), data).ok()
+}
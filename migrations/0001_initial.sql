@@ -0,0 +1,42 @@
+-- alert audit log, swaps and the candles old swaps are compacted into
+-- IF NOT EXISTS since databases from before migrations already have them
+CREATE TABLE IF NOT EXISTS alert_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    rule TEXT NOT NULL,
+    key TEXT NOT NULL,
+    pool TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    deliveries TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS alert_log_pool ON alert_log (pool, timestamp);
+
+CREATE TABLE IF NOT EXISTS swaps (
+    pool TEXT NOT NULL,
+    block INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    tx TEXT,
+    timestamp INTEGER NOT NULL,
+    price REAL NOT NULL,
+    sqrt_price_x96 TEXT NOT NULL,
+    liquidity TEXT NOT NULL,
+    tick INTEGER NOT NULL,
+    amount0 REAL NOT NULL,
+    PRIMARY KEY (pool, block, log_index)
+);
+CREATE INDEX IF NOT EXISTS swaps_pool_time ON swaps (pool, timestamp);
+
+CREATE TABLE IF NOT EXISTS candles (
+    pool TEXT NOT NULL,
+    interval INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    open REAL NOT NULL,
+    high REAL NOT NULL,
+    low REAL NOT NULL,
+    close REAL NOT NULL,
+    volume0 REAL NOT NULL,
+    swaps INTEGER NOT NULL,
+    PRIMARY KEY (pool, interval, time)
+);
@@ -0,0 +1,9 @@
+-- realized volatility series computed after backfills
+CREATE TABLE IF NOT EXISTS volatility (
+    pool TEXT NOT NULL,
+    interval INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    realized REAL NOT NULL,
+    returns INTEGER NOT NULL,
+    PRIMARY KEY (pool, interval, time)
+);
@@ -0,0 +1,7 @@
+-- imported price series (e.g. CEX klines) the pools are compared against
+CREATE TABLE IF NOT EXISTS external_prices (
+    series TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    price REAL NOT NULL,
+    PRIMARY KEY (series, time)
+);
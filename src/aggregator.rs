@@ -0,0 +1,193 @@
+use crate::config::{AggregatorRule, AggregatorSource};
+use crate::route::amount_out;
+use crate::state::SharedState;
+use crate::storage::unix_now;
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info};
+
+#[derive(Debug, Error)]
+pub enum AggregatorError {
+    #[error("Quote request to {0} failed: {1}")]
+    RequestFailed(&'static str, String),
+
+    #[error("{0} returned an unusable quote: {1}")]
+    InvalidQuote(&'static str, String),
+}
+
+// https://0x.org/docs/api#tag/Swap/operation/swap::permit2::getPrice
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ZeroExPrice {
+    buy_amount: String,
+}
+
+// https://portal.1inch.dev/documentation/apis/swap/classic-swap/methods/v6.0/1/quote/get
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OneInchQuote {
+    dst_amount: String,
+}
+
+// what an aggregator pays for selling `size` token0 compared with the pool alone
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregatorQuote {
+    pub pool: Address,
+    pub source: AggregatorSource,
+    pub size: f64,
+    // token1 out of the pool, from the last swap's price and liquidity
+    pub pool_out: f64,
+    pub aggregator_out: f64,
+    // how much more the pool pays than the aggregator, negative when routing wins
+    pub pool_vs_aggregator_bps: f64,
+    pub updated_at: u64,
+}
+
+// quote the pool's pair at every size every `refresh_secs`, once the pool has
+// seen a swap
+pub async fn run(rule: AggregatorRule, state: SharedState) {
+    let client = reqwest::Client::new();
+    let api_key = match rule.source {
+        AggregatorSource::ZeroEx => env::var("ZEROX_API_KEY").ok(),
+        AggregatorSource::OneInch => env::var("ONEINCH_API_KEY").ok(),
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(rule.refresh_secs));
+
+    loop {
+        interval.tick().await;
+
+        let Some(snapshot) = state.read().unwrap().pools.get(&rule.pool).cloned() else {
+            continue;
+        };
+
+        let mut quotes = Vec::with_capacity(rule.sizes.len());
+        for &size in &rule.sizes {
+            let raw = size * 10f64.powi(snapshot.decimals0 as i32);
+            let sell_amount = U256::from(raw as u128);
+            let bought = match quote(
+                &client,
+                rule.source,
+                api_key.as_deref(),
+                snapshot.chain_id,
+                (snapshot.token0, snapshot.token1),
+                sell_amount,
+            )
+            .await
+            {
+                Ok(b) => b,
+                Err(e) => {
+                    error!("Failed to quote {} {}: {}", size, snapshot.pair(), e);
+                    continue;
+                }
+            };
+
+            let aggregator_out = f64::from(bought) / 10f64.powi(snapshot.decimals1 as i32);
+            let pool_out = amount_out(&snapshot, true, size);
+            if aggregator_out <= 0.0 {
+                continue;
+            }
+            let diff_bps = (pool_out / aggregator_out - 1.0) * 10_000.0;
+
+            info!(
+                "{} {} via pool {:?}: {:.6} out, {:?} pays {:.6} ({:+.2} bps for the pool)",
+                size,
+                snapshot.pair(),
+                rule.pool,
+                pool_out,
+                rule.source,
+                aggregator_out,
+                diff_bps
+            );
+
+            quotes.push(AggregatorQuote {
+                pool: rule.pool,
+                source: rule.source,
+                size,
+                pool_out,
+                aggregator_out,
+                pool_vs_aggregator_bps: diff_bps,
+                updated_at: unix_now(),
+            });
+        }
+
+        state
+            .write()
+            .unwrap()
+            .aggregator_quotes
+            .insert((rule.pool, rule.source), quotes);
+    }
+}
+
+// raw token1 the aggregator returns for `sell_amount` raw token0
+async fn quote(
+    client: &reqwest::Client,
+    source: AggregatorSource,
+    api_key: Option<&str>,
+    chain_id: u64,
+    (sell, buy): (Address, Address),
+    sell_amount: U256,
+) -> Result<U256, AggregatorError> {
+    let name = source.name();
+    let request = match source {
+        AggregatorSource::ZeroEx => {
+            let mut request = client
+                .get("https://api.0x.org/swap/permit2/price")
+                .query(&[
+                    ("chainId", chain_id.to_string()),
+                    ("sellToken", sell.to_string()),
+                    ("buyToken", buy.to_string()),
+                    ("sellAmount", sell_amount.to_string()),
+                ])
+                .header("0x-version", "v2");
+            if let Some(key) = api_key {
+                request = request.header("0x-api-key", key);
+            }
+            request
+        }
+        AggregatorSource::OneInch => {
+            let mut request = client
+                .get(format!(
+                    "https://api.1inch.dev/swap/v6.0/{}/quote",
+                    chain_id
+                ))
+                .query(&[
+                    ("src", sell.to_string()),
+                    ("dst", buy.to_string()),
+                    ("amount", sell_amount.to_string()),
+                ]);
+            if let Some(key) = api_key {
+                request = request.bearer_auth(key);
+            }
+            request
+        }
+    };
+
+    let response = match request.send().await {
+        Ok(r) => r,
+        Err(e) => return Err(AggregatorError::RequestFailed(name, e.to_string())),
+    };
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(AggregatorError::RequestFailed(
+            name,
+            format!("{}: {}", status, body),
+        ));
+    }
+
+    let amount = match source {
+        AggregatorSource::ZeroEx => response.json::<ZeroExPrice>().await.map(|p| p.buy_amount),
+        AggregatorSource::OneInch => response.json::<OneInchQuote>().await.map(|q| q.dst_amount),
+    };
+    let amount = match amount {
+        Ok(a) => a,
+        Err(e) => return Err(AggregatorError::InvalidQuote(name, e.to_string())),
+    };
+    match amount.parse() {
+        Ok(a) => Ok(a),
+        Err(_) => Err(AggregatorError::InvalidQuote(name, amount)),
+    }
+}
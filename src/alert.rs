@@ -0,0 +1,869 @@
+mod escalation;
+mod ntfy;
+mod pagerduty;
+mod pushover;
+mod schedule;
+#[cfg(feature = "telegram")]
+mod telegram;
+mod webhook;
+
+use crate::config::{EscalationRule, QuietHoursConfig};
+use crate::metrics;
+use crate::storage::{AlertRecord, Delivery, Storage, unix_now};
+#[cfg(feature = "telegram")]
+use crate::telegram::TelegramClient;
+use alloy::primitives::Address;
+pub use escalation::Unacknowledged;
+use escalation::{Escalation, Pending};
+#[cfg(feature = "templates")]
+use handlebars::Handlebars;
+use ntfy::Ntfy;
+use pagerduty::PagerDuty;
+use pushover::Pushover;
+use schedule::QuietHours;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+#[cfg(feature = "telegram")]
+use telegram::Telegram;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use webhook::Webhook;
+
+#[derive(Debug, Error)]
+pub enum AlertError {
+    #[error("Failed to deliver alert to {0}")]
+    DeliveryFailed(&'static str),
+
+    #[error("Unknown alert severity: {0}")]
+    UnknownSeverity(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = AlertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(AlertError::UnknownSeverity(s.to_string())),
+        }
+    }
+}
+
+// a single alert condition
+// `rule` names the kind of condition (e.g. "stalled") and selects its cooldown,
+// `key` identifies the concrete condition (e.g. "stalled:<pool>") so that the same
+// condition firing again updates the existing incident and can be resolved later
+// `vars` are exposed to message templates next to rule, severity and summary
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub rule: String,
+    pub key: String,
+    pub pool: Address,
+    pub severity: Severity,
+    pub summary: String,
+    pub vars: BTreeMap<String, String>,
+}
+
+enum Backend {
+    PagerDuty(PagerDuty),
+    Ntfy(Ntfy),
+    Pushover(Pushover),
+    #[cfg(feature = "telegram")]
+    Telegram(Telegram),
+    Webhook(Webhook),
+}
+
+impl Backend {
+    // channel name used to look up channel specific templates
+    fn channel(&self) -> &'static str {
+        match self {
+            Backend::PagerDuty(_) => "pagerduty",
+            Backend::Ntfy(_) => "ntfy",
+            Backend::Pushover(_) => "pushover",
+            #[cfg(feature = "telegram")]
+            Backend::Telegram(_) => "telegram",
+            Backend::Webhook(_) => "webhook",
+        }
+    }
+}
+
+// suppression state shared by all pool listeners
+#[derive(Default)]
+struct Throttle {
+    // keys that fired and have not been resolved yet; a condition has to
+    // clear before it is allowed to fire again
+    active: HashSet<String>,
+    last_fired: HashMap<String, Instant>,
+    // send times of the alerts delivered within the last minute
+    recent: VecDeque<Instant>,
+    suppressed: u64,
+}
+
+// fans alerts out to every configured notification backend
+// delivery failures are logged and never propagated, an unreachable
+// notification service must not stop the pool listeners
+pub struct Alerter {
+    backends: Vec<Backend>,
+    default_cooldown: Duration,
+    rule_cooldowns: HashMap<String, Duration>,
+    max_per_minute: usize,
+    throttle: Mutex<Throttle>,
+    #[cfg(feature = "templates")]
+    templates: Handlebars<'static>,
+    // audit log of every alert sent or suppressed
+    storage: Option<Storage>,
+    quiet_hours: Vec<QuietHours>,
+    // messages held back during quiet hours, by channel
+    queued: Mutex<HashMap<&'static str, Vec<(Severity, String)>>>,
+    escalations: Vec<Escalation>,
+    // fired alerts under an escalation policy, by key, until resolved
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+enum Admission {
+    Send,
+    // the condition is already firing
+    Active,
+    // cooldown or global rate limit
+    Suppressed,
+}
+
+impl Alerter {
+    pub fn from_env() -> Self {
+        let client = reqwest::Client::new();
+        let mut backends = Vec::new();
+
+        if let Ok(routing_key) = env::var("PAGERDUTY_ROUTING_KEY") {
+            let min_severity = min_severity_from_env("PAGERDUTY_MIN_SEVERITY", Severity::Error);
+            backends.push(Backend::PagerDuty(PagerDuty::new(
+                client.clone(),
+                routing_key,
+                min_severity,
+            )));
+            info!("PagerDuty alerts enabled (min severity {:?})", min_severity);
+        }
+
+        if let Ok(topic) = env::var("NTFY_TOPIC") {
+            let server = env::var("NTFY_URL").unwrap_or_else(|_| "https://ntfy.sh".to_string());
+            let token = env::var("NTFY_TOKEN").ok();
+            let min_severity = min_severity_from_env("NTFY_MIN_SEVERITY", Severity::Warning);
+            backends.push(Backend::Ntfy(Ntfy::new(
+                client.clone(),
+                server,
+                topic,
+                token,
+                min_severity,
+            )));
+            info!("ntfy alerts enabled (min severity {:?})", min_severity);
+        }
+
+        if let (Ok(app_token), Ok(user_key)) =
+            (env::var("PUSHOVER_TOKEN"), env::var("PUSHOVER_USER"))
+        {
+            let min_severity = min_severity_from_env("PUSHOVER_MIN_SEVERITY", Severity::Warning);
+            backends.push(Backend::Pushover(Pushover::new(
+                client.clone(),
+                app_token,
+                user_key,
+                min_severity,
+            )));
+            info!("Pushover alerts enabled (min severity {:?})", min_severity);
+        }
+
+        #[cfg(feature = "telegram")]
+        if let (Ok(token), Ok(chat_id)) =
+            (env::var("TELEGRAM_BOT_TOKEN"), env::var("TELEGRAM_CHAT_ID"))
+        {
+            let min_severity = min_severity_from_env("TELEGRAM_MIN_SEVERITY", Severity::Warning);
+            backends.push(Backend::Telegram(Telegram::new(
+                TelegramClient::new(client.clone(), &token),
+                chat_id,
+                min_severity,
+            )));
+            info!("Telegram alerts enabled (min severity {:?})", min_severity);
+        }
+
+        if let Ok(url) = env::var("WEBHOOK_URL") {
+            let secret = env::var("WEBHOOK_SECRET").ok();
+            let headers = env::var("WEBHOOK_HEADERS")
+                .map(|h| webhook::parse_headers(&h))
+                .unwrap_or_default();
+            let min_severity = min_severity_from_env("WEBHOOK_MIN_SEVERITY", Severity::Warning);
+            info!(
+                "Webhook alerts enabled (min severity {:?}, {})",
+                min_severity,
+                if secret.is_some() {
+                    "signed"
+                } else {
+                    "unsigned"
+                }
+            );
+            backends.push(Backend::Webhook(Webhook::new(
+                client.clone(),
+                url,
+                secret,
+                headers,
+                min_severity,
+            )));
+        }
+
+        let default_cooldown =
+            duration_from_env("ALERT_COOLDOWN_SECS").unwrap_or(Duration::from_secs(600));
+        let max_per_minute = env::var("ALERT_MAX_PER_MINUTE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
+
+        #[cfg(not(feature = "templates"))]
+        if env::vars().any(|(var, _)| var.starts_with("ALERT_TEMPLATE_")) {
+            warn!("Built without the templates feature, ignoring ALERT_TEMPLATE_ variables");
+        }
+
+        Alerter {
+            backends,
+            default_cooldown,
+            rule_cooldowns: rule_cooldowns_from_env(),
+            max_per_minute,
+            throttle: Mutex::new(Throttle::default()),
+            #[cfg(feature = "templates")]
+            templates: templates_from_env(),
+            storage: None,
+            quiet_hours: Vec::new(),
+            queued: Mutex::new(HashMap::new()),
+            escalations: Vec::new(),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // no notification backends and nothing read from the environment, alerts are only
+    // logged; the default of an embedded PoolTracker
+    pub fn log_only() -> Self {
+        Alerter {
+            backends: Vec::new(),
+            default_cooldown: Duration::from_secs(600),
+            rule_cooldowns: HashMap::new(),
+            max_per_minute: 30,
+            throttle: Mutex::new(Throttle::default()),
+            #[cfg(feature = "templates")]
+            templates: Handlebars::new(),
+            storage: None,
+            quiet_hours: Vec::new(),
+            queued: Mutex::new(HashMap::new()),
+            escalations: Vec::new(),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_quiet_hours(mut self, config: &[QuietHoursConfig]) -> Self {
+        self.quiet_hours = config.iter().filter_map(QuietHours::from_config).collect();
+        self
+    }
+
+    pub fn has_quiet_hours(&self) -> bool {
+        !self.quiet_hours.is_empty()
+    }
+
+    pub fn with_escalations(mut self, config: &[EscalationRule]) -> Self {
+        self.escalations = config.iter().filter_map(Escalation::from_config).collect();
+        for channel in self
+            .escalations
+            .iter()
+            .filter_map(|e| e.escalate_to.as_deref())
+        {
+            if !self.backends.iter().any(|b| b.channel() == channel) {
+                warn!("Escalation channel {} is not configured", channel);
+            }
+        }
+        self
+    }
+
+    // names of the configured notification backends
+    pub fn channels(&self) -> Vec<&'static str> {
+        self.backends.iter().map(Backend::channel).collect()
+    }
+
+    pub fn has_escalations(&self) -> bool {
+        !self.escalations.is_empty()
+    }
+
+    // replayed swaps arrive much faster than they happened, so the wall-clock cooldowns
+    // and rate limit are dropped and only hysteresis remains; without `notify` alerts
+    // are only logged
+    pub fn for_replay(mut self, notify: bool) -> Self {
+        if !notify {
+            self.backends.clear();
+        }
+        self.default_cooldown = Duration::ZERO;
+        self.rule_cooldowns.clear();
+        self.max_per_minute = usize::MAX;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    // decides whether an alert may be delivered and records it if so
+    fn admit(&self, alert: &Alert) -> Admission {
+        let mut throttle = self.throttle.lock().unwrap();
+        let now = Instant::now();
+
+        if throttle.active.contains(&alert.key) {
+            return Admission::Active;
+        }
+
+        let cooldown = self
+            .rule_cooldowns
+            .get(&alert.rule)
+            .copied()
+            .unwrap_or(self.default_cooldown);
+        if let Some(last) = throttle.last_fired.get(&alert.key)
+            && now.duration_since(*last) < cooldown
+        {
+            warn!(
+                "Alert {} suppressed, still in {}s cooldown",
+                alert.key,
+                cooldown.as_secs()
+            );
+            throttle.suppressed += 1;
+            return Admission::Suppressed;
+        }
+
+        while let Some(sent) = throttle.recent.front() {
+            if now.duration_since(*sent) >= Duration::from_secs(60) {
+                throttle.recent.pop_front();
+            } else {
+                break;
+            }
+        }
+        if throttle.recent.len() >= self.max_per_minute {
+            throttle.suppressed += 1;
+            warn!(
+                "Alert {} suppressed, global rate limit of {}/min reached ({} suppressed so far)",
+                alert.key, self.max_per_minute, throttle.suppressed
+            );
+            return Admission::Suppressed;
+        }
+
+        throttle.recent.push_back(now);
+        throttle.last_fired.insert(alert.key.clone(), now);
+        throttle.active.insert(alert.key.clone());
+        Admission::Send
+    }
+
+    pub async fn fire(&self, alert: &Alert) {
+        match self.admit(alert) {
+            Admission::Send => {}
+            Admission::Active => return,
+            Admission::Suppressed => {
+                self.audit(alert, "suppressed", Vec::new()).await;
+                return;
+            }
+        }
+
+        info!("ALERT [{:?}] {}", alert.severity, alert.summary);
+
+        let policy = self.escalations.iter().position(|e| e.applies(alert));
+        let held_back = policy.and_then(|i| self.escalations[i].escalate_to.as_deref());
+
+        let mut deliveries = Vec::new();
+        for backend in &self.backends {
+            if Some(backend.channel()) == held_back {
+                continue;
+            }
+            deliveries.push(self.deliver(backend, alert, false).await);
+        }
+
+        if let Some(policy) = policy {
+            self.pending
+                .lock()
+                .unwrap()
+                .insert(alert.key.clone(), Pending::new(alert.clone(), policy));
+        }
+
+        self.audit(alert, "fire", deliveries).await;
+    }
+
+    // a one-off event (e.g. a single large mint) rather than a condition that clears
+    // later: throttled like any other alert, but never kept active or escalated
+    pub async fn notify(&self, alert: &Alert) {
+        let admission = self.admit(alert);
+        self.throttle.lock().unwrap().active.remove(&alert.key);
+        match admission {
+            Admission::Send => {}
+            Admission::Active => return,
+            Admission::Suppressed => {
+                self.audit(alert, "suppressed", Vec::new()).await;
+                return;
+            }
+        }
+
+        info!("ALERT [{:?}] {}", alert.severity, alert.summary);
+
+        let mut deliveries = Vec::new();
+        for backend in &self.backends {
+            deliveries.push(self.deliver(backend, alert, false).await);
+        }
+
+        self.audit(alert, "fire", deliveries).await;
+    }
+
+    async fn audit(&self, alert: &Alert, action: &str, deliveries: Vec<Delivery>) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+
+        let record = AlertRecord {
+            id: 0,
+            timestamp: unix_now(),
+            action: action.to_string(),
+            rule: alert.rule.clone(),
+            key: alert.key.clone(),
+            pool: format!("{:?}", alert.pool),
+            severity: format!("{:?}", alert.severity).to_ascii_lowercase(),
+            summary: alert.summary.clone(),
+            deliveries,
+        };
+        if let Err(e) = storage.record_alert(record).await {
+            error!(
+                "Failed to write alert {} to the audit log: {}",
+                alert.key, e
+            );
+        }
+    }
+
+    // send a synthetic alert (and its resolution) through every backend, bypassing
+    // throttling and severity filters, and report the outcome per channel
+    pub async fn test_backends(&self) -> Vec<(&'static str, Result<(), AlertError>)> {
+        let alert = Alert {
+            rule: "test".to_string(),
+            key: format!("test:{}", std::process::id()),
+            pool: Address::ZERO,
+            severity: Severity::Critical,
+            summary:
+                "Test alert from uniswapv3-pool-price-tracker, your notification backend works"
+                    .to_string(),
+            vars: BTreeMap::new(),
+        };
+
+        let mut results = Vec::new();
+        for backend in &self.backends {
+            let message = self.render(&alert, backend.channel());
+            let result = match backend {
+                Backend::PagerDuty(pd) => match pd.trigger(&alert, &message).await {
+                    Ok(_) => pd.resolve(&alert).await,
+                    Err(e) => Err(e),
+                },
+                Backend::Ntfy(ntfy) => ntfy.trigger(&alert, &message).await,
+                Backend::Pushover(po) => po.trigger(&alert, &message).await,
+                #[cfg(feature = "telegram")]
+                Backend::Telegram(tg) => tg.trigger(&alert, &message).await,
+                Backend::Webhook(wh) => match wh.trigger(&alert, &message).await {
+                    Ok(_) => wh.resolve(&alert, &message).await,
+                    Err(e) => Err(e),
+                },
+            };
+            results.push((backend.channel(), result));
+        }
+        results
+    }
+
+    // render the message body for one channel
+    // a "<rule>_<channel>" template wins over a "<rule>" template,
+    // without either the alert summary is sent as is
+    #[cfg(feature = "templates")]
+    fn render(&self, alert: &Alert, channel: &str) -> String {
+        let channel_template = format!("{}_{}", alert.rule, channel);
+        let name = if self.templates.has_template(&channel_template) {
+            channel_template
+        } else if self.templates.has_template(&alert.rule) {
+            alert.rule.clone()
+        } else {
+            return alert.summary.clone();
+        };
+
+        let mut vars = alert.vars.clone();
+        vars.insert("rule".to_string(), alert.rule.clone());
+        vars.insert("severity".to_string(), format!("{:?}", alert.severity));
+        vars.insert("summary".to_string(), alert.summary.clone());
+        vars.entry("pool".to_string())
+            .or_insert_with(|| alert.pool.to_string());
+
+        match self.templates.render(&name, &vars) {
+            Ok(message) => message,
+            Err(e) => {
+                error!("Failed to render alert template {}: {}", name, e);
+                alert.summary.clone()
+            }
+        }
+    }
+
+    #[cfg(not(feature = "templates"))]
+    fn render(&self, alert: &Alert, _channel: &str) -> String {
+        alert.summary.clone()
+    }
+
+    // called once the condition behind `alert` has cleared
+    // resolves are never rate limited, but only sent for alerts that actually fired
+    pub async fn resolve(&self, alert: &Alert) {
+        if !self.throttle.lock().unwrap().active.remove(&alert.key) {
+            return;
+        }
+
+        info!("RESOLVED [{:?}] {}", alert.severity, alert.summary);
+
+        // the escalation channel is only told about the resolve if it saw the alert
+        let held_back = match self.pending.lock().unwrap().remove(&alert.key) {
+            Some(p) if !p.escalated => self.escalations[p.policy].escalate_to.clone(),
+            _ => None,
+        };
+
+        let mut deliveries = Vec::new();
+        for backend in &self.backends {
+            if Some(backend.channel()) == held_back.as_deref() {
+                continue;
+            }
+            deliveries.push(self.deliver(backend, alert, true).await);
+        }
+
+        self.audit(alert, "resolve", deliveries).await;
+    }
+
+    // send one alert (or its resolution) to one backend, unless the channel
+    // is in quiet hours and the alert is not severe enough to bypass them
+    async fn deliver(&self, backend: &Backend, alert: &Alert, resolved: bool) -> Delivery {
+        let channel = backend.channel();
+        let message = self.render(alert, channel);
+
+        if let Some(quiet) = self.quiet_period(channel)
+            && alert.severity < quiet.bypass
+        {
+            let line = if resolved {
+                format!("Resolved: {}", message)
+            } else {
+                format!("[{:?}] {}", alert.severity, message)
+            };
+            self.queued
+                .lock()
+                .unwrap()
+                .entry(channel)
+                .or_default()
+                .push((alert.severity, line));
+            return Delivery {
+                channel: channel.to_string(),
+                ok: true,
+                error: None,
+                queued: true,
+            };
+        }
+
+        let started = std::time::Instant::now();
+        let result = match (backend, resolved) {
+            (Backend::PagerDuty(pd), false) => pd.trigger(alert, &message).await,
+            (Backend::PagerDuty(pd), true) => pd.resolve(alert).await,
+            (Backend::Ntfy(ntfy), false) => ntfy.trigger(alert, &message).await,
+            (Backend::Ntfy(ntfy), true) => ntfy.resolve(alert, &message).await,
+            (Backend::Pushover(po), false) => po.trigger(alert, &message).await,
+            (Backend::Pushover(po), true) => po.resolve(alert, &message).await,
+            #[cfg(feature = "telegram")]
+            (Backend::Telegram(tg), false) => tg.trigger(alert, &message).await,
+            #[cfg(feature = "telegram")]
+            (Backend::Telegram(tg), true) => tg.resolve(alert, &message).await,
+            (Backend::Webhook(wh), false) => wh.trigger(alert, &message).await,
+            (Backend::Webhook(wh), true) => wh.resolve(alert, &message).await,
+        };
+        metrics::ALERT_SINK.observe(started.elapsed());
+        if let Err(e) = &result {
+            error!(
+                "Failed to deliver alert {} to {}: {}",
+                alert.key, channel, e
+            );
+        }
+        delivery(channel, result)
+    }
+
+    // the quiet hours currently active for a channel
+    fn quiet_period(&self, channel: &str) -> Option<&QuietHours> {
+        let now = chrono::Utc::now();
+        self.quiet_hours
+            .iter()
+            .find(|q| q.channel == channel && q.is_quiet(now))
+    }
+
+    // once a minute, send a summary of everything queued for a channel whose quiet hours ended
+    pub async fn run_quiet_hours(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+
+            for backend in &self.backends {
+                let channel = backend.channel();
+                if self.quiet_period(channel).is_some() {
+                    continue;
+                }
+                let Some(queued) = self.queued.lock().unwrap().remove(channel) else {
+                    continue;
+                };
+                if queued.is_empty() {
+                    continue;
+                }
+
+                let severity = queued
+                    .iter()
+                    .map(|(s, _)| *s)
+                    .max()
+                    .unwrap_or(Severity::Info);
+                let lines: Vec<String> = queued.into_iter().map(|(_, line)| line).collect();
+                let summary = format!(
+                    "{} alerts during quiet hours:\n{}",
+                    lines.len(),
+                    lines.join("\n")
+                );
+                let alert = Alert {
+                    rule: "quiet_hours_summary".to_string(),
+                    key: format!("quiet_hours_summary:{}:{}", channel, unix_now()),
+                    pool: Address::ZERO,
+                    severity,
+                    summary,
+                    vars: BTreeMap::new(),
+                };
+
+                if let Err(e) = send_summary(backend, &alert).await {
+                    error!("Failed to send quiet hours summary to {}: {}", channel, e);
+                }
+            }
+        }
+    }
+
+    // send a scheduled report to `channels`, or every backend when empty, bypassing
+    // throttling, quiet hours and escalation but not the channels' minimum severity
+    pub async fn send_report(
+        &self,
+        channels: &[String],
+        severity: Severity,
+        key: &str,
+        summary: &str,
+    ) {
+        let alert = Alert {
+            rule: "report".to_string(),
+            key: key.to_string(),
+            pool: Address::ZERO,
+            severity,
+            summary: summary.to_string(),
+            vars: BTreeMap::new(),
+        };
+
+        let mut deliveries = Vec::new();
+        for backend in &self.backends {
+            let channel = backend.channel();
+            if !channels.is_empty() && !channels.iter().any(|c| c.eq_ignore_ascii_case(channel)) {
+                continue;
+            }
+            let result = send_summary(backend, &alert).await;
+            if let Err(e) = &result {
+                error!("Failed to send report {} to {}: {}", key, channel, e);
+            }
+            deliveries.push(delivery(channel, result));
+        }
+        self.audit(&alert, "fire", deliveries).await;
+    }
+
+    // stop re-notifying and escalating an alert, returns false for unknown
+    // or already acknowledged keys
+    pub async fn ack(&self, key: &str, by: &str) -> bool {
+        let alert = match self.pending.lock().unwrap().get_mut(key) {
+            Some(p) if !p.acked => {
+                p.acked = true;
+                p.alert.clone()
+            }
+            _ => return false,
+        };
+
+        info!("ACKNOWLEDGED {} by {}", key, by);
+        self.audit(&alert, "ack", Vec::new()).await;
+        true
+    }
+
+    // alerts under an escalation policy that nobody has acknowledged yet, oldest first
+    pub fn unacknowledged(&self) -> Vec<Unacknowledged> {
+        let pending = self.pending.lock().unwrap();
+        let mut list: Vec<&Pending> = pending.values().filter(|p| !p.acked).collect();
+        list.sort_by_key(|p| p.fired_at);
+        list.into_iter()
+            .map(|p| Unacknowledged {
+                key: p.alert.key.clone(),
+                rule: p.alert.rule.clone(),
+                pool: format!("{:?}", p.alert.pool),
+                severity: format!("{:?}", p.alert.severity).to_ascii_lowercase(),
+                summary: p.alert.summary.clone(),
+                age_secs: p.fired_at.elapsed().as_secs(),
+                escalated: p.escalated,
+            })
+            .collect()
+    }
+
+    // every 30 seconds, re-notify unacknowledged alerts whose repeat interval passed
+    // and escalate the ones that have been unacknowledged for too long
+    pub async fn run_escalation(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+
+            // collect the due alerts first, the lock is not held across deliveries
+            let now = Instant::now();
+            let due: Vec<(Alert, usize, bool, bool)> = {
+                let mut pending = self.pending.lock().unwrap();
+                pending
+                    .values_mut()
+                    .filter(|p| !p.acked)
+                    .filter_map(|p| {
+                        let policy = &self.escalations[p.policy];
+                        let age = now.duration_since(p.fired_at);
+                        let escalate = !p.escalated
+                            && policy.escalate_to.is_some()
+                            && policy.escalate_after.is_some_and(|after| age >= after);
+                        let repeat = policy
+                            .repeat
+                            .is_some_and(|every| now.duration_since(p.notified_at) >= every);
+                        if !escalate && !repeat {
+                            return None;
+                        }
+
+                        p.notified_at = now;
+                        p.escalated |= escalate;
+
+                        let mut alert = p.alert.clone();
+                        alert.summary = format!(
+                            "Unacknowledged for {}m (/ack {}): {}",
+                            age.as_secs() / 60,
+                            alert.key,
+                            alert.summary
+                        );
+                        Some((alert, p.policy, escalate, p.escalated))
+                    })
+                    .collect()
+            };
+
+            for (alert, policy, escalate, escalated) in due {
+                let escalate_to = self.escalations[policy].escalate_to.as_deref();
+                if escalate {
+                    warn!(
+                        "ESCALATED {} to {}",
+                        alert.key,
+                        escalate_to.unwrap_or_default()
+                    );
+                }
+
+                let mut deliveries = Vec::new();
+                for backend in &self.backends {
+                    if !escalated && Some(backend.channel()) == escalate_to {
+                        continue;
+                    }
+                    deliveries.push(self.deliver(backend, &alert, false).await);
+                }
+
+                let action = if escalate { "escalate" } else { "renotify" };
+                self.audit(&alert, action, deliveries).await;
+            }
+        }
+    }
+}
+
+// an informational message sent as is, without leaving an incident open
+async fn send_summary(backend: &Backend, alert: &Alert) -> Result<(), AlertError> {
+    let summary = &alert.summary;
+    match backend {
+        Backend::PagerDuty(pd) => match pd.trigger(alert, summary).await {
+            Ok(_) => pd.resolve(alert).await,
+            Err(e) => Err(e),
+        },
+        Backend::Ntfy(ntfy) => ntfy.trigger(alert, summary).await,
+        Backend::Pushover(po) => po.trigger(alert, summary).await,
+        #[cfg(feature = "telegram")]
+        Backend::Telegram(tg) => tg.trigger(alert, summary).await,
+        Backend::Webhook(wh) => wh.trigger(alert, summary).await,
+    }
+}
+
+fn delivery(channel: &str, result: Result<(), AlertError>) -> Delivery {
+    Delivery {
+        channel: channel.to_string(),
+        ok: result.is_ok(),
+        error: result.err().map(|e| e.to_string()),
+        queued: false,
+    }
+}
+
+// read a minimum severity from `var`, falling back to `default` when unset or invalid
+fn min_severity_from_env(var: &str, default: Severity) -> Severity {
+    match env::var(var) {
+        Ok(val) => match val.parse() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Invalid {}, using {:?}: {}", var, default, e);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+// per rule cooldowns from ALERT_COOLDOWN_<RULE>_SECS,
+// e.g. ALERT_COOLDOWN_PRICE_BAND_EXIT_SECS for rule "price_band_exit"
+fn rule_cooldowns_from_env() -> HashMap<String, Duration> {
+    env::vars()
+        .filter_map(|(var, val)| {
+            let rule = var
+                .strip_prefix("ALERT_COOLDOWN_")?
+                .strip_suffix("_SECS")?
+                .to_ascii_lowercase();
+            let secs = val.parse().ok()?;
+            Some((rule, Duration::from_secs(secs)))
+        })
+        .collect()
+}
+
+fn duration_from_env(var: &str) -> Option<Duration> {
+    env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+}
+
+// register every ALERT_TEMPLATE_<NAME> variable as template "<name>",
+// e.g. ALERT_TEMPLATE_STALLED or ALERT_TEMPLATE_STALLED_NTFY
+#[cfg(feature = "templates")]
+fn templates_from_env() -> Handlebars<'static> {
+    let mut templates = Handlebars::new();
+    // messages are plain text, not HTML
+    templates.register_escape_fn(handlebars::no_escape);
+
+    for (var, template) in env::vars() {
+        let Some(name) = var.strip_prefix("ALERT_TEMPLATE_") else {
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        match templates.register_template_string(&name, template) {
+            Ok(_) => info!("Loaded alert template {}", name),
+            Err(e) => error!("Invalid alert template {}: {}", var, e),
+        }
+    }
+
+    templates
+}
@@ -0,0 +1,76 @@
+use super::{Alert, Severity};
+use crate::config::EscalationRule;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// re-notification and escalation policy for alerts that need an acknowledgement
+pub struct Escalation {
+    rule: Option<String>,
+    min_severity: Severity,
+    pub repeat: Option<Duration>,
+    pub escalate_after: Option<Duration>,
+    // channel that only hears about the alert once it is escalated
+    pub escalate_to: Option<String>,
+}
+
+impl Escalation {
+    pub fn from_config(config: &EscalationRule) -> Option<Self> {
+        if config.repeat_secs.is_none() && config.escalate_after_secs.is_none() {
+            warn!(
+                "Ignoring escalation rule for {}: neither repeat_secs nor escalate_after_secs is set",
+                config.rule.as_deref().unwrap_or("every rule")
+            );
+            return None;
+        }
+
+        Some(Escalation {
+            rule: config.rule.clone(),
+            min_severity: config.min_severity,
+            repeat: config.repeat_secs.map(Duration::from_secs),
+            escalate_after: config.escalate_after_secs.map(Duration::from_secs),
+            escalate_to: config.escalate_to.as_ref().map(|c| c.to_ascii_lowercase()),
+        })
+    }
+
+    pub fn applies(&self, alert: &Alert) -> bool {
+        alert.severity >= self.min_severity && self.rule.as_ref().is_none_or(|r| *r == alert.rule)
+    }
+}
+
+// a fired alert under an escalation policy, kept until its condition resolves
+pub struct Pending {
+    pub alert: Alert,
+    // index into the alerter's escalation policies
+    pub policy: usize,
+    pub fired_at: Instant,
+    pub notified_at: Instant,
+    pub escalated: bool,
+    pub acked: bool,
+}
+
+impl Pending {
+    pub fn new(alert: Alert, policy: usize) -> Self {
+        let now = Instant::now();
+        Pending {
+            alert,
+            policy,
+            fired_at: now,
+            notified_at: now,
+            escalated: false,
+            acked: false,
+        }
+    }
+}
+
+// an alert still waiting for an acknowledgement, as listed by the API and the bot
+#[derive(Debug, Serialize)]
+pub struct Unacknowledged {
+    pub key: String,
+    pub rule: String,
+    pub pool: String,
+    pub severity: String,
+    pub summary: String,
+    pub age_secs: u64,
+    pub escalated: bool,
+}
@@ -0,0 +1,100 @@
+use super::{Alert, AlertError, Severity};
+use tracing::error;
+
+// ntfy publisher: https://docs.ntfy.sh/publish/
+// works with the public ntfy.sh server or a self-hosted instance
+pub struct Ntfy {
+    client: reqwest::Client,
+    topic_url: String,
+    token: Option<String>,
+    min_severity: Severity,
+}
+
+impl Ntfy {
+    pub fn new(
+        client: reqwest::Client,
+        server: String,
+        topic: String,
+        token: Option<String>,
+        min_severity: Severity,
+    ) -> Self {
+        Ntfy {
+            client,
+            topic_url: format!("{}/{}", server.trim_end_matches('/'), topic),
+            token,
+            min_severity,
+        }
+    }
+
+    pub async fn trigger(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        self.publish(
+            format!("{:?} alert", alert.severity),
+            message.to_string(),
+            ntfy_priority(alert.severity),
+            "warning",
+        )
+        .await
+    }
+
+    pub async fn resolve(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        self.publish(
+            "Resolved".to_string(),
+            message.to_string(),
+            2,
+            "white_check_mark",
+        )
+        .await
+    }
+
+    async fn publish(
+        &self,
+        title: String,
+        message: String,
+        priority: u8,
+        tags: &str,
+    ) -> Result<(), AlertError> {
+        let mut req = self
+            .client
+            .post(&self.topic_url)
+            .header("Title", title)
+            .header("Priority", priority.to_string())
+            .header("Tags", tags)
+            .body(message);
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+
+        let res = match req.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to reach ntfy: {}", e);
+                return Err(AlertError::DeliveryFailed("ntfy"));
+            }
+        };
+
+        if !res.status().is_success() {
+            error!("ntfy rejected message with status {}", res.status());
+            return Err(AlertError::DeliveryFailed("ntfy"));
+        }
+
+        Ok(())
+    }
+}
+
+// ntfy priorities range from 1 (min) to 5 (max/urgent)
+fn ntfy_priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 2,
+        Severity::Warning => 3,
+        Severity::Error => 4,
+        Severity::Critical => 5,
+    }
+}
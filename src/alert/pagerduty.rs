@@ -0,0 +1,87 @@
+use super::{Alert, AlertError, Severity};
+use serde_json::json;
+use tracing::error;
+
+const EVENTS_V2_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+// PagerDuty Events API v2 backend: https://developer.pagerduty.com/docs/events-api-v2/overview/
+// the alert key is used as dedup_key, so a condition that fires repeatedly
+// stays a single incident and the resolve event closes it
+pub struct PagerDuty {
+    client: reqwest::Client,
+    routing_key: String,
+    min_severity: Severity,
+}
+
+impl PagerDuty {
+    pub fn new(client: reqwest::Client, routing_key: String, min_severity: Severity) -> Self {
+        PagerDuty {
+            client,
+            routing_key,
+            min_severity,
+        }
+    }
+
+    pub async fn trigger(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        let body = json!({
+            "routing_key": self.routing_key,
+            "event_action": "trigger",
+            "dedup_key": alert.key,
+            "payload": {
+                "summary": message,
+                "source": alert.pool.to_string(),
+                "severity": pagerduty_severity(alert.severity),
+                "component": "uniswapv3-pool-price-tracker",
+                "custom_details": alert.vars,
+            },
+        });
+
+        self.send(body).await
+    }
+
+    pub async fn resolve(&self, alert: &Alert) -> Result<(), AlertError> {
+        // alerts below the threshold never opened an incident
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        let body = json!({
+            "routing_key": self.routing_key,
+            "event_action": "resolve",
+            "dedup_key": alert.key,
+        });
+
+        self.send(body).await
+    }
+
+    async fn send(&self, body: serde_json::Value) -> Result<(), AlertError> {
+        let res = match self.client.post(EVENTS_V2_URL).json(&body).send().await {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to reach PagerDuty: {}", e);
+                return Err(AlertError::DeliveryFailed("pagerduty"));
+            }
+        };
+
+        if !res.status().is_success() {
+            error!("PagerDuty rejected event with status {}", res.status());
+            return Err(AlertError::DeliveryFailed("pagerduty"));
+        }
+
+        Ok(())
+    }
+}
+
+// map tracker severities onto the four levels accepted by PagerDuty
+fn pagerduty_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+        Severity::Critical => "critical",
+    }
+}
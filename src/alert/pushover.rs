@@ -0,0 +1,86 @@
+use super::{Alert, AlertError, Severity};
+use tracing::error;
+
+const MESSAGES_URL: &str = "https://api.pushover.net/1/messages.json";
+
+// Pushover message API: https://pushover.net/api
+pub struct Pushover {
+    client: reqwest::Client,
+    app_token: String,
+    user_key: String,
+    min_severity: Severity,
+}
+
+impl Pushover {
+    pub fn new(
+        client: reqwest::Client,
+        app_token: String,
+        user_key: String,
+        min_severity: Severity,
+    ) -> Self {
+        Pushover {
+            client,
+            app_token,
+            user_key,
+            min_severity,
+        }
+    }
+
+    pub async fn trigger(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        self.send(
+            format!("{:?} alert", alert.severity),
+            message,
+            pushover_priority(alert.severity),
+        )
+        .await
+    }
+
+    pub async fn resolve(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        self.send("Resolved".to_string(), message, -1).await
+    }
+
+    async fn send(&self, title: String, message: &str, priority: i8) -> Result<(), AlertError> {
+        let priority = priority.to_string();
+        let form = [
+            ("token", self.app_token.as_str()),
+            ("user", self.user_key.as_str()),
+            ("title", title.as_str()),
+            ("message", message),
+            ("priority", priority.as_str()),
+        ];
+
+        let res = match self.client.post(MESSAGES_URL).form(&form).send().await {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to reach Pushover: {}", e);
+                return Err(AlertError::DeliveryFailed("pushover"));
+            }
+        };
+
+        if !res.status().is_success() {
+            error!("Pushover rejected message with status {}", res.status());
+            return Err(AlertError::DeliveryFailed("pushover"));
+        }
+
+        Ok(())
+    }
+}
+
+// Pushover priorities range from -2 to 2; 2 (emergency) requires
+// retry/expire acknowledgement parameters, so critical maps to 1
+fn pushover_priority(severity: Severity) -> i8 {
+    match severity {
+        Severity::Info => -1,
+        Severity::Warning => 0,
+        Severity::Error => 1,
+        Severity::Critical => 1,
+    }
+}
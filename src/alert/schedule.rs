@@ -0,0 +1,53 @@
+use super::Severity;
+use crate::config::QuietHoursConfig;
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+use tracing::error;
+
+// a daily period during which a channel only receives alerts at or above `bypass`
+// everything else is queued and sent as one summary when the period ends
+pub struct QuietHours {
+    pub channel: String,
+    start: NaiveTime,
+    end: NaiveTime,
+    offset: FixedOffset,
+    pub bypass: Severity,
+}
+
+impl QuietHours {
+    pub fn from_config(config: &QuietHoursConfig) -> Option<Self> {
+        let start = parse_time(&config.start)?;
+        let end = parse_time(&config.end)?;
+        let Some(offset) = FixedOffset::east_opt(config.utc_offset_minutes * 60) else {
+            error!("Invalid utc_offset_minutes {}", config.utc_offset_minutes);
+            return None;
+        };
+
+        Some(QuietHours {
+            channel: config.channel.to_ascii_lowercase(),
+            start,
+            end,
+            offset,
+            bypass: config.bypass_severity,
+        })
+    }
+
+    pub fn is_quiet(&self, now: DateTime<Utc>) -> bool {
+        let time = now.with_timezone(&self.offset).time();
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            // the period wraps around midnight, e.g. 22:00 - 07:00
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    match NaiveTime::parse_from_str(value, "%H:%M") {
+        Ok(t) => Some(t),
+        Err(e) => {
+            error!("Invalid quiet hours time {}: {}", value, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Alert, AlertError, Severity};
+use crate::telegram::TelegramClient;
+
+// sends alerts as Telegram messages to a single chat
+pub struct Telegram {
+    client: TelegramClient,
+    chat_id: String,
+    min_severity: Severity,
+}
+
+impl Telegram {
+    pub fn new(client: TelegramClient, chat_id: String, min_severity: Severity) -> Self {
+        Telegram {
+            client,
+            chat_id,
+            min_severity,
+        }
+    }
+
+    pub async fn trigger(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        let text = format!("🚨 {:?}: {}", alert.severity, message);
+        match self.client.send_message(&self.chat_id, &text).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(AlertError::DeliveryFailed("telegram")),
+        }
+    }
+
+    pub async fn resolve(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+
+        let text = format!("✅ Resolved: {}", message);
+        match self.client.send_message(&self.chat_id, &text).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(AlertError::DeliveryFailed("telegram")),
+        }
+    }
+}
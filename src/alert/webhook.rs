@@ -0,0 +1,121 @@
+use super::{Alert, AlertError, Severity};
+use crate::storage::unix_now;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use tracing::error;
+
+// generic JSON webhook, POSTs every alert and resolve to `url`
+// with a secret the body is signed so receivers can verify it came from the tracker:
+// X-Tracker-Signature is "sha256=" + hex HMAC-SHA256 of "<X-Tracker-Timestamp>.<body>"
+pub struct Webhook {
+    client: reqwest::Client,
+    url: String,
+    secret: Option<String>,
+    // extra headers sent with every request, e.g. an Authorization header
+    headers: Vec<(String, String)>,
+    min_severity: Severity,
+}
+
+impl Webhook {
+    pub fn new(
+        client: reqwest::Client,
+        url: String,
+        secret: Option<String>,
+        headers: Vec<(String, String)>,
+        min_severity: Severity,
+    ) -> Self {
+        Webhook {
+            client,
+            url,
+            secret,
+            headers,
+            min_severity,
+        }
+    }
+
+    pub async fn trigger(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+        self.post(alert, message, "trigger").await
+    }
+
+    pub async fn resolve(&self, alert: &Alert, message: &str) -> Result<(), AlertError> {
+        if alert.severity < self.min_severity {
+            return Ok(());
+        }
+        self.post(alert, message, "resolve").await
+    }
+
+    async fn post(&self, alert: &Alert, message: &str, event: &str) -> Result<(), AlertError> {
+        let timestamp = unix_now();
+        let body = json!({
+            "event": event,
+            "rule": alert.rule,
+            "key": alert.key,
+            "pool": format!("{:?}", alert.pool),
+            "severity": alert.severity,
+            "summary": alert.summary,
+            "message": message,
+            "vars": alert.vars,
+            "timestamp": timestamp,
+        })
+        .to_string();
+
+        let mut req = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            req = req.header(name, value);
+        }
+        if let Some(secret) = &self.secret {
+            req = req
+                .header("X-Tracker-Timestamp", timestamp.to_string())
+                .header(
+                    "X-Tracker-Signature",
+                    format!("sha256={}", sign(secret, timestamp, &body)),
+                );
+        }
+
+        let res = match req.body(body).send().await {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to reach webhook {}: {}", self.url, e);
+                return Err(AlertError::DeliveryFailed("webhook"));
+            }
+        };
+
+        if !res.status().is_success() {
+            error!("Webhook rejected alert with status {}", res.status());
+            return Err(AlertError::DeliveryFailed("webhook"));
+        }
+
+        Ok(())
+    }
+}
+
+fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac key");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// parse "Name: value" pairs separated by ';', as set in WEBHOOK_HEADERS
+pub fn parse_headers(value: &str) -> Vec<(String, String)> {
+    value
+        .split(';')
+        .filter(|h| !h.trim().is_empty())
+        .filter_map(|h| match h.split_once(':') {
+            Some((name, value)) => Some((name.trim().to_string(), value.trim().to_string())),
+            None => {
+                error!("Ignoring malformed webhook header {:?}", h.trim());
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,496 @@
+use crate::aggregator::AggregatorQuote;
+use crate::alert::Alerter;
+use crate::chain;
+use crate::pool::PoolEvent;
+use crate::route::Route;
+use crate::state::{Basis, CrossChainSpread, ListenerStatus, PoolSnapshot, SharedState};
+use crate::storage::{Storage, VOLATILITY_INTERVALS, parse_interval, unix_now};
+use alloy::primitives::Address;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::{Stream, StreamExt, stream};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, info, warn};
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("Failed to bind API server to {0}")]
+    BindFailed(String),
+
+    #[error("API server stopped unexpectedly")]
+    ServeFailed,
+}
+
+// state shared by all request handlers
+#[derive(Clone)]
+pub struct ApiState {
+    pub storage: Option<Storage>,
+    pub alerter: Arc<Alerter>,
+    pub state: SharedState,
+    // what the listeners saw in their pools, for /pool-events
+    pub events: broadcast::Sender<PoolEvent>,
+    // bearer token required by admin endpoints, they are closed when unset
+    pub admin_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertsQuery {
+    pool: Option<Address>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PoolEventsQuery {
+    // events of every pool by default
+    pool: Option<Address>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CandlesQuery {
+    // e.g. 30s, 5m, 1h or 1d
+    interval: Option<String>,
+    // unix timestamps, the last MAX_CANDLES intervals up to now by default
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolatilityQuery {
+    // 1h or 1d
+    interval: Option<String>,
+    // unix timestamps, everything stored by default
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceQuery {
+    block: u64,
+}
+
+// candles per request, bounds the range a single query scans
+const MAX_CANDLES: u64 = 5_000;
+
+// how often the event stream looks for pools with new swaps
+const EVENT_POLL: Duration = Duration::from_secs(1);
+
+// web dashboard, a single page without external scripts
+const DASHBOARD: &str = include_str!("../assets/dashboard.html");
+
+#[derive(Debug, Deserialize)]
+struct AckRequest {
+    key: String,
+}
+
+pub async fn serve(addr: String, state: ApiState) -> Result<(), ApiError> {
+    let app = Router::new()
+        .route("/", get(dashboard))
+        .route("/events", get(events))
+        .route("/pool-events", get(pool_events))
+        .route("/pools", get(pools))
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .route("/pools/{addr}/candles", get(candles))
+        .route("/pools/{addr}/price", get(price_at_block))
+        .route("/pools/{addr}/volatility", get(volatility))
+        .route("/spreads", get(spreads))
+        .route("/basis", get(basis))
+        .route("/routes", get(routes))
+        .route("/fee-tiers", get(fee_tiers))
+        .route("/quotes", get(quotes))
+        .route("/alerts", get(alerts))
+        .route("/alerts/unacknowledged", get(unacknowledged))
+        .route("/alerts/ack", post(ack))
+        .with_state(state);
+
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Failed to bind API server to {}: {}", addr, e);
+            return Err(ApiError::BindFailed(addr));
+        }
+    };
+
+    info!("API server listening on {}", addr);
+
+    if let Err(e) = axum::serve(listener, app).await {
+        error!("API server failed: {}", e);
+        return Err(ApiError::ServeFailed);
+    }
+    Ok(())
+}
+
+// GET /
+// live prices from /events and stored candles of the selected pool
+async fn dashboard() -> Html<&'static str> {
+    Html(DASHBOARD)
+}
+
+// GET /events
+// server-sent `pool` events carrying the snapshot of every pool with new swaps, all
+// pools once on connect
+async fn events(
+    State(state): State<ApiState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // swap count of each pool when it was last sent
+    let sent: HashMap<Address, u64> = HashMap::new();
+    let ticker = tokio::time::interval(EVENT_POLL);
+
+    let updates = stream::unfold(
+        (state.state, sent, ticker),
+        |(shared, mut sent, mut ticker)| async move {
+            ticker.tick().await;
+            let changed: Vec<PoolSnapshot> = shared
+                .read()
+                .unwrap()
+                .pools
+                .values()
+                .filter(|p| sent.get(&p.pool) != Some(&p.swaps))
+                .cloned()
+                .collect();
+            for pool in &changed {
+                sent.insert(pool.pool, pool.swaps);
+            }
+            Some((changed, (shared, sent, ticker)))
+        },
+    );
+    let events = updates
+        .flat_map(stream::iter)
+        .filter_map(|pool| async move { Event::default().event("pool").json_data(&pool).ok() })
+        .map(Ok);
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+// GET /pool-events?pool=0x...
+// server-sent events named after the PoolEvent they carry (swap, mint, burn, collect,
+// flash, initialize, price_update, liquidity_update) as they happen; a client too slow
+// to keep up skips the oldest
+async fn pool_events(
+    State(state): State<ApiState>,
+    Query(query): Query<PoolEventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = stream::unfold(state.events.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Event stream client fell behind, skipped {} events",
+                        skipped
+                    )
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = events
+        .filter(move |event| {
+            let wanted = query.pool.is_none_or(|pool| pool == event.pool());
+            async move { wanted }
+        })
+        .filter_map(
+            |event| async move { Event::default().event(event.kind()).json_data(&event).ok() },
+        )
+        .map(Ok);
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+// GET /pools
+// latest snapshot of every pool that has seen a swap, with token metadata and logos
+async fn pools(State(state): State<ApiState>) -> Response {
+    let mut pools: Vec<PoolSnapshot> = state
+        .state
+        .read()
+        .unwrap()
+        .pools
+        .values()
+        .cloned()
+        .collect();
+    pools.sort_by_key(|p| p.pool);
+    Json(pools).into_response()
+}
+
+// GET /metrics
+// pipeline latency histograms, counters and endpoint health in the Prometheus text format
+async fn metrics(State(state): State<ApiState>) -> Response {
+    let body = crate::metrics::render(&state.state.read().unwrap());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+// GET /status
+// the resolved configuration and the state of every pool listener with its last swap,
+// for dashboards and scripts
+async fn status(State(state): State<ApiState>) -> Response {
+    let shared = state.state.read().unwrap();
+    let mut listeners: Vec<(&Address, &ListenerStatus)> = shared.listeners.iter().collect();
+    listeners.sort_by_key(|(pool, _)| **pool);
+    let statuses: Vec<serde_json::Value> = listeners
+        .into_iter()
+        .map(|(pool, status)| {
+            let snapshot = shared.pools.get(pool);
+            let latency = crate::metrics::swap_latency(*pool);
+            json!({
+                "pool": pool,
+                "chain_id": status.chain_id,
+                "pair": status.pair,
+                "fee": status.fee,
+                "state": status.state,
+                "subscribed_at": status.subscribed_at,
+                "last_event_at": status.last_event_at,
+                "last_swap_at": snapshot.map(|p| p.updated_at),
+                "last_price": snapshot.map(|p| p.price),
+                "swaps": snapshot.map_or(0, |p| p.swaps),
+                "errors": status.errors,
+                "last_error": status.last_error,
+                "resubscriptions": status.resubscriptions,
+                "stalls": status.stalls,
+                "unconfirmed": status.unconfirmed,
+                "latency": latency,
+            })
+        })
+        .collect();
+    let endpoints: Vec<serde_json::Value> = crate::health::labelled(&shared.endpoints)
+        .into_iter()
+        .map(|(endpoint, health)| json!({ "endpoint": endpoint, "health": health }))
+        .collect();
+    Json(json!({
+        "config": shared.summary,
+        "endpoints": endpoints,
+        "listeners": statuses,
+    }))
+    .into_response()
+}
+
+// GET /pools/<address>/candles?interval=5m&from=<unix>&to=<unix>
+// OHLC candles and token0 volume from the stored swaps of a pool
+async fn candles(
+    State(state): State<ApiState>,
+    Path(pool): Path<Address>,
+    Query(query): Query<CandlesQuery>,
+) -> Response {
+    let Some(storage) = &state.storage else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "storage is not configured");
+    };
+    let Some(interval) = parse_interval(query.interval.as_deref().unwrap_or("5m")) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "interval must be a number followed by s, m, h or d",
+        );
+    };
+    let chain = (state.state.read().unwrap().listeners.get(&pool))
+        .and_then(|status| chain::by_id(status.chain_id));
+    if let Some(chain) = chain.filter(|chain| interval < chain.min_candle_interval()) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "interval must be at least {}s on {}",
+                chain.min_candle_interval(),
+                chain.name
+            ),
+        );
+    }
+
+    let to = query.to.unwrap_or_else(|| unix_now() + 1);
+    let from = query
+        .from
+        .unwrap_or_else(|| to.saturating_sub(interval * MAX_CANDLES));
+    if to.saturating_sub(from) / interval > MAX_CANDLES {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("range spans more than {} candles", MAX_CANDLES),
+        );
+    }
+
+    match storage.candles(pool, interval, from, to).await {
+        Ok(candles) => Json(candles).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+// GET /pools/<address>/price?block=<number>
+// price at the end of a past block from the last stored swap in or before it
+async fn price_at_block(
+    State(state): State<ApiState>,
+    Path(pool): Path<Address>,
+    Query(query): Query<PriceQuery>,
+) -> Response {
+    let Some(storage) = &state.storage else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "storage is not configured");
+    };
+
+    match storage.swap_at_block(pool, query.block).await {
+        Ok(Some(swap)) => Json(json!({
+            "pool": pool,
+            "block": query.block,
+            "price": swap.price,
+            "sqrt_price_x96": swap.sqrt_price_x96,
+            "swap_block": swap.block,
+            "swap_tx": swap.tx,
+            "timestamp": swap.timestamp,
+        }))
+        .into_response(),
+        Ok(None) => error_response(
+            StatusCode::NOT_FOUND,
+            "no stored swaps of this pool up to the block",
+        ),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+// GET /pools/<address>/volatility?interval=1d&from=<unix>&to=<unix>
+// realized volatility series of a pool, computed by backfills from the stored swaps
+async fn volatility(
+    State(state): State<ApiState>,
+    Path(pool): Path<Address>,
+    Query(query): Query<VolatilityQuery>,
+) -> Response {
+    let Some(storage) = &state.storage else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "storage is not configured");
+    };
+    let interval = parse_interval(query.interval.as_deref().unwrap_or("1d"));
+    let Some(interval) = interval.filter(|i| VOLATILITY_INTERVALS.contains(i)) else {
+        return error_response(StatusCode::BAD_REQUEST, "interval must be 1h or 1d");
+    };
+
+    let (from, to) = (query.from.unwrap_or(0), query.to.unwrap_or(u64::MAX));
+    match storage.volatility(pool, interval, from, to).await {
+        Ok(points) => Json(points).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+// GET /basis
+// latest basis of every [[basis]] rule that has seen a swap
+async fn basis(State(state): State<ApiState>) -> Response {
+    let mut basis: Vec<Basis> = state
+        .state
+        .read()
+        .unwrap()
+        .basis
+        .values()
+        .cloned()
+        .collect();
+    basis.sort_by(|a, b| (a.pool, &a.reference).cmp(&(b.pool, &b.reference)));
+    Json(basis).into_response()
+}
+
+// GET /routes
+// latest best routes of every [[routes]] pair
+async fn routes(State(state): State<ApiState>) -> Response {
+    let state = state.state.read().unwrap();
+    let mut pairs: Vec<&String> = state.routes.keys().collect();
+    pairs.sort();
+    let routes: Vec<&Route> = pairs
+        .into_iter()
+        .flat_map(|pair| &state.routes[pair])
+        .collect();
+    Json(routes).into_response()
+}
+
+// GET /quotes
+// latest [[aggregator]] comparisons
+async fn quotes(State(state): State<ApiState>) -> Response {
+    let state = state.state.read().unwrap();
+    let mut quotes: Vec<&AggregatorQuote> = state.aggregator_quotes.values().flatten().collect();
+    quotes.sort_by(|a, b| (a.pool, a.source.name()).cmp(&(b.pool, b.source.name())));
+    Json(quotes).into_response()
+}
+
+// GET /fee-tiers
+// latest fee tier comparison, empty unless [fee_tier_report] is configured
+async fn fee_tiers(State(state): State<ApiState>) -> Response {
+    Json(state.state.read().unwrap().fee_tiers.clone()).into_response()
+}
+
+// GET /spreads
+// cross-chain spreads between pools of the same pair, each pool pair once
+async fn spreads(State(state): State<ApiState>) -> Response {
+    let state = state.state.read().unwrap();
+    let mut pools: Vec<Address> = state.pools.keys().copied().collect();
+    pools.sort();
+    let spreads: Vec<CrossChainSpread> = pools
+        .into_iter()
+        .flat_map(|pool| state.cross_chain_spreads(pool))
+        .filter(|s| s.pool < s.other_pool)
+        .collect();
+    Json(spreads).into_response()
+}
+
+// GET /alerts?pool=<address>&limit=<n>
+// most recent entries of the alert audit log
+async fn alerts(State(state): State<ApiState>, Query(query): Query<AlertsQuery>) -> Response {
+    let Some(storage) = &state.storage else {
+        return error_response(StatusCode::SERVICE_UNAVAILABLE, "storage is not configured");
+    };
+
+    let limit = query.limit.unwrap_or(100).min(1000);
+    match storage.recent_alerts(query.pool, limit).await {
+        Ok(records) => Json(records).into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+// GET /alerts/unacknowledged
+// alerts under an escalation policy that are still waiting for an acknowledgement
+async fn unacknowledged(State(state): State<ApiState>) -> Response {
+    Json(state.alerter.unacknowledged()).into_response()
+}
+
+// POST /alerts/ack {"key": "<alert key>"}
+// stops re-notifications and escalation of a firing alert
+async fn ack(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<AckRequest>,
+) -> Response {
+    if let Err((status, message)) = authorized(&state, &headers) {
+        warn!("Rejected acknowledgement of {}: {}", request.key, message);
+        return error_response(status, message);
+    }
+
+    if state.alerter.ack(&request.key, "api").await {
+        Json(json!({ "acked": request.key })).into_response()
+    } else {
+        error_response(
+            StatusCode::NOT_FOUND,
+            "no unacknowledged alert with this key",
+        )
+    }
+}
+
+// admin routes are closed unless API_ADMIN_TOKEN is set; the token is compared in
+// constant time so its prefix cannot be guessed from response times
+fn authorized(state: &ApiState, headers: &HeaderMap) -> Result<(), (StatusCode, &'static str)> {
+    let Some(token) = &state.admin_token else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "admin routes are disabled, set API_ADMIN_TOKEN",
+        ));
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match given {
+        Some(given) if bool::from(given.as_bytes().ct_eq(token.as_bytes())) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "invalid admin token")),
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
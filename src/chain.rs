@@ -0,0 +1,123 @@
+mod capabilities;
+
+use alloy::primitives::{Address, address};
+use std::time::Duration;
+use thiserror::Error;
+
+pub use capabilities::{NodeCapabilities, RECENT_STATE_BLOCKS};
+
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("Unknown chain {0}, expected ethereum, arbitrum, base, optimism or polygon")]
+    UnknownChain(String),
+}
+
+// defaults for a chain the tracker knows, selected with CHAIN=<name>
+pub struct ChainPreset {
+    pub name: &'static str,
+    pub chain_id: u64,
+    pub rpc_url: &'static str,
+    pub explorer_url: &'static str,
+    // Uniswap V3 factory, used when discovery, pairs or pool specs name no factory
+    pub uniswap_v3_factory: Address,
+    // WETH/USDC 0.05% Uniswap V3 pool, tracked when no pool is configured
+    pub usd_pool: Address,
+    pub block_time: Duration,
+    // deepest reorg expected in normal operation, deeper ones are warned about
+    pub reorg_depth: u64,
+}
+
+// a pool is stale after this many blocks without a swap, but never sooner than
+// MIN_STALL_TIMEOUT: blocks on L2s are too short to be quiet for long
+const STALE_BLOCKS: u32 = 25;
+const MIN_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+// a connection delivering neither logs nor heads for this many blocks is stalled, but
+// never sooner than MIN_WS_STALL_TIMEOUT
+const WS_STALL_BLOCKS: u32 = 10;
+const MIN_WS_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub const PRESETS: &[ChainPreset] = &[
+    ChainPreset {
+        name: "ethereum",
+        chain_id: 1,
+        rpc_url: "wss://ethereum-rpc.publicnode.com",
+        explorer_url: "https://etherscan.io",
+        uniswap_v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+        usd_pool: address!("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+        block_time: Duration::from_secs(12),
+        reorg_depth: 2,
+    },
+    ChainPreset {
+        name: "arbitrum",
+        chain_id: 42161,
+        rpc_url: "wss://arbitrum-one-rpc.publicnode.com",
+        explorer_url: "https://arbiscan.io",
+        uniswap_v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+        usd_pool: address!("0xC6962004f452bE9203591991D15f6b388e09E8D0"),
+        block_time: Duration::from_millis(250),
+        reorg_depth: 0,
+    },
+    ChainPreset {
+        name: "base",
+        chain_id: 8453,
+        rpc_url: "wss://base-rpc.publicnode.com",
+        explorer_url: "https://basescan.org",
+        uniswap_v3_factory: address!("0x33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+        usd_pool: address!("0xd0b53D9277642d899DF5C87A3966A349A798F224"),
+        block_time: Duration::from_secs(2),
+        reorg_depth: 0,
+    },
+    ChainPreset {
+        name: "optimism",
+        chain_id: 10,
+        rpc_url: "wss://optimism-rpc.publicnode.com",
+        explorer_url: "https://optimistic.etherscan.io",
+        uniswap_v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+        // bridged USDC.e, the deepest USD pool on Optimism
+        usd_pool: address!("0x85149247691df622eaF1a8Bd0CaFd40BC45154a9"),
+        block_time: Duration::from_secs(2),
+        reorg_depth: 0,
+    },
+    ChainPreset {
+        name: "polygon",
+        chain_id: 137,
+        rpc_url: "wss://polygon-bor-rpc.publicnode.com",
+        explorer_url: "https://polygonscan.com",
+        uniswap_v3_factory: address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+        // bridged USDC.e
+        usd_pool: address!("0x45dDa9cb7c25131DF268515131f647d726f50608"),
+        block_time: Duration::from_secs(2),
+        // PoS checkpoints finalize late, reorgs of dozens of blocks have happened
+        reorg_depth: 32,
+    },
+];
+
+impl ChainPreset {
+    // default for STALL_TIMEOUT_SECS on this chain
+    pub fn stall_timeout(&self) -> Duration {
+        (self.block_time * STALE_BLOCKS).max(MIN_STALL_TIMEOUT)
+    }
+
+    // default for WS_STALL_SECS on this chain
+    pub fn ws_stall_timeout(&self) -> Duration {
+        (self.block_time * WS_STALL_BLOCKS).max(MIN_WS_STALL_TIMEOUT)
+    }
+
+    // shortest candle interval in seconds, a bucket shorter than a block would mostly
+    // hold no swap at all
+    pub fn min_candle_interval(&self) -> u64 {
+        (self.block_time.as_millis().div_ceil(1000) as u64).max(1)
+    }
+}
+
+// preset of the chain with this id
+pub fn by_id(chain_id: u64) -> Option<&'static ChainPreset> {
+    PRESETS.iter().find(|p| p.chain_id == chain_id)
+}
+
+// preset by name (case insensitive) or chain id
+pub fn preset(name: &str) -> Option<&'static ChainPreset> {
+    PRESETS
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name) || p.chain_id.to_string() == name)
+}
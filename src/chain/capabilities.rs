@@ -0,0 +1,77 @@
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use alloy::rpc::types::{BlockId, BlockNumberOrTag};
+use tracing::{info, warn};
+
+// blocks of state a full node keeps, older state needs an archive node
+pub const RECENT_STATE_BLOCKS: u64 = 128;
+
+// what a node serves beyond the latest blocks, probed once per endpoint so that
+// features relying on it are turned off up front instead of failing mid-run
+#[derive(Debug, Clone, Copy)]
+pub struct NodeCapabilities {
+    // state of old blocks, for reading a pool's price at a past block
+    pub archive: bool,
+    // blocks back to the first one, nodes that expired old history cannot backfill them
+    pub history: bool,
+}
+
+impl NodeCapabilities {
+    pub async fn probe<P: Provider>(provider: &P) -> Self {
+        // full nodes answer with "missing trie node" or similar for pruned state
+        let archive = provider
+            .get_balance(Address::ZERO)
+            .block_id(BlockId::number(1))
+            .await
+            .is_ok();
+        let history = matches!(
+            provider
+                .get_block_by_number(BlockNumberOrTag::Number(1))
+                .await,
+            Ok(Some(_))
+        );
+        NodeCapabilities { archive, history }
+    }
+
+    // whether logs and headers of `block` can still be fetched
+    pub async fn serves_block<P: Provider>(&self, provider: &P, block: u64) -> bool {
+        self.history
+            || matches!(
+                provider
+                    .get_block_by_number(BlockNumberOrTag::Number(block))
+                    .await,
+                Ok(Some(_))
+            )
+    }
+
+    pub fn describe(&self) -> String {
+        let state = if self.archive {
+            "archive state"
+        } else {
+            "recent state only"
+        };
+        let history = if self.history {
+            "full history"
+        } else {
+            "expired history"
+        };
+        format!("{}, {}", state, history)
+    }
+
+    // log what the node is missing and which features are off because of it
+    pub fn report(&self, url: &str) {
+        info!("RPC {} serves {}", url, self.describe());
+        if !self.archive {
+            warn!(
+                "RPC {} is not an archive node, prices at past blocks come from stored swaps only",
+                url
+            );
+        }
+        if !self.history {
+            warn!(
+                "RPC {} does not serve old blocks, backfill them with --subgraph instead",
+                url
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::rules::RulesCheckpoint;
+use crate::storage::unix_now;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info};
+
+// rolling stats of one pool that would otherwise start from zero after a restart
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PoolCheckpoint {
+    pub swaps: u64,
+    // token0 traded by each swap of the last 24 hours, by unix timestamp
+    pub volume: VecDeque<(u64, f64)>,
+    pub rules: RulesCheckpoint,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckpointFile {
+    saved_at: u64,
+    pools: HashMap<Address, PoolCheckpoint>,
+}
+
+// latest checkpoint of every pool listener, written to CHECKPOINT_PATH periodically
+// and read back once when the tracker starts
+pub struct Checkpoints {
+    path: String,
+    interval: Duration,
+    pools: Mutex<HashMap<Address, PoolCheckpoint>>,
+}
+
+impl Checkpoints {
+    // a missing or unreadable file starts every pool from scratch
+    pub fn load(path: String, interval: Duration) -> Self {
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<CheckpointFile>(&content) {
+                Ok(file) => {
+                    info!(
+                        "Restoring stats of {} pools from {}, saved {}s ago",
+                        file.pools.len(),
+                        path,
+                        unix_now().saturating_sub(file.saved_at)
+                    );
+                    file
+                }
+                Err(e) => {
+                    error!("Failed to parse checkpoint {}: {}", path, e);
+                    CheckpointFile::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CheckpointFile::default(),
+            Err(e) => {
+                error!("Failed to read checkpoint {}: {}", path, e);
+                CheckpointFile::default()
+            }
+        };
+
+        Checkpoints {
+            path,
+            interval,
+            pools: Mutex::new(file.pools),
+        }
+    }
+
+    // how often listeners hand in their stats
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // pools with a checkpoint
+    pub fn pool_count(&self) -> usize {
+        self.pools.lock().unwrap().len()
+    }
+
+    pub fn get(&self, pool: Address) -> Option<PoolCheckpoint> {
+        self.pools.lock().unwrap().get(&pool).cloned()
+    }
+
+    pub fn update(&self, pool: Address, checkpoint: PoolCheckpoint) {
+        self.pools.lock().unwrap().insert(pool, checkpoint);
+    }
+
+    // write to a temporary file first, a crash mid-write keeps the previous checkpoint
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = {
+            let pools = self.pools.lock().unwrap();
+            let file = CheckpointFile {
+                saved_at: unix_now(),
+                pools: pools.clone(),
+            };
+            serde_json::to_vec(&file)?
+        };
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+// save the checkpoints every interval
+pub async fn run_checkpoints(checkpoints: Arc<Checkpoints>) {
+    let mut interval = tokio::time::interval(checkpoints.interval);
+    // the first tick fires immediately, before any listener reported
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let store = checkpoints.clone();
+        match tokio::task::spawn_blocking(move || store.save()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to write checkpoint {}: {}", checkpoints.path, e),
+            Err(e) => error!("Checkpoint writer panicked: {}", e),
+        }
+    }
+}
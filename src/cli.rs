@@ -0,0 +1,225 @@
+use alloy::primitives::{Address, B256};
+use chrono::NaiveDate;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+
+// without a subcommand the tracker starts listening to the configured pools
+#[derive(Debug, Parser)]
+#[command(version, about = "Real-time Uniswap V3 pool price tracker")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Show a live table of the pools instead of log lines, logs go to TUI_LOG_PATH
+    #[arg(long, conflicts_with = "daemon")]
+    pub tui: bool,
+    /// Run in the background, detached from the terminal
+    #[arg(long)]
+    pub daemon: bool,
+    /// Where --daemon writes its process id
+    #[arg(long, default_value = "tracker.pid", requires = "daemon")]
+    pub pid_file: String,
+    /// Override a config file setting, e.g. `--set writer.batch_size=1000`; wins over
+    /// TRACKER_ environment variables
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub overrides: Vec<String>,
+    /// Where --daemon writes its logs
+    #[arg(long, default_value = "tracker.log", requires = "daemon")]
+    pub log_file: String,
+    /// Log filter in the RUST_LOG syntax, e.g. `warn` or
+    /// `info,uniswapv3_pool_price_tracker::pool=debug`; overrides RUST_LOG
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+    /// Only log alerts, warnings and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Also log raw swap details, -vv adds RPC diagnostics
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+}
+
+impl Cli {
+    // log filter for -q and -v, None without either
+    pub fn verbosity_filter(&self) -> Option<&'static str> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some("warn,uniswapv3_pool_price_tracker::alert=info"),
+            (false, 0) => None,
+            (false, 1) => Some("info,uniswapv3_pool_price_tracker=debug"),
+            (false, _) => Some("info,uniswapv3_pool_price_tracker=trace,alloy=debug"),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Alert related tools
+    Alert {
+        #[command(subcommand)]
+        action: AlertCommand,
+    },
+    /// Store the swaps of the configured pools over a block or date range in DATABASE_PATH
+    Backfill {
+        #[arg(long, required_unless_present = "from", conflicts_with = "from")]
+        from_block: Option<u64>,
+        /// Defaults to the latest block
+        #[arg(long, conflicts_with = "to")]
+        to_block: Option<u64>,
+        /// First day to backfill (UTC), e.g. 2024-01-01
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Day to stop before (UTC), e.g. 2024-02-01
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Read the swaps from this Uniswap V3 schema subgraph instead of eth_getLogs
+        #[arg(long, requires = "from", conflicts_with = "to_block")]
+        subgraph: Option<String>,
+    },
+    /// Validate the config, RPC endpoints, database and pools without listening
+    Check {
+        /// Also send a test alert through every notification backend
+        #[arg(long)]
+        notify: bool,
+    },
+    /// Print the current price of every configured pool and exit
+    Snapshot {
+        /// One JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run the swaps stored in DATABASE_PATH through the price calculation and alert rules
+    Replay {
+        /// First day to replay (UTC), defaults to the first stored swap
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Day to stop before (UTC), defaults to the last stored swap
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Send the alerts to the notification backends instead of only logging them
+        #[arg(long)]
+        notify: bool,
+    },
+    /// Write the stored swaps or candles of a pool to a file
+    Export {
+        #[arg(long)]
+        pool: Address,
+        /// Unix timestamp, defaults to the first stored swap
+        #[arg(long)]
+        from: Option<u64>,
+        /// Unix timestamp (exclusive), defaults to the last stored swap
+        #[arg(long)]
+        to: Option<u64>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Candles of this interval (e.g. 1m, 1h, 1d) instead of single swaps
+        #[arg(long)]
+        candles: Option<String>,
+        /// Defaults to <pool>-swaps.<format> or <pool>-<interval>.<format>
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print the stored prices of a pool, or candles built from them
+    Query {
+        #[arg(long)]
+        pool: Address,
+        /// Unix timestamp, defaults to a day before --to
+        #[arg(long)]
+        from: Option<u64>,
+        /// Unix timestamp (exclusive), defaults to now
+        #[arg(long)]
+        to: Option<u64>,
+        /// Candles of this interval (e.g. 1m, 1h, 1d) instead of single swaps
+        #[arg(long)]
+        agg: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Store a CSV price series, e.g. exchange klines, to compare pools against
+    Import {
+        /// Name the series is stored and compared under, e.g. binance-ethusdc-1h
+        #[arg(long)]
+        series: String,
+        #[arg(long)]
+        file: String,
+        /// Zero based column of the unix timestamp (s, ms or µs)
+        #[arg(long, default_value_t = 0)]
+        time_column: usize,
+        /// Zero based column of the price, the close of Binance klines by default
+        #[arg(long, default_value_t = 4)]
+        price_column: usize,
+    },
+    /// Summaries of the swaps stored in DATABASE_PATH
+    Report {
+        #[command(subcommand)]
+        report: ReportCommand,
+    },
+    /// Print the price of a configured pool at a past block, or around a transaction
+    PriceAt {
+        #[arg(long)]
+        pool: Address,
+        #[arg(long, required_unless_present = "tx", conflicts_with = "tx")]
+        block: Option<u64>,
+        /// Prices at the end of the block before the transaction's and of its own
+        #[arg(long)]
+        tx: Option<B256>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the time weighted average price of a configured pool
+    Twap {
+        #[arg(long)]
+        pool: Address,
+        /// Averaging window, e.g. 30m, 1h, 1d
+        #[arg(long, default_value = "1h")]
+        window: String,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+    /// Columnar Apache Parquet
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ReportCommand {
+    /// Traded volume per configured pool and in total, in tokens and USD
+    Volume {
+        /// Period up to now, e.g. 1h, 24h, 7d
+        #[arg(long, default_value = "24h")]
+        period: String,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Basis and tracking error of a pool against an imported price series
+    Compare {
+        #[arg(long)]
+        pool: Address,
+        #[arg(long)]
+        series: String,
+        /// Unix timestamp, defaults to the first stored swap
+        #[arg(long)]
+        from: Option<u64>,
+        /// Unix timestamp (exclusive), defaults to now
+        #[arg(long)]
+        to: Option<u64>,
+        /// Intervals the prices are aligned in, e.g. 5m, 1h, 1d
+        #[arg(long, default_value = "1h")]
+        interval: String,
+        /// The series is quoted in token0 per token1
+        #[arg(long)]
+        invert: bool,
+        /// Also every aligned interval, as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AlertCommand {
+    /// Send a synthetic test alert through every configured notification backend
+    Test,
+}
@@ -0,0 +1,994 @@
+use crate::alert::Severity;
+use alloy::primitives::{Address, B256};
+use figment::providers::{Env, Format, Serialized, Toml};
+use figment::value::{Dict, Map, Value};
+use figment::{Figment, Metadata, Profile, Provider};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Path;
+use std::sync::OnceLock;
+use thiserror::Error;
+use tracing::{error, info};
+
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+// prefix of environment variables that override config settings
+const ENV_PREFIX: &str = "TRACKER_";
+// settings that were environment variables of their own before the config file, still
+// read under those names: RPC_URL sets rpc_url
+const LEGACY_VARS: &[&str] = &[
+    "RPC_URL",
+    "RPC_FALLBACK_URLS",
+    "RPC_PROBE_INTERVAL_SECS",
+    "CHAIN",
+    "EXPLORER_URL",
+    "CONFIRMATIONS",
+    "STALL_TIMEOUT_SECS",
+    "WS_STALL_SECS",
+    "REFERENCE_REFRESH_SECS",
+    "DATABASE_PATH",
+    "CHECKPOINT_PATH",
+    "CHECKPOINT_INTERVAL_SECS",
+    "API_ADDR",
+    "TOKEN_LIST_URL",
+    "TOKEN_LIST_CACHE",
+    "TUI_LOG_PATH",
+    "SHUTDOWN_TIMEOUT_SECS",
+];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file {0}")]
+    ReadFailed(String),
+
+    #[error("Failed to parse config {0}: {1}")]
+    ParseFailed(String, String),
+
+    #[error("Invalid config override {0}, expected key=value")]
+    InvalidOverride(String),
+}
+
+// optional TOML config file, read from CONFIG_PATH, or ./config.toml when it exists
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // websocket endpoint, the chain preset's public one by default
+    pub rpc_url: Option<String>,
+    // endpoints of the same chain backing up rpc_url, a list or separated by commas
+    #[serde(deserialize_with = "comma_list")]
+    pub rpc_fallback_urls: Vec<String>,
+    pub rpc_probe_interval_secs: Option<u64>,
+    // preset name or chain id, ethereum by default
+    #[serde(deserialize_with = "name_or_id")]
+    pub chain: Option<String>,
+    pub explorer_url: Option<String>,
+    // blocks a log waits for, the chain's usual reorg depth by default
+    pub confirmations: Option<u64>,
+    pub stall_timeout_secs: Option<u64>,
+    pub ws_stall_secs: Option<u64>,
+    pub reference_refresh_secs: Option<u64>,
+    // sinks, each disabled when unset
+    pub database_path: Option<String>,
+    pub checkpoint_path: Option<String>,
+    pub checkpoint_interval_secs: Option<u64>,
+    pub api_addr: Option<String>,
+    pub token_list_url: Option<String>,
+    pub token_list_cache: Option<String>,
+    pub tui_log_path: Option<String>,
+    pub shutdown_timeout_secs: Option<u64>,
+    pub pools: Vec<PoolEntry>,
+    pub price_bands: Vec<PriceBand>,
+    pub price_change: Vec<PriceChangeRule>,
+    pub volatility: Vec<VolatilityRule>,
+    pub liquidity_drop: Vec<LiquidityDropRule>,
+    pub stale: Vec<StaleRule>,
+    pub deviation: Vec<DeviationRule>,
+    pub basis: Vec<BasisRule>,
+    pub arbitrage: Vec<ArbitrageRule>,
+    pub triangular: Option<TriangularRule>,
+    pub routes: Vec<RouteRule>,
+    pub aggregator: Vec<AggregatorRule>,
+    pub fee_tier_report: Option<FeeTierReportConfig>,
+    pub retention: Option<RetentionConfig>,
+    pub large_mint: Vec<LargeMintRule>,
+    pub tick_cross: Vec<TickCrossRule>,
+    pub quiet_hours: Vec<QuietHoursConfig>,
+    pub escalation: Vec<EscalationRule>,
+    pub discovery: Vec<DiscoveryRule>,
+    pub pairs: Vec<PairRule>,
+    pub subgraph: Vec<SubgraphRule>,
+    pub tokens: Vec<TokenOverride>,
+    pub reports: Vec<ReportRule>,
+    pub writer: WriterConfig,
+    pub output: Vec<OutputRule>,
+    pub format: FormatConfig,
+}
+
+// protocol spoken by a tracked pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    #[default]
+    UniswapV3,
+    UniswapV2,
+    UniswapV4,
+    SushiswapV3,
+    PancakeswapV3,
+    Algebra,
+    Slipstream,
+    Curve,
+    BalancerWeighted,
+}
+
+// a tracked pool, POOLS adds Uniswap V3 pools given by address or pair spec
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PoolEntry {
+    // the pool contract, or the PoolManager for V4 (defaults to the mainnet one)
+    pub address: Option<Address>,
+    // V3 style pools can be given as "WETH/USDC@0.05%" instead of an address,
+    // symbols are looked up in the token list
+    pub pair: Option<String>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    // swap fee in hundredths of a bip for protocols that do not expose it on-chain,
+    // defaults to 3000 (0.3%) for V2 pairs
+    pub fee: Option<u32>,
+    // V4 pools are identified by their PoolId; the rest of the pool key is looked up
+    // from the Initialize event (searched from `from_block`) unless given here
+    pub pool_id: Option<B256>,
+    pub currency0: Option<Address>,
+    pub currency1: Option<Address>,
+    pub tick_spacing: Option<i32>,
+    pub hooks: Option<Address>,
+    pub from_block: Option<u64>,
+    // indices of the two tokens to track in multi-token pools (Curve, Balancer),
+    // priced as coins[1] per coins[0]; defaults to [0, 1]
+    pub coins: Option<[u8; 2]>,
+    // factory the pool must have been deployed by, for V3 style pools on chains or forks
+    // whose factory is not built in
+    pub factory: Option<Address>,
+    // websocket endpoint of the chain the pool lives on, defaults to RPC_URL
+    pub rpc_url: Option<String>,
+}
+
+// token metadata to use instead of what the token contract reports, for tokens
+// with broken or missing symbol() / decimals()
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenOverride {
+    pub address: Address,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+// alert when a pool's price enters or leaves [lower, upper]
+// price is quoted as token1 per token0 unless `inverse` is set
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceBand {
+    pub pool: Address,
+    pub name: Option<String>,
+    pub lower: f64,
+    pub upper: f64,
+    #[serde(default)]
+    pub inverse: bool,
+    // price has to move this far outside the band before it counts as an exit,
+    // so a price hovering around a bound does not flap
+    #[serde(default)]
+    pub hysteresis_bps: f64,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// alert when the price moves more than `change_pct` percent within `window_secs`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceChangeRule {
+    // applies to every pool without a pool specific rule when omitted
+    pub pool: Option<Address>,
+    #[serde(default = "default_volatility_window")]
+    pub window_secs: u64,
+    pub change_pct: f64,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// alert when a pool becomes turbulent, either condition can be configured:
+// realized volatility over `window_secs` above `max_volatility_bps`, or
+// `consecutive_swaps` swaps in a row each moving the price more than `move_bps`
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolatilityRule {
+    // applies to every pool without a pool specific rule when omitted
+    pub pool: Option<Address>,
+    #[serde(default = "default_volatility_window")]
+    pub window_secs: u64,
+    pub max_volatility_bps: Option<f64>,
+    pub consecutive_swaps: Option<u32>,
+    pub move_bps: Option<f64>,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// alert when in-range liquidity falls more than `drop_pct` percent
+// below its highest value within the last `window_secs`
+#[derive(Debug, Clone, Deserialize)]
+pub struct LiquidityDropRule {
+    // applies to every pool without a pool specific rule when omitted
+    pub pool: Option<Address>,
+    #[serde(default = "default_liquidity_window")]
+    pub window_secs: u64,
+    pub drop_pct: f64,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// alert when a pool has had no swaps for `after_secs`
+// with `poll_slot0` the last known price is checked against the pool contract
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaleRule {
+    // applies to every pool without a pool specific rule when omitted
+    pub pool: Option<Address>,
+    pub after_secs: u64,
+    #[serde(default)]
+    pub poll_slot0: bool,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// alert when the pool price deviates from a reference price by more than
+// `max_deviation_bps` for longer than `for_secs`
+// `reference` names the source, e.g. "chainlink:<feed address>", "binance:<symbol>"
+// or "coinbase:<product>"
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviationRule {
+    pub pool: Address,
+    pub reference: String,
+    // compare token0 per token1 instead of token1 per token0
+    #[serde(default)]
+    pub inverse: bool,
+    pub max_deviation_bps: f64,
+    #[serde(default)]
+    pub for_secs: u64,
+    // reference prices older than this are ignored
+    #[serde(default = "default_max_reference_age")]
+    pub max_reference_age_secs: u64,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// log the pool's basis against a reference after every swap, usually a centralized
+// exchange ticker like "binance:ETHUSDC" or "coinbase:ETH-USD"
+#[derive(Debug, Clone, Deserialize)]
+pub struct BasisRule {
+    pub pool: Address,
+    pub reference: String,
+    // compare token0 per token1 instead of token1 per token0
+    #[serde(default)]
+    pub inverse: bool,
+    // reference prices older than this are ignored
+    #[serde(default = "default_max_reference_age")]
+    pub max_reference_age_secs: u64,
+}
+
+// alert when pools trading the same pair (e.g. different fee tiers) diverge by more
+// than their combined fees plus the gas cost of the two swaps
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArbitrageRule {
+    pub pools: Vec<Address>,
+    // gas used by both legs of the arbitrage
+    #[serde(default = "default_arbitrage_gas_units")]
+    pub gas_units: u64,
+    // minimum profit in token1 after fees and gas
+    #[serde(default)]
+    pub min_profit: f64,
+    // gas cost is converted into token1 through the pool price when the pair contains
+    // the wrapped native token, otherwise through a fixed `native_price` in token1
+    pub wrapped_native: Option<Address>,
+    pub native_price: Option<f64>,
+    #[serde(default = "default_arbitrage_severity")]
+    pub severity: Severity,
+}
+
+// alert when trading around a cycle of three tracked pools (e.g. WETH -> USDC -> WBTC
+// -> WETH) returns more than `min_profit_bps` after the pools' fees
+#[derive(Debug, Clone, Deserialize)]
+pub struct TriangularRule {
+    #[serde(default)]
+    pub min_profit_bps: f64,
+    #[serde(default = "default_arbitrage_severity")]
+    pub severity: Severity,
+}
+
+// best way to sell each of `sizes` of the first token of `pair` (e.g. "WETH/USDC")
+// across the tracked pools of the pair, splitting the order when that pays more
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteRule {
+    pub pair: String,
+    pub sizes: Vec<f64>,
+    // chain of the pools to route through, defaults to CHAIN
+    pub chain_id: Option<u64>,
+    #[serde(default = "default_route_refresh")]
+    pub refresh_secs: u64,
+}
+
+// compare what a pool pays for selling `sizes` token0 against a DEX aggregator quote
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggregatorRule {
+    pub pool: Address,
+    pub source: AggregatorSource,
+    pub sizes: Vec<f64>,
+    #[serde(default = "default_aggregator_refresh")]
+    pub refresh_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum AggregatorSource {
+    #[serde(rename = "0x")]
+    ZeroEx,
+    #[serde(rename = "1inch")]
+    OneInch,
+}
+
+impl AggregatorSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            AggregatorSource::ZeroEx => "0x",
+            AggregatorSource::OneInch => "1inch",
+        }
+    }
+}
+
+// periodic comparison of the fee tiers of every pair tracked in more than one tier
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeTierReportConfig {
+    #[serde(default = "default_fee_tier_interval")]
+    pub interval_secs: u64,
+    // token0 amounts whose trading cost is compared
+    #[serde(default = "default_fee_tier_sizes")]
+    pub sizes: Vec<f64>,
+}
+
+// how long stored swaps are kept at full detail; older swaps are compacted into one
+// minute candles, older minute candles into daily ones, which are kept forever
+// a period that is not set keeps its data forever
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetentionConfig {
+    pub swap_days: Option<u64>,
+    pub minute_candle_days: Option<u64>,
+    #[serde(default = "default_compaction_interval")]
+    pub compact_interval_secs: u64,
+}
+
+// summary of every tracked pool over the last day or week (price change, high/low,
+// volume, biggest swaps) sent through the notification channels at `at` (HH:MM in
+// `utc_offset_minutes`), every day or every `weekday` (e.g. "mon")
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportRule {
+    pub period: ReportPeriod,
+    #[serde(default = "default_report_time")]
+    pub at: String,
+    // weekly reports only, defaults to Monday
+    pub weekday: Option<String>,
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    // channel names like "telegram", every configured channel when empty
+    #[serde(default)]
+    pub channels: Vec<String>,
+    // largest swaps listed per pool
+    #[serde(default = "default_biggest_swaps")]
+    pub biggest_swaps: usize,
+    // sent like an alert of this severity, so it has to reach each channel's minimum
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPeriod {
+    Daily,
+    Weekly,
+}
+
+// queue between the pool listeners and the database, swaps are inserted in batches
+// of up to `batch_size` by one writer task
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WriterConfig {
+    pub queue_size: usize,
+    pub batch_size: usize,
+    // what a listener does with a swap while the queue is full
+    pub overflow: OverflowPolicy,
+    // JSON lines file swaps are spilled to, and read back once the database caught up
+    pub spill_path: String,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        WriterConfig {
+            queue_size: 10_000,
+            batch_size: 500,
+            overflow: OverflowPolicy::Block,
+            spill_path: "swaps.spill.jsonl".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    // wait for room, the listener stalls with the database
+    Block,
+    // make room by dropping the oldest queued swap
+    DropOldest,
+    // append the swap to `spill_path`
+    Spill,
+}
+
+// how numbers are written in log lines, alert summaries and the terminal dashboard
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    pub locale: Locale,
+    // override the locale's separators, "" for no thousands separator
+    pub thousands_separator: Option<String>,
+    pub decimal_separator: Option<String>,
+    // digits after the decimal separator, by default fewer the larger a number gets
+    pub decimals: Option<usize>,
+}
+
+// thousands and decimal separators: 1,234.5 (en), 1.234,5 (de), 1 234,5 (fr),
+// 1'234.5 (ch)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Ch,
+}
+
+// which swap prices of a pool are printed as they arrive, every one by default
+// stored swaps and alert rules see every swap regardless
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OutputRule {
+    // applies to every pool without a pool specific rule when omitted
+    pub pool: Option<Address>,
+    // print at most one price per this many milliseconds, the latest one wins
+    #[serde(default)]
+    pub min_interval_ms: u64,
+    // print a price only when it moved by more than this many basis points since the
+    // last printed one
+    #[serde(default)]
+    pub min_change_bps: f64,
+    // print one line per this many seconds with the last price, swap count and token0
+    // volume of the interval instead of a line per swap, the other settings are ignored
+    pub interval_secs: Option<u64>,
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    // the pair and price with the move since the last printed price
+    #[default]
+    Price,
+    // every swap as a sentence with who traded how much, the other settings are ignored
+    Narrative,
+}
+
+// alert when a single Mint adds liquidity of more than `min_share_pct` percent
+// of the pool's in-range liquidity
+#[derive(Debug, Clone, Deserialize)]
+pub struct LargeMintRule {
+    // applies to every pool without a pool specific rule when omitted
+    pub pool: Option<Address>,
+    pub min_share_pct: f64,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// alert the first time a swap crosses one of `ticks` or `prices`, in either direction
+// prices are quoted as token1 per token0 unless `inverse` is set
+#[derive(Debug, Clone, Deserialize)]
+pub struct TickCrossRule {
+    pub pool: Address,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub ticks: Vec<i32>,
+    #[serde(default)]
+    pub prices: Vec<f64>,
+    #[serde(default)]
+    pub inverse: bool,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+}
+
+// daily period ("HH:MM" local to `utc_offset_minutes`) during which a notification
+// channel only receives alerts at or above `bypass_severity`; the rest is queued
+// and sent as one summary once the period ends
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuietHoursConfig {
+    pub channel: String,
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    #[serde(default = "default_critical_severity")]
+    pub bypass_severity: Severity,
+}
+
+// re-notify alerts at or above `min_severity` every `repeat_secs` until they are
+// acknowledged, and bring in the `escalate_to` channel once an alert has been
+// unacknowledged for `escalate_after_secs`
+#[derive(Debug, Clone, Deserialize)]
+pub struct EscalationRule {
+    // applies to every rule when omitted
+    pub rule: Option<String>,
+    #[serde(default = "default_critical_severity")]
+    pub min_severity: Severity,
+    pub repeat_secs: Option<u64>,
+    pub escalate_after_secs: Option<u64>,
+    pub escalate_to: Option<String>,
+}
+
+// start tracking pools a V3 factory creates from now on, when they match the filters
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryRule {
+    // uniswap_v3, sushiswap_v3 or pancakeswap_v3
+    #[serde(default)]
+    pub protocol: Protocol,
+    // defaults to the CHAIN's Uniswap V3 factory, other protocols to their Ethereum one
+    pub factory: Option<Address>,
+    // a new pool must contain at least one of these tokens, any pool when empty
+    #[serde(default)]
+    pub tokens: Vec<Address>,
+    // fee tiers in hundredths of a bip, any tier when empty
+    #[serde(default)]
+    pub fees: Vec<u32>,
+    // websocket endpoint of the factory's chain, defaults to RPC_URL
+    pub rpc_url: Option<String>,
+}
+
+// track a token pair in every fee tier the factory has a pool for, including
+// tiers created later
+#[derive(Debug, Clone, Deserialize)]
+pub struct PairRule {
+    pub tokens: [Address; 2],
+    // uniswap_v3, sushiswap_v3 or pancakeswap_v3
+    #[serde(default)]
+    pub protocol: Protocol,
+    // defaults to the CHAIN's Uniswap V3 factory, other protocols to their Ethereum one
+    pub factory: Option<Address>,
+    // fee tiers to look up, the protocol's standard tiers when empty
+    #[serde(default)]
+    pub fees: Vec<u32>,
+    // websocket endpoint of the factory's chain, defaults to RPC_URL
+    pub rpc_url: Option<String>,
+}
+
+// track the top pools of a subgraph by 24h volume or TVL, refreshed periodically
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubgraphRule {
+    // GraphQL endpoint of a Uniswap V3 schema subgraph, including the api key
+    pub url: String,
+    #[serde(default = "default_subgraph_top")]
+    pub top: usize,
+    #[serde(default)]
+    pub order_by: SubgraphOrder,
+    // only pools containing this token
+    pub token: Option<Address>,
+    #[serde(default = "default_subgraph_refresh")]
+    pub refresh_secs: u64,
+    // uniswap_v3, sushiswap_v3 or pancakeswap_v3
+    #[serde(default)]
+    pub protocol: Protocol,
+    // factory the pools must come from, for chains whose factory is not built in
+    pub factory: Option<Address>,
+    // websocket endpoint of the subgraph's chain, defaults to RPC_URL
+    pub rpc_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubgraphOrder {
+    // volume of the last complete UTC day
+    #[default]
+    Volume,
+    Tvl,
+}
+
+fn default_subgraph_top() -> usize {
+    10
+}
+
+fn default_subgraph_refresh() -> u64 {
+    3600
+}
+
+fn default_rule_severity() -> Severity {
+    Severity::Warning
+}
+
+fn default_volatility_window() -> u64 {
+    300
+}
+
+fn default_liquidity_window() -> u64 {
+    3600
+}
+
+fn default_max_reference_age() -> u64 {
+    300
+}
+
+fn default_critical_severity() -> Severity {
+    Severity::Critical
+}
+
+fn default_aggregator_refresh() -> u64 {
+    300
+}
+
+fn default_compaction_interval() -> u64 {
+    3600
+}
+
+fn default_report_time() -> String {
+    "08:00".to_string()
+}
+
+fn default_biggest_swaps() -> usize {
+    3
+}
+
+fn default_fee_tier_interval() -> u64 {
+    3600
+}
+
+fn default_fee_tier_sizes() -> Vec<f64> {
+    vec![1.0, 10.0, 100.0]
+}
+
+fn default_route_refresh() -> u64 {
+    60
+}
+
+fn default_arbitrage_gas_units() -> u64 {
+    300_000
+}
+
+fn default_arbitrage_severity() -> Severity {
+    Severity::Info
+}
+
+// rules that can be configured globally (no pool) and overridden per pool
+pub trait PoolScoped {
+    fn pool(&self) -> Option<Address>;
+}
+
+impl PoolScoped for PriceChangeRule {
+    fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+}
+
+impl PoolScoped for VolatilityRule {
+    fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+}
+
+impl PoolScoped for LiquidityDropRule {
+    fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+}
+
+impl PoolScoped for LargeMintRule {
+    fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+}
+
+impl PoolScoped for StaleRule {
+    fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+}
+
+impl PoolScoped for OutputRule {
+    fn pool(&self) -> Option<Address> {
+        self.pool
+    }
+}
+
+// the rules of one kind that apply to `pool`: entries for this pool override
+// the global entries, other pools keep using the global ones
+pub fn rules_for_pool<R: PoolScoped + Clone>(rules: &[R], pool: Address) -> Vec<R> {
+    let specific: Vec<R> = rules
+        .iter()
+        .filter(|r| r.pool() == Some(pool))
+        .cloned()
+        .collect();
+    if !specific.is_empty() {
+        return specific;
+    }
+
+    rules
+        .iter()
+        .filter(|r| r.pool().is_none())
+        .cloned()
+        .collect()
+}
+
+impl Config {
+    // the config file, overridden by the legacy environment variables, by TRACKER_
+    // environment variables, and by --set flags
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = match env::var("CONFIG_PATH") {
+            Ok(p) => Some(p),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Some(DEFAULT_CONFIG_PATH.to_string())
+            }
+            Err(_) => None,
+        };
+
+        let mut figment = Figment::new();
+        if let Some(path) = &path {
+            if !Path::new(path).is_file() {
+                error!("Failed to read config file {}", path);
+                return Err(ConfigError::ReadFailed(path.clone()));
+            }
+            figment = figment.merge(Toml::file_exact(path));
+        }
+        figment = figment
+            .merge(Env::raw().only(LEGACY_VARS))
+            // POOLS adds to the [[pools]] of the file
+            .admerge(Serialized::default("pools", legacy_pools()));
+
+        let env_overrides: Vec<(String, String)> = Env::prefixed(ENV_PREFIX)
+            .split("__")
+            .iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        let cli_overrides = OVERRIDES.get().cloned().unwrap_or_default();
+        let overrides = env_overrides.len() + cli_overrides.len();
+        for (name, pairs) in [
+            ("TRACKER_ environment variables", env_overrides),
+            ("--set flags", cli_overrides),
+        ] {
+            let provider = Overrides::new(name, &figment, pairs)?;
+            figment = figment.merge(provider);
+        }
+
+        let source = match (&path, overrides) {
+            (Some(path), 0) => path.clone(),
+            (Some(path), n) => format!("{} with {} overrides", path, n),
+            (None, 0) => "the environment".to_string(),
+            (None, n) => format!("the environment with {} overrides", n),
+        };
+        let config: Config = match figment.extract() {
+            Ok(c) => c,
+            Err(e) => return Err(ConfigError::ParseFailed(source, e.to_string())),
+        };
+
+        info!(
+            "Loaded config from {} ({} pools, {} price bands, {} volatility rules, {} liquidity rules)",
+            source,
+            config.pools.len(),
+            config.price_bands.len(),
+            config.volatility.len(),
+            config.liquidity_drop.len()
+        );
+
+        Ok(config)
+    }
+}
+
+// --set flags, applied over the config file and the environment
+static OVERRIDES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+// `key=value` pairs of --set, set before the config is loaded
+pub fn set_overrides(pairs: &[String]) -> Result<(), ConfigError> {
+    let mut overrides = Vec::new();
+    for pair in pairs {
+        let Some((key, value)) = pair.split_once('=') else {
+            return Err(ConfigError::InvalidOverride(pair.clone()));
+        };
+        overrides.push((key.trim().to_string(), value.to_string()));
+    }
+    let _ = OVERRIDES.set(overrides);
+    Ok(())
+}
+
+// POOLS split by commas, each an address or a spec like WETH/USDC@0.05%
+fn legacy_pools() -> Vec<BTreeMap<&'static str, String>> {
+    let pools = env::var("POOLS").unwrap_or_default();
+    pools
+        .split(',')
+        .map(str::trim)
+        .filter(|pool| !pool.is_empty())
+        .map(|pool| match pool.parse::<Address>() {
+            Ok(_) => BTreeMap::from([("address", pool.to_string())]),
+            Err(_) => BTreeMap::from([("pair", pool.to_string())]),
+        })
+        .collect()
+}
+
+// dotted `key=value` pairs set over what `figment` holds so far, a number indexes into
+// an array: pools.0.fee=500 sets the fee of the first [[pools]] entry, and the index
+// after the last entry adds one
+struct Overrides {
+    name: &'static str,
+    // the top level values the pairs touch, with the pairs applied
+    dict: Dict,
+}
+
+impl Overrides {
+    fn new(
+        name: &'static str,
+        figment: &Figment,
+        mut pairs: Vec<(String, String)>,
+    ) -> Result<Self, ConfigError> {
+        // nested keys come after their parents
+        pairs.sort();
+        let mut dict = Dict::new();
+        for (key, raw) in &pairs {
+            let top = key.split('.').next().unwrap_or_default().to_string();
+            if let (false, Ok(value)) = (dict.contains_key(&top), figment.find_value(&top)) {
+                dict.insert(top, value);
+            }
+            set_key(&mut dict, key, raw)?;
+        }
+        Ok(Overrides { name, dict })
+    }
+}
+
+impl Provider for Overrides {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(self.name)
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Ok(Profile::Default.collect(self.dict.clone()))
+    }
+}
+
+// set a dotted `key` in the dict, the value is parsed like an environment variable:
+// 1000, true, [1, 2] and { a = 1 } keep their type, anything else is a string
+fn set_key(dict: &mut Dict, key: &str, raw: &str) -> Result<(), ConfigError> {
+    let value: Value = match raw.parse() {
+        Ok(value) => value,
+        Err(never) => match never {},
+    };
+    let invalid = |part: &str, what: &str| {
+        error!("Config override {} goes into {}, which {}", key, part, what);
+        ConfigError::InvalidOverride(key.to_string())
+    };
+
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(ConfigError::InvalidOverride(key.to_string()));
+    }
+    let mut current = dict
+        .entry(parts[0].to_string())
+        .or_insert_with(|| Dict::new().into());
+    for window in parts.windows(2) {
+        let (parent, part) = (window[0], window[1]);
+        current = match current {
+            Value::Dict(_, d) => d
+                .entry(part.to_string())
+                .or_insert_with(|| Dict::new().into()),
+            Value::Array(_, entries) => {
+                let Ok(i) = part.parse::<usize>() else {
+                    return Err(invalid(parent, "is an array"));
+                };
+                if i == entries.len() {
+                    entries.push(Dict::new().into());
+                }
+                match entries.get_mut(i) {
+                    Some(entry) => entry,
+                    None => return Err(invalid(parent, "has fewer entries")),
+                }
+            }
+            _ => return Err(invalid(parent, "is not a table")),
+        };
+    }
+    *current = value;
+    Ok(())
+}
+
+// a list, or a string of items separated by commas as environment variables hold them
+fn comma_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Items(Vec<String>),
+        Joined(String),
+    }
+    Ok(match List::deserialize(deserializer)? {
+        List::Items(items) => items,
+        List::Joined(joined) => joined
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+// CHAIN=137 reads as a number, but names the chain like CHAIN=polygon
+fn name_or_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Chain {
+        Name(String),
+        Id(u64),
+    }
+    Ok(
+        Option::<Chain>::deserialize(deserializer)?.map(|chain| match chain {
+            Chain::Name(name) => name,
+            Chain::Id(id) => id.to_string(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pools(dict: &Dict) -> Vec<Value> {
+        match &dict["pools"] {
+            Value::Array(_, entries) => entries.clone(),
+            value => panic!("pools is {:?}", value),
+        }
+    }
+
+    fn find(value: &Value, key: &str) -> Option<Value> {
+        value.clone().find(key)
+    }
+
+    #[test]
+    fn set_key_creates_nested_tables() {
+        let mut dict = Dict::new();
+        set_key(&mut dict, "writer.batch_size", "1000").unwrap();
+        set_key(&mut dict, "format.locale", "de").unwrap();
+
+        let value = Value::from(dict);
+        assert_eq!(
+            find(&value, "writer.batch_size").and_then(|v| v.to_u128()),
+            Some(1000)
+        );
+        assert_eq!(
+            find(&value, "format.locale").and_then(|v| v.into_string()),
+            Some("de".to_string())
+        );
+    }
+
+    #[test]
+    fn set_key_indexes_and_appends_to_arrays() {
+        let mut dict = Dict::new();
+        dict.insert("pools".to_string(), "[{ fee = 3000 }]".parse().unwrap());
+        set_key(&mut dict, "pools.0.fee", "500").unwrap();
+        set_key(&mut dict, "pools.1.pair", "WETH/USDC@0.05%").unwrap();
+
+        let pools = pools(&dict);
+        assert_eq!(pools.len(), 2);
+        assert_eq!(find(&pools[0], "fee").and_then(|v| v.to_u128()), Some(500));
+        assert_eq!(
+            find(&pools[1], "pair").and_then(|v| v.into_string()),
+            Some("WETH/USDC@0.05%".to_string())
+        );
+    }
+
+    #[test]
+    fn set_key_rejects_gaps_and_scalars() {
+        let mut dict = Dict::new();
+        dict.insert("pools".to_string(), "[{ fee = 3000 }]".parse().unwrap());
+        set_key(&mut dict, "rpc_url", "wss://example.org").unwrap();
+
+        assert!(set_key(&mut dict, "pools.2.fee", "500").is_err());
+        assert!(set_key(&mut dict, "pools.fee", "500").is_err());
+        assert!(set_key(&mut dict, "rpc_url.host", "example.org").is_err());
+        assert!(set_key(&mut dict, "writer..batch_size", "1").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::config::{FormatConfig, Locale};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// whether log lines go to a terminal that shows ANSI colors
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+pub enum Color {
+    Green,
+    Red,
+}
+
+// `text` in `color` when colors are enabled
+pub fn paint(text: &str, color: Color) -> String {
+    if !COLOR.load(Ordering::Relaxed) {
+        return text.to_string();
+    }
+    let code = match color {
+        Color::Green => "32",
+        Color::Red => "31",
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+// prices in fixed columns, 4 decimals or 8 below one
+pub fn format_price(price: f64) -> String {
+    let decimals = number_format()
+        .decimals
+        .unwrap_or(if price >= 1.0 { 4 } else { 8 });
+    localize(&format!("{:.*}", decimals, price), false)
+}
+
+// a token amount or price for people: thousands separated, fewer decimals the larger
+// it gets and no trailing zeros, e.g. 41,203 or 3,296.2 or 0.000303
+pub fn format_amount(value: f64) -> String {
+    if let Some(decimals) = number_format().decimals {
+        return localize(&format!("{:.*}", decimals, value), false);
+    }
+    let decimals = match value.abs() {
+        v if v >= 10_000.0 => 0,
+        v if v >= 1_000.0 => 1,
+        v if v >= 1.0 => 4,
+        // six significant digits
+        v if v > 0.0 => (5 - v.log10().floor() as i32).clamp(0, 18) as usize,
+        _ => 0,
+    };
+    localize(&format!("{:.*}", decimals, value), true)
+}
+
+// 19812345 -> 19,812,345
+pub fn group_thousands(digits: &str) -> String {
+    let separator = &number_format().thousands;
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// separators of the [format] config section applied to a number formatted by Rust
+fn localize(text: &str, trim_zeros: bool) -> String {
+    let (int, fraction) = match text.split_once('.') {
+        Some((int, fraction)) if trim_zeros => (int, fraction.trim_end_matches('0')),
+        Some((int, fraction)) => (int, fraction),
+        None => (text, ""),
+    };
+    let (sign, digits) = match int.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", int),
+    };
+    let grouped = group_thousands(digits);
+    if fraction.is_empty() {
+        format!("{}{}", sign, grouped)
+    } else {
+        format!("{}{}{}{}", sign, grouped, number_format().decimal, fraction)
+    }
+}
+
+struct NumberFormat {
+    thousands: String,
+    decimal: String,
+    decimals: Option<usize>,
+}
+
+static NUMBER_FORMAT: OnceLock<NumberFormat> = OnceLock::new();
+
+// set once the config is loaded, numbers formatted before use the en locale
+pub fn set_number_format(config: &FormatConfig) {
+    let (thousands, decimal) = match config.locale {
+        Locale::En => (",", "."),
+        Locale::De => (".", ","),
+        // narrow no-break space
+        Locale::Fr => ("\u{202f}", ","),
+        Locale::Ch => ("'", "."),
+    };
+    let _ = NUMBER_FORMAT.set(NumberFormat {
+        thousands: config
+            .thousands_separator
+            .clone()
+            .unwrap_or_else(|| thousands.to_string()),
+        decimal: config
+            .decimal_separator
+            .clone()
+            .unwrap_or_else(|| decimal.to_string()),
+        decimals: config.decimals,
+    });
+}
+
+fn number_format() -> &'static NumberFormat {
+    NUMBER_FORMAT.get_or_init(|| NumberFormat {
+        thousands: ",".to_string(),
+        decimal: ".".to_string(),
+        decimals: None,
+    })
+}
@@ -0,0 +1,93 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DaemonError {
+    #[error("Tracker already running with pid {0}, see {1}")]
+    AlreadyRunning(i32, String),
+
+    #[error("Failed to detach from the terminal")]
+    DetachFailed,
+
+    #[error("Failed to write pid file {0}")]
+    PidFileFailed(String),
+}
+
+// removes the pid file when the tracker exits normally
+pub struct PidFile(PathBuf);
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// fork into the background, detached from the terminal, with stdout and stderr going
+// to `log` and our pid in `pid_path`
+// must run before the tokio runtime starts, a forked child only keeps the calling thread
+pub fn daemonize(pid_path: &Path, log: &File) -> Result<PidFile, DaemonError> {
+    let path = pid_path.display().to_string();
+    // a pid file left by a crash is overwritten, one of a running tracker is not
+    if let Ok(content) = std::fs::read_to_string(pid_path)
+        && let Ok(pid) = content.trim().parse::<i32>()
+        // SAFETY: signal 0 only checks whether the process exists
+        && unsafe { libc::kill(pid, 0) } == 0
+    {
+        return Err(DaemonError::AlreadyRunning(pid, path));
+    }
+
+    // the parent returns to the shell, the child leaves its session and forks once
+    // more so it can never reacquire a controlling terminal
+    detach()?;
+    // SAFETY: setsid has no preconditions, it fails only for a process group leader
+    if unsafe { libc::setsid() } < 0 {
+        eprintln!(
+            "Failed to start a new session: {}",
+            std::io::Error::last_os_error()
+        );
+        return Err(DaemonError::DetachFailed);
+    }
+    detach()?;
+
+    let null = match File::open("/dev/null") {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Failed to open /dev/null: {}", e);
+            return Err(DaemonError::DetachFailed);
+        }
+    };
+    // SAFETY: both descriptors are open for the duration of the calls
+    unsafe {
+        libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO);
+    }
+
+    let written = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(pid_path)
+        .and_then(|mut f| writeln!(f, "{}", std::process::id()));
+    if let Err(e) = written {
+        eprintln!("Failed to write pid file {}: {}", path, e);
+        return Err(DaemonError::PidFileFailed(path));
+    }
+    Ok(PidFile(pid_path.to_path_buf()))
+}
+
+// fork and let the parent exit
+fn detach() -> Result<(), DaemonError> {
+    // SAFETY: no other threads run yet, the child continues with a copy of this one
+    match unsafe { libc::fork() } {
+        -1 => {
+            eprintln!("Failed to fork: {}", std::io::Error::last_os_error());
+            Err(DaemonError::DetachFailed)
+        }
+        0 => Ok(()),
+        _ => std::process::exit(0),
+    }
+}
@@ -0,0 +1,13 @@
+use alloy::transports::TransportError;
+
+// the error types live in the core crate so the wasm build shares them
+pub use uniswap_pool_core::error::*;
+
+// whether an error ending up in a report may go away when tried again: a TrackerError
+// says so itself, transport errors of the endpoint may, anything else is assumed not to
+pub fn is_retryable(report: &eyre::Report) -> bool {
+    match TrackerError::of(report) {
+        Some(error) => error.is_retryable(),
+        None => report.downcast_ref::<TransportError>().is_some(),
+    }
+}
@@ -0,0 +1,189 @@
+use crate::cli::ExportFormat;
+use crate::storage::{Candle, SwapRecord};
+#[cfg(feature = "parquet")]
+use arrow_array::{ArrayRef, Float64Array, Int32Array, RecordBatch, StringArray, UInt64Array};
+#[cfg(feature = "parquet")]
+use arrow_schema::ArrowError;
+#[cfg(feature = "parquet")]
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+#[cfg(feature = "parquet")]
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::error;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Failed to create {0}")]
+    CreateFailed(String),
+
+    #[error("Failed to write {0}")]
+    WriteFailed(String),
+}
+
+// rows of an export file: a CSV line each, or Arrow columns for Parquet
+trait Rows: Serialize + Sized {
+    const CSV_HEADER: &str;
+
+    fn csv_row(&self) -> String;
+
+    #[cfg(feature = "parquet")]
+    fn batch(rows: &[Self]) -> Result<RecordBatch, ArrowError>;
+}
+
+impl Rows for SwapRecord {
+    const CSV_HEADER: &str =
+        "pool,chain_id,block,log_index,tx,timestamp,price,sqrt_price_x96,liquidity,tick,amount0";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{:?},{},{},{},{},{},{},{},{},{},{}",
+            self.pool,
+            self.chain_id,
+            self.block,
+            self.log_index,
+            self.tx.map(|tx| format!("{:?}", tx)).unwrap_or_default(),
+            self.timestamp,
+            self.price,
+            self.sqrt_price_x96,
+            self.liquidity,
+            self.tick,
+            self.amount0
+        )
+    }
+
+    // sqrtPriceX96 and liquidity overflow Parquet's integers, they are decimal strings
+    #[cfg(feature = "parquet")]
+    fn batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+        let strings = |f: fn(&Self) -> String| -> ArrayRef {
+            Arc::new(StringArray::from_iter_values(rows.iter().map(f)))
+        };
+        let numbers = |f: fn(&Self) -> u64| -> ArrayRef {
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(f)))
+        };
+        let floats = |f: fn(&Self) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(f)))
+        };
+        let tx: StringArray = rows
+            .iter()
+            .map(|s| s.tx.map(|tx| format!("{:?}", tx)))
+            .collect();
+        RecordBatch::try_from_iter([
+            ("pool", strings(|s| format!("{:?}", s.pool))),
+            ("chain_id", numbers(|s| s.chain_id)),
+            ("block", numbers(|s| s.block)),
+            ("log_index", numbers(|s| s.log_index)),
+            ("tx", Arc::new(tx) as ArrayRef),
+            ("timestamp", numbers(|s| s.timestamp)),
+            ("price", floats(|s| s.price)),
+            ("sqrt_price_x96", strings(|s| s.sqrt_price_x96.to_string())),
+            ("liquidity", strings(|s| s.liquidity.to_string())),
+            (
+                "tick",
+                Arc::new(Int32Array::from_iter_values(rows.iter().map(|s| s.tick))),
+            ),
+            ("amount0", floats(|s| s.amount0)),
+        ])
+    }
+}
+
+impl Rows for Candle {
+    const CSV_HEADER: &str = "time,open,high,low,close,volume0,swaps";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{}",
+            self.time, self.open, self.high, self.low, self.close, self.volume0, self.swaps
+        )
+    }
+
+    #[cfg(feature = "parquet")]
+    fn batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+        let numbers = |f: fn(&Self) -> u64| -> ArrayRef {
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(f)))
+        };
+        let floats = |f: fn(&Self) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(f)))
+        };
+        RecordBatch::try_from_iter([
+            ("time", numbers(|c| c.time)),
+            ("open", floats(|c| c.open)),
+            ("high", floats(|c| c.high)),
+            ("low", floats(|c| c.low)),
+            ("close", floats(|c| c.close)),
+            ("volume0", floats(|c| c.volume0)),
+            ("swaps", numbers(|c| c.swaps)),
+        ])
+    }
+}
+
+pub fn write_swaps(
+    path: &str,
+    format: ExportFormat,
+    swaps: &[SwapRecord],
+) -> Result<(), ExportError> {
+    write(path, format, swaps)
+}
+
+pub fn write_candles(
+    path: &str,
+    format: ExportFormat,
+    candles: &[Candle],
+) -> Result<(), ExportError> {
+    write(path, format, candles)
+}
+
+// every field is a number or a hex string, so CSV rows need no quoting
+fn write<T: Rows>(path: &str, format: ExportFormat, rows: &[T]) -> Result<(), ExportError> {
+    let file = match File::create(path) {
+        Ok(f) => f,
+        Err(e) => {
+            error!("Failed to create {}: {}", path, e);
+            return Err(ExportError::CreateFailed(path.to_string()));
+        }
+    };
+    let mut out = BufWriter::new(file);
+
+    let result = (|| -> std::io::Result<()> {
+        match format {
+            ExportFormat::Csv => {
+                writeln!(out, "{}", T::CSV_HEADER)?;
+                for row in rows {
+                    writeln!(out, "{}", row.csv_row())?;
+                }
+            }
+            ExportFormat::Jsonl => {
+                for row in rows {
+                    serde_json::to_writer(&mut out, row)?;
+                    writeln!(out)?;
+                }
+            }
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => {
+                let batch = T::batch(rows).map_err(std::io::Error::other)?;
+                write_parquet(&mut out, batch)?;
+            }
+        }
+        out.flush()
+    })();
+
+    if let Err(e) = result {
+        error!("Failed to write {}: {}", path, e);
+        return Err(ExportError::WriteFailed(path.to_string()));
+    }
+    Ok(())
+}
+
+// one row group of snappy compressed columns
+#[cfg(feature = "parquet")]
+fn write_parquet(out: &mut (impl Write + Send), batch: RecordBatch) -> std::io::Result<()> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(&mut *out, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
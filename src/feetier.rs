@@ -0,0 +1,115 @@
+use crate::config::FeeTierReportConfig;
+use crate::route::amount_out;
+use crate::state::{PoolSnapshot, SharedState, TrackerState};
+use alloy::primitives::Address;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::info;
+
+// the fee tiers of one pair side by side
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeTierReport {
+    pub chain_id: u64,
+    pub pair: String,
+    // ordered by fee
+    pub tiers: Vec<FeeTier>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeTier {
+    pub pool: Address,
+    pub fee: u32,
+    pub price: f64,
+    pub liquidity: u128,
+    pub volume0_24h: f64,
+    pub costs: Vec<TradeCost>,
+}
+
+// what selling `size` token0 costs against the pool price, fee and price impact
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeCost {
+    pub size: f64,
+    pub cost_bps: f64,
+}
+
+// publish and log the comparison of every pair tracked in more than one fee tier
+pub async fn run(config: FeeTierReportConfig, state: SharedState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        interval.tick().await;
+
+        let reports = report(&state.read().unwrap(), &config.sizes);
+        for report in &reports {
+            for tier in &report.tiers {
+                let costs: Vec<String> = tier
+                    .costs
+                    .iter()
+                    .map(|c| format!("{} for {:.2} bps", c.size, c.cost_bps))
+                    .collect();
+                info!(
+                    "{} {:.2}% tier {:?}: price {}, liquidity {}, 24h volume {:.4}, selling {}",
+                    report.pair,
+                    tier.fee as f64 / 10_000.0,
+                    tier.pool,
+                    tier.price,
+                    tier.liquidity,
+                    tier.volume0_24h,
+                    costs.join(", ")
+                );
+            }
+        }
+        state.write().unwrap().fee_tiers = reports;
+    }
+}
+
+fn report(state: &TrackerState, sizes: &[f64]) -> Vec<FeeTierReport> {
+    // same pair means same chain and same tokens, which V3 style factories sort
+    let mut pairs: BTreeMap<(u64, Address, Address), Vec<&PoolSnapshot>> = BTreeMap::new();
+    for pool in state.pools.values() {
+        pairs
+            .entry((pool.chain_id, pool.token0, pool.token1))
+            .or_default()
+            .push(pool);
+    }
+
+    pairs
+        .into_iter()
+        .filter(|(_, pools)| pools.len() > 1)
+        .map(|((chain_id, _, _), mut pools)| {
+            pools.sort_by_key(|p| p.fee);
+            FeeTierReport {
+                chain_id,
+                pair: pools[0].pair(),
+                tiers: pools.into_iter().map(|p| tier(p, sizes)).collect(),
+            }
+        })
+        .collect()
+}
+
+fn tier(pool: &PoolSnapshot, sizes: &[f64]) -> FeeTier {
+    let costs = sizes
+        .iter()
+        .map(|&size| {
+            let ideal = size * pool.price;
+            let out = amount_out(pool, true, size);
+            TradeCost {
+                size,
+                cost_bps: if ideal > 0.0 {
+                    (1.0 - out / ideal) * 10_000.0
+                } else {
+                    f64::NAN
+                },
+            }
+        })
+        .collect();
+
+    FeeTier {
+        pool: pool.pool,
+        fee: pool.fee,
+        price: pool.price,
+        liquidity: pool.liquidity,
+        volume0_24h: pool.volume0_24h,
+        costs,
+    }
+}
@@ -0,0 +1,230 @@
+use crate::state::SharedState;
+use crate::summary::redact;
+use alloy::providers::{Provider, RootProvider};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// probes a score's error rate is taken over
+const PROBE_WINDOW: usize = 30;
+// weight of the latest probe in the latency average
+const LATENCY_ALPHA: f64 = 0.3;
+// endpoints scoring at least this are healthy and picked by latency
+const HEALTHY_SCORE: f64 = 70.0;
+// a healthy endpoint takes over from another healthy one when it is faster by this
+// share and at least LATENCY_MARGIN_MS, so noise does not move calls back and forth
+const LATENCY_MARGIN: f64 = 0.2;
+const LATENCY_MARGIN_MS: f64 = 5.0;
+// without a healthy endpoint, another one must score this many points more to take over
+const SWITCH_MARGIN: f64 = 10.0;
+
+// how well an RPC endpoint is doing, from probing its head block every
+// RPC_PROBE_INTERVAL_SECS
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointHealth {
+    pub chain_id: u64,
+    // moving average of the probe round trips
+    pub latency_ms: Option<f64>,
+    pub probes: u64,
+    pub errors: u64,
+    // of the last PROBE_WINDOW probes
+    pub error_rate: f64,
+    pub head: Option<u64>,
+    // blocks behind the highest head of the chain's endpoints at the last probe
+    pub missed_blocks: u64,
+    // 100 for a fast endpoint without errors that is at the head, see `score`
+    pub score: f64,
+    // probe outcomes, oldest first
+    #[serde(skip)]
+    window: VecDeque<bool>,
+}
+
+impl EndpointHealth {
+    fn record(&mut self, outcome: Result<(u64, Duration), ()>) {
+        self.probes += 1;
+        if self.window.len() == PROBE_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(outcome.is_ok());
+        match outcome {
+            Ok((head, latency)) => {
+                let ms = latency.as_secs_f64() * 1000.0;
+                self.latency_ms = Some(match self.latency_ms {
+                    Some(avg) => avg + LATENCY_ALPHA * (ms - avg),
+                    None => ms,
+                });
+                self.head = Some(head);
+            }
+            Err(()) => self.errors += 1,
+        }
+        let failed = self.window.iter().filter(|ok| !**ok).count();
+        self.error_rate = failed as f64 / self.window.len() as f64;
+    }
+
+    // up to 50 points off for errors, 30 for lagging blocks and 20 for latency, an
+    // endpoint that never answered has 30 left
+    fn score(&self) -> f64 {
+        let errors = 50.0 * self.error_rate;
+        let lag = 3.0 * self.missed_blocks.min(10) as f64;
+        let latency = self.latency_ms.map_or(20.0, |ms| (ms / 10.0).min(20.0));
+        100.0 - errors - lag - latency
+    }
+}
+
+// the endpoints' health labelled without path and credentials, sorted by url; endpoints
+// on the same host get a #2, #3, .. so keys of one provider stay apart
+pub fn labelled(endpoints: &HashMap<String, EndpointHealth>) -> Vec<(String, &EndpointHealth)> {
+    let mut urls: Vec<&String> = endpoints.keys().collect();
+    urls.sort();
+    let mut labelled: Vec<(String, &EndpointHealth)> = Vec::with_capacity(urls.len());
+    for url in urls {
+        let label = redact(url);
+        let same = labelled
+            .iter()
+            .filter(|(l, _)| l.split(" #").next() == Some(label.as_str()))
+            .count();
+        let label = match same {
+            0 => label,
+            n => format!("{} #{}", label, n + 1),
+        };
+        labelled.push((label, &endpoints[url]));
+    }
+    labelled
+}
+
+// probe `url` for its head block every `interval` and keep its health in the state
+pub fn spawn_probe<P: Provider + 'static>(
+    url: String,
+    provider: P,
+    state: SharedState,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let chain_id = match provider.get_chain_id().await {
+            Ok(id) => id,
+            Err(e) => {
+                error!("Failed to fetch chain id of {}: {}", redact(&url), e);
+                0
+            }
+        };
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let started = Instant::now();
+            // a hanging endpoint counts as failing once the next probe is due
+            let outcome = match tokio::time::timeout(interval, provider.get_block_number()).await {
+                Ok(Ok(head)) => Ok((head, started.elapsed())),
+                Ok(Err(e)) => {
+                    warn!("Probe of {} failed: {}", redact(&url), e);
+                    Err(())
+                }
+                Err(_) => {
+                    warn!("Probe of {} timed out", redact(&url));
+                    Err(())
+                }
+            };
+
+            let mut shared = state.write().unwrap();
+            let best = shared
+                .endpoints
+                .values()
+                .filter(|h| h.chain_id == chain_id)
+                .filter_map(|h| h.head)
+                .chain(outcome.map(|(head, _)| head))
+                .max();
+            let health = shared.endpoints.entry(url.clone()).or_default();
+            health.chain_id = chain_id;
+            health.record(outcome);
+            health.missed_blocks = match (best, health.head) {
+                (Some(best), Some(head)) => best.saturating_sub(head),
+                _ => 0,
+            };
+            health.score = health.score();
+        }
+    });
+}
+
+// the endpoints of one chain in configured order, calls and new subscriptions go to the
+// best scored one
+#[derive(Clone)]
+pub struct Endpoints<P> {
+    providers: Arc<Vec<(String, P)>>,
+    state: SharedState,
+    // index of the endpoint used last, to log switches
+    current: Arc<AtomicUsize>,
+}
+
+impl<P: Provider> Endpoints<P> {
+    pub fn new(providers: Vec<(String, P)>, state: SharedState) -> Self {
+        Endpoints {
+            providers: Arc::new(providers),
+            state,
+            current: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // the fastest healthy endpoint, the one in use stays while it is healthy and no
+    // other is clearly faster; without a healthy one the best scored, on a tie the one
+    // configured first
+    fn primary(&self) -> &P {
+        if self.providers.len() == 1 {
+            return &self.providers[0].1;
+        }
+        let shared = self.state.read().unwrap();
+        let health = |i: usize| shared.endpoints.get(&self.providers[i].0);
+        let score = |i: usize| health(i).map_or(0.0, |h| h.score);
+        let latency = |i: usize| health(i).and_then(|h| h.latency_ms).unwrap_or(f64::MAX);
+        let healthy = |i: usize| score(i) >= HEALTHY_SCORE;
+
+        let current = self.current.load(Ordering::Relaxed);
+        let fastest = (0..self.providers.len())
+            .filter(|i| healthy(*i))
+            .min_by(|a, b| latency(*a).total_cmp(&latency(*b)));
+        let next = match fastest {
+            Some(fastest) if healthy(current) => {
+                let saved = latency(current) - latency(fastest);
+                let faster =
+                    saved >= LATENCY_MARGIN_MS && saved >= latency(current) * LATENCY_MARGIN;
+                if faster { fastest } else { current }
+            }
+            Some(fastest) => fastest,
+            None => {
+                let best = (0..self.providers.len())
+                    .rev()
+                    .max_by(|a, b| score(*a).total_cmp(&score(*b)))
+                    .unwrap_or(current);
+                if score(best) > score(current) + SWITCH_MARGIN {
+                    best
+                } else {
+                    current
+                }
+            }
+        };
+        if self.current.swap(next, Ordering::Relaxed) != next {
+            let describe = |i: usize| match health(i).and_then(|h| h.latency_ms) {
+                Some(ms) => format!(
+                    "{} (score {:.0}, {:.0}ms)",
+                    redact(&self.providers[i].0),
+                    score(i),
+                    ms
+                ),
+                None => format!("{} (score {:.0})", redact(&self.providers[i].0), score(i)),
+            };
+            info!(
+                "Switching RPC calls from {} to {}",
+                describe(current),
+                describe(next)
+            );
+        }
+        &self.providers[next].1
+    }
+}
+
+impl<P: Provider> Provider for Endpoints<P> {
+    fn root(&self) -> &RootProvider {
+        self.primary().root()
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use thiserror::Error;
+use tracing::{error, warn};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Failed to read {0}")]
+    ReadFailed(String),
+
+    #[error("No prices found in {0}")]
+    NoPrices(String),
+}
+
+// (unix timestamp, price) rows of a CSV file like exchange klines, `time_column` and
+// `price_column` are zero based
+// timestamps in milliseconds or microseconds are converted to seconds, header and other
+// lines without a number in both columns are skipped
+pub fn read_prices(
+    path: &str,
+    time_column: usize,
+    price_column: usize,
+) -> Result<Vec<(u64, f64)>, ImportError> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            error!("Failed to open {}: {}", path, e);
+            return Err(ImportError::ReadFailed(path.to_string()));
+        }
+    };
+
+    let mut prices = Vec::new();
+    let mut skipped = 0;
+    for line in BufReader::new(file).lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                error!("Failed to read {}: {}", path, e);
+                return Err(ImportError::ReadFailed(path.to_string()));
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(',')
+            .map(|f| f.trim().trim_matches('"'))
+            .collect();
+        let time = fields.get(time_column).and_then(|f| f.parse::<u64>().ok());
+        let price = fields.get(price_column).and_then(|f| f.parse::<f64>().ok());
+        match (time, price) {
+            (Some(time), Some(price)) if price > 0.0 => prices.push((seconds(time), price)),
+            _ => skipped += 1,
+        }
+    }
+
+    if skipped > 0 {
+        warn!(
+            "Skipped {} lines of {} without a time and price",
+            skipped, path
+        );
+    }
+    if prices.is_empty() {
+        error!("No prices found in {}", path);
+        return Err(ImportError::NoPrices(path.to_string()));
+    }
+    Ok(prices)
+}
+
+// exchanges export milliseconds, some microseconds
+fn seconds(time: u64) -> u64 {
+    match time {
+        t if t >= 100_000_000_000_000 => t / 1_000_000,
+        t if t >= 100_000_000_000 => t / 1_000,
+        t => t,
+    }
+}
@@ -0,0 +1,71 @@
+// the tracker as a library: a PoolTracker follows pools in the background and hands
+// their price updates to subscribers; the API is what is re-exported below, the
+// modules are only public for the binary, which runs the same pipeline with
+// everything the environment and config file turn on
+#[doc(hidden)]
+pub mod aggregator;
+#[doc(hidden)]
+pub mod alert;
+#[doc(hidden)]
+#[cfg(feature = "api")]
+pub mod api;
+#[doc(hidden)]
+pub mod chain;
+#[doc(hidden)]
+pub mod checkpoint;
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod console;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod export;
+#[doc(hidden)]
+pub mod feetier;
+#[doc(hidden)]
+pub mod health;
+#[doc(hidden)]
+pub mod import;
+#[doc(hidden)]
+pub mod metrics;
+#[doc(hidden)]
+pub mod pool;
+#[doc(hidden)]
+pub mod reference;
+#[doc(hidden)]
+pub mod report;
+#[doc(hidden)]
+pub mod route;
+#[doc(hidden)]
+pub mod rpc;
+#[doc(hidden)]
+pub mod rules;
+#[doc(hidden)]
+pub mod secrets;
+#[doc(hidden)]
+pub mod state;
+#[doc(hidden)]
+pub mod storage;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+#[cfg(feature = "telegram")]
+pub mod telegram;
+#[doc(hidden)]
+pub mod token;
+#[doc(hidden)]
+pub mod tokenlist;
+mod tracker;
+
+pub use alert::Alerter;
+pub use config::{PoolEntry, Protocol};
+pub use error::{ErrorKind, TrackerError};
+pub use pool::{
+    CollectEvent, EVENTS_CAPACITY, FlashEvent, InitializeEvent, LiquidityUpdate, ListenerError,
+    PoolEvent, PositionEvent, PriceUpdate, Snapshot, SwapEvent, TradeSummary,
+};
+pub use tracker::{NoRpc, PoolTracker, Sink, TrackerBuilder, pool_snapshot};
+pub use uniswap_pool_core::calc;
@@ -1,82 +1,1598 @@
-mod calc;
-mod pool;
-mod token;
+#[cfg(all(unix, feature = "daemon"))]
+mod daemon;
+mod shutdown;
+#[cfg(unix)]
+mod systemd;
+#[cfg(feature = "tui")]
+mod tui;
 
-use alloy::primitives::Address;
-use alloy::providers::{ProviderBuilder, WsConnect};
+#[cfg(feature = "api")]
+use uniswapv3_pool_price_tracker::api;
+#[cfg(feature = "telegram")]
+use uniswapv3_pool_price_tracker::telegram;
+use uniswapv3_pool_price_tracker::{
+    aggregator, alert, chain, checkpoint, cli, config, console, error, export, feetier, health,
+    import, pool, reference, report, route, rpc, secrets, state, storage, summary, token,
+    tokenlist,
+};
+
+use alert::Alerter;
+use alloy::primitives::{Address, B256};
+use alloy::providers::Provider;
+use clap::Parser;
+use cli::{AlertCommand, Cli, Command, ReportCommand};
+use config::{Config, PoolEntry, Protocol};
+use error::{ProviderFailure, TrackerError};
 use eyre::Result;
-use pool::listen_pool;
+use pool::{
+    Bound, CurveAdapter, ListenerSettings, TrackedPools, V2Adapter, V3Adapter, V3Flavor, V4Adapter,
+    WeightedPoolAdapter, backfill, backfill_from_subgraph, discover, find_pool, first_block_at,
+    load_pool, price_at_block, replay, snapshot, spawn_listener, track_pair, track_top_pools, twap,
+};
+use rpc::connect;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::env;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::{Notify, broadcast};
 use tokio::task::JoinHandle;
-use tracing::{error, info};
-use tracing_subscriber;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
 
 #[derive(Debug, Error)]
-pub enum ProviderError {
-    #[error("Failed to connect WS")]
-    WSConnectionFailed,
+pub enum CommandError {
+    #[error("{0} of {1} alert backends failed")]
+    AlertTestFailed(usize, usize),
+
+    #[error("DATABASE_PATH is not set")]
+    DatabaseRequired,
+
+    #[error("{0} of {1} checks failed")]
+    CheckFailed(usize, usize),
+
+    #[error("Invalid interval {0}")]
+    InvalidInterval(String),
+
+    #[error("Pool {0} is not configured")]
+    PoolNotConfigured(Address),
+
+    #[error("Transaction {0} not found")]
+    TransactionNotFound(B256),
+
+    #[error("Built without the {0} feature")]
+    FeatureDisabled(&'static str),
+
+    #[error("{0} is only supported on Unix")]
+    Unsupported(&'static str),
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     dotenvy::dotenv().ok();
+    secrets::load_files()?;
+    let cli = Cli::parse();
+    config::set_overrides(&cli.overrides)?;
+    if cli.tui && !cfg!(feature = "tui") {
+        return Err(CommandError::FeatureDisabled("tui").into());
+    }
+    if cli.daemon && !cfg!(feature = "daemon") {
+        return Err(CommandError::FeatureDisabled("daemon").into());
+    }
+    if cli.daemon && !cfg!(unix) {
+        return Err(CommandError::Unsupported("--daemon").into());
+    }
 
-    let pools_str = match env::var("POOLS") {
-        Ok(val) => val,
-        Err(_) => {
-            tracing::error!("Environment variable POOLS is missing");
-            panic!("POOLS environment variable is not set");
-        }
+    // the terminal UI draws on stdout and a daemon has none, so logs go to a file
+    let log_path = match (cli.tui, cli.daemon, &cli.command) {
+        (true, _, None) => Some(
+            Config::load()?
+                .tui_log_path
+                .unwrap_or_else(|| "tracker.log".to_string()),
+        ),
+        (_, true, None) => Some(cli.log_file.clone()),
+        _ => None,
+    };
+    let log_file = match log_path {
+        Some(path) => Some(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?,
+        ),
+        None => None,
+    };
+    // forking is only safe before the runtime starts its threads
+    #[cfg(all(unix, feature = "daemon"))]
+    let _pid_file = match (&log_file, cli.daemon && cli.command.is_none()) {
+        (Some(log), true) => Some(daemon::daemonize(Path::new(&cli.pid_file), log)?),
+        _ => None,
     };
-    let rpc_url =
-        env::var("RPC_URL").unwrap_or_else(|_| "wss://ethereum-rpc.publicnode.com".to_string());
 
-    let ws = WsConnect::new(rpc_url);
-    let provider = match ProviderBuilder::new().connect_ws(ws).await {
-        Ok(p) => p,
-        Err(e) => {
-            tracing::error!("Failed to connect WebSocket provider: {}", e);
-            return Err(ProviderError::WSConnectionFailed.into());
-        }
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(start(cli, log_file))
+}
+
+async fn start(cli: Cli, log_file: Option<std::fs::File>) -> Result<()> {
+    // RUST_LOG syntax: `target=level` directives separated by commas, a bare level
+    // applies to every target, `target[span]=level` to events inside a span
+    let directives = cli
+        .log_level
+        .clone()
+        .or_else(|| cli.verbosity_filter().map(str::to_string))
+        .or_else(|| env::var("RUST_LOG").ok())
+        .unwrap_or_else(|| "info".to_string());
+    // logging is not set up yet, so the error is only printed on exit
+    let filter = match EnvFilter::try_new(&directives) {
+        Ok(f) => f,
+        Err(e) => return Err(eyre::eyre!("Invalid log filter {:?}: {}", directives, e)),
     };
+    // price moves are colored on a terminal, not in the TUI log file
+    console::set_color(
+        log_file.is_none() && std::io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+    );
+    let registry = tracing_subscriber::registry().with(filter);
+    match log_file {
+        Some(file) => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(std::sync::Mutex::new(file)),
+            )
+            .init(),
+        None => registry.with(tracing_subscriber::fmt::layer()).init(),
+    }
 
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
+    match cli.command {
+        None => run(cli.tui).await,
+        Some(Command::Alert {
+            action: AlertCommand::Test,
+        }) => alert_test().await,
+        Some(Command::Backfill {
+            from_block,
+            to_block,
+            from,
+            to,
+            subgraph,
+        }) => {
+            // dates are midnight UTC
+            let time = |date: chrono::NaiveDate| {
+                Bound::Time(date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp() as u64)
+            };
+            let from = from_block
+                .map(Bound::Block)
+                .or(from.map(time))
+                .unwrap_or(Bound::Block(0));
+            let to = to_block.map(Bound::Block).or(to.map(time));
+            match (subgraph, from, to) {
+                (Some(url), Bound::Time(from), Some(Bound::Time(to))) => {
+                    backfill_subgraph(&url, from, to).await
+                }
+                (Some(url), Bound::Time(from), _) => {
+                    backfill_subgraph(&url, from, storage::unix_now()).await
+                }
+                _ => backfill_swaps(from, to).await,
+            }
+        }
+        Some(Command::Export {
+            pool,
+            from,
+            to,
+            format,
+            candles,
+            output,
+        }) => export_swaps(pool, from, to, format, candles, output).await,
+        Some(Command::Query {
+            pool,
+            from,
+            to,
+            agg,
+            json,
+        }) => query_prices(pool, from, to, agg, json).await,
+        Some(Command::Snapshot { json }) => snapshot_pools(json).await,
+        Some(Command::Report {
+            report: ReportCommand::Volume { period, json },
+        }) => report_volume(period, json).await,
+        Some(Command::Report {
+            report:
+                ReportCommand::Compare {
+                    pool,
+                    series,
+                    from,
+                    to,
+                    interval,
+                    invert,
+                    json,
+                },
+        }) => report_compare(pool, series, from, to, interval, invert, json).await,
+        Some(Command::Import {
+            series,
+            file,
+            time_column,
+            price_column,
+        }) => import_prices(series, file, time_column, price_column).await,
+        Some(Command::PriceAt {
+            pool,
+            block,
+            tx,
+            json,
+        }) => print_price_at(pool, block, tx, json).await,
+        Some(Command::Twap { pool, window, json }) => print_twap(pool, window, json).await,
+        Some(Command::Check { notify }) => check(notify).await,
+        Some(Command::Replay { from, to, notify }) => {
+            let time = |date: chrono::NaiveDate| {
+                date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp() as u64
+            };
+            replay_swaps(
+                from.map(time).unwrap_or(0),
+                to.map(time).unwrap_or(u64::MAX),
+                notify,
+            )
+            .await
+        }
+    }
+}
+
+// send a test alert through every configured backend and summarize the results
+async fn alert_test() -> Result<()> {
+    let alerter = Alerter::from_env();
+    let results = alerter.test_backends().await;
+    if results.is_empty() {
+        println!("No alert backends configured");
+        return Ok(());
+    }
 
-    //split pool addresses by commas
-    let pool_addresses: Vec<Address> = pools_str
-        .split(',')
-        .filter_map(|addr| addr.trim().parse().ok())
+    let mut failed = 0;
+    for (channel, result) in &results {
+        match result {
+            Ok(_) => println!("{:<10} ok", channel),
+            Err(e) => {
+                failed += 1;
+                println!("{:<10} FAILED: {}", channel, e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(CommandError::AlertTestFailed(failed, results.len()).into());
+    }
+    Ok(())
+}
+
+// listen to every configured pool until all listeners have stopped
+async fn run(tui: bool) -> Result<()> {
+    let config = Config::load()?;
+    console::set_number_format(&config.format);
+    if config.api_addr.is_some() && !cfg!(feature = "api") {
+        return Err(CommandError::FeatureDisabled("api").into());
+    }
+    // chain defaults for the endpoint, explorer, factory and default pool
+    let chain = chain_preset(&config)?;
+    let rpc_url = (config.rpc_url.clone()).unwrap_or_else(|| chain.rpc_url.to_string());
+
+    let provider = connect(&rpc_url).await?;
+    match provider.get_chain_id().await {
+        Ok(id) if id != chain.chain_id => tracing::warn!(
+            "RPC_URL serves chain {} but CHAIN is {} ({}), factory and explorer defaults will be wrong",
+            id,
+            chain.name,
+            chain.chain_id
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to fetch chain id: {}", e),
+    }
+    chain::NodeCapabilities::probe(&provider)
+        .await
+        .report(&rpc_url);
+
+    let mut pools = config.pools.clone();
+    if pools.is_empty()
+        && config.discovery.is_empty()
+        && config.pairs.is_empty()
+        && config.subgraph.is_empty()
+    {
+        // nothing configured, track the chain's WETH/USDC pool
+        if config.chain.is_none() {
+            tracing::error!("No pools configured");
+            panic!(
+                "Set CHAIN, POOLS or add [[pools]], [[pairs]], [[discovery]] or [[subgraph]] to the config file"
+            );
+        }
+        info!(
+            "No pools configured, tracking the {} USD pool {:?}",
+            chain.name, chain.usd_pool
+        );
+        pools.push(PoolEntry {
+            address: Some(chain.usd_pool),
+            factory: Some(chain.uniswap_v3_factory),
+            ..PoolEntry::default()
+        });
+    }
+
+    // reference prices (e.g. Chainlink feeds) used by deviation rules
+    let reference_refresh = Duration::from_secs(config.reference_refresh_secs.unwrap_or(30));
+    let references = reference::spawn_feeds(&config, provider.clone(), reference_refresh);
+
+    let state = state::SharedState::default();
+    // gas prices are only needed to cost arbitrage opportunities
+    if !config.arbitrage.is_empty() {
+        state::spawn_gas_price_poller(state.clone(), provider.clone(), reference_refresh);
+    }
+
+    let tokens = load_token_list(&config).await;
+    let storage = open_storage(&config)?;
+
+    // 24h volume and rule windows survive restarts when CHECKPOINT_PATH is set
+    let checkpoints = config.checkpoint_path.clone().map(|path| {
+        let interval = config.checkpoint_interval_secs.unwrap_or(60);
+        Arc::new(checkpoint::Checkpoints::load(
+            path,
+            Duration::from_secs(interval),
+        ))
+    });
+    if let Some(checkpoints) = &checkpoints {
+        tokio::spawn(checkpoint::run_checkpoints(checkpoints.clone()));
+    }
+
+    let writer = storage
+        .clone()
+        .map(|storage| storage::SwapWriter::start(storage, config.writer.clone()));
+
+    let settings = Arc::new(ListenerSettings {
+        stall_timeout: config.stall_timeout_secs.map(Duration::from_secs),
+        ws_stall_timeout: config.ws_stall_secs.map(Duration::from_secs),
+        confirmations: config.confirmations.unwrap_or(chain.reorg_depth),
+        explorer_url: (config.explorer_url.clone())
+            .unwrap_or_else(|| chain.explorer_url.to_string()),
+        // every pool event is streamed on /pool-events
+        all_events: config.api_addr.is_some(),
+        chain,
+        config,
+        references,
+        state,
+        tokens,
+        proxies: token::ProxyWatcher::default(),
+        writer,
+        checkpoints,
+        events: broadcast::channel(pool::EVENTS_CAPACITY).0,
+        errors: broadcast::channel(pool::EVENTS_CAPACITY).0,
+    });
+
+    let mut alerter = Alerter::from_env()
+        .with_quiet_hours(&settings.config.quiet_hours)
+        .with_escalations(&settings.config.escalation);
+    if let Some(storage) = &storage {
+        alerter = alerter.with_storage(storage.clone());
+    }
+    let alerter = Arc::new(alerter);
+    if alerter.has_quiet_hours() {
+        tokio::spawn(alerter.clone().run_quiet_hours());
+    }
+    if alerter.has_escalations() {
+        tokio::spawn(alerter.clone().run_escalation());
+    }
+
+    // set when the TUI quits, the tracker then shuts down like on SIGTERM
+    let stop = Arc::new(Notify::new());
+    #[cfg(feature = "tui")]
+    if tui {
+        tokio::spawn(tui::run_tui(
+            settings.state.clone(),
+            storage.clone(),
+            stop.clone(),
+        ));
+    }
+
+    if let (Some(storage), Some(retention)) = (&storage, settings.config.retention.clone()) {
+        tokio::spawn(storage::run_retention(storage.clone(), retention));
+    }
+
+    // scheduled summaries are compiled from the stored swaps
+    match &storage {
+        Some(storage) => {
+            for rule in settings.config.reports.iter().cloned() {
+                let state = settings.state.clone();
+                tokio::spawn(report::run_summaries(
+                    rule,
+                    storage.clone(),
+                    state,
+                    alerter.clone(),
+                ));
+            }
+        }
+        None if !settings.config.reports.is_empty() => {
+            tracing::warn!("[[reports]] are ignored without DATABASE_PATH");
+        }
+        None => {}
+    }
+
+    // pool prices against 0x / 1inch quotes
+    for rule in settings.config.aggregator.iter().cloned() {
+        tokio::spawn(aggregator::run(rule, settings.state.clone()));
+    }
+
+    if let Some(report) = settings.config.fee_tier_report.clone() {
+        tokio::spawn(feetier::run(report, settings.state.clone()));
+    }
+
+    // best routes across the tracked pools of a pair, from the live state
+    for rule in settings.config.routes.iter().cloned() {
+        tokio::spawn(route::run(
+            rule,
+            settings.chain.chain_id,
+            settings.state.clone(),
+        ));
+    }
+
+    // answer /price, /pools and /stats from the live state, /ack acknowledges alerts
+    #[cfg(feature = "telegram")]
+    if let Ok(token) = env::var("TELEGRAM_BOT_TOKEN") {
+        let telegram = telegram::TelegramClient::new(reqwest::Client::new(), &token);
+        let allowed_chat = env::var("TELEGRAM_CHAT_ID").ok();
+        tokio::spawn(telegram::run_bot(
+            telegram,
+            settings.state.clone(),
+            alerter.clone(),
+            allowed_chat,
+        ));
+    }
+
+    #[cfg(feature = "api")]
+    if let Some(addr) = settings.config.api_addr.clone() {
+        let state = api::ApiState {
+            storage,
+            alerter: alerter.clone(),
+            state: settings.state.clone(),
+            events: settings.events.clone(),
+            admin_token: env::var("API_ADMIN_TOKEN").ok(),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(addr, state).await {
+                tracing::error!("API server stopped: {}", e);
+            }
+        });
+    }
+
+    // RPC_FALLBACK_URLS back up RPC_URL, every endpoint is probed and calls go to the
+    // best scored one of its chain
+    let probe_interval = Duration::from_secs(settings.config.rpc_probe_interval_secs.unwrap_or(10));
+    let mut endpoints = vec![(rpc_url.clone(), provider)];
+    for url in &settings.config.rpc_fallback_urls {
+        let Ok(fallback) = connect(url).await else {
+            continue;
+        };
+        match fallback.get_chain_id().await {
+            Ok(id) if id == chain.chain_id => endpoints.push((url.to_string(), fallback)),
+            Ok(id) => tracing::warn!(
+                "Skipping fallback endpoint {}, it serves chain {} instead of {}",
+                url,
+                id,
+                chain.chain_id
+            ),
+            Err(e) => tracing::error!("Failed to fetch chain id of {}: {}", url, e),
+        }
+    }
+    let fallbacks: Vec<String> = endpoints
+        .iter()
+        .skip(1)
+        .map(|(url, _)| url.clone())
         .collect();
+    for (url, provider) in &endpoints {
+        health::spawn_probe(
+            url.clone(),
+            provider.clone(),
+            settings.state.clone(),
+            probe_interval,
+        );
+    }
+
+    // pools on other chains bring their own endpoint, one connection per endpoint
+    let mut providers = HashMap::new();
+    providers.insert(
+        rpc_url.clone(),
+        health::Endpoints::new(endpoints, settings.state.clone()),
+    );
+    let discovery_urls = settings.config.discovery.iter().map(|d| d.rpc_url.clone());
+    let pair_urls = settings.config.pairs.iter().map(|p| p.rpc_url.clone());
+    let subgraph_urls = settings.config.subgraph.iter().map(|s| s.rpc_url.clone());
+    for url in pools
+        .iter()
+        .map(|p| p.rpc_url.clone())
+        .chain(discovery_urls)
+        .chain(pair_urls)
+        .chain(subgraph_urls)
+        .flatten()
+    {
+        if let Entry::Vacant(slot) = providers.entry(url) {
+            let url = slot.key().clone();
+            let provider = connect(&url).await?;
+            health::spawn_probe(
+                url.clone(),
+                provider.clone(),
+                settings.state.clone(),
+                probe_interval,
+            );
+            slot.insert(health::Endpoints::new(
+                vec![(url, provider)],
+                settings.state.clone(),
+            ));
+        }
+    }
+
+    let pools = resolve_pair_specs(pools, &providers, &rpc_url, &settings.tokens, chain).await;
 
-    info!("Loaded {} pools from .env", pool_addresses.len());
+    let mut sinks = vec![if tui { "tui" } else { "console" }.to_string()];
+    if let Some(path) = &settings.config.database_path {
+        sinks.push(format!("sqlite {}", path));
+    }
+    if let Some(path) = &settings.config.checkpoint_path {
+        sinks.push(format!("checkpoints {}", path));
+    }
+    if let Some(addr) = &settings.config.api_addr {
+        sinks.push(format!("api {}", addr));
+    }
+    if cfg!(feature = "telegram") && env::var("TELEGRAM_BOT_TOKEN").is_ok() {
+        sinks.push("telegram bot".to_string());
+    }
+    let summary = summary::ConfigSummary::new(
+        chain,
+        &rpc_url,
+        &fallbacks,
+        &pools,
+        &settings.config,
+        sinks,
+        alerter.channels(),
+    );
+    summary.log();
+    settings.state.write().unwrap().summary = Some(summary);
 
     let mut handles: Vec<JoinHandle<Result<()>>> = Vec::new();
 
+    // configured pools are never started a second time by discovery
+    let tracked = TrackedPools::default();
+    for address in pools.iter().filter_map(|p| p.address) {
+        tracked.insert(address);
+    }
+    for rule in settings.config.pairs.iter().cloned() {
+        let provider = providers[rule.rpc_url.as_ref().unwrap_or(&rpc_url)].clone();
+        handles.push(tokio::spawn(track_pair(
+            rule,
+            provider,
+            tracked.clone(),
+            alerter.clone(),
+            settings.clone(),
+        )));
+    }
+    for rule in settings.config.subgraph.iter().cloned() {
+        let provider = providers[rule.rpc_url.as_ref().unwrap_or(&rpc_url)].clone();
+        handles.push(tokio::spawn(track_top_pools(
+            rule,
+            provider,
+            tracked.clone(),
+            alerter.clone(),
+            settings.clone(),
+        )));
+    }
+    for rule in settings.config.discovery.iter().cloned() {
+        let provider = providers[rule.rpc_url.as_ref().unwrap_or(&rpc_url)].clone();
+        handles.push(tokio::spawn(discover(
+            rule,
+            provider,
+            tracked.clone(),
+            alerter.clone(),
+            settings.clone(),
+        )));
+    }
+
     // spawn a separate async task for each pool
     // each task listens to swaps and updates price info concurrently
-    for pool_addr in pool_addresses {
-        let provider = provider.clone();
-        handles.push(tokio::spawn(async move {
-            listen_pool(pool_addr, provider).await
-        }));
+    for entry in pools {
+        let provider = providers[entry.rpc_url.as_ref().unwrap_or(&rpc_url)].clone();
+        handles.push(spawn_listener(
+            entry,
+            provider,
+            alerter.clone(),
+            settings.clone(),
+        ));
     }
 
-    for handle in handles {
-        match handle.await {
-            Ok(task_result) => match task_result {
-                Ok(_) => {}
-                Err(e) => {
-                    tracing::error!("Task returned an error: {:?}", e);
+    // every listener is started, systemd units with Type=notify count as up from here
+    #[cfg(unix)]
+    {
+        systemd::notify("READY=1");
+        systemd::spawn_watchdog();
+    }
+
+    // on shutdown the listeners stop first, so no new swaps reach the sinks while they
+    // are drained
+    let aborts: Vec<_> = handles.iter().map(|h| h.abort_handle()).collect();
+    let listeners = async {
+        for handle in handles {
+            match handle.await {
+                Ok(task_result) => match task_result {
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::error!("Task returned an error: {:?}", e);
+                    }
+                },
+                Err(join_err) => {
+                    tracing::error!("Task panicked: {:?}", join_err);
+                }
+            }
+        }
+    };
+    tokio::select! {
+        _ = listeners => {}
+        _ = shutdown::requested(&stop) => {
+            for abort in aborts {
+                abort.abort();
+            }
+        }
+    }
+
+    let timeout = Duration::from_secs(settings.config.shutdown_timeout_secs.unwrap_or(10));
+    shutdown::drain(&settings, timeout).await;
+
+    Ok(())
+}
+
+// store the swaps of the configured pools from `from` up to `to`, a time bound excludes its own
+// block, without `to` up to the latest block
+async fn backfill_swaps(from: Bound, to: Option<Bound>) -> Result<()> {
+    let (storage, _, tokens, pools) = stored_pools(Config::load()?).await?;
+
+    for (entry, provider) in pools {
+        let latest = match provider.get_block_number().await {
+            Ok(block) => block,
+            Err(e) => {
+                tracing::error!("Failed to fetch latest block: {}", e);
+                return Err(TrackerError::new(ProviderFailure::WSConnectionFailed)
+                    .with_source(e)
+                    .into());
+            }
+        };
+        let from_block = match from {
+            Bound::Block(block) => block,
+            Bound::Time(t) => first_block_at(&provider, t, latest).await?,
+        };
+        let to_block = match to {
+            Some(Bound::Block(block)) => block,
+            Some(Bound::Time(t)) => first_block_at(&provider, t, latest)
+                .await?
+                .saturating_sub(1),
+            None => latest,
+        };
+        if from_block > to_block {
+            tracing::error!("Empty range, blocks {} to {}", from_block, to_block);
+            continue;
+        }
+        // nodes with expired history fail on every old range, tell up front
+        let capabilities = chain::NodeCapabilities::probe(&provider).await;
+        if !capabilities.serves_block(&provider, from_block).await {
+            tracing::error!(
+                "The RPC does not serve block {} any more, use --subgraph for this range",
+                from_block
+            );
+            continue;
+        }
+
+        let result = match (entry.protocol, entry.address) {
+            (Protocol::UniswapV4, _) => {
+                let adapter = V4Adapter::new(entry);
+                backfill(adapter, provider, &tokens, &storage, from_block, to_block).await
+            }
+            (Protocol::UniswapV2, Some(address)) => {
+                let adapter = V2Adapter::new(address, entry.fee.unwrap_or(3000));
+                backfill(adapter, provider, &tokens, &storage, from_block, to_block).await
+            }
+            (protocol, Some(address)) => {
+                // their prices are read from the pool's current state, not the event
+                let Some(flavor) = v3_flavor(protocol) else {
+                    tracing::error!("Cannot backfill {:?} pool {:?}", protocol, address);
+                    continue;
+                };
+                let adapter = V3Adapter::new(address, flavor, entry.factory);
+                backfill(adapter, provider, &tokens, &storage, from_block, to_block).await
+            }
+            (protocol, None) => {
+                tracing::error!("{:?} pool entry without an address", protocol);
+                continue;
+            }
+        };
+        match result {
+            Ok(count) => info!("Stored {} swaps", count),
+            Err(e) => tracing::error!("Backfill failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// store the swaps of the configured V3 style pools between two unix timestamps from
+// a subgraph
+async fn backfill_subgraph(url: &str, from: u64, to: u64) -> Result<()> {
+    let (storage, _, tokens, pools) = stored_pools(Config::load()?).await?;
+
+    for (entry, provider) in pools {
+        let (Some(address), Some(flavor)) = (entry.address, v3_flavor(entry.protocol)) else {
+            tracing::error!(
+                "Cannot backfill {:?} pool {:?} from a subgraph",
+                entry.protocol,
+                entry.address
+            );
+            continue;
+        };
+        let adapter = V3Adapter::new(address, flavor, entry.factory);
+        match backfill_from_subgraph(adapter, provider, &tokens, &storage, url, from, to).await {
+            Ok(count) => info!("Stored {} swaps", count),
+            Err(e) => tracing::error!("Backfill failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// write the stored swaps of a pool, or candles built from them, to a file
+async fn export_swaps(
+    pool: Address,
+    from: Option<u64>,
+    to: Option<u64>,
+    format: cli::ExportFormat,
+    candles: Option<String>,
+    output: Option<String>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let Some(storage) = open_storage(&config)? else {
+        tracing::error!("DATABASE_PATH is needed to export swaps");
+        return Err(CommandError::DatabaseRequired.into());
+    };
+    let (from, to) = (from.unwrap_or(0), to.unwrap_or(u64::MAX));
+    let extension = match format {
+        cli::ExportFormat::Csv => "csv",
+        cli::ExportFormat::Jsonl => "jsonl",
+        #[cfg(feature = "parquet")]
+        cli::ExportFormat::Parquet => "parquet",
+    };
+
+    match candles {
+        Some(interval_str) => {
+            let Some(interval) = storage::parse_interval(&interval_str) else {
+                tracing::error!("Invalid candle interval {}", interval_str);
+                return Err(CommandError::InvalidInterval(interval_str).into());
+            };
+            check_candle_interval(&config, interval, &interval_str)?;
+            let path =
+                output.unwrap_or_else(|| format!("{:?}-{}.{}", pool, interval_str, extension));
+            let candles = storage.candles(pool, interval, from, to).await?;
+            export::write_candles(&path, format, &candles)?;
+            info!("Exported {} candles to {}", candles.len(), path);
+        }
+        None => {
+            let path = output.unwrap_or_else(|| format!("{:?}-swaps.{}", pool, extension));
+            let swaps = storage.swaps(vec![pool], from, to).await?;
+            export::write_swaps(&path, format, &swaps)?;
+            info!("Exported {} swaps to {}", swaps.len(), path);
+        }
+    }
+    Ok(())
+}
+
+// print the stored swaps of a pool, or candles built from them, without a SQL client
+async fn query_prices(
+    pool: Address,
+    from: Option<u64>,
+    to: Option<u64>,
+    agg: Option<String>,
+    json: bool,
+) -> Result<()> {
+    let config = Config::load()?;
+    let Some(storage) = open_storage(&config)? else {
+        tracing::error!("DATABASE_PATH is needed to query prices");
+        return Err(CommandError::DatabaseRequired.into());
+    };
+    let to = to.unwrap_or_else(|| storage::unix_now() + 1);
+    let from = from.unwrap_or_else(|| to.saturating_sub(86_400));
+    let time = |t: u64| {
+        chrono::DateTime::from_timestamp(t as i64, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| t.to_string())
+    };
+
+    let Some(interval_str) = agg else {
+        let swaps = storage.swaps(vec![pool], from, to).await?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&swaps)?);
+            return Ok(());
+        }
+        println!(
+            "{:<19} {:>10} {:>24} {:>20}  tx",
+            "time (UTC)", "block", "price", "amount0"
+        );
+        for s in &swaps {
+            println!(
+                "{:<19} {:>10} {:>24} {:>20.4}  {}",
+                time(s.timestamp),
+                s.block,
+                s.price,
+                s.amount0,
+                s.tx.map(|tx| format!("{:?}", tx)).unwrap_or_default()
+            );
+        }
+        println!("{} swaps", swaps.len());
+        return Ok(());
+    };
+
+    let Some(interval) = storage::parse_interval(&interval_str) else {
+        tracing::error!("Invalid candle interval {}", interval_str);
+        return Err(CommandError::InvalidInterval(interval_str).into());
+    };
+    check_candle_interval(&config, interval, &interval_str)?;
+    let candles = storage.candles(pool, interval, from, to).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&candles)?);
+        return Ok(());
+    }
+    println!(
+        "{:<19} {:>20} {:>20} {:>20} {:>20} {:>16} {:>8}",
+        "time (UTC)", "open", "high", "low", "close", "volume0", "swaps"
+    );
+    for c in &candles {
+        println!(
+            "{:<19} {:>20} {:>20} {:>20} {:>20} {:>16.4} {:>8}",
+            time(c.time),
+            c.open,
+            c.high,
+            c.low,
+            c.close,
+            c.volume0,
+            c.swaps
+        );
+    }
+    println!("{} candles", candles.len());
+    Ok(())
+}
+
+// replay the stored swaps of the configured pools between two unix timestamps through
+// the price calculation and alert rules
+async fn replay_swaps(from: u64, to: u64, notify: bool) -> Result<()> {
+    let config = Config::load()?;
+    let chain = chain_preset(&config)?;
+    let explorer_url =
+        (config.explorer_url.clone()).unwrap_or_else(|| chain.explorer_url.to_string());
+    let (storage, config, tokens, pools) = stored_pools(config).await?;
+
+    let mut loaded = Vec::with_capacity(pools.len());
+    for (entry, provider) in pools {
+        match load_entry(entry, &provider, &tokens).await {
+            Ok(Some(pool)) => loaded.push(pool),
+            Ok(None) => {}
+            Err(e) => tracing::error!("Skipping pool: {}", e),
+        }
+    }
+
+    // reference feeds are live only, so deviation and basis rules stay silent
+    let settings = Arc::new(ListenerSettings {
+        stall_timeout: None,
+        ws_stall_timeout: None,
+        confirmations: 0,
+        explorer_url,
+        chain,
+        config,
+        references: reference::ReferencePrices::default(),
+        state: state::SharedState::default(),
+        tokens,
+        proxies: token::ProxyWatcher::default(),
+        writer: None,
+        checkpoints: None,
+        events: broadcast::channel(pool::EVENTS_CAPACITY).0,
+        errors: broadcast::channel(pool::EVENTS_CAPACITY).0,
+        all_events: false,
+    });
+    let alerter = Alerter::from_env()
+        .with_escalations(&settings.config.escalation)
+        .for_replay(notify);
+
+    let count = replay(loaded, &storage, from, to, Arc::new(alerter), settings).await?;
+    info!("Replayed {} swaps", count);
+    Ok(())
+}
+
+// metadata of a configured pool and its chain id, None for entries without an address
+async fn load_entry<P: Provider>(
+    entry: PoolEntry,
+    provider: &P,
+    tokens: &tokenlist::TokenList,
+) -> Result<Option<(pool::PoolInfo, u64)>> {
+    let pool = match (entry.protocol, entry.address) {
+        (Protocol::UniswapV4, _) => load_pool(V4Adapter::new(entry), provider, tokens).await?,
+        (Protocol::UniswapV2, Some(address)) => {
+            let fee = entry.fee.unwrap_or(3000);
+            load_pool(V2Adapter::new(address, fee), provider, tokens).await?
+        }
+        (Protocol::Curve, Some(address)) => {
+            let [i, j] = entry.coins.unwrap_or([0, 1]);
+            load_pool(CurveAdapter::new(address, (i, j)), provider, tokens).await?
+        }
+        (Protocol::BalancerWeighted, Some(address)) => {
+            let [i, j] = entry.coins.unwrap_or([0, 1]);
+            load_pool(WeightedPoolAdapter::new(address, (i, j)), provider, tokens).await?
+        }
+        (protocol, Some(address)) => {
+            let Some(flavor) = v3_flavor(protocol) else {
+                return Ok(None);
+            };
+            let adapter = V3Adapter::new(address, flavor, entry.factory);
+            load_pool(adapter, provider, tokens).await?
+        }
+        (protocol, None) => {
+            tracing::error!("{:?} pool entry without an address", protocol);
+            return Ok(None);
+        }
+    };
+    Ok(Some(pool))
+}
+
+fn v3_flavor(protocol: Protocol) -> Option<V3Flavor> {
+    match protocol {
+        Protocol::UniswapV3 => Some(V3Flavor::Uniswap),
+        Protocol::SushiswapV3 => Some(V3Flavor::SushiSwap),
+        Protocol::PancakeswapV3 => Some(V3Flavor::PancakeSwap),
+        Protocol::Algebra => Some(V3Flavor::Algebra),
+        Protocol::Slipstream => Some(V3Flavor::Slipstream),
+        _ => None,
+    }
+}
+
+// read the current state of every configured pool once and print it
+async fn snapshot_pools(json: bool) -> Result<()> {
+    let (_, tokens, pools) = configured_pools(Config::load()?).await?;
+
+    let mut snapshots = Vec::with_capacity(pools.len());
+    for (entry, provider) in pools {
+        let Some(result) = snapshot_entry(entry, &provider, &tokens).await else {
+            continue;
+        };
+        match result {
+            Ok(s) => snapshots.push(s),
+            Err(e) => tracing::error!("Skipping pool: {}", e),
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&snapshots)?);
+        return Ok(());
+    }
+    println!(
+        "{:<42} {:<8} {:<14} {:<16} {:>7} {:>24} {:>40}",
+        "pool", "chain", "protocol", "pair", "fee", "price", "liquidity"
+    );
+    for s in &snapshots {
+        println!(
+            "{:<42} {:<8} {:<14} {:<16} {:>6.2}% {:>24} {:>40}",
+            format!("{:?}", s.pool),
+            s.chain_id,
+            s.protocol,
+            format!("{}/{}", s.symbol0, s.symbol1),
+            s.fee as f64 / 10_000.0,
+            s.price.map_or("-".to_string(), |p| p.to_string()),
+            s.liquidity.map_or("-".to_string(), |l| l.to_string())
+        );
+    }
+    Ok(())
+}
+
+// volume of the configured pools over the last `period_str` from the stored swaps
+async fn report_volume(period_str: String, json: bool) -> Result<()> {
+    let Some(period) = storage::parse_interval(&period_str) else {
+        tracing::error!("Invalid report period {}", period_str);
+        return Err(CommandError::InvalidInterval(period_str).into());
+    };
+    let (storage, _, tokens, pools) = stored_pools(Config::load()?).await?;
+
+    let mut infos = Vec::with_capacity(pools.len());
+    for (entry, provider) in pools {
+        match load_entry(entry, &provider, &tokens).await {
+            Ok(Some((info, _))) => infos.push(report::ReportedPool::from(&info)),
+            Ok(None) => {}
+            Err(e) => tracing::error!("Skipping pool: {}", e),
+        }
+    }
+
+    let now = storage::unix_now();
+    let report = report::volume_report(&infos, &storage, now.saturating_sub(period), now).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    println!(
+        "{:<42} {:<16} {:>8} {:>20} {:>20} {:>16}",
+        "pool", "pair", "swaps", "volume0", "volume1", "usd"
+    );
+    for p in &report.pools {
+        println!(
+            "{:<42} {:<16} {:>8} {:>20.4} {:>20.4} {:>16}",
+            format!("{:?}", p.pool),
+            format!("{}/{}", p.symbol0, p.symbol1),
+            p.swaps,
+            p.volume0,
+            p.volume1,
+            p.volume_usd
+                .map_or("-".to_string(), |v| format!("{:.2}", v))
+        );
+    }
+    println!(
+        "{:<42} {:<16} {:>8} {:>20} {:>20} {:>16.2}",
+        "total", "", report.swaps, "", "", report.volume_usd
+    );
+    Ok(())
+}
+
+// store an external price series from a CSV file
+async fn import_prices(
+    series: String,
+    file: String,
+    time_column: usize,
+    price_column: usize,
+) -> Result<()> {
+    let config = Config::load()?;
+    let Some(storage) = open_storage(&config)? else {
+        tracing::error!("DATABASE_PATH is needed to import prices");
+        return Err(CommandError::DatabaseRequired.into());
+    };
+    let prices = import::read_prices(&file, time_column, price_column)?;
+    let count = prices.len();
+    storage
+        .record_external_prices(series.clone(), prices)
+        .await?;
+    info!("Imported {} prices of {} from {}", count, series, file);
+    Ok(())
+}
+
+// basis and tracking error of a pool's stored prices against an imported series
+async fn report_compare(
+    pool: Address,
+    series: String,
+    from: Option<u64>,
+    to: Option<u64>,
+    interval_str: String,
+    invert: bool,
+    json: bool,
+) -> Result<()> {
+    let Some(interval) = storage::parse_interval(&interval_str) else {
+        tracing::error!("Invalid interval {}", interval_str);
+        return Err(CommandError::InvalidInterval(interval_str).into());
+    };
+    let config = Config::load()?;
+    let Some(storage) = open_storage(&config)? else {
+        tracing::error!("DATABASE_PATH is needed to compare prices");
+        return Err(CommandError::DatabaseRequired.into());
+    };
+    let (from, to) = (
+        from.unwrap_or(0),
+        to.unwrap_or_else(|| storage::unix_now() + 1),
+    );
+
+    let Some(comparison) =
+        report::compare(&storage, pool, &series, interval, from, to, invert).await?
+    else {
+        println!(
+            "No {} interval has both a swap of {:?} and a price of {}",
+            interval_str, pool, series
+        );
+        return Ok(());
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        return Ok(());
+    }
+    println!(
+        "{:?} against {}, {} intervals of {}",
+        pool,
+        series,
+        comparison.intervals.len(),
+        interval_str
+    );
+    println!(
+        "basis: mean {:.2} bps, mean absolute {:.2} bps, stddev {:.2} bps, min {:.2} bps, max {:.2} bps",
+        comparison.mean_basis_bps,
+        comparison.mean_abs_basis_bps,
+        comparison.stddev_basis_bps,
+        comparison.min_basis_bps,
+        comparison.max_basis_bps
+    );
+    match comparison.tracking_error_bps {
+        Some(te) => println!("tracking error: {:.2} bps per interval", te),
+        None => println!("tracking error: too few adjacent intervals"),
+    }
+    Ok(())
+}
+
+// price of one configured pool at a past block, or before and after a transaction
+async fn print_price_at(
+    pool: Address,
+    block: Option<u64>,
+    tx: Option<B256>,
+    json: bool,
+) -> Result<()> {
+    let config = Config::load()?;
+    let storage = open_storage(&config)?;
+    let (_, tokens, pools) = configured_pools(config).await?;
+    let Some((entry, provider)) = pools.into_iter().find(|(e, _)| e.address == Some(pool)) else {
+        tracing::error!("Pool {:?} is not in POOLS or the config file", pool);
+        return Err(CommandError::PoolNotConfigured(pool).into());
+    };
+
+    let blocks = match (block, tx) {
+        (Some(block), _) => vec![block],
+        (None, Some(tx)) => match provider.get_transaction_receipt(tx).await {
+            Ok(Some(receipt)) => {
+                let block = receipt.block_number.unwrap_or_default();
+                vec![block.saturating_sub(1), block]
+            }
+            Ok(None) => {
+                tracing::error!("Transaction {:?} not found", tx);
+                return Err(CommandError::TransactionNotFound(tx).into());
+            }
+            Err(e) => {
+                tracing::error!("Failed to fetch receipt of {:?}: {}", tx, e);
+                return Err(TrackerError::new(ProviderFailure::WSConnectionFailed)
+                    .with_source(e)
+                    .into());
+            }
+        },
+        (None, None) => Vec::new(),
+    };
+
+    // without an archive node only the last blocks' state can be read
+    let capabilities = chain::NodeCapabilities::probe(&provider).await;
+    let latest = match provider.get_block_number().await {
+        Ok(block) => block,
+        Err(e) => {
+            tracing::error!("Failed to fetch latest block: {}", e);
+            return Err(TrackerError::new(ProviderFailure::WSConnectionFailed)
+                .with_source(e)
+                .into());
+        }
+    };
+    if !capabilities.archive {
+        info!("RPC is not an archive node, older blocks are priced from stored swaps");
+    }
+
+    let mut prices = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        let has_state = capabilities.archive || block + chain::RECENT_STATE_BLOCKS >= latest;
+        let entry = entry.clone();
+        let storage = storage.as_ref();
+        let result = match (entry.protocol, entry.address) {
+            (Protocol::UniswapV2, Some(address)) => {
+                let adapter = V2Adapter::new(address, entry.fee.unwrap_or(3000));
+                price_at_block(adapter, &provider, &tokens, block, has_state, storage).await
+            }
+            (Protocol::Curve, Some(address)) => {
+                let [i, j] = entry.coins.unwrap_or([0, 1]);
+                let adapter = CurveAdapter::new(address, (i, j));
+                price_at_block(adapter, &provider, &tokens, block, has_state, storage).await
+            }
+            (Protocol::BalancerWeighted, Some(address)) => {
+                let [i, j] = entry.coins.unwrap_or([0, 1]);
+                let adapter = WeightedPoolAdapter::new(address, (i, j));
+                price_at_block(adapter, &provider, &tokens, block, has_state, storage).await
+            }
+            (protocol, _) => match v3_flavor(protocol) {
+                Some(flavor) => {
+                    let adapter = V3Adapter::new(pool, flavor, entry.factory);
+                    price_at_block(adapter, &provider, &tokens, block, has_state, storage).await
+                }
+                None => {
+                    let adapter = V4Adapter::new(entry);
+                    price_at_block(adapter, &provider, &tokens, block, has_state, storage).await
                 }
             },
-            Err(join_err) => {
-                tracing::error!("Task panicked: {:?}", join_err);
+        };
+        prices.push(result?);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&prices)?);
+        return Ok(());
+    }
+    for p in &prices {
+        let source = match p.swap_block {
+            Some(swap_block) => format!("stored swap in block {}", swap_block),
+            None => p.source.to_string(),
+        };
+        println!(
+            "{}/{} at block {}: {} ({})",
+            p.symbol0, p.symbol1, p.block, p.price, source
+        );
+    }
+    Ok(())
+}
+
+// time weighted average price of one configured pool over the window
+async fn print_twap(pool: Address, window_str: String, json: bool) -> Result<()> {
+    let Some(window) = storage::parse_interval(&window_str) else {
+        tracing::error!("Invalid TWAP window {}", window_str);
+        return Err(CommandError::InvalidInterval(window_str).into());
+    };
+    let config = Config::load()?;
+    let storage = open_storage(&config)?;
+    let (_, tokens, pools) = configured_pools(config).await?;
+    let Some((entry, provider)) = pools.into_iter().find(|(e, _)| e.address == Some(pool)) else {
+        tracing::error!("Pool {:?} is not in POOLS or the config file", pool);
+        return Err(CommandError::PoolNotConfigured(pool).into());
+    };
+
+    let storage = storage.as_ref();
+    let result = match (entry.protocol, entry.address) {
+        (Protocol::UniswapV2, Some(address)) => {
+            let fee = entry.fee.unwrap_or(3000);
+            twap(
+                V2Adapter::new(address, fee),
+                &provider,
+                &tokens,
+                window,
+                storage,
+            )
+            .await
+        }
+        (Protocol::Curve, Some(address)) => {
+            let [i, j] = entry.coins.unwrap_or([0, 1]);
+            let adapter = CurveAdapter::new(address, (i, j));
+            twap(adapter, &provider, &tokens, window, storage).await
+        }
+        (Protocol::BalancerWeighted, Some(address)) => {
+            let [i, j] = entry.coins.unwrap_or([0, 1]);
+            let adapter = WeightedPoolAdapter::new(address, (i, j));
+            twap(adapter, &provider, &tokens, window, storage).await
+        }
+        (protocol, _) => match v3_flavor(protocol) {
+            Some(flavor) => {
+                let adapter = V3Adapter::new(pool, flavor, entry.factory);
+                twap(adapter, &provider, &tokens, window, storage).await
+            }
+            None => twap(V4Adapter::new(entry), &provider, &tokens, window, storage).await,
+        },
+    };
+    let twap = result?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&twap)?);
+        return Ok(());
+    }
+    println!(
+        "{}/{} over {}: {} ({})",
+        twap.symbol0, twap.symbol1, window_str, twap.price, twap.source
+    );
+    Ok(())
+}
+
+// current state of a configured pool, None for entries without an address
+async fn snapshot_entry<P: Provider>(
+    entry: PoolEntry,
+    provider: &P,
+    tokens: &tokenlist::TokenList,
+) -> Option<Result<pool::Snapshot>> {
+    let result = match (entry.protocol, entry.address) {
+        (Protocol::UniswapV4, _) => snapshot(V4Adapter::new(entry), provider, tokens).await,
+        (Protocol::UniswapV2, Some(address)) => {
+            let fee = entry.fee.unwrap_or(3000);
+            snapshot(V2Adapter::new(address, fee), provider, tokens).await
+        }
+        (Protocol::Curve, Some(address)) => {
+            let [i, j] = entry.coins.unwrap_or([0, 1]);
+            snapshot(CurveAdapter::new(address, (i, j)), provider, tokens).await
+        }
+        (Protocol::BalancerWeighted, Some(address)) => {
+            let [i, j] = entry.coins.unwrap_or([0, 1]);
+            snapshot(WeightedPoolAdapter::new(address, (i, j)), provider, tokens).await
+        }
+        (protocol, Some(address)) => {
+            let flavor = v3_flavor(protocol)?;
+            let adapter = V3Adapter::new(address, flavor, entry.factory);
+            snapshot(adapter, provider, tokens).await
+        }
+        (protocol, None) => {
+            tracing::error!("{:?} pool entry without an address", protocol);
+            return None;
+        }
+    };
+    Some(result)
+}
+
+// validate the config and every connection without listening, one line per check
+async fn check(notify: bool) -> Result<()> {
+    let mut total = 0;
+    let mut failed = 0;
+    let mut report = |name: &str, result: Result<String, String>| {
+        total += 1;
+        match result {
+            Ok(detail) => println!("{:<48} ok      {}", name, detail),
+            Err(e) => {
+                failed += 1;
+                println!("{:<48} FAILED  {}", name, e);
+            }
+        }
+    };
+
+    let config = match Config::load() {
+        Ok(config) => {
+            report("config", Ok(format!("{} pools", config.pools.len())));
+            config
+        }
+        Err(e) => {
+            report("config", Err(e.to_string()));
+            return Err(CommandError::CheckFailed(1, 1).into());
+        }
+    };
+    let chain = match chain_preset(&config) {
+        Ok(chain) => chain,
+        Err(e) => {
+            report("chain", Err(e.to_string()));
+            return Err(CommandError::CheckFailed(1, 2).into());
+        }
+    };
+
+    let mut pools = config.pools.clone();
+    if pools.is_empty() {
+        pools.push(PoolEntry {
+            address: Some(chain.usd_pool),
+            factory: Some(chain.uniswap_v3_factory),
+            ..PoolEntry::default()
+        });
+    }
+
+    let rpc_url = (config.rpc_url.clone()).unwrap_or_else(|| chain.rpc_url.to_string());
+    let providers = match connect_all(&rpc_url, &pools).await {
+        Ok(providers) => providers,
+        Err(e) => {
+            report("rpc", Err(e.to_string()));
+            return Err(CommandError::CheckFailed(1, 2).into());
+        }
+    };
+    for (url, provider) in &providers {
+        let chain_id = match provider.get_chain_id().await {
+            Ok(id) if url == &rpc_url && id != chain.chain_id => Err(format!(
+                "serves chain {} but CHAIN is {} ({})",
+                id, chain.name, chain.chain_id
+            )),
+            Ok(id) => Ok(format!("chain {}", id)),
+            Err(e) => Err(e.to_string()),
+        };
+        report(&format!("rpc {}", url), chain_id);
+        let subscription = match provider.subscribe_blocks().await {
+            Ok(_) => Ok(String::new()),
+            Err(e) => Err(e.to_string()),
+        };
+        report(&format!("subscription {}", url), subscription);
+        // informational, the tracker itself needs neither
+        let capabilities = chain::NodeCapabilities::probe(provider).await;
+        report(&format!("archive {}", url), Ok(capabilities.describe()));
+    }
+
+    if let Some(path) = &config.database_path {
+        let database = match open_storage(&config) {
+            Ok(_) => Ok(path.clone()),
+            Err(e) => Err(e.to_string()),
+        };
+        report("database", database);
+    }
+
+    let tokens = load_token_list(&config).await;
+    for mut entry in pools {
+        let provider = &providers[entry.rpc_url.as_ref().unwrap_or(&rpc_url)];
+        let name = match (&entry.pair, entry.address) {
+            (_, Some(address)) => format!("pool {:?}", address),
+            (Some(spec), None) => format!("pool {}", spec),
+            (None, None) => "pool".to_string(),
+        };
+        if let (None, Some(spec)) = (entry.address, &entry.pair) {
+            match find_pool(
+                spec,
+                entry.protocol,
+                entry.factory,
+                provider,
+                &tokens,
+                chain,
+            )
+            .await
+            {
+                Ok(address) => entry.address = Some(address),
+                Err(e) => {
+                    report(&name, Err(e.to_string()));
+                    continue;
+                }
             }
         }
+        let Some(result) = snapshot_entry(entry, provider, &tokens).await else {
+            report(&name, Err("no address".to_string()));
+            continue;
+        };
+        report(
+            &name,
+            result
+                .map(|s| {
+                    format!(
+                        "{} {}/{} {:.2}% price {}",
+                        s.protocol,
+                        s.symbol0,
+                        s.symbol1,
+                        s.fee as f64 / 10_000.0,
+                        s.price.map_or("-".to_string(), |p| p.to_string())
+                    )
+                })
+                .map_err(|e| e.to_string()),
+        );
     }
 
+    let alerter = Alerter::from_env();
+    if notify {
+        for (channel, result) in alerter.test_backends().await {
+            report(
+                &format!("alert {}", channel),
+                result
+                    .map(|_| "test alert sent".to_string())
+                    .map_err(|e| e.to_string()),
+            );
+        }
+    } else {
+        for channel in alerter.channels() {
+            println!(
+                "{:<48} -       configured, --notify sends a test alert",
+                format!("alert {}", channel)
+            );
+        }
+    }
+
+    println!("{} of {} checks failed", failed, total);
+    if failed > 0 {
+        return Err(CommandError::CheckFailed(failed, total).into());
+    }
+    Ok(())
+}
+
+// one provider per endpoint, RPC_URL and every pool's own rpc_url
+async fn connect_all(
+    rpc_url: &str,
+    pools: &[PoolEntry],
+) -> Result<HashMap<String, impl Provider + Clone + use<>>> {
+    let mut providers = HashMap::new();
+    providers.insert(rpc_url.to_string(), connect(rpc_url).await?);
+    for url in pools.iter().filter_map(|p| p.rpc_url.clone()) {
+        if let Entry::Vacant(slot) = providers.entry(url) {
+            let provider = connect(slot.key()).await?;
+            slot.insert(provider);
+        }
+    }
+    Ok(providers)
+}
+
+// the database and the configured pools, each with the provider of its chain, for the
+// subcommands working on stored swaps
+async fn stored_pools(
+    config: Config,
+) -> Result<(
+    storage::Storage,
+    Config,
+    tokenlist::TokenList,
+    Vec<(PoolEntry, impl Provider + Clone + 'static)>,
+)> {
+    let Some(storage) = open_storage(&config)? else {
+        tracing::error!("DATABASE_PATH is needed to store and read swaps");
+        return Err(CommandError::DatabaseRequired.into());
+    };
+    let (config, tokens, pools) = configured_pools(config).await?;
+    Ok((storage, config, tokens, pools))
+}
+
+// the configured pools, pair specs resolved, each with the provider of its chain
+async fn configured_pools(
+    config: Config,
+) -> Result<(
+    Config,
+    tokenlist::TokenList,
+    Vec<(PoolEntry, impl Provider + Clone + 'static)>,
+)> {
+    let chain = chain_preset(&config)?;
+    let rpc_url = (config.rpc_url.clone()).unwrap_or_else(|| chain.rpc_url.to_string());
+
+    console::set_number_format(&config.format);
+    let mut pools = config.pools.clone();
+    // like the listener, the chain's WETH/USDC pool when nothing is configured
+    if pools.is_empty() {
+        pools.push(PoolEntry {
+            address: Some(chain.usd_pool),
+            factory: Some(chain.uniswap_v3_factory),
+            ..PoolEntry::default()
+        });
+    }
+    let tokens = load_token_list(&config).await;
+
+    let providers = connect_all(&rpc_url, &pools).await?;
+    let pools = resolve_pair_specs(pools, &providers, &rpc_url, &tokens, chain)
+        .await
+        .into_iter()
+        .map(|entry| {
+            let provider = providers[entry.rpc_url.as_ref().unwrap_or(&rpc_url)].clone();
+            (entry, provider)
+        })
+        .collect();
+    Ok((config, tokens, pools))
+}
+
+// the CHAIN preset, Ethereum unless set
+fn chain_preset(config: &Config) -> Result<&'static chain::ChainPreset> {
+    match &config.chain {
+        Some(name) => match chain::preset(name) {
+            Some(preset) => Ok(preset),
+            None => {
+                tracing::error!("Unknown CHAIN {}", name);
+                Err(chain::ChainError::UnknownChain(name.clone()).into())
+            }
+        },
+        None => Ok(&chain::PRESETS[0]),
+    }
+}
+
+// candles shorter than a block of CHAIN are refused, see ChainPreset::min_candle_interval
+fn check_candle_interval(config: &Config, interval: u64, interval_str: &str) -> Result<()> {
+    let chain = chain_preset(config)?;
+    if interval < chain.min_candle_interval() {
+        tracing::error!(
+            "Candle interval {} is shorter than a block on {}, use at least {}s",
+            interval_str,
+            chain.name,
+            chain.min_candle_interval()
+        );
+        return Err(CommandError::InvalidInterval(interval_str.to_string()).into());
+    }
     Ok(())
 }
+
+// token list for symbols in pool specs and as fallback for token metadata
+async fn load_token_list(config: &Config) -> tokenlist::TokenList {
+    let token_list_cache = (config.token_list_cache.as_deref()).unwrap_or("tokenlist.cache.json");
+    tokenlist::TokenList::load(
+        config.token_list_url.as_deref(),
+        Path::new(token_list_cache),
+    )
+    .await
+    .with_overrides(&config.tokens)
+}
+
+// SQLite database for the alert audit log and swaps, disabled unless DATABASE_PATH is set
+fn open_storage(config: &Config) -> Result<Option<storage::Storage>> {
+    match &config.database_path {
+        Some(path) => Ok(Some(storage::Storage::open(path)?)),
+        None => Ok(None),
+    }
+}
+
+// pools configured by token symbols, resolved through the factory of their chain
+async fn resolve_pair_specs<P: Provider>(
+    pools: Vec<PoolEntry>,
+    providers: &HashMap<String, P>,
+    rpc_url: &String,
+    tokens: &tokenlist::TokenList,
+    chain: &chain::ChainPreset,
+) -> Vec<PoolEntry> {
+    let mut resolved = Vec::with_capacity(pools.len());
+    for mut entry in pools {
+        if let (None, Some(spec)) = (entry.address, &entry.pair) {
+            let provider = &providers[entry.rpc_url.as_ref().unwrap_or(rpc_url)];
+            match find_pool(spec, entry.protocol, entry.factory, provider, tokens, chain).await {
+                Ok(address) => entry.address = Some(address),
+                Err(e) => {
+                    tracing::error!("Skipping pool {}: {}", spec, e);
+                    continue;
+                }
+            }
+        }
+        resolved.push(entry);
+    }
+    resolved
+}
@@ -0,0 +1,345 @@
+mod rpc;
+
+pub use rpc::RpcCountLayer;
+
+use crate::health::{self, EndpointHealth};
+use crate::state::TrackerState;
+use alloy::primitives::Address;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// upper bounds of the histogram buckets in seconds, 10µs to 10s
+const BUCKETS: [f64; 13] = [
+    0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0,
+];
+
+// time spent in one step of the pipeline, rendered as a Prometheus histogram
+pub struct Histogram {
+    // observations per bucket, not cumulative
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Histogram {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, bucket) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, cumulative
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, count
+        );
+        let braces = |labels: &str| {
+            if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels)
+            }
+        };
+        let sum = self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(out, "{}_sum{} {}", name, braces(labels), sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braces(labels), count);
+    }
+}
+
+// decoding a pool log into a swap or mint, including any calls the adapter makes
+pub static DECODE: Histogram = Histogram::new();
+// turning a swap's sqrtPriceX96 or reserves into a price
+pub static PRICE: Histogram = Histogram::new();
+// inserting a batch of swaps into the database
+pub static STORAGE_SINK: Histogram = Histogram::new();
+// delivering an alert to one notification backend
+pub static ALERT_SINK: Histogram = Histogram::new();
+
+// log subscriptions set up again after the node ended them
+pub static RESUBSCRIPTIONS: AtomicU64 = AtomicU64::new(0);
+// subscriptions that went silent while blocks were produced, see WS_STALL_SECS
+pub static STALLS: AtomicU64 = AtomicU64::new(0);
+// logs a pool adapter failed to decode, each stops its listener
+pub static DECODE_FAILURES: AtomicU64 = AtomicU64::new(0);
+// swaps queued for the database writer
+pub static WRITE_QUEUE: AtomicU64 = AtomicU64::new(0);
+
+// requests sent to the nodes, by JSON-RPC method
+static RPC_REQUESTS: LazyLock<Mutex<HashMap<String, u64>>> = LazyLock::new(Default::default);
+
+pub fn count_rpc_request(method: &str) {
+    let mut requests = RPC_REQUESTS.lock().unwrap();
+    match requests.get_mut(method) {
+        Some(count) => *count += 1,
+        None => {
+            requests.insert(method.to_string(), 1);
+        }
+    }
+}
+
+// latest swaps per pool the latency percentiles are taken over
+const LATENCY_SAMPLES: usize = 1_000;
+
+// seconds from the block timestamp of each swap to its price being handed to the sinks,
+// by pool
+static SWAP_LATENCY: LazyLock<Mutex<HashMap<Address, VecDeque<f64>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapLatency {
+    pub samples: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+// record how long after its block a swap of `pool` was emitted, `block_timestamp` has
+// whole seconds so single values are only accurate to a second
+pub fn observe_swap_latency(pool: Address, block_timestamp: u64) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    let latency = (now - block_timestamp as f64).max(0.0);
+
+    let mut latencies = SWAP_LATENCY.lock().unwrap();
+    let samples = latencies.entry(pool).or_default();
+    if samples.len() == LATENCY_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(latency);
+}
+
+// percentiles over the last LATENCY_SAMPLES swaps of a pool
+pub fn swap_latency(pool: Address) -> Option<SwapLatency> {
+    let latencies = SWAP_LATENCY.lock().unwrap();
+    percentiles(latencies.get(&pool)?)
+}
+
+fn percentiles(samples: &VecDeque<f64>) -> Option<SwapLatency> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<f64> = samples.iter().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    let at = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+    Some(SwapLatency {
+        samples: sorted.len(),
+        p50: at(0.5),
+        p95: at(0.95),
+        p99: at(0.99),
+    })
+}
+
+// every histogram, counter and gauge in the Prometheus text format
+pub fn render(state: &TrackerState) -> String {
+    let mut out = String::new();
+    render_counters(&mut out);
+    render_endpoints(&mut out, state);
+    let _ = writeln!(
+        out,
+        "# HELP tracker_decode_seconds Time to decode a pool log\n# TYPE tracker_decode_seconds histogram"
+    );
+    DECODE.render(&mut out, "tracker_decode_seconds", "");
+    let _ = writeln!(
+        out,
+        "# HELP tracker_price_seconds Time to calculate the price of a swap\n# TYPE tracker_price_seconds histogram"
+    );
+    PRICE.render(&mut out, "tracker_price_seconds", "");
+    let _ = writeln!(
+        out,
+        "# HELP tracker_sink_seconds Time to write a swap batch or deliver an alert\n# TYPE tracker_sink_seconds histogram"
+    );
+    STORAGE_SINK.render(&mut out, "tracker_sink_seconds", "sink=\"storage\"");
+    ALERT_SINK.render(&mut out, "tracker_sink_seconds", "sink=\"alert\"");
+
+    let _ = writeln!(
+        out,
+        "# HELP tracker_swap_latency_seconds Time from a swap's block to its price being emitted\n# TYPE tracker_swap_latency_seconds summary"
+    );
+    let latencies = SWAP_LATENCY.lock().unwrap();
+    let mut pools: Vec<&Address> = latencies.keys().collect();
+    pools.sort();
+    for pool in pools {
+        let samples = &latencies[pool];
+        let Some(latency) = percentiles(samples) else {
+            continue;
+        };
+        for (quantile, value) in [(0.5, latency.p50), (0.95, latency.p95), (0.99, latency.p99)] {
+            let _ = writeln!(
+                out,
+                "tracker_swap_latency_seconds{{pool=\"{:?}\",quantile=\"{}\"}} {}",
+                pool, quantile, value
+            );
+        }
+        let _ = writeln!(
+            out,
+            "tracker_swap_latency_seconds_sum{{pool=\"{:?}\"}} {}",
+            pool,
+            samples.iter().sum::<f64>()
+        );
+        let _ = writeln!(
+            out,
+            "tracker_swap_latency_seconds_count{{pool=\"{:?}\"}} {}",
+            pool,
+            samples.len()
+        );
+    }
+    out
+}
+
+fn render_counters(out: &mut String) {
+    let _ = writeln!(
+        out,
+        "# HELP tracker_rpc_requests_total JSON-RPC requests sent to the nodes\n# TYPE tracker_rpc_requests_total counter"
+    );
+    let requests = RPC_REQUESTS.lock().unwrap();
+    let mut methods: Vec<(&String, &u64)> = requests.iter().collect();
+    methods.sort();
+    for (method, count) in methods {
+        let _ = writeln!(
+            out,
+            "tracker_rpc_requests_total{{method=\"{}\"}} {}",
+            method, count
+        );
+    }
+    drop(requests);
+
+    for (name, help, kind, value) in [
+        (
+            "tracker_resubscriptions_total",
+            "Log subscriptions set up again after the node ended them",
+            "counter",
+            RESUBSCRIPTIONS.load(Ordering::Relaxed),
+        ),
+        (
+            "tracker_ws_stalls_total",
+            "Subscriptions that went silent while blocks were produced",
+            "counter",
+            STALLS.load(Ordering::Relaxed),
+        ),
+        (
+            "tracker_decode_failures_total",
+            "Pool logs that failed to decode",
+            "counter",
+            DECODE_FAILURES.load(Ordering::Relaxed),
+        ),
+        (
+            "tracker_write_queue_swaps",
+            "Swaps queued for the database writer",
+            "gauge",
+            WRITE_QUEUE.load(Ordering::Relaxed),
+        ),
+    ] {
+        let _ = writeln!(
+            out,
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+        );
+    }
+
+    // tasks and the runtime queue, listeners and sink tasks that pile up show here
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        let metrics = runtime.metrics();
+        let _ = writeln!(
+            out,
+            "# HELP tracker_tasks Tasks alive in the runtime\n# TYPE tracker_tasks gauge\ntracker_tasks {}",
+            metrics.num_alive_tasks()
+        );
+        let _ = writeln!(
+            out,
+            "# HELP tracker_runtime_queue_tasks Tasks waiting in the runtime's global queue\n# TYPE tracker_runtime_queue_tasks gauge\ntracker_runtime_queue_tasks {}",
+            metrics.global_queue_depth()
+        );
+    }
+
+    if let Some(bytes) = resident_memory() {
+        let _ = writeln!(
+            out,
+            "# HELP process_resident_memory_bytes Resident memory size\n# TYPE process_resident_memory_bytes gauge\nprocess_resident_memory_bytes {}",
+            bytes
+        );
+    }
+}
+
+// VmRSS of /proc/self/status in kB, Linux only
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+// name, help, type and value of a per endpoint series
+type EndpointGauge = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&EndpointHealth) -> Option<f64>,
+);
+
+// health of the RPC endpoints
+fn render_endpoints(out: &mut String, state: &TrackerState) {
+    let endpoints = health::labelled(&state.endpoints);
+    let gauges: [EndpointGauge; 4] = [
+        (
+            "tracker_rpc_endpoint_score",
+            "Health score of an RPC endpoint from 0 to 100",
+            "gauge",
+            |h| Some(h.score),
+        ),
+        (
+            "tracker_rpc_endpoint_latency_seconds",
+            "Moving average of an RPC endpoint's probe round trips",
+            "gauge",
+            |h| h.latency_ms.map(|ms| ms / 1000.0),
+        ),
+        (
+            "tracker_rpc_endpoint_errors_total",
+            "Failed probes of an RPC endpoint",
+            "counter",
+            |h| Some(h.errors as f64),
+        ),
+        (
+            "tracker_rpc_endpoint_missed_blocks",
+            "Blocks an RPC endpoint is behind the highest head of its chain",
+            "gauge",
+            |h| Some(h.missed_blocks as f64),
+        ),
+    ];
+    for (name, help, kind, value) in gauges {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+        for (endpoint, health) in &endpoints {
+            if let Some(value) = value(health) {
+                let _ = writeln!(out, "{name}{{endpoint=\"{endpoint}\"}} {value}");
+            }
+        }
+    }
+}
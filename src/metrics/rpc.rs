@@ -0,0 +1,47 @@
+use super::count_rpc_request;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportFut};
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+// counts every request sent through a provider by method, batches count each call
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpcCountLayer;
+
+impl<S> Layer<S> for RpcCountLayer {
+    type Service = RpcCount<S>;
+
+    fn layer(&self, inner: S) -> RpcCount<S> {
+        RpcCount { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcCount<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for RpcCount<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        >,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        for method in request.method_names() {
+            count_rpc_request(method);
+        }
+        self.inner.call(request)
+    }
+}
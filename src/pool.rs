@@ -1,153 +1,1411 @@
-use crate::{calc::calculate_prices, token::load_token_info};
-use alloy::primitives::Address;
+mod adapter;
+mod algebra;
+mod backfill;
+mod balancer;
+mod curve;
+mod discovery;
+mod event;
+mod history;
+mod output;
+mod pancake;
+mod replay;
+mod slipstream;
+mod snapshot;
+mod subgraph;
+mod twap;
+mod v2;
+mod v3;
+mod v4;
+mod verify;
+
+use crate::alert::{Alert, Alerter, Severity};
+use crate::calc::calculate_prices;
+use crate::calc::scaled_to_f64;
+use crate::chain::ChainPreset;
+use crate::checkpoint::{Checkpoints, PoolCheckpoint};
+use crate::config::{Config, PoolEntry, Protocol, StaleRule, rules_for_pool};
+use crate::error::{self, LogFailure, PriceFailure, TokenFailure, TrackerError};
+use crate::metrics;
+use crate::reference::ReferencePrices;
+use crate::rules::{MintObservation, Observation, PoolRules};
+use crate::state::{ListenerState, ListenerStatus, PoolSnapshot, SharedState};
+use crate::storage::{SwapRecord, SwapWriter, unix_now};
+use crate::token::{ProxyWatcher, load_token_info};
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, B256, I256, U256, aliases::U160};
 use alloy::{
     providers::Provider,
-    rpc::types::{BlockNumberOrTag, Filter},
+    rpc::types::{BlockId, BlockNumberOrTag, Log, TransactionRequest},
 };
-use alloy_sol_types::sol;
+use alloy_rpc_client::WeakClient;
 use eyre::Result;
 use futures_util::stream::StreamExt;
-use thiserror::Error;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, interval_at, timeout_at};
+use tracing::debug;
 use tracing::error;
+use tracing::field::{self, Empty};
 use tracing::info;
+use tracing::warn;
+use tracing::{Span, instrument};
+
+use adapter::PositionChange;
+pub use adapter::{Decoded, Logs, PoolAdapter};
+use backfill::LogPages;
+pub use backfill::{Bound, backfill, first_block_at};
+pub use balancer::WeightedPoolAdapter;
+pub use curve::CurveAdapter;
+pub use discovery::{TrackedPools, discover, find_pool, parse_pair_spec, track_pair};
+pub use event::{
+    CollectEvent, FlashEvent, InitializeEvent, LiquidityUpdate, ListenerError, PoolEvent,
+    PositionEvent, SwapEvent,
+};
+pub use history::price_at_block;
+use output::PriceOutput;
+pub use output::{PriceUpdate, TradeSummary};
+pub use replay::{load_pool, replay};
+pub use snapshot::{Snapshot, snapshot};
+pub use subgraph::{backfill_from_subgraph, track_top_pools};
+pub use twap::twap;
+pub use v2::V2Adapter;
+pub use v3::{V3Adapter, V3Flavor};
+pub use v4::V4Adapter;
 
-#[derive(Debug, Error)]
-pub enum TokenError {
-    #[error("Failed to fetch token from pool")]
-    TokenFetchFailed,
+// wait before subscribing again after a subscription ended
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+// how often a listener checks whether calls moved to another endpoint of its chain, its
+// subscription then follows
+const REBALANCE_CHECK: Duration = Duration::from_secs(30);
+// a listener that heard nothing for this long pings its endpoint, alloy only sends
+// WebSocket pings on connections without requests, which probes never leave idle
+const KEEPALIVE: Duration = Duration::from_secs(15);
+// swaps of this many blocks behind the head are remembered, to be taken back when a
+// reorg removes their logs
+const REORG_WINDOW: u64 = 64;
+// events a subscriber may fall behind by before it skips the oldest
+pub const EVENTS_CAPACITY: usize = 1024;
 
-    #[error("Failed to fetch token info from address")]
-    TokenInfoFetchFailed,
+// settings shared by every pool listener
+pub struct ListenerSettings {
+    // a pool without swaps for this long is reported as stale, unless a [[stale]]
+    // rule in the config says otherwise, defaults to the pool's chain's timeout
+    pub stall_timeout: Option<Duration>,
+    // subscriptions silent for this long while the chain moves on are set up again,
+    // defaults to the pool's chain's timeout
+    pub ws_stall_timeout: Option<Duration>,
+    // blocks on top of a log's block before it is handled, 0 handles logs as they arrive
+    pub confirmations: u64,
+    // block explorer base url used for tx links in alerts
+    pub explorer_url: String,
+    pub config: Config,
+    pub references: ReferencePrices,
+    pub state: SharedState,
+    // token list used for symbols in pool specs and as metadata fallback
+    pub tokens: TokenList,
+    pub proxies: ProxyWatcher,
+    // CHAIN preset, its factory is the default for discovery and pool specs
+    pub chain: &'static ChainPreset,
+    // swaps are queued for the database here when DATABASE_PATH is set
+    pub writer: Option<SwapWriter>,
+    // rolling stats saved across restarts when CHECKPOINT_PATH is set
+    pub checkpoints: Option<Arc<Checkpoints>>,
+    // what the listeners saw in their pools, for subscribers of the library API and
+    // the API's /pool-events stream
+    pub events: broadcast::Sender<PoolEvent>,
+    // errors of the listeners, those they recovered from too
+    pub errors: broadcast::Sender<ListenerError>,
+    // subscribe to every event of the pools, not only to swaps and the mints rules want
+    pub all_events: bool,
 }
 
-#[derive(Debug, Error)]
-pub enum LogError {
-    #[error("Failed to subscribe logs")]
-    LogSubscriptionFailed,
+impl ListenerSettings {
+    // write out what the sinks still hold once the listeners are stopped: the swaps
+    // queued for the database, given up to `timeout`, and the checkpoints
+    pub async fn drain(&self, timeout: Duration) {
+        // the listeners are stopped, logs still waiting for confirmations are never handled
+        let unconfirmed: usize = {
+            let state = self.state.read().unwrap();
+            state.listeners.values().map(|s| s.unconfirmed).sum()
+        };
+        if unconfirmed > 0 {
+            warn!(
+                "Dropped {} logs still waiting for {} confirmations",
+                unconfirmed, self.confirmations
+            );
+        }
+
+        if let Some(writer) = &self.writer {
+            let report = writer.close(timeout).await;
+            if report.dropped > 0 {
+                warn!(
+                    "Flushed {} swaps to the database, spilled {}, dropped {}",
+                    report.flushed, report.spilled, report.dropped
+                );
+            } else {
+                info!(
+                    "Flushed {} swaps to the database, spilled {}",
+                    report.flushed, report.spilled
+                );
+            }
+        }
+
+        if let Some(checkpoints) = &self.checkpoints {
+            let store = checkpoints.clone();
+            match tokio::task::spawn_blocking(move || store.save()).await {
+                Ok(Ok(())) => info!(
+                    "Saved the checkpoints of {} pools",
+                    checkpoints.pool_count()
+                ),
+                Ok(Err(e)) => error!("Failed to write checkpoint {}: {}", checkpoints.path(), e),
+                Err(e) => error!("Checkpoint writer panicked: {}", e),
+            }
+        }
+    }
+}
 
-    #[error("Failed to fetch sqrt price")]
-    SqrtPriceFetchFailed,
+// last swap seen by a listener, used to enrich alerts
+#[derive(Default)]
+struct LastSwap {
+    price: Option<f64>,
+    sqrt_price_x96: Option<U160>,
+    tx: Option<B256>,
+    // in-range liquidity after the last swap or mint
+    liquidity: Option<u128>,
 }
 
-#[derive(Error, Debug)]
-pub enum PriceError {
-    #[error("Failed to calculate price for pool {0}, tokens {1}/{2}: {3}")]
-    CalculationFailed(Address, String, String, String),
+// token metadata of a pool, loaded once when its listener starts
+pub struct PoolInfo {
+    pub pool: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub sym0: String,
+    pub sym1: String,
+    pub dec0: u8,
+    pub dec1: u8,
+    // fee tier in hundredths of a bip, e.g. 500 = 0.05%
+    pub fee: u32,
+    // token logos from the token list
+    pub logo0: Option<String>,
+    pub logo1: Option<String>,
+    // implementations of tokens that are upgradeable proxies
+    pub impl0: Option<Address>,
+    pub impl1: Option<Address>,
 }
 
-sol! {
-    #[sol(rpc)]
-    interface IUniswapV3Pool {
-        function token0() external view returns (address);
-        function token1() external view returns (address);
+impl PoolInfo {
+    //call token contracts with load_token_info function for fetch decimals and symbols
+    pub async fn load(
+        pool: Address,
+        token0: Address,
+        token1: Address,
+        fee: u32,
+        provider: impl Provider,
+        tokens: &TokenList,
+    ) -> Result<PoolInfo> {
+        let info0 = match load_token_info(token0, &provider, tokens).await {
+            Ok(info) => info,
+            Err(e) => {
+                error!("Failed to load token info for token {:?}: {}", token0, e);
+                return Err(TrackerError::new(TokenFailure::TokenInfoFetchFailed)
+                    .with_pool(pool)
+                    .with_source(e)
+                    .into());
+            }
+        };
+        let info1 = match load_token_info(token1, &provider, tokens).await {
+            Ok(info) => info,
+            Err(e) => {
+                error!("Failed to load token info for token {:?}: {}", token1, e);
+                return Err(TrackerError::new(TokenFailure::TokenInfoFetchFailed)
+                    .with_pool(pool)
+                    .with_source(e)
+                    .into());
+            }
+        };
+
+        Ok(PoolInfo {
+            pool,
+            token0,
+            token1,
+            sym0: info0.symbol,
+            sym1: info1.symbol,
+            dec0: info0.decimals,
+            dec1: info1.decimals,
+            fee,
+            logo0: info0.logo_uri,
+            logo1: info1.logo_uri,
+            impl0: info0.implementation,
+            impl1: info1.implementation,
+        })
     }
+}
 
-    event Swap(
-        address indexed sender,
-        address indexed recipient,
-        int256 amount0,
-        int256 amount1,
-        uint160 sqrtPriceX96,
-        uint128 liquidity,
-        int24 tick
-    );
+impl PoolInfo {
+    // price in token1 per token0 for a sqrtPriceX96, scaled by the token decimals
+    fn price(&self, sqrt_price_x96: U160) -> Result<f64> {
+        //calculate price with sqrtpricex96 and token decimals
+        let price = match calculate_prices(
+            sqrt_price_x96.to_string(),
+            self.dec0 as u32,
+            self.dec1 as u32,
+            &self.sym0,
+            &self.sym1,
+        ) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to calculate price for {}/{}: {}",
+                    self.sym0,
+                    self.sym1,
+                    e
+                );
+                return Err(TrackerError::new(PriceFailure::CalculationFailed {
+                    symbol0: self.sym0.clone(),
+                    symbol1: self.sym1.clone(),
+                })
+                .with_pool(self.pool)
+                .with_source(e)
+                .into());
+            }
+        };
+
+        debug!(
+            "sqrtPriceX96 {} of pool {:?} is {} {} per {}",
+            sqrt_price_x96,
+            self.pool,
+            scaled_to_f64(&price.0),
+            self.sym1,
+            self.sym0
+        );
+
+        Ok(scaled_to_f64(&price.0))
+    }
 }
 
-pub async fn listen_pool(pool_addr: Address, provider: impl Provider) -> Result<()> {
-    let pool = IUniswapV3Pool::new(pool_addr, &provider);
+// a swap translated into Uniswap V3 terms, whatever protocol the pool speaks
+pub struct SwapUpdate {
+    pub sqrt_price_x96: U160,
+    // in-range liquidity after the swap
+    pub liquidity: u128,
+    pub tick: i32,
+    // fee charged by this swap, for pools whose fee changes per swap
+    pub fee: Option<u32>,
+    // raw token0 bought or sold, zero when a multi-coin pool swapped other coins
+    pub amount0: U256,
+    pub tx: Option<B256>,
+    // direction and other side of the swap, when the event tells
+    pub trade: Option<Trade>,
+}
 
-    // fetch token0 address from the pool contract
-    // returns an Ethereum address for token0
-    let token0 = match pool.token0().call().await {
-        Ok(addr) => addr,
-        Err(e) => {
-            error!(
-                "Failed to fetch token0 address for pool {}: {}",
-                pool_addr, e
-            );
-            return Err(TokenError::TokenFetchFailed.into());
+// who swapped which way
+pub struct Trade {
+    // recipient of the bought tokens, or whoever the event names
+    pub trader: Option<Address>,
+    // whether token0 went into the pool
+    pub sold0: bool,
+    // raw token1 bought or sold
+    pub amount1: U256,
+}
+
+impl Trade {
+    // from the signed amounts of a Uniswap V3 style event, positive into the pool
+    fn from_deltas(trader: Address, amount0: I256, amount1: I256) -> Self {
+        Trade {
+            trader: Some(trader),
+            sold0: amount0.is_positive(),
+            amount1: amount1.unsigned_abs(),
         }
-    };
+    }
+}
 
-    // fetch token1 address from the pool contract
-    // returns an Ethereum address for token1
-    let token1 = match pool.token1().call().await {
-        Ok(addr) => addr,
-        Err(e) => {
-            error!(
-                "Failed to fetch token1 address for pool {:?}: {:?}",
-                pool_addr, e
+// everything that happens after a swap has been decoded: price calculation,
+// the shared state snapshot, alert rules and stale detection
+// each protocol's adapter feeds it with SwapUpdates
+struct Tracker {
+    info: PoolInfo,
+    chain_id: u64,
+    pair: String,
+    rules: PoolRules,
+    last: LastSwap,
+    stale: StaleRule,
+    // alert raised while the pool is quiet, resolved by the next swap
+    quiet_alert: Option<Alert>,
+    // token0 traded by each swap of the last 24 hours, by unix timestamp
+    volume: VecDeque<(u64, f64)>,
+    // block, log index, timestamp and token0 amount of the swaps of the last
+    // REORG_WINDOW blocks
+    recent: VecDeque<(u64, u64, u64, f64)>,
+    // swap count of the previous run, from the checkpoint
+    restored_swaps: u64,
+    output: PriceOutput,
+    checkpointed_at: Instant,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+}
+
+impl Tracker {
+    fn new(
+        info: PoolInfo,
+        chain_id: u64,
+        chain: &ChainPreset,
+        alerter: Arc<Alerter>,
+        settings: Arc<ListenerSettings>,
+    ) -> Self {
+        let pair = format!("{}/{}", info.sym0, info.sym1);
+        let mut rules = PoolRules::for_pool(info.pool, &settings);
+        let stale = stale_rule(info.pool, chain, &settings);
+        let output = PriceOutput::new(info.pool, &settings.config.output, settings.events.clone());
+
+        let checkpoint = settings.checkpoints.as_ref().and_then(|c| c.get(info.pool));
+        let (restored_swaps, volume) = match checkpoint {
+            Some(checkpoint) => {
+                rules.restore(info.pool, checkpoint.rules);
+                let since = unix_now().saturating_sub(86_400);
+                let volume = checkpoint
+                    .volume
+                    .into_iter()
+                    .filter(|(at, _)| *at >= since)
+                    .collect();
+                (checkpoint.swaps, volume)
+            }
+            None => (0, VecDeque::new()),
+        };
+
+        Tracker {
+            info,
+            chain_id,
+            pair,
+            rules,
+            last: LastSwap::default(),
+            stale,
+            quiet_alert: None,
+            volume,
+            recent: VecDeque::new(),
+            restored_swaps,
+            output,
+            checkpointed_at: Instant::now(),
+            alerter,
+            settings,
+        }
+    }
+
+    fn stale_after(&self) -> Duration {
+        Duration::from_secs(self.stale.after_secs)
+    }
+
+    // whether on_quiet wants the current on-chain price to tell "stale" from "stalled"
+    fn polls_price(&self) -> bool {
+        self.stale.poll_slot0 && self.last.sqrt_price_x96.is_some() && self.quiet_alert.is_none()
+    }
+
+    fn tx_url(&self, tx: Option<B256>) -> Option<String> {
+        tx.map(|tx| format!("{}/tx/{:?}", self.settings.explorer_url, tx))
+    }
+
+    // no swap arrived for `stale_after`, `onchain` is the pool's current price if polled
+    async fn on_quiet(&mut self, onchain: Option<U160>) {
+        if self.quiet_alert.is_some() {
+            return;
+        }
+        let alert = self.stale_alert(onchain);
+        self.alerter.fire(&alert).await;
+        self.quiet_alert = Some(alert);
+    }
+
+    // the swap's price in token1 per token0
+    async fn on_swap(&mut self, swap: &SwapUpdate, block: u64, timestamp: u64) -> Result<f64> {
+        if let Some(alert) = self.quiet_alert.take() {
+            self.alerter.resolve(&alert).await;
+        }
+
+        if let Some(fee) = swap.fee {
+            self.info.fee = fee;
+        }
+        let info = &self.info;
+
+        let started = Instant::now();
+        let price_f64 = info.price(swap.sqrt_price_x96)?;
+        metrics::PRICE.observe(started.elapsed());
+        self.last.price = Some(price_f64);
+        self.last.sqrt_price_x96 = Some(swap.sqrt_price_x96);
+        self.last.tx = swap.tx;
+        let trade = swap.trade.as_ref().map(|trade| TradeSummary {
+            trader: trade.trader,
+            sold0: trade.sold0,
+            amount1: token_amount(trade.amount1, info.dec1),
+        });
+        self.emit(PoolEvent::Swap(SwapEvent {
+            pool: info.pool,
+            chain_id: self.chain_id,
+            block,
+            timestamp,
+            tx: swap.tx,
+            sqrt_price_x96: swap.sqrt_price_x96,
+            liquidity: swap.liquidity,
+            tick: swap.tick,
+            fee: info.fee,
+            amount0: token_amount(swap.amount0, info.dec0),
+            trade: trade.clone(),
+        }));
+        self.on_liquidity(swap.liquidity, block, swap.tx);
+        let info = &self.info;
+
+        self.volume
+            .push_back((timestamp, token_amount(swap.amount0, info.dec0)));
+        while self
+            .volume
+            .front()
+            .is_some_and(|(at, _)| at + 86_400 < timestamp)
+        {
+            self.volume.pop_front();
+        }
+
+        let (swaps, usd_price0) = {
+            let mut state = self.settings.state.write().unwrap();
+            let swaps = state
+                .pools
+                .get(&info.pool)
+                .map_or(self.restored_swaps, |s| s.swaps)
+                + 1;
+            state.pools.insert(
+                info.pool,
+                PoolSnapshot {
+                    pool: info.pool,
+                    chain_id: self.chain_id,
+                    token0: info.token0,
+                    token1: info.token1,
+                    symbol0: info.sym0.clone(),
+                    symbol1: info.sym1.clone(),
+                    decimals0: info.dec0,
+                    decimals1: info.dec1,
+                    fee: info.fee,
+                    logo0: info.logo0.clone(),
+                    logo1: info.logo1.clone(),
+                    price: price_f64,
+                    sqrt_price_x96: swap.sqrt_price_x96,
+                    liquidity: swap.liquidity,
+                    swaps,
+                    volume0_24h: self.volume.iter().map(|(_, amount)| amount).sum(),
+                    updated_at: timestamp,
+                },
+            );
+            for spread in state.cross_chain_spreads(info.pool) {
+                info!(
+                    "Cross-chain spread {} {} -> {}: {:.2} bps ({} vs {})",
+                    spread.pair,
+                    chain_name(spread.chain_id),
+                    chain_name(spread.other_chain_id),
+                    spread.spread_bps,
+                    spread.price,
+                    spread.other_price
+                );
+            }
+            (swaps, state.usd_price(self.chain_id, info.token0))
+        };
+        let update = PriceUpdate {
+            pool: info.pool,
+            chain_id: self.chain_id,
+            symbol0: info.sym0.clone(),
+            symbol1: info.sym1.clone(),
+            fee: info.fee,
+            block,
+            timestamp,
+            tx: swap.tx,
+            price: price_f64,
+            amount0: token_amount(swap.amount0, info.dec0),
+            trade,
+            usd_price0,
+        };
+        // printed and emitted as the pool's [[output]] rule allows
+        self.output.on_price(update);
+
+        let obs = Observation {
+            pool: info.pool,
+            pair: &self.pair,
+            price: price_f64,
+            liquidity: swap.liquidity,
+            tick: swap.tick,
+            timestamp,
+            tx_url: self.tx_url(swap.tx),
+        };
+        self.rules.on_swap(&obs, &self.alerter).await;
+
+        if let Some(checkpoints) = &self.settings.checkpoints
+            && self.checkpointed_at.elapsed() >= checkpoints.interval()
+        {
+            self.checkpointed_at = Instant::now();
+            checkpoints.update(
+                info.pool,
+                PoolCheckpoint {
+                    swaps,
+                    volume: self.volume.clone(),
+                    rules: self.rules.checkpoint(),
+                },
             );
-            return Err(TokenError::TokenFetchFailed.into());
         }
-    };
+        Ok(price_f64)
+    }
 
-    //call token contracts with load_token_info function for fetch decimals and symbols
-    let (dec0, sym0) = match load_token_info(token0, &provider).await {
-        Ok(info) => info,
-        Err(e) => {
-            error!("Failed to load token info for token {:?}: {}", token0, e);
-            return Err(TokenError::TokenInfoFetchFailed.into());
+    // a reorg removed the log of a swap handled before, take it out of the rolling
+    // volume, the swap count and the database
+    fn on_removed_swap(&mut self, log: &Log) {
+        let block = log.block_number.unwrap_or_default();
+        let log_index = log.log_index.unwrap_or_default();
+        let Some(i) = (self.recent.iter()).position(|&(b, i, ..)| b == block && i == log_index)
+        else {
+            return;
+        };
+        let (_, _, timestamp, amount0) = self.recent.remove(i).unwrap_or_default();
+        if let Some(i) = (self.volume.iter()).rposition(|&entry| entry == (timestamp, amount0)) {
+            self.volume.remove(i);
         }
-    };
-    let (dec1, sym1) = match load_token_info(token1, &provider).await {
-        Ok(info) => info,
-        Err(e) => {
-            error!("Failed to load token info for token {:?}: {}", token1, e);
-            return Err(TokenError::TokenInfoFetchFailed.into());
+        let volume0_24h = self.volume.iter().map(|(_, amount)| amount).sum();
+        if let Some(snapshot) = self
+            .settings
+            .state
+            .write()
+            .unwrap()
+            .pools
+            .get_mut(&self.info.pool)
+        {
+            snapshot.swaps = snapshot.swaps.saturating_sub(1);
+            snapshot.volume0_24h = volume0_24h;
         }
-    };
 
-    //filter to listen only for swap events from this pool
-    let filter = Filter::new()
-        .address(pool_addr)
-        .event("Swap(address,address,int256,int256,uint160,uint128,int24)")
-        .from_block(BlockNumberOrTag::Latest);
+        if let Some(writer) = &self.settings.writer {
+            writer.remove(SwapRecord {
+                pool: self.info.pool,
+                chain_id: self.chain_id,
+                block,
+                log_index,
+                tx: log.transaction_hash,
+                timestamp,
+                price: 0.0,
+                sqrt_price_x96: U160::ZERO,
+                liquidity: 0,
+                tick: 0,
+                amount0,
+            });
+        }
+    }
+
+    // hand an event to the library API's subscribers, nobody subscribed is not an error
+    fn emit(&self, event: PoolEvent) {
+        let _ = self.settings.events.send(event);
+    }
+
+    // a Mint or Burn log in whole tokens
+    fn position_event(
+        &self,
+        change: &PositionChange,
+        block: u64,
+        tx: Option<B256>,
+    ) -> PositionEvent {
+        PositionEvent {
+            pool: self.info.pool,
+            chain_id: self.chain_id,
+            block,
+            tx,
+            owner: change.owner,
+            tick_lower: change.tick_lower,
+            tick_upper: change.tick_upper,
+            amount: change.amount,
+            amount0: token_amount(change.amount0, self.info.dec0),
+            amount1: token_amount(change.amount1, self.info.dec1),
+            pool_liquidity: change.pool_liquidity,
+        }
+    }
+
+    // report the in-range liquidity after a swap, mint or burn when it changed
+    fn on_liquidity(&mut self, liquidity: u128, block: u64, tx: Option<B256>) {
+        let previous = self.last.liquidity.replace(liquidity);
+        if previous == Some(liquidity) {
+            return;
+        }
+        self.emit(PoolEvent::LiquidityUpdate(LiquidityUpdate {
+            pool: self.info.pool,
+            chain_id: self.chain_id,
+            block,
+            tx,
+            liquidity,
+            previous,
+        }));
+    }
+
+    // build the alert for a pool without swaps for `after_secs`
+    // with poll_slot0 the on-chain price is compared against the last swap: an unchanged
+    // price confirms the pool is just quiet ("stale"), a different one means swaps
+    // happened that the subscription never delivered ("stalled")
+    fn stale_alert(&self, onchain: Option<U160>) -> Alert {
+        let pool_addr = self.info.pool;
+        let rule = &self.stale;
+
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), self.pair.clone());
+        vars.insert("timeout_secs".to_string(), rule.after_secs.to_string());
+        if let Some(price) = self.last.price {
+            vars.insert("price".to_string(), price.to_string());
+        }
+        if let Some(url) = self.tx_url(self.last.tx) {
+            vars.insert("tx_url".to_string(), url);
+        }
 
-    let sub = match provider.subscribe_logs(&filter).await {
-        Ok(s) => s,
+        let mut summary = format!(
+            "No swaps from pool {:?} ({}) for {}s",
+            pool_addr, self.pair, rule.after_secs
+        );
+
+        if let (Some(onchain), Some(last_sqrt_price)) = (onchain, self.last.sqrt_price_x96) {
+            if onchain != last_sqrt_price {
+                return Alert {
+                    rule: "stalled".to_string(),
+                    key: format!("stalled:{:?}", pool_addr),
+                    pool: pool_addr,
+                    severity: Severity::Critical,
+                    summary: format!(
+                        "Pool {:?} ({}) price moved on-chain but no swap events arrived for {}s, subscription may be broken",
+                        pool_addr, self.pair, rule.after_secs
+                    ),
+                    vars,
+                };
+            }
+            summary.push_str(", price unchanged (confirmed on-chain)");
+        }
+
+        Alert {
+            rule: "stale".to_string(),
+            key: format!("stale:{:?}", pool_addr),
+            pool: pool_addr,
+            severity: rule.severity,
+            summary,
+            vars,
+        }
+    }
+}
+
+// run one pool through its adapter: subscribe to the adapter's logs, feed decoded
+// swaps and mints to the Tracker and report the pool when it goes quiet
+// everything logged by the listener is inside a `pool` span, filled in once the pool is
+// loaded
+#[instrument(name = "pool", skip_all, fields(pool = Empty, chain_id = Empty, pair = Empty))]
+pub async fn listen<A: PoolAdapter, P: Provider + Clone + 'static>(
+    adapter: A,
+    provider: P,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> Result<()> {
+    let state = settings.state.clone();
+    let errors = settings.errors.clone();
+    let mut pool = None;
+    let result = follow(adapter, provider, alerter, settings, &mut pool).await;
+    if let Err(e) = &result {
+        let _ = errors.send(ListenerError {
+            pool,
+            message: e.to_string(),
+            kind: TrackerError::of(e).map(|e| e.kind().clone()),
+            retryable: error::is_retryable(e),
+            stopped: true,
+        });
+    }
+    if let (Err(e), Some(pool)) = (&result, pool) {
+        update_status(&state, pool, |s| {
+            s.state = ListenerState::Stopped;
+            s.errors += 1;
+            s.last_error = Some(e.to_string());
+        });
+    }
+    result
+}
+
+// spawn the listener of a configured pool with the adapter of its protocol
+pub fn spawn_listener<P: Provider + Clone + 'static>(
+    entry: PoolEntry,
+    provider: P,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> JoinHandle<Result<()>> {
+    tokio::spawn(async move {
+        match (entry.protocol, entry.address) {
+            (Protocol::UniswapV4, _) => {
+                listen(V4Adapter::new(entry), provider, alerter, settings).await
+            }
+            (Protocol::UniswapV3, Some(address)) => {
+                let adapter = V3Adapter::new(address, V3Flavor::Uniswap, entry.factory);
+                listen(adapter, provider, alerter, settings).await
+            }
+            (Protocol::Algebra, Some(address)) => {
+                let adapter = V3Adapter::new(address, V3Flavor::Algebra, entry.factory);
+                listen(adapter, provider, alerter, settings).await
+            }
+            (Protocol::Slipstream, Some(address)) => {
+                let adapter = V3Adapter::new(address, V3Flavor::Slipstream, entry.factory);
+                listen(adapter, provider, alerter, settings).await
+            }
+            (Protocol::SushiswapV3, Some(address)) => {
+                let adapter = V3Adapter::new(address, V3Flavor::SushiSwap, entry.factory);
+                listen(adapter, provider, alerter, settings).await
+            }
+            (Protocol::PancakeswapV3, Some(address)) => {
+                let adapter = V3Adapter::new(address, V3Flavor::PancakeSwap, entry.factory);
+                listen(adapter, provider, alerter, settings).await
+            }
+            (Protocol::Curve, Some(address)) => {
+                let [i, j] = entry.coins.unwrap_or([0, 1]);
+                listen(
+                    CurveAdapter::new(address, (i, j)),
+                    provider,
+                    alerter,
+                    settings,
+                )
+                .await
+            }
+            (Protocol::BalancerWeighted, Some(address)) => {
+                let [i, j] = entry.coins.unwrap_or([0, 1]);
+                let adapter = WeightedPoolAdapter::new(address, (i, j));
+                listen(adapter, provider, alerter, settings).await
+            }
+            (Protocol::UniswapV2, Some(address)) => {
+                let fee = entry.fee.unwrap_or(3000);
+                listen(V2Adapter::new(address, fee), provider, alerter, settings).await
+            }
+            (protocol, None) => {
+                error!("{:?} pool entry without an address", protocol);
+                Ok(())
+            }
+        }
+    })
+}
+
+// the listener's entry in the status shown by GET /status
+fn update_status(state: &SharedState, pool: Address, update: impl FnOnce(&mut ListenerStatus)) {
+    update(state.write().unwrap().listeners.entry(pool).or_default());
+}
+
+// everything listen does, `pool` is set once the pool is loaded so an error that stops
+// the listener can be recorded in its status
+async fn follow<A: PoolAdapter, P: Provider + Clone + 'static>(
+    mut adapter: A,
+    provider: P,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+    pool: &mut Option<Address>,
+) -> Result<()> {
+    let info = adapter.load(&provider, &settings.tokens).await?;
+    let pool_addr = info.pool;
+    *pool = Some(pool_addr);
+
+    let proxies = [
+        (info.token0, &info.sym0, info.impl0),
+        (info.token1, &info.sym1, info.impl1),
+    ];
+    for (token, symbol, implementation) in proxies {
+        if let Some(implementation) = implementation {
+            settings.proxies.watch(
+                token,
+                symbol.clone(),
+                implementation,
+                pool_addr,
+                provider.clone(),
+                alerter.clone(),
+            );
+        }
+    }
+
+    // the chain the pool lives on, pools with their own rpc_url may be on another one
+    // than CHAIN
+    let chain_id = match provider.get_chain_id().await {
+        Ok(id) => id,
         Err(e) => {
-            error!("Failed to subscribe logs with filter {:?}: {}", filter, e);
-            return Err(LogError::LogSubscriptionFailed.into());
+            error!("Failed to fetch chain id: {}", e);
+            settings.chain.chain_id
         }
     };
+    let chain = crate::chain::by_id(chain_id).unwrap_or(settings.chain);
+
+    let state = settings.state.clone();
+    let pair = format!("{}/{}", info.sym0, info.sym1);
+    let fee = info.fee;
+    Span::current()
+        .record("pool", field::debug(pool_addr))
+        .record("chain_id", chain_id)
+        .record("pair", &pair);
+    update_status(&state, pool_addr, |s| {
+        s.chain_id = chain_id;
+        s.pair = pair;
+        s.fee = fee;
+        s.state = ListenerState::Starting;
+    });
 
-    let mut stream = sub.into_stream();
+    let ws_stall_timeout = settings
+        .ws_stall_timeout
+        .unwrap_or_else(|| chain.ws_stall_timeout());
+    let confirmations = settings.confirmations;
+    let all_events = settings.all_events;
+    let mut tracker = Tracker::new(info, chain_id, chain, alerter, settings);
 
-    info!("Listening pool: {:?}", pool_addr);
+    let filter = adapter.filter(if all_events {
+        Logs::All
+    } else if tracker.rules.watches_mints() {
+        Logs::Mints
+    } else {
+        Logs::Swaps
+    });
+    // only swaps count as activity, mints and bookkeeping logs do not push the deadline out
+    let mut stale_at = Instant::now() + tracker.stale_after();
+    // highest block seen, to measure the depth of reorgs
+    let mut head = 0;
+    // block and index of the last log handled; after a dropped subscription everything
+    // from its block on is fetched again and what was handled already is skipped
+    let mut last_log: Option<(u64, u64)> = None;
+    // where a gap starts when no log was handled yet, the block after the first subscription
+    let mut first_block = None;
+    // logs waiting for `confirmations` blocks on top, oldest first; they count as handled
+    // for last_log, so they are kept across resubscriptions
+    let mut pending: VecDeque<Log> = VecDeque::new();
 
-    while let Some(log) = stream.next().await {
-        let Swap { sqrtPriceX96, .. } = match log.log_decode() {
-            Ok(decoded) => decoded.inner.data,
+    loop {
+        let live = filter.clone().from_block(BlockNumberOrTag::Latest);
+        let subscribed_on = provider.weak_client();
+        let sub = match provider.subscribe_logs(&live).await {
+            Ok(s) => s,
             Err(e) => {
-                tracing::error!("Failed to decode log: {}", e);
-                return Err(LogError::SqrtPriceFetchFailed.into());
+                error!("Failed to subscribe logs with filter {:?}: {}", live, e);
+                return Err(TrackerError::new(LogFailure::LogSubscriptionFailed)
+                    .with_pool(pool_addr)
+                    .with_chain(chain_id)
+                    .with_source(e)
+                    .into());
             }
         };
+        let mut stream = sub.into_stream();
+        // heads tell a quiet pool from a silent connection, alloy shares one subscription
+        // among all listeners of a connection
+        let mut heads = match provider.subscribe_blocks().await {
+            Ok(s) => s.into_stream(),
+            Err(e) => {
+                error!("Failed to subscribe blocks: {}", e);
+                return Err(TrackerError::new(LogFailure::LogSubscriptionFailed)
+                    .with_pool(pool_addr)
+                    .with_chain(chain_id)
+                    .with_source(e)
+                    .into());
+            }
+        };
+        update_status(&state, pool_addr, |s| {
+            s.state = ListenerState::Subscribed;
+            s.subscribed_at = Some(unix_now());
+        });
 
-        //calculate price with sqrtpricex96 and token decimals
-        let price = match calculate_prices(
-            sqrtPriceX96.to_string(),
-            dec0 as u32,
-            dec1 as u32,
-            &sym0,
-            &sym1,
-        ) {
-            Ok(p) => p,
+        let current = match provider.get_block_number().await {
+            Ok(block) => Some(block),
             Err(e) => {
-                tracing::error!("Failed to calculate price for {}/{}: {}", sym0, sym1, e);
-                return Err(PriceError::CalculationFailed(
-                    pool_addr,
-                    sym0.clone(),
-                    sym1.clone(),
-                    e.to_string(),
-                )
-                .into());
+                error!("Failed to fetch latest block: {}", e);
+                update_status(&state, pool_addr, |s| {
+                    s.errors += 1;
+                    s.last_error = Some(e.to_string());
+                });
+                let _ = tracker.settings.errors.send(ListenerError {
+                    pool: Some(pool_addr),
+                    message: e.to_string(),
+                    kind: None,
+                    retryable: true,
+                    stopped: false,
+                });
+                None
             }
         };
+        // logs up to this block may arrive twice, from the gap and from the subscription
+        let mut replayed_through = None;
+        match (last_log.map(|(block, _)| block).or(first_block), current) {
+            (Some(from), Some(to)) if from <= to => {
+                info!(
+                    "Backfilling blocks {}-{} of pool {:?} missed while resubscribing",
+                    from, to, pool_addr
+                );
+                let mut pages = LogPages::new(filter.clone(), from, to);
+                while let Some((_, _, logs)) = pages.next(&provider).await? {
+                    for log in logs {
+                        if Some(log_position(&log)) <= last_log {
+                            debug!("Skipping log handled before resubscribing");
+                            continue;
+                        }
+                        if confirmations > 0 && log_position(&log).0 + confirmations > to {
+                            last_log = Some(log_position(&log));
+                            pending.push_back(log);
+                            continue;
+                        }
+                        if handle_log(
+                            &mut tracker,
+                            &mut adapter,
+                            &provider,
+                            chain,
+                            &mut head,
+                            &log,
+                        )
+                        .await?
+                        {
+                            stale_at = Instant::now() + tracker.stale_after();
+                        }
+                        last_log = Some(log_position(&log));
+                    }
+                }
+                replayed_through = Some(to);
+                update_status(&state, pool_addr, |s| s.unconfirmed = pending.len());
+            }
+            (None, Some(to)) => first_block = Some(to + 1),
+            (from, to) => debug!("Nothing to backfill between {:?} and {:?}", from, to),
+        }
 
-        info!("SQRT_PRICE: {:#?} from pool: {:?}", price, pool_addr);
+        info!("Listening {} pool: {:?}", adapter.protocol(), pool_addr);
+
+        let mut moved = false;
+        let mut stalled = false;
+        let mut rebalance = interval_at(Instant::now() + REBALANCE_CHECK, REBALANCE_CHECK);
+        let mut keepalive = interval_at(Instant::now() + KEEPALIVE, KEEPALIVE);
+        // when either subscription delivered last, and the last head it delivered
+        let mut heard_at = Instant::now();
+        let mut heard_head = current;
+
+        loop {
+            let next = tokio::select! {
+                next = timeout_at(stale_at, stream.next()) => next,
+                header = heads.next() => {
+                    match header {
+                        Some(header) => {
+                            heard_at = Instant::now();
+                            heard_head = Some(header.number);
+                            while pending
+                                .front()
+                                .is_some_and(|log| log_position(log).0 + confirmations <= header.number)
+                            {
+                                let log = pending.pop_front().unwrap();
+                                if handle_log(
+                                    &mut tracker,
+                                    &mut adapter,
+                                    &provider,
+                                    chain,
+                                    &mut head,
+                                    &log,
+                                )
+                                .await?
+                                {
+                                    stale_at = Instant::now() + tracker.stale_after();
+                                }
+                            }
+                            update_status(&state, pool_addr, |s| s.unconfirmed = pending.len());
+                            continue;
+                        }
+                        None => break,
+                    }
+                }
+                _ = keepalive.tick() => {
+                    if heard_at.elapsed() < KEEPALIVE {
+                        continue;
+                    }
+                    let silent = heard_at.elapsed();
+                    if is_stalled(&provider, heard_head, silent, ws_stall_timeout).await {
+                        stalled = true;
+                        break;
+                    }
+                    continue;
+                }
+                _ = rebalance.tick() => {
+                    if WeakClient::ptr_eq(&provider.weak_client(), &subscribed_on) {
+                        continue;
+                    }
+                    moved = true;
+                    break;
+                }
+            };
+            let log = match next {
+                Ok(Some(log)) => log,
+                Ok(None) => break,
+                Err(_) => {
+                    let onchain = if tracker.polls_price() {
+                        adapter.poll_price(&provider).await
+                    } else {
+                        None
+                    };
+                    tracker.on_quiet(onchain).await;
+                    stale_at = Instant::now() + tracker.stale_after();
+                    continue;
+                }
+            };
+
+            heard_at = Instant::now();
+            update_status(&state, pool_addr, |s| s.last_event_at = Some(unix_now()));
+            let position = log_position(&log);
+            if replayed_through.is_some_and(|block| position.0 <= block)
+                && Some(position) <= last_log
+            {
+                debug!("Skipping log already backfilled from the gap");
+                continue;
+            }
+            if confirmations > 0 {
+                if !log.removed {
+                    last_log = Some(position);
+                    pending.push_back(log);
+                    update_status(&state, pool_addr, |s| s.unconfirmed = pending.len());
+                    continue;
+                }
+                // a reorg took back a log before it was confirmed, it is never handled
+                let queued = pending
+                    .iter()
+                    .position(|p| p.block_hash == log.block_hash && p.log_index == log.log_index);
+                if let Some(index) = queued {
+                    debug!("Dropping unconfirmed log removed by a reorg");
+                    pending.remove(index);
+                    update_status(&state, pool_addr, |s| s.unconfirmed = pending.len());
+                    continue;
+                }
+            }
+            if handle_log(
+                &mut tracker,
+                &mut adapter,
+                &provider,
+                chain,
+                &mut head,
+                &log,
+            )
+            .await?
+            {
+                stale_at = Instant::now() + tracker.stale_after();
+            }
+            if !log.removed {
+                last_log = Some(position);
+            }
+        }
+
+        if moved {
+            info!(
+                "Moving the log subscription of pool {:?} to the endpoint calls go to",
+                pool_addr
+            );
+            continue;
+        }
+        if stalled {
+            warn!(
+                "Subscriptions of pool {:?} went silent for {}s while blocks were produced, resubscribing in {}s",
+                pool_addr,
+                heard_at.elapsed().as_secs(),
+                RESUBSCRIBE_DELAY.as_secs()
+            );
+            metrics::STALLS.fetch_add(1, Ordering::Relaxed);
+        } else {
+            warn!(
+                "Log subscription of pool {:?} ended, resubscribing in {}s",
+                pool_addr,
+                RESUBSCRIBE_DELAY.as_secs()
+            );
+        }
+        update_status(&state, pool_addr, |s| {
+            s.state = ListenerState::Resubscribing;
+            s.resubscriptions += 1;
+            if stalled {
+                s.stalls += 1;
+            }
+        });
+        metrics::RESUBSCRIPTIONS.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+// ping the endpoint of subscriptions that delivered nothing for `silent`: they stalled
+// once silent for `stall_after` while the chain moved past the last head they delivered,
+// or when the endpoint does not answer the ping either
+async fn is_stalled<P: Provider>(
+    provider: &P,
+    heard_head: Option<u64>,
+    silent: Duration,
+    stall_after: Duration,
+) -> bool {
+    let head = match tokio::time::timeout(KEEPALIVE, provider.get_block_number()).await {
+        Ok(Ok(head)) => head,
+        Ok(Err(e)) => {
+            warn!("Keepalive ping failed: {}", e);
+            return false;
+        }
+        Err(_) => {
+            warn!("Keepalive ping got no answer in {}s", KEEPALIVE.as_secs());
+            return silent >= stall_after;
+        }
+    };
+    debug!(
+        "Subscriptions silent for {}s, head {} after {:?}",
+        silent.as_secs(),
+        head,
+        heard_head
+    );
+    silent >= stall_after && heard_head.is_none_or(|heard| head > heard)
+}
+
+fn log_position(log: &Log) -> (u64, u64) {
+    (
+        log.block_number.unwrap_or_default(),
+        log.log_index.unwrap_or_default(),
+    )
+}
+
+// feed one log to the tracker, returns whether it was a swap
+#[instrument(
+    name = "event",
+    skip_all,
+    fields(
+        block = log.block_number,
+        tx = log.transaction_hash.map(field::debug),
+        log_index = log.log_index
+    )
+)]
+async fn handle_log<A: PoolAdapter, P: Provider>(
+    tracker: &mut Tracker,
+    adapter: &mut A,
+    provider: &P,
+    chain: &ChainPreset,
+    head: &mut u64,
+    log: &Log,
+) -> Result<bool> {
+    let pool_addr = tracker.info.pool;
+    let block = log.block_number.unwrap_or_default();
+    *head = (*head).max(block);
+    // the node resends logs of reorged blocks flagged as removed, they were
+    // already processed once
+    if log.removed {
+        let depth = head.saturating_sub(block) + 1;
+        if depth > chain.reorg_depth {
+            warn!(
+                "Reorg of {} blocks on {} removed a log of pool {:?} at block {}, deeper than the usual {}",
+                depth, chain.name, pool_addr, block, chain.reorg_depth
+            );
+        } else {
+            info!(
+                "Reorg removed a log of pool {:?} at block {}",
+                pool_addr, block
+            );
+        }
+        tracker.on_removed_swap(log);
+        return Ok(false);
     }
 
-    Ok(())
+    let started = Instant::now();
+    let decoded = match adapter.decode(log, provider).await {
+        Ok(d) => d,
+        Err(e) => {
+            metrics::DECODE_FAILURES.fetch_add(1, Ordering::Relaxed);
+            return Err(e);
+        }
+    };
+    metrics::DECODE.observe(started.elapsed());
+    match decoded {
+        Decoded::Swap(swap) => {
+            debug!(
+                "Swap sqrtPriceX96 {} liquidity {} tick {} amount0 {} fee {:?}",
+                swap.sqrt_price_x96, swap.liquidity, swap.tick, swap.amount0, swap.fee
+            );
+            let timestamp = log.block_timestamp.unwrap_or_else(unix_now);
+            let price = tracker.on_swap(&swap, block, timestamp).await?;
+            let amount0 = token_amount(swap.amount0, tracker.info.dec0);
+            let log_index = log.log_index.unwrap_or_default();
+            tracker
+                .recent
+                .push_back((block, log_index, timestamp, amount0));
+            while (tracker.recent.front()).is_some_and(|&(b, ..)| b + REORG_WINDOW < *head) {
+                tracker.recent.pop_front();
+            }
+            if let Some(writer) = &tracker.settings.writer {
+                let record = swap_record(
+                    &tracker.info,
+                    tracker.chain_id,
+                    &swap,
+                    price,
+                    log,
+                    timestamp,
+                );
+                writer.push(record).await;
+            }
+            // nodes that leave out the block timestamp give nothing to measure against
+            if let Some(block_timestamp) = log.block_timestamp {
+                metrics::observe_swap_latency(tracker.info.pool, block_timestamp);
+            }
+            Ok(true)
+        }
+        Decoded::Mint(mint) => {
+            let obs = MintObservation {
+                pool: pool_addr,
+                pair: &tracker.pair,
+                owner: mint.owner,
+                tick_lower: mint.tick_lower,
+                tick_upper: mint.tick_upper,
+                amount: mint.amount,
+                amount0: token_amount(mint.amount0, tracker.info.dec0),
+                amount1: token_amount(mint.amount1, tracker.info.dec1),
+                pool_liquidity: mint.pool_liquidity,
+                tx_url: tracker.tx_url(log.transaction_hash),
+            };
+            tracker.rules.on_mint(&obs, &tracker.alerter).await;
+            let event = tracker.position_event(&mint, block, log.transaction_hash);
+            tracker.emit(PoolEvent::Mint(event));
+            tracker.on_liquidity(mint.pool_liquidity, block, log.transaction_hash);
+            Ok(false)
+        }
+        Decoded::Burn(burn) => {
+            let event = tracker.position_event(&burn, block, log.transaction_hash);
+            tracker.emit(PoolEvent::Burn(event));
+            tracker.on_liquidity(burn.pool_liquidity, block, log.transaction_hash);
+            Ok(false)
+        }
+        Decoded::Collect(collect) => {
+            let info = &tracker.info;
+            tracker.emit(PoolEvent::Collect(CollectEvent {
+                pool: pool_addr,
+                chain_id: tracker.chain_id,
+                block,
+                tx: log.transaction_hash,
+                owner: collect.owner,
+                recipient: collect.recipient,
+                tick_lower: collect.tick_lower,
+                tick_upper: collect.tick_upper,
+                amount0: token_amount(U256::from(collect.amount0), info.dec0),
+                amount1: token_amount(U256::from(collect.amount1), info.dec1),
+            }));
+            Ok(false)
+        }
+        Decoded::Flash(flash) => {
+            let info = &tracker.info;
+            tracker.emit(PoolEvent::Flash(FlashEvent {
+                pool: pool_addr,
+                chain_id: tracker.chain_id,
+                block,
+                tx: log.transaction_hash,
+                sender: flash.sender,
+                recipient: flash.recipient,
+                amount0: token_amount(flash.amount0, info.dec0),
+                amount1: token_amount(flash.amount1, info.dec1),
+                paid0: token_amount(flash.paid0, info.dec0),
+                paid1: token_amount(flash.paid1, info.dec1),
+            }));
+            Ok(false)
+        }
+        Decoded::Initialize(init) => {
+            tracker.emit(PoolEvent::Initialize(InitializeEvent {
+                pool: pool_addr,
+                chain_id: tracker.chain_id,
+                block,
+                tx: log.transaction_hash,
+                sqrt_price_x96: init.sqrt_price_x96,
+                tick: init.tick,
+                price: tracker.info.price(init.sqrt_price_x96).ok(),
+            }));
+            Ok(false)
+        }
+        Decoded::Skip => Ok(false),
+    }
+}
+
+// whether a failed call means the address is not the contract it was configured as:
+// the call reverted or returned nothing, instead of failing on the way to the node
+fn is_not_a_contract(e: &alloy::contract::Error) -> bool {
+    match e {
+        alloy::contract::Error::ZeroData(..) | alloy::contract::Error::AbiError(_) => true,
+        alloy::contract::Error::TransportError(e) => e
+            .as_error_resp()
+            .is_some_and(|resp| resp.message.contains("revert")),
+        _ => false,
+    }
+}
+
+// call a parameterless getter and return the `index`th word of its output, used for
+// slot0 and its fork specific equivalents without decoding their whole layout
+async fn call_word(
+    provider: impl Provider,
+    pool: Address,
+    selector: [u8; 4],
+    index: usize,
+) -> Option<U256> {
+    call_word_at(provider, pool, selector, index, BlockId::latest()).await
+}
+
+// like call_word, against the state of a past block, which needs an archive node
+// for anything older than the last few minutes on most providers
+async fn call_word_at(
+    provider: impl Provider,
+    pool: Address,
+    selector: [u8; 4],
+    index: usize,
+    block: BlockId,
+) -> Option<U256> {
+    let tx = TransactionRequest::default()
+        .to(pool)
+        .input(selector.to_vec().into());
+    match provider.call(tx).block(block).await {
+        Ok(out) => match out.get(index * 32..(index + 1) * 32) {
+            Some(word) => Some(U256::from_be_slice(word)),
+            None => {
+                error!("Unexpected getter output from pool {:?}", pool);
+                None
+            }
+        },
+        Err(e) => {
+            error!("Failed to call getter of pool {:?}: {}", pool, e);
+            None
+        }
+    }
+}
+
+// the row stored for a swap decoded from `log`
+fn swap_record(
+    info: &PoolInfo,
+    chain_id: u64,
+    swap: &SwapUpdate,
+    price: f64,
+    log: &Log,
+    timestamp: u64,
+) -> SwapRecord {
+    SwapRecord {
+        pool: info.pool,
+        chain_id,
+        block: log.block_number.unwrap_or_default(),
+        log_index: log.log_index.unwrap_or_default(),
+        tx: swap.tx,
+        timestamp,
+        price,
+        sqrt_price_x96: swap.sqrt_price_x96,
+        liquidity: swap.liquidity,
+        tick: swap.tick,
+        amount0: token_amount(swap.amount0, info.dec0),
+    }
+}
+
+// preset name of a chain, or its id for chains without one
+fn chain_name(chain_id: u64) -> String {
+    match crate::chain::by_id(chain_id) {
+        Some(chain) => chain.name.to_string(),
+        None => chain_id.to_string(),
+    }
+}
+
+// raw token amount in whole tokens
+fn token_amount(amount: U256, decimals: u8) -> f64 {
+    f64::from(amount) / 10f64.powi(decimals as i32)
+}
+
+// the [[stale]] rule for this pool: a pool specific rule wins over a global one,
+// without either STALL_TIMEOUT_SECS or the chain's default applies at critical severity
+fn stale_rule(pool_addr: Address, chain: &ChainPreset, settings: &ListenerSettings) -> StaleRule {
+    rules_for_pool(&settings.config.stale, pool_addr)
+        .into_iter()
+        .next()
+        .unwrap_or(StaleRule {
+            pool: None,
+            after_secs: settings
+                .stall_timeout
+                .unwrap_or_else(|| chain.stall_timeout())
+                .as_secs(),
+            poll_slot0: false,
+            severity: Severity::Critical,
+        })
 }
@@ -0,0 +1,123 @@
+use super::{PoolInfo, SwapUpdate};
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, U256, aliases::U160};
+use alloy::providers::Provider;
+use alloy::rpc::types::{Filter, Log};
+use eyre::Result;
+use std::future::Future;
+
+// one DEX integration: how to load a pool, which logs to subscribe to and how to
+// turn them into SwapUpdates; `pool::listen` runs the subscription, stale detection
+// and the Tracker for every adapter the same way
+pub trait PoolAdapter: Send {
+    // protocol name used in log lines, e.g. "Curve"
+    fn protocol(&self) -> &'static str;
+
+    // resolve and verify the pool and load its token metadata, once on startup
+    fn load<P: Provider>(
+        &mut self,
+        provider: &P,
+        tokens: &TokenList,
+    ) -> impl Future<Output = Result<PoolInfo>> + Send;
+
+    // logs to subscribe to, called after `load`; adapters without the events `logs`
+    // asks for besides swaps subscribe to their swaps alone
+    fn filter(&self, logs: Logs) -> Filter;
+
+    // turn one log of the subscription into a swap, a position change or another pool
+    // event, or nothing
+    fn decode<P: Provider>(
+        &mut self,
+        log: &Log,
+        provider: &P,
+    ) -> impl Future<Output = Result<Decoded>> + Send;
+
+    // current on-chain sqrtPriceX96, for telling a quiet pool from a broken subscription
+    fn poll_price<P: Provider>(&self, provider: &P) -> impl Future<Output = Option<U160>> + Send;
+
+    // current in-range liquidity, as reported by the adapter's swaps
+    fn poll_liquidity<P: Provider>(
+        &self,
+        _provider: &P,
+    ) -> impl Future<Output = Option<u128>> + Send {
+        async { None }
+    }
+
+    // price at the end of a past block, None for pools whose price is only known
+    // from their swaps or when the node no longer has the block's state
+    fn price_at_block<P: Provider>(
+        &self,
+        _provider: &P,
+        _block: u64,
+    ) -> impl Future<Output = Option<U160>> + Send {
+        async { None }
+    }
+
+    // arithmetic mean tick over the last `window` seconds from the pool's own price
+    // oracle, None for pools without one
+    fn observe_tick<P: Provider>(
+        &self,
+        _provider: &P,
+        _window: u32,
+    ) -> impl Future<Output = Option<f64>> + Send {
+        async { None }
+    }
+}
+
+// the logs a listener subscribes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Logs {
+    Swaps,
+    // swaps and mints, when a rule wants new positions reported
+    Mints,
+    // every event of the pool, for subscribers of the library API and /pool-events
+    All,
+}
+
+pub enum Decoded {
+    Swap(SwapUpdate),
+    Mint(PositionChange),
+    Burn(PositionChange),
+    Collect(Collected),
+    Flash(FlashLoan),
+    Initialize(Initialized),
+    // bookkeeping logs like V2 Sync, or logs that could not be priced
+    Skip,
+}
+
+// a Mint or Burn log with the pool's in-range liquidity right after it
+pub struct PositionChange {
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount: u128,
+    pub amount0: U256,
+    pub amount1: U256,
+    pub pool_liquidity: u128,
+}
+
+// a Collect log, amounts in token units
+pub struct Collected {
+    pub owner: Address,
+    pub recipient: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount0: u128,
+    pub amount1: u128,
+}
+
+// a Flash log, amounts in token units
+pub struct FlashLoan {
+    pub sender: Address,
+    pub recipient: Address,
+    pub amount0: U256,
+    pub amount1: U256,
+    pub paid0: U256,
+    pub paid1: U256,
+}
+
+// the Initialize log setting a pool's first price
+pub struct Initialized {
+    pub sqrt_price_x96: U160,
+    pub tick: i32,
+}
@@ -0,0 +1,40 @@
+use super::Trade;
+use alloy::primitives::U256;
+use alloy::primitives::aliases::U160;
+use alloy::rpc::types::Log;
+use alloy_sol_types::{SolEvent, sol};
+
+sol! {
+    interface IAlgebraPool {
+        // first words shared by Algebra V1, V1.9 (directional fees) and Integral:
+        // the price, the tick and the current fee in hundredths of a bip
+        function globalState() external view returns (uint160 price, int24 tick, uint16 fee);
+    }
+
+    // Algebra Integral reports the fees applied by the swap
+    event Swap(
+        address indexed sender,
+        address indexed recipient,
+        int256 amount0,
+        int256 amount1,
+        uint160 price,
+        uint128 liquidity,
+        int24 tick,
+        uint24 overrideFee,
+        uint24 pluginFee
+    );
+}
+
+pub const SWAP_SIGNATURE: &str = Swap::SIGNATURE;
+
+// decodes Algebra Integral swaps, Algebra V1 swaps share the Uniswap V3 layout
+pub fn decode_swap(log: &Log) -> Option<(U160, u128, i32, U256, Trade)> {
+    let swap = log.log_decode::<Swap>().ok()?.inner.data;
+    Some((
+        swap.price,
+        swap.liquidity,
+        swap.tick.as_i32(),
+        swap.amount0.unsigned_abs(),
+        Trade::from_deltas(swap.recipient, swap.amount0, swap.amount1),
+    ))
+}
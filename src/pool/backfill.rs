@@ -0,0 +1,230 @@
+use super::{Decoded, Logs, PoolAdapter, swap_record};
+use crate::storage::Storage;
+use crate::tokenlist::TokenList;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use alloy::rpc::types::{BlockNumberOrTag, Filter, Log};
+use eyre::Result;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+// blocks per eth_getLogs request, halved whenever the provider rejects a range
+const INITIAL_CHUNK: u64 = 2_000;
+// attempts for a single block range before giving up
+const MAX_RETRIES: u32 = 5;
+
+// one end of a backfill range, times are resolved per chain since block numbers differ
+#[derive(Debug, Clone, Copy)]
+pub enum Bound {
+    Block(u64),
+    // unix timestamp
+    Time(u64),
+}
+
+#[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
+pub enum BackfillError {
+    #[error("Failed to fetch logs of blocks {0}-{1}")]
+    LogFetchFailed(u64, u64),
+
+    #[error("Failed to fetch block {0}")]
+    BlockFetchFailed(u64),
+
+    #[error("Failed to fetch chain id")]
+    ChainIdFetchFailed,
+}
+
+// fetch the pool's swaps in `from..=to` and store them with their prices, through
+// the same decoding and price calculation as live swaps
+// returns the number of swaps stored
+pub async fn backfill<A: PoolAdapter, P: Provider>(
+    mut adapter: A,
+    provider: P,
+    tokens: &TokenList,
+    storage: &Storage,
+    from: u64,
+    to: u64,
+) -> Result<usize> {
+    let mut info = adapter.load(&provider, tokens).await?;
+    info!(
+        "Backfilling {} pool {:?} ({}/{}) from block {} to {}",
+        adapter.protocol(),
+        info.pool,
+        info.sym0,
+        info.sym1,
+        from,
+        to
+    );
+
+    let chain_id = chain_id(&provider).await?;
+    let mut stored = 0;
+    // first and last swap timestamp
+    let mut span: Option<(u64, u64)> = None;
+    let mut pages = LogPages::new(adapter.filter(Logs::Swaps), from, to);
+    // block timestamps, many swaps share a block
+    let mut timestamps: HashMap<u64, u64> = HashMap::new();
+
+    while let Some((start, end, logs)) = pages.next(&provider).await? {
+        let mut records = Vec::new();
+        for log in logs {
+            let Decoded::Swap(swap) = adapter.decode(&log, &provider).await? else {
+                continue;
+            };
+            if let Some(fee) = swap.fee {
+                info.fee = fee;
+            }
+            let price = info.price(swap.sqrt_price_x96)?;
+
+            let block = log.block_number.unwrap_or_default();
+            let timestamp = match (log.block_timestamp, timestamps.get(&block)) {
+                (Some(t), _) | (None, Some(&t)) => t,
+                (None, None) => {
+                    let t = block_timestamp(&provider, block).await?;
+                    timestamps.insert(block, t);
+                    t
+                }
+            };
+            span = Some(widen(span, timestamp));
+            records.push(swap_record(&info, chain_id, &swap, price, &log, timestamp));
+        }
+
+        stored += records.len();
+        if !records.is_empty() {
+            storage.record_swaps(records).await?;
+        }
+        info!(
+            "Backfilled blocks {}-{} of pool {:?}, {} swaps so far",
+            start, end, info.pool, stored
+        );
+
+        timestamps.clear();
+    }
+
+    refresh_volatility(storage, info.pool, span).await;
+    Ok(stored)
+}
+
+// stored with every swap, pools with their own rpc_url may be on another chain than CHAIN
+pub(super) async fn chain_id(provider: &impl Provider) -> Result<u64, BackfillError> {
+    match provider.get_chain_id().await {
+        Ok(id) => Ok(id),
+        Err(e) => {
+            error!("Failed to fetch chain id: {}", e);
+            Err(BackfillError::ChainIdFetchFailed)
+        }
+    }
+}
+
+pub(super) fn widen(span: Option<(u64, u64)>, timestamp: u64) -> (u64, u64) {
+    match span {
+        Some((first, last)) => (first.min(timestamp), last.max(timestamp)),
+        None => (timestamp, timestamp),
+    }
+}
+
+// recompute the stored volatility series over the backfilled swaps, a failure only
+// leaves the series stale
+pub(super) async fn refresh_volatility(storage: &Storage, pool: Address, span: Option<(u64, u64)>) {
+    let Some((first, last)) = span else {
+        return;
+    };
+    if let Ok(count) = storage.update_volatility(pool, first, last + 1).await {
+        info!("Updated {} volatility intervals of pool {:?}", count, pool);
+    }
+}
+
+// logs matching a filter in `from..=to`, fetched in ranges small enough for the provider
+pub(super) struct LogPages {
+    filter: Filter,
+    start: u64,
+    to: u64,
+    chunk: u64,
+    retries: u32,
+}
+
+impl LogPages {
+    pub(super) fn new(filter: Filter, from: u64, to: u64) -> Self {
+        LogPages {
+            filter,
+            start: from,
+            to,
+            chunk: INITIAL_CHUNK,
+            retries: 0,
+        }
+    }
+
+    // the next range and its logs, None once `to` has been reached
+    pub(super) async fn next<P: Provider>(
+        &mut self,
+        provider: &P,
+    ) -> Result<Option<(u64, u64, Vec<Log>)>, BackfillError> {
+        while self.start <= self.to {
+            let (start, end) = (self.start, self.to.min(self.start + self.chunk - 1));
+            let filter = self.filter.clone().from_block(start).to_block(end);
+
+            match provider.get_logs(&filter).await {
+                Ok(logs) => {
+                    self.retries = 0;
+                    self.start = end + 1;
+                    return Ok(Some((start, end, logs)));
+                }
+                // most providers cap the range or the result size, try a smaller range
+                Err(e) if self.chunk > 1 => {
+                    self.chunk /= 2;
+                    warn!(
+                        "Failed to fetch logs of blocks {}-{} ({}), retrying with {} blocks",
+                        start, end, e, self.chunk
+                    );
+                }
+                Err(e) => {
+                    self.retries += 1;
+                    if self.retries > MAX_RETRIES {
+                        error!("Failed to fetch logs of block {}: {}", start, e);
+                        return Err(BackfillError::LogFetchFailed(start, end));
+                    }
+                    warn!(
+                        "Failed to fetch logs of block {}, retry {}: {}",
+                        start, self.retries, e
+                    );
+                    tokio::time::sleep(Duration::from_secs(1 << self.retries)).await;
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+// first block with a timestamp at or after `timestamp`, `latest + 1` if it is in the future
+// binary search, about log2(latest) block requests
+pub async fn first_block_at<P: Provider>(provider: &P, timestamp: u64, latest: u64) -> Result<u64> {
+    if block_timestamp(provider, latest).await? < timestamp {
+        return Ok(latest + 1);
+    }
+    let (mut low, mut high) = (0, latest);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if block_timestamp(provider, mid).await? >= timestamp {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    info!("Resolved timestamp {} to block {}", timestamp, low);
+    Ok(low)
+}
+
+async fn block_timestamp(provider: &impl Provider, block: u64) -> Result<u64, BackfillError> {
+    match provider
+        .get_block_by_number(BlockNumberOrTag::Number(block))
+        .await
+    {
+        Ok(Some(b)) => Ok(b.header.timestamp),
+        Ok(None) => Err(BackfillError::BlockFetchFailed(block)),
+        Err(e) => {
+            error!("Failed to fetch block {}: {}", block, e);
+            Err(BackfillError::BlockFetchFailed(block))
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use super::adapter::{Decoded, Logs, PoolAdapter};
+use super::{PoolInfo, SwapUpdate, Trade};
+use crate::calc::{liquidity_from_reserves, sqrt_price_x96_from_amounts, tick_from_sqrt_price};
+use crate::error::{PoolFailure, TokenFailure, TrackerError};
+use crate::tokenlist::TokenList;
+use alloy::primitives::aliases::U160;
+use alloy::primitives::{Address, B256, U256};
+use alloy::{
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use alloy_sol_types::{SolEvent, sol};
+use eyre::Result;
+use tracing::{error, info};
+
+sol! {
+    #[sol(rpc)]
+    interface IWeightedPool {
+        function getPoolId() external view returns (bytes32);
+        function getVault() external view returns (address);
+        function getNormalizedWeights() external view returns (uint256[]);
+        function getSwapFeePercentage() external view returns (uint256);
+    }
+
+    #[sol(rpc)]
+    interface IVault {
+        function getPoolTokens(bytes32 poolId) external view returns (
+            address[] tokens,
+            uint256[] balances,
+            uint256 lastChangeBlock
+        );
+    }
+
+    event Swap(
+        bytes32 indexed poolId,
+        address indexed tokenIn,
+        address indexed tokenOut,
+        uint256 amountIn,
+        uint256 amountOut
+    );
+}
+
+// Balancer fees are scaled by 1e18, the tracker uses hundredths of a bip (1e6)
+const FEE_SCALE: u64 = 1_000_000_000_000;
+
+// a Balancer V2 weighted pool, followed through the Vault's Swap events and tracking
+// token `tokens.1` priced in token `tokens.0`
+// weighted math spot price (without fees): (balance1 / weight1) / (balance0 / weight0)
+pub struct WeightedPoolAdapter {
+    pool: Address,
+    tokens: (usize, usize),
+    // known once the pool is loaded
+    pool_id: B256,
+    vault: Address,
+    weights: (U256, U256),
+}
+
+impl WeightedPoolAdapter {
+    pub fn new(pool: Address, tokens: (u8, u8)) -> Self {
+        WeightedPoolAdapter {
+            pool,
+            tokens: (tokens.0 as usize, tokens.1 as usize),
+            pool_id: B256::ZERO,
+            vault: Address::ZERO,
+            weights: (U256::ZERO, U256::ZERO),
+        }
+    }
+
+    // addresses and balances of the two tracked tokens
+    async fn pool_tokens<P: Provider>(
+        &self,
+        provider: &P,
+    ) -> Option<((Address, Address), (U256, U256))> {
+        let vault = IVault::new(self.vault, provider);
+        let tokens = match vault.getPoolTokens(self.pool_id).call().await {
+            Ok(t) => t,
+            Err(e) => {
+                error!(
+                    "Failed to fetch tokens of Balancer pool {:?}: {}",
+                    self.pool_id, e
+                );
+                return None;
+            }
+        };
+        let (i, j) = self.tokens;
+        Some((
+            (*tokens.tokens.get(i)?, *tokens.tokens.get(j)?),
+            (*tokens.balances.get(i)?, *tokens.balances.get(j)?),
+        ))
+    }
+}
+
+impl PoolAdapter for WeightedPoolAdapter {
+    fn protocol(&self) -> &'static str {
+        "Balancer"
+    }
+
+    async fn load<P: Provider>(&mut self, provider: &P, tokens: &TokenList) -> Result<PoolInfo> {
+        let pool_addr = self.pool;
+        let pool = IWeightedPool::new(pool_addr, provider);
+
+        let (pool_id, vault, weights, fee) = match (
+            pool.getPoolId().call().await,
+            pool.getVault().call().await,
+            pool.getNormalizedWeights().call().await,
+            pool.getSwapFeePercentage().call().await,
+        ) {
+            (Ok(id), Ok(vault), Ok(weights), Ok(fee)) => (id, vault, weights, fee),
+            (Err(e), ..) | (_, Err(e), ..) | (_, _, Err(e), _) | (.., Err(e)) => {
+                error!(
+                    "Pool {:?} is not a Balancer weighted pool: {}",
+                    pool_addr, e
+                );
+                return Err(
+                    TrackerError::new(PoolFailure::Unsupported("Balancer weighted"))
+                        .with_pool(pool_addr)
+                        .with_source(e)
+                        .into(),
+                );
+            }
+        };
+
+        let (i, j) = self.tokens;
+        let Some(weights) = weights
+            .get(i)
+            .zip(weights.get(j))
+            .map(|(w0, w1)| (*w0, *w1))
+        else {
+            error!(
+                "Balancer pool {:?} has only {} tokens",
+                pool_addr,
+                weights.len()
+            );
+            return Err(TrackerError::new(PoolFailure::NoSuchToken(i.max(j)))
+                .with_pool(pool_addr)
+                .into());
+        };
+        self.pool_id = pool_id;
+        self.vault = vault;
+        self.weights = weights;
+
+        let Some(((token0, token1), _)) = self.pool_tokens(provider).await else {
+            return Err(TrackerError::new(TokenFailure::TokenFetchFailed)
+                .with_pool(pool_addr)
+                .into());
+        };
+        let fee = (fee / U256::from(FEE_SCALE)).to::<u32>();
+
+        info!("Balancer pool {:?} has pool id {:?}", pool_addr, pool_id);
+        PoolInfo::load(pool_addr, token0, token1, fee, provider, tokens).await
+    }
+
+    fn filter(&self, _logs: Logs) -> Filter {
+        Filter::new()
+            .address(self.vault)
+            .event_signature(Swap::SIGNATURE_HASH)
+            .topic1(self.pool_id)
+    }
+
+    async fn decode<P: Provider>(&mut self, log: &Log, provider: &P) -> Result<Decoded> {
+        // a swap between any two tokens of the pool changes the balances of our pair
+        let Some(((token0, token1), balances)) = self.pool_tokens(provider).await else {
+            return Ok(Decoded::Skip);
+        };
+        let swap = log
+            .log_decode::<Swap>()
+            .ok()
+            .map(|decoded| decoded.inner.data);
+        let amount0 = match &swap {
+            Some(swap) if swap.tokenIn == token0 => swap.amountIn,
+            Some(swap) if swap.tokenOut == token0 => swap.amountOut,
+            _ => U256::ZERO,
+        };
+        // the Vault's event does not name the trader
+        let trade = match &swap {
+            Some(swap) if swap.tokenIn == token0 && swap.tokenOut == token1 => Some(Trade {
+                trader: None,
+                sold0: true,
+                amount1: swap.amountOut,
+            }),
+            Some(swap) if swap.tokenIn == token1 && swap.tokenOut == token0 => Some(Trade {
+                trader: None,
+                sold0: false,
+                amount1: swap.amountIn,
+            }),
+            _ => None,
+        };
+        let Some(sqrt_price_x96) = spot_price(balances, self.weights) else {
+            return Ok(Decoded::Skip);
+        };
+
+        Ok(Decoded::Swap(SwapUpdate {
+            sqrt_price_x96,
+            liquidity: liquidity_from_reserves(balances.0, balances.1),
+            tick: tick_from_sqrt_price(sqrt_price_x96),
+            fee: None,
+            amount0,
+            tx: log.transaction_hash,
+            trade,
+        }))
+    }
+
+    async fn poll_price<P: Provider>(&self, provider: &P) -> Option<U160> {
+        let (_, balances) = self.pool_tokens(provider).await?;
+        spot_price(balances, self.weights)
+    }
+
+    async fn poll_liquidity<P: Provider>(&self, provider: &P) -> Option<u128> {
+        let (_, balances) = self.pool_tokens(provider).await?;
+        Some(liquidity_from_reserves(balances.0, balances.1))
+    }
+}
+
+// raw token1 per raw token0 is (balance1 * weight0) / (balance0 * weight1)
+fn spot_price(balances: (U256, U256), weights: (U256, U256)) -> Option<U160> {
+    sqrt_price_x96_from_amounts(
+        balances.0.checked_mul(weights.1)?,
+        balances.1.checked_mul(weights.0)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q96: u128 = 1 << 96;
+
+    fn weight(percent: u64) -> U256 {
+        U256::from(percent) * U256::from(10u64).pow(U256::from(16))
+    }
+
+    #[test]
+    fn spot_price_of_equal_weights_is_the_balance_ratio() {
+        let price = spot_price((U256::from(100), U256::from(400)), (weight(50), weight(50)));
+        // 400 / 100 = 4, whose square root is 2
+        assert_eq!(price, Some(U160::from(2 * Q96)));
+    }
+
+    #[test]
+    fn spot_price_weighs_the_balances() {
+        // (400 * 0.8) / (100 * 0.2) = 16, whose square root is 4
+        let price = spot_price((U256::from(100), U256::from(400)), (weight(80), weight(20)));
+        assert_eq!(price, Some(U160::from(4 * Q96)));
+    }
+
+    #[test]
+    fn spot_price_of_an_empty_pool_is_unknown() {
+        let price = spot_price((U256::ZERO, U256::from(400)), (weight(50), weight(50)));
+        assert_eq!(price, None);
+    }
+}
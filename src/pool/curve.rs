@@ -0,0 +1,210 @@
+use super::adapter::{Decoded, Logs, PoolAdapter};
+use super::{PoolInfo, SwapUpdate, Trade};
+use crate::calc::{liquidity_from_reserves, sqrt_price_x96_from_amounts, tick_from_sqrt_price};
+use crate::error::{TokenFailure, TrackerError};
+use crate::tokenlist::TokenList;
+use alloy::primitives::aliases::U160;
+use alloy::primitives::{Address, U256};
+use alloy::{
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use alloy_sol_types::{SolEvent, sol};
+use eyre::Result;
+use tracing::{error, info};
+
+sol! {
+    #[sol(rpc)]
+    interface ICurvePool {
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function fee() external view returns (uint256);
+        function get_dy(int128 i, int128 j, uint256 dx) external view returns (uint256);
+        function get_virtual_price() external view returns (uint256);
+    }
+
+    event TokenExchange(
+        address indexed buyer,
+        int128 sold_id,
+        uint256 tokens_sold,
+        int128 bought_id,
+        uint256 tokens_bought
+    );
+}
+
+// Curve fees are scaled by 1e10, the tracker uses hundredths of a bip (1e6)
+const FEE_SCALE: u64 = 10_000;
+
+// a Curve stableswap pool, tracking the price of coin `coins.1` in coin `coins.0`
+// the price is quoted with get_dy for one whole coin after every TokenExchange, which
+// includes the amplified curve and the pool fee unlike the raw balances
+pub struct CurveAdapter {
+    pool: Address,
+    coins: (u8, u8),
+    // one whole coin0 in its raw units, known once the pool is loaded
+    one_token0: U256,
+}
+
+impl CurveAdapter {
+    pub fn new(pool: Address, coins: (u8, u8)) -> Self {
+        CurveAdapter {
+            pool,
+            coins,
+            one_token0: U256::ZERO,
+        }
+    }
+}
+
+impl PoolAdapter for CurveAdapter {
+    fn protocol(&self) -> &'static str {
+        "Curve"
+    }
+
+    async fn load<P: Provider>(&mut self, provider: &P, tokens: &TokenList) -> Result<PoolInfo> {
+        let pool_addr = self.pool;
+        let pool = ICurvePool::new(pool_addr, provider);
+
+        let (token0, token1) = match (
+            pool.coins(U256::from(self.coins.0)).call().await,
+            pool.coins(U256::from(self.coins.1)).call().await,
+        ) {
+            (Ok(t0), Ok(t1)) => (t0, t1),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Failed to fetch coins of Curve pool {:?}: {}", pool_addr, e);
+                return Err(TrackerError::new(TokenFailure::TokenFetchFailed)
+                    .with_pool(pool_addr)
+                    .with_source(e)
+                    .into());
+            }
+        };
+        let fee = match pool.fee().call().await {
+            Ok(f) => (f / U256::from(FEE_SCALE)).to::<u32>(),
+            Err(e) => {
+                error!("Failed to fetch fee of Curve pool {:?}: {}", pool_addr, e);
+                return Err(TrackerError::new(TokenFailure::TokenFetchFailed)
+                    .with_pool(pool_addr)
+                    .with_source(e)
+                    .into());
+            }
+        };
+
+        let info = PoolInfo::load(pool_addr, token0, token1, fee, provider, tokens).await?;
+        self.one_token0 = U256::from(10u64).pow(U256::from(info.dec0));
+
+        match pool.get_virtual_price().call().await {
+            Ok(vp) => info!(
+                "Curve pool {:?} virtual price {}",
+                pool_addr,
+                f64::from(vp) / 1e18
+            ),
+            Err(e) => error!("Failed to fetch virtual price of {:?}: {}", pool_addr, e),
+        }
+
+        Ok(info)
+    }
+
+    fn filter(&self, _logs: Logs) -> Filter {
+        Filter::new()
+            .address(self.pool)
+            .event(TokenExchange::SIGNATURE)
+    }
+
+    async fn decode<P: Provider>(&mut self, log: &Log, provider: &P) -> Result<Decoded> {
+        // exchanges between other coins of the pool move our pair's price too
+        let exchange = match log.log_decode::<TokenExchange>() {
+            Ok(decoded) => decoded.inner.data,
+            Err(e) => {
+                error!("Failed to decode exchange log: {}", e);
+                return Ok(Decoded::Skip);
+            }
+        };
+        let coin0 = self.coins.0 as i128;
+        let coin1 = self.coins.1 as i128;
+        let amount0 = if exchange.sold_id == coin0 {
+            exchange.tokens_sold
+        } else if exchange.bought_id == coin0 {
+            exchange.tokens_bought
+        } else {
+            U256::ZERO
+        };
+        let trade = match (exchange.sold_id, exchange.bought_id) {
+            (sold, bought) if sold == coin0 && bought == coin1 => {
+                Some((true, exchange.tokens_bought))
+            }
+            (sold, bought) if sold == coin1 && bought == coin0 => {
+                Some((false, exchange.tokens_sold))
+            }
+            _ => None,
+        }
+        .map(|(sold0, amount1)| Trade {
+            trader: Some(exchange.buyer),
+            sold0,
+            amount1,
+        });
+
+        let pool = ICurvePool::new(self.pool, provider);
+        let Some(sqrt_price_x96) = quote(&pool, self.coins, self.one_token0).await else {
+            return Ok(Decoded::Skip);
+        };
+        let liquidity = match (
+            pool.balances(U256::from(self.coins.0)).call().await,
+            pool.balances(U256::from(self.coins.1)).call().await,
+        ) {
+            // there is no V3 equivalent for a stableswap curve, the constant product
+            // liquidity of the balances at least follows deposits and withdrawals
+            (Ok(b0), Ok(b1)) => liquidity_from_reserves(b0, b1),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Failed to fetch balances of {:?}: {}", self.pool, e);
+                0
+            }
+        };
+
+        Ok(Decoded::Swap(SwapUpdate {
+            sqrt_price_x96,
+            liquidity,
+            tick: tick_from_sqrt_price(sqrt_price_x96),
+            fee: None,
+            amount0,
+            tx: log.transaction_hash,
+            trade,
+        }))
+    }
+
+    async fn poll_price<P: Provider>(&self, provider: &P) -> Option<U160> {
+        let pool = ICurvePool::new(self.pool, provider);
+        quote(&pool, self.coins, self.one_token0).await
+    }
+
+    async fn poll_liquidity<P: Provider>(&self, provider: &P) -> Option<u128> {
+        let pool = ICurvePool::new(self.pool, provider);
+        match (
+            pool.balances(U256::from(self.coins.0)).call().await,
+            pool.balances(U256::from(self.coins.1)).call().await,
+        ) {
+            (Ok(b0), Ok(b1)) => Some(liquidity_from_reserves(b0, b1)),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Failed to fetch balances of {:?}: {}", self.pool, e);
+                None
+            }
+        }
+    }
+}
+
+// sqrtPriceX96 of selling one whole coin0 for coin1
+async fn quote<P: Provider>(
+    pool: &ICurvePool::ICurvePoolInstance<P>,
+    coins: (u8, u8),
+    one_token0: U256,
+) -> Option<U160> {
+    match pool
+        .get_dy(coins.0 as i128, coins.1 as i128, one_token0)
+        .call()
+        .await
+    {
+        Ok(dy) => sqrt_price_x96_from_amounts(one_token0, dy),
+        Err(e) => {
+            error!("Failed to quote Curve pool {:?}: {}", pool.address(), e);
+            None
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use super::verify::{IV3Factory, known_factories};
+use super::{ListenerSettings, V3Adapter, V3Flavor, listen};
+use crate::alert::Alerter;
+use crate::chain::ChainPreset;
+use crate::config::{DiscoveryRule, PairRule, Protocol};
+use crate::error::{LogFailure, TrackerError};
+use crate::tokenlist::TokenList;
+use alloy::primitives::Address;
+use alloy::primitives::aliases::U24;
+use alloy::{
+    providers::Provider,
+    rpc::types::{BlockNumberOrTag, Filter},
+};
+use alloy_sol_types::{SolEvent, sol};
+use eyre::Result;
+use futures_util::stream::StreamExt;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::task::AbortHandle;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Error)]
+pub enum DiscoveryError {
+    #[error("Pool discovery does not support {0:?} factories")]
+    UnsupportedProtocol(Protocol),
+
+    #[error("Invalid pool {0}, expected e.g. WETH/USDC@0.05%")]
+    InvalidPairSpec(String),
+
+    #[error("Token {0} is not in the token list for chain {1}")]
+    UnknownSymbol(String, u64),
+
+    #[error("Factory {0} has no pool {1}")]
+    PoolNotFound(Address, String),
+}
+
+sol! {
+    event PoolCreated(
+        address indexed token0,
+        address indexed token1,
+        uint24 indexed fee,
+        int24 tickSpacing,
+        address pool
+    );
+}
+
+// fee tiers every factory of a flavor enables, probed for configured pairs
+const UNISWAP_FEE_TIERS: &[u32] = &[100, 500, 3000, 10000];
+const PANCAKESWAP_FEE_TIERS: &[u32] = &[100, 500, 2500, 10000];
+
+// pools with a running listener, so a pool that is configured and discovered,
+// or found by several rules, is only tracked once
+#[derive(Clone, Default)]
+pub struct TrackedPools(Arc<Mutex<HashSet<Address>>>);
+
+impl TrackedPools {
+    // false when the pool is already tracked
+    pub fn insert(&self, pool: Address) -> bool {
+        self.0.lock().unwrap().insert(pool)
+    }
+
+    pub fn remove(&self, pool: Address) {
+        self.0.lock().unwrap().remove(&pool);
+    }
+}
+
+// subscribe to a factory's PoolCreated events and start a listener for every new
+// pool that passes the rule's token and fee filters
+pub async fn discover<P: Provider + Clone + 'static>(
+    rule: DiscoveryRule,
+    provider: P,
+    tracked: TrackedPools,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> Result<()> {
+    let (flavor, factory) = resolve_factory(rule.protocol, rule.factory, settings.chain)?;
+
+    let matches = |token0: Address, token1: Address, fee: u32| {
+        let token_ok = rule.tokens.is_empty()
+            || rule.tokens.contains(&token0)
+            || rule.tokens.contains(&token1);
+        let fee_ok = rule.fees.is_empty() || rule.fees.contains(&fee);
+        token_ok && fee_ok
+    };
+
+    watch_factory(
+        flavor, factory, matches, provider, tracked, alerter, settings,
+    )
+    .await
+}
+
+// track the pools of every fee tier the factory has for a token pair, then keep
+// watching it for tiers created later
+pub async fn track_pair<P: Provider + Clone + 'static>(
+    rule: PairRule,
+    provider: P,
+    tracked: TrackedPools,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> Result<()> {
+    let (flavor, factory) = resolve_factory(rule.protocol, rule.factory, settings.chain)?;
+    let [token_a, token_b] = rule.tokens;
+
+    let fees = if rule.fees.is_empty() {
+        match flavor {
+            V3Flavor::PancakeSwap => PANCAKESWAP_FEE_TIERS,
+            _ => UNISWAP_FEE_TIERS,
+        }
+    } else {
+        &rule.fees[..]
+    };
+
+    let contract = IV3Factory::new(factory, &provider);
+    for &fee in fees {
+        let pool = match contract
+            .getPool(token_a, token_b, U24::from(fee))
+            .call()
+            .await
+        {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Failed to query factory {:?}: {}", factory, e);
+                continue;
+            }
+        };
+        // no pool for this tier (yet)
+        if pool == Address::ZERO || !tracked.insert(pool) {
+            continue;
+        }
+        info!(
+            "Found pool {:?} for {:?}/{:?} at fee {}",
+            pool, token_a, token_b, fee
+        );
+        spawn_listener(
+            V3Adapter::new(pool, flavor, Some(factory)),
+            provider.clone(),
+            alerter.clone(),
+            settings.clone(),
+        );
+    }
+
+    // new tiers of the pair, in any token order
+    let matches = |token0: Address, token1: Address, _fee: u32| {
+        (token0, token1) == (token_a, token_b) || (token0, token1) == (token_b, token_a)
+    };
+
+    watch_factory(
+        flavor, factory, matches, provider, tracked, alerter, settings,
+    )
+    .await
+}
+
+// the pool for a spec like "WETH/USDC@0.05%": symbols are looked up in the token list
+// for the provider's chain, the pool in the protocol's factory
+pub async fn find_pool(
+    spec: &str,
+    protocol: Protocol,
+    factory: Option<Address>,
+    provider: impl Provider,
+    tokens: &TokenList,
+    chain: &ChainPreset,
+) -> Result<Address, DiscoveryError> {
+    let Some((sym_a, sym_b, fee)) = parse_pair_spec(spec) else {
+        return Err(DiscoveryError::InvalidPairSpec(spec.to_string()));
+    };
+    let (_, factory) = resolve_factory(protocol, factory, chain)?;
+
+    let chain_id = match provider.get_chain_id().await {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to fetch chain id: {}", e);
+            return Err(DiscoveryError::PoolNotFound(factory, spec.to_string()));
+        }
+    };
+    let address_of = |symbol: &str| {
+        tokens
+            .address_of(symbol, chain_id)
+            .ok_or_else(|| DiscoveryError::UnknownSymbol(symbol.to_string(), chain_id))
+    };
+    let (token_a, token_b) = (address_of(sym_a)?, address_of(sym_b)?);
+
+    let pool = match IV3Factory::new(factory, &provider)
+        .getPool(token_a, token_b, U24::from(fee))
+        .call()
+        .await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to query factory {:?}: {}", factory, e);
+            return Err(DiscoveryError::PoolNotFound(factory, spec.to_string()));
+        }
+    };
+    if pool == Address::ZERO {
+        return Err(DiscoveryError::PoolNotFound(factory, spec.to_string()));
+    }
+
+    info!("Resolved {} to pool {:?}", spec, pool);
+    Ok(pool)
+}
+
+// "WETH/USDC@0.05%" into the two symbols and the fee in hundredths of a bip
+pub fn parse_pair_spec(spec: &str) -> Option<(&str, &str, u32)> {
+    let (pair, fee) = spec.split_once('@')?;
+    let (sym_a, sym_b) = pair.split_once('/')?;
+    let pct: f64 = fee.trim().trim_end_matches('%').parse().ok()?;
+    Some((sym_a.trim(), sym_b.trim(), (pct * 10_000.0).round() as u32))
+}
+
+// the flavor of a factory and its address unless given: the chain's factory for
+// Uniswap V3, the protocol's Ethereum factory otherwise
+fn resolve_factory(
+    protocol: Protocol,
+    factory: Option<Address>,
+    chain: &ChainPreset,
+) -> Result<(V3Flavor, Address), DiscoveryError> {
+    let flavor = match protocol {
+        Protocol::UniswapV3 => V3Flavor::Uniswap,
+        Protocol::SushiswapV3 => V3Flavor::SushiSwap,
+        Protocol::PancakeswapV3 => V3Flavor::PancakeSwap,
+        protocol => {
+            error!("Cannot discover {:?} pools", protocol);
+            return Err(DiscoveryError::UnsupportedProtocol(protocol));
+        }
+    };
+    let default = match flavor {
+        V3Flavor::Uniswap => Some(chain.uniswap_v3_factory),
+        _ => known_factories(flavor).first().copied(),
+    };
+    match factory.or(default) {
+        Some(factory) => Ok((flavor, factory)),
+        None => Err(DiscoveryError::UnsupportedProtocol(protocol)),
+    }
+}
+
+async fn watch_factory<P: Provider + Clone + 'static>(
+    flavor: V3Flavor,
+    factory: Address,
+    matches: impl Fn(Address, Address, u32) -> bool,
+    provider: P,
+    tracked: TrackedPools,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> Result<()> {
+    let filter = Filter::new()
+        .address(factory)
+        .event_signature(PoolCreated::SIGNATURE_HASH)
+        .from_block(BlockNumberOrTag::Latest);
+
+    let sub = match provider.subscribe_logs(&filter).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to subscribe logs with filter {:?}: {}", filter, e);
+            return Err(TrackerError::new(LogFailure::LogSubscriptionFailed)
+                .with_source(e)
+                .into());
+        }
+    };
+
+    let mut stream = sub.into_stream();
+
+    info!("Watching factory {:?} for new {:?} pools", factory, flavor);
+
+    while let Some(log) = stream.next().await {
+        let created = match log.log_decode::<PoolCreated>() {
+            Ok(decoded) => decoded.inner.data,
+            Err(e) => {
+                error!("Failed to decode PoolCreated log: {}", e);
+                continue;
+            }
+        };
+
+        let fee = created.fee.to::<u32>();
+        if !matches(created.token0, created.token1, fee) || !tracked.insert(created.pool) {
+            continue;
+        }
+
+        info!(
+            "Discovered pool {:?} ({:?}/{:?}, fee {})",
+            created.pool, created.token0, created.token1, fee
+        );
+
+        spawn_listener(
+            V3Adapter::new(created.pool, flavor, Some(factory)),
+            provider.clone(),
+            alerter.clone(),
+            settings.clone(),
+        );
+    }
+
+    warn!("PoolCreated subscription of factory {:?} ended", factory);
+    Ok(())
+}
+
+pub fn spawn_listener<P: Provider + Clone + 'static>(
+    adapter: V3Adapter,
+    provider: P,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> AbortHandle {
+    tokio::spawn(async move {
+        if let Err(e) = listen(adapter, provider, alerter, settings).await {
+            error!("Listener of discovered pool stopped: {}", e);
+        }
+    })
+    .abort_handle()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pair_spec() {
+        assert_eq!(
+            parse_pair_spec("WETH/USDC@0.05%"),
+            Some(("WETH", "USDC", 500))
+        );
+        assert_eq!(
+            parse_pair_spec("WBTC/WETH@0.3"),
+            Some(("WBTC", "WETH", 3000))
+        );
+        assert_eq!(
+            parse_pair_spec(" USDC / USDT @ 0.01% "),
+            Some(("USDC", "USDT", 100))
+        );
+    }
+
+    #[test]
+    fn rejects_pair_spec_without_fee_or_pair() {
+        assert_eq!(parse_pair_spec("WETH/USDC"), None);
+        assert_eq!(parse_pair_spec("WETHUSDC@0.05%"), None);
+        assert_eq!(parse_pair_spec("WETH/USDC@low"), None);
+    }
+}
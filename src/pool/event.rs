@@ -0,0 +1,151 @@
+use super::output::{PriceUpdate, TradeSummary};
+use crate::error::ErrorKind;
+use alloy::primitives::{Address, B256, aliases::U160};
+use serde::Serialize;
+
+// everything a listener reports about its pool, to the library API's subscribers and
+// the API's /pool-events stream; serialized with a `type` field naming the variant
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PoolEvent {
+    Swap(SwapEvent),
+    Mint(PositionEvent),
+    Burn(PositionEvent),
+    Collect(CollectEvent),
+    Flash(FlashEvent),
+    Initialize(InitializeEvent),
+    PriceUpdate(PriceUpdate),
+    LiquidityUpdate(LiquidityUpdate),
+}
+
+impl PoolEvent {
+    pub fn pool(&self) -> Address {
+        match self {
+            PoolEvent::Swap(e) => e.pool,
+            PoolEvent::Mint(e) | PoolEvent::Burn(e) => e.pool,
+            PoolEvent::Collect(e) => e.pool,
+            PoolEvent::Flash(e) => e.pool,
+            PoolEvent::Initialize(e) => e.pool,
+            PoolEvent::PriceUpdate(e) => e.pool,
+            PoolEvent::LiquidityUpdate(e) => e.pool,
+        }
+    }
+
+    // the variant's name as in the `type` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PoolEvent::Swap(_) => "swap",
+            PoolEvent::Mint(_) => "mint",
+            PoolEvent::Burn(_) => "burn",
+            PoolEvent::Collect(_) => "collect",
+            PoolEvent::Flash(_) => "flash",
+            PoolEvent::Initialize(_) => "initialize",
+            PoolEvent::PriceUpdate(_) => "price_update",
+            PoolEvent::LiquidityUpdate(_) => "liquidity_update",
+        }
+    }
+}
+
+// a decoded swap in Uniswap V3 terms, whatever protocol the pool speaks
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapEvent {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub timestamp: u64,
+    pub tx: Option<B256>,
+    pub sqrt_price_x96: U160,
+    // in-range liquidity after the swap
+    pub liquidity: u128,
+    pub tick: i32,
+    // fee tier in hundredths of a bip
+    pub fee: u32,
+    // token0 bought or sold, in whole tokens
+    pub amount0: f64,
+    pub trade: Option<TradeSummary>,
+}
+
+// liquidity added to (Mint) or removed from (Burn) a position, amounts in whole tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionEvent {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub tx: Option<B256>,
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount: u128,
+    pub amount0: f64,
+    pub amount1: f64,
+    // in-range liquidity right after it
+    pub pool_liquidity: u128,
+}
+
+// fees and burned liquidity withdrawn from a position, in whole tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectEvent {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub tx: Option<B256>,
+    pub owner: Address,
+    pub recipient: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount0: f64,
+    pub amount1: f64,
+}
+
+// a flash loan, borrowed amounts and the fees paid back in whole tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashEvent {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub tx: Option<B256>,
+    pub sender: Address,
+    pub recipient: Address,
+    pub amount0: f64,
+    pub amount1: f64,
+    pub paid0: f64,
+    pub paid1: f64,
+}
+
+// the pool's first price, set once when it is created
+#[derive(Debug, Clone, Serialize)]
+pub struct InitializeEvent {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub tx: Option<B256>,
+    pub sqrt_price_x96: U160,
+    pub tick: i32,
+    // token1 per token0
+    pub price: Option<f64>,
+}
+
+// the pool's in-range liquidity moved, through a swap crossing a tick or a position
+// changing
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidityUpdate {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub tx: Option<B256>,
+    pub liquidity: u128,
+    // as of the previous swap, mint or burn, None for the first one seen
+    pub previous: Option<u128>,
+}
+
+// an error of a pool listener, `stopped` when the listener gave up
+#[derive(Debug, Clone)]
+pub struct ListenerError {
+    pub pool: Option<Address>,
+    pub message: String,
+    // what failed, for errors raised by the tracker itself
+    pub kind: Option<ErrorKind>,
+    // the listener may get past it when started again, see TrackerError::is_retryable
+    pub retryable: bool,
+    pub stopped: bool,
+}
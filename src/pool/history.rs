@@ -0,0 +1,84 @@
+use super::PoolAdapter;
+use crate::error::{PriceFailure, TrackerError};
+use crate::storage::Storage;
+use crate::tokenlist::TokenList;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use eyre::Result;
+use serde::Serialize;
+use tracing::{error, warn};
+
+// price of a pool at the end of a past block
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceAt {
+    pub pool: Address,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub block: u64,
+    // token1 per token0
+    pub price: f64,
+    // "archive" for the pool's state at the block, "stored" for the last stored swap
+    // in or before it
+    pub source: &'static str,
+    // block of the stored swap the price comes from
+    pub swap_block: Option<u64>,
+}
+
+// the pool's own state at the block when the node still has it (`has_state`),
+// otherwise the last swap stored in DATABASE_PATH up to the block
+pub async fn price_at_block<A: PoolAdapter, P: Provider>(
+    mut adapter: A,
+    provider: &P,
+    tokens: &TokenList,
+    block: u64,
+    has_state: bool,
+    storage: Option<&Storage>,
+) -> Result<PriceAt> {
+    let info = adapter.load(provider, tokens).await?;
+    let mut price_at = PriceAt {
+        pool: info.pool,
+        symbol0: info.sym0.clone(),
+        symbol1: info.sym1.clone(),
+        block,
+        price: 0.0,
+        source: "archive",
+        swap_block: None,
+    };
+
+    if has_state && let Some(sqrt_price_x96) = adapter.price_at_block(provider, block).await {
+        price_at.price = info.price(sqrt_price_x96)?;
+        return Ok(price_at);
+    }
+
+    let Some(storage) = storage else {
+        error!(
+            "No state of pool {:?} at block {} and DATABASE_PATH is not set",
+            info.pool, block
+        );
+        return Err(TrackerError::new(PriceFailure::NoHistoricalPrice(block))
+            .with_pool(info.pool)
+            .into());
+    };
+    warn!(
+        "No state of pool {:?} at block {}, using stored swaps",
+        info.pool, block
+    );
+
+    match storage.swap_at_block(info.pool, block).await? {
+        Some(swap) => {
+            price_at.price = swap.price;
+            price_at.source = "stored";
+            price_at.swap_block = Some(swap.block);
+            Ok(price_at)
+        }
+        None => {
+            error!(
+                "No stored swaps of pool {:?} up to block {}",
+                info.pool, block
+            );
+            Err(TrackerError::new(PriceFailure::NoHistoricalPrice(block))
+                .with_pool(info.pool)
+                .into())
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use super::PoolEvent;
+use crate::config::{OutputFormat, OutputRule, rules_for_pool};
+use crate::console::{Color, format_amount, format_price, group_thousands, paint};
+use crate::report::USD_STABLECOINS;
+use alloy::primitives::{Address, B256};
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+use tracing::{Instrument, Span, info};
+
+// a swap's price as it leaves the tracker
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceUpdate {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub symbol0: String,
+    pub symbol1: String,
+    // fee tier in hundredths of a bip
+    pub fee: u32,
+    pub block: u64,
+    // unix timestamp of the block
+    pub timestamp: u64,
+    pub tx: Option<B256>,
+    // token1 per token0
+    pub price: f64,
+    // token0 bought or sold
+    pub amount0: f64,
+    pub trade: Option<TradeSummary>,
+    // USD price of token0, when a tracked pool prices it
+    pub usd_price0: Option<f64>,
+}
+
+// the swap's direction and other side, in whole tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeSummary {
+    pub trader: Option<Address>,
+    pub sold0: bool,
+    pub amount1: f64,
+}
+
+// swaps a pool saw since its listener started, for intervals taken by difference
+#[derive(Debug, Clone, Default)]
+pub(super) struct Totals {
+    swaps: u64,
+    volume0: f64,
+    last: Option<PriceUpdate>,
+}
+
+// decides which swap prices of one pool are printed and emitted as PriceUpdate events,
+// by the pool's [[output]] rule, so every sink sees the same reduced stream
+pub(super) enum PriceOutput {
+    Direct(Emitter),
+    // a task prints the latest price once per interval
+    Throttled(watch::Sender<Option<PriceUpdate>>),
+    // a task prints a summary of the swaps per interval
+    Aggregated(watch::Sender<Totals>),
+}
+
+impl PriceOutput {
+    pub(super) fn new(
+        pool: Address,
+        rules: &[OutputRule],
+        events: broadcast::Sender<PoolEvent>,
+    ) -> Self {
+        let rule = rules_for_pool(rules, pool)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let emitter = Emitter {
+            min_change_bps: rule.min_change_bps,
+            narrative: rule.format == OutputFormat::Narrative,
+            last: None,
+            events,
+        };
+
+        if emitter.narrative {
+            return PriceOutput::Direct(emitter);
+        }
+        if let Some(secs) = rule.interval_secs {
+            let (tx, rx) = watch::channel(Totals::default());
+            let interval = Duration::from_secs(secs.max(1));
+            tokio::spawn(aggregate(rx, emitter, interval).instrument(Span::current()));
+            return PriceOutput::Aggregated(tx);
+        }
+        if rule.min_interval_ms == 0 {
+            return PriceOutput::Direct(emitter);
+        }
+        let (tx, rx) = watch::channel(None);
+        let interval = Duration::from_millis(rule.min_interval_ms);
+        tokio::spawn(throttle(rx, emitter, interval).instrument(Span::current()));
+        PriceOutput::Throttled(tx)
+    }
+
+    pub(super) fn on_price(&mut self, update: PriceUpdate) {
+        match self {
+            PriceOutput::Direct(emitter) => {
+                emitter.emit(&update);
+            }
+            PriceOutput::Throttled(tx) => {
+                tx.send_replace(Some(update));
+            }
+            PriceOutput::Aggregated(tx) => tx.send_modify(|totals| {
+                totals.swaps += 1;
+                totals.volume0 += update.amount0;
+                totals.last = Some(update);
+            }),
+        }
+    }
+}
+
+// print the latest price, then wait `interval` before the next one; prices arriving in
+// between replace each other, ends with the listener
+async fn throttle(
+    mut rx: watch::Receiver<Option<PriceUpdate>>,
+    mut emitter: Emitter,
+    interval: Duration,
+) {
+    while rx.changed().await.is_ok() {
+        let update = rx.borrow_and_update().clone();
+        if let Some(update) = update
+            && emitter.emit(&update)
+        {
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+// print the swaps of each `interval` as one line and emit the last price, quiet
+// intervals print nothing; ends with the listener
+async fn aggregate(mut rx: watch::Receiver<Totals>, mut emitter: Emitter, interval: Duration) {
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    let mut printed = Totals::default();
+    loop {
+        ticker.tick().await;
+        match rx.has_changed() {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => return,
+        }
+        let totals = rx.borrow_and_update().clone();
+        if let Some(last) = &totals.last {
+            info!(
+                "{}  {} swaps, {} {} in {}s",
+                emitter.line(last),
+                totals.swaps - printed.swaps,
+                format_price(totals.volume0 - printed.volume0),
+                last.symbol0,
+                interval.as_secs()
+            );
+            emitter.publish(last);
+        }
+        printed = totals;
+    }
+}
+
+pub(super) struct Emitter {
+    min_change_bps: f64,
+    // every swap as a sentence instead of the price line
+    narrative: bool,
+    // last printed price
+    last: Option<f64>,
+    // subscribers of the library API, the API's /pool-events and the handlers
+    events: broadcast::Sender<PoolEvent>,
+}
+
+impl Emitter {
+    // print and emit the price unless it is within `min_change_bps` of the last printed
+    // one, returns whether it was printed
+    fn emit(&mut self, update: &PriceUpdate) -> bool {
+        if self.narrative
+            && let Some(trade) = &update.trade
+        {
+            info!("{}", narrative(update, trade));
+            self.publish(update);
+            return true;
+        }
+        if let Some(last) = self.last
+            && self.min_change_bps > 0.0
+            && ((update.price / last - 1.0) * 10_000.0).abs() <= self.min_change_bps
+        {
+            return false;
+        }
+        info!("{}", self.line(update));
+        self.publish(update);
+        true
+    }
+
+    // no subscribers is not an error
+    fn publish(&self, update: &PriceUpdate) {
+        let _ = self.events.send(PoolEvent::PriceUpdate(update.clone()));
+    }
+
+    // pair and price in columns, with the move since the last printed price in green
+    // or red
+    fn line(&mut self, update: &PriceUpdate) -> String {
+        let pair = format!("{}/{}", update.symbol0, update.symbol1);
+        let price = format!("{:>18} {:<8}", format_price(update.price), update.symbol1);
+        let change = self
+            .last
+            .filter(|last| *last > 0.0)
+            .map(|last| (update.price / last - 1.0) * 100.0);
+        self.last = Some(update.price);
+
+        let change = match change {
+            Some(c) if c > 0.0 => paint(&format!("{} ▲ {:+.2}%", price, c), Color::Green),
+            Some(c) if c < 0.0 => paint(&format!("{} ▼ {:+.2}%", price, c), Color::Red),
+            Some(_) => format!("{}   {:+.2}%", price, 0.0),
+            None => price,
+        };
+        format!("{:<18} {}", pair, change)
+    }
+}
+
+// [WETH/USDC 0.05%] 0xabcd… sold 12.5 WETH for 41,203 USDC @ 3,296.2 (block 19,812,345)
+fn narrative(update: &PriceUpdate, trade: &TradeSummary) -> String {
+    let trader = match trade.trader {
+        Some(trader) => format!("{:?}", trader).chars().take(6).collect::<String>() + "…",
+        None => "someone".to_string(),
+    };
+    let stable = |symbol: &str| USD_STABLECOINS.contains(&symbol);
+    let usd = match update.usd_price0 {
+        Some(usd) if !stable(&update.symbol0) && !stable(&update.symbol1) => {
+            format!(" (${})", format_amount(update.amount0 * usd))
+        }
+        _ => String::new(),
+    };
+    format!(
+        "[{}/{} {}%] {} {} {} {} for {} {}{} @ {} (block {})",
+        update.symbol0,
+        update.symbol1,
+        format_amount(update.fee as f64 / 10_000.0),
+        trader,
+        if trade.sold0 { "sold" } else { "bought" },
+        format_amount(update.amount0),
+        update.symbol0,
+        format_amount(trade.amount1),
+        update.symbol1,
+        usd,
+        format_amount(update.price),
+        group_thousands(&update.block.to_string())
+    )
+}
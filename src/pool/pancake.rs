@@ -0,0 +1,31 @@
+use super::Trade;
+use alloy::primitives::U256;
+use alloy::primitives::aliases::U160;
+use alloy::rpc::types::Log;
+use alloy_sol_types::sol;
+
+sol! {
+    // PancakeSwap V3 adds the protocol fees taken by the swap to the Uniswap V3 event
+    event Swap(
+        address indexed sender,
+        address indexed recipient,
+        int256 amount0,
+        int256 amount1,
+        uint160 sqrtPriceX96,
+        uint128 liquidity,
+        int24 tick,
+        uint128 protocolFeesToken0,
+        uint128 protocolFeesToken1
+    );
+}
+
+pub fn decode_swap(log: &Log) -> Option<(U160, u128, i32, U256, Trade)> {
+    let swap = log.log_decode::<Swap>().ok()?.inner.data;
+    Some((
+        swap.sqrtPriceX96,
+        swap.liquidity,
+        swap.tick.as_i32(),
+        swap.amount0.unsigned_abs(),
+        Trade::from_deltas(swap.recipient, swap.amount0, swap.amount1),
+    ))
+}
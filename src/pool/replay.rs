@@ -0,0 +1,85 @@
+use super::{ListenerSettings, PoolAdapter, PoolInfo, SwapUpdate, Tracker};
+use crate::alert::Alerter;
+use crate::chain::by_id;
+use crate::storage::Storage;
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use eyre::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info};
+
+// load a pool's metadata and chain id, replayed swaps are priced with them
+pub async fn load_pool<A: PoolAdapter, P: Provider>(
+    mut adapter: A,
+    provider: &P,
+    tokens: &TokenList,
+) -> Result<(PoolInfo, u64)> {
+    let info = adapter.load(provider, tokens).await?;
+    let chain_id = match provider.get_chain_id().await {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to fetch chain id: {}", e);
+            return Err(e.into());
+        }
+    };
+    Ok((info, chain_id))
+}
+
+// run the stored swaps of `pools` with timestamps in `from..to` through the same price
+// calculation, state snapshots and alert rules as live swaps, in the order they happened
+// across all pools so rules over several pools see them interleaved
+// returns the number of swaps replayed
+pub async fn replay(
+    pools: Vec<(PoolInfo, u64)>,
+    storage: &Storage,
+    from: u64,
+    to: u64,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> Result<usize> {
+    let mut trackers: HashMap<Address, Tracker> = pools
+        .into_iter()
+        .map(|(info, chain_id)| {
+            let chain = by_id(chain_id).unwrap_or(settings.chain);
+            let pool = info.pool;
+            let tracker = Tracker::new(info, chain_id, chain, alerter.clone(), settings.clone());
+            (pool, tracker)
+        })
+        .collect();
+
+    let records = storage
+        .swaps(trackers.keys().copied().collect(), from, to)
+        .await?;
+    info!(
+        "Replaying {} swaps of {} pools",
+        records.len(),
+        trackers.len()
+    );
+
+    for record in &records {
+        let Some(tracker) = trackers.get_mut(&record.pool) else {
+            continue;
+        };
+        let swap = SwapUpdate {
+            sqrt_price_x96: record.sqrt_price_x96,
+            liquidity: record.liquidity,
+            tick: record.tick,
+            fee: None,
+            amount0: raw_amount(record.amount0, tracker.info.dec0),
+            tx: record.tx,
+            trade: None,
+        };
+        tracker
+            .on_swap(&swap, record.block, record.timestamp)
+            .await?;
+    }
+
+    Ok(records.len())
+}
+
+// stored amounts are in whole tokens
+fn raw_amount(amount: f64, decimals: u8) -> U256 {
+    U256::from((amount * 10f64.powi(decimals as i32)) as u128)
+}
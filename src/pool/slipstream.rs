@@ -0,0 +1,45 @@
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use alloy_sol_types::sol;
+use tracing::{info, warn};
+
+sol! {
+    // Aerodrome / Velodrome Slipstream CLPool: the Uniswap V3 Swap event and a slot0
+    // without feeProtocol; pools are created per tick spacing rather than per fee tier
+    // and swap fees of staked positions go to the pool's gauge
+    #[sol(rpc)]
+    interface ICLPool {
+        function tickSpacing() external view returns (int24);
+        function gauge() external view returns (address);
+        function unstakedFee() external view returns (uint24);
+    }
+}
+
+// log the Slipstream specifics of a pool once when its listener starts
+pub async fn describe(provider: impl Provider, pool: Address) {
+    let contract = ICLPool::new(pool, &provider);
+    let (tick_spacing, gauge, unstaked_fee) = match (
+        contract.tickSpacing().call().await,
+        contract.gauge().call().await,
+        contract.unstakedFee().call().await,
+    ) {
+        (Ok(t), Ok(g), Ok(f)) => (t, g, f),
+        _ => {
+            warn!("Pool {:?} does not look like a Slipstream pool", pool);
+            return;
+        }
+    };
+
+    if gauge == Address::ZERO {
+        info!(
+            "Slipstream pool {:?}: tick spacing {}, no gauge",
+            pool, tick_spacing
+        );
+    } else {
+        // unstaked positions pay an extra fee to the gauge on top of the swap fee
+        info!(
+            "Slipstream pool {:?}: tick spacing {}, gauge {:?}, unstaked fee {}",
+            pool, tick_spacing, gauge, unstaked_fee
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use super::PoolAdapter;
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, aliases::U160};
+use alloy::providers::Provider;
+use eyre::Result;
+use serde::Serialize;
+use tracing::error;
+
+// current on-chain state of a pool, read once without subscribing
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub protocol: &'static str,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub fee: u32,
+    // token1 per token0, None when the adapter cannot read the current price
+    pub price: Option<f64>,
+    pub sqrt_price_x96: Option<U160>,
+    pub liquidity: Option<u128>,
+}
+
+pub async fn snapshot<A: PoolAdapter, P: Provider>(
+    mut adapter: A,
+    provider: &P,
+    tokens: &TokenList,
+) -> Result<Snapshot> {
+    let info = adapter.load(provider, tokens).await?;
+    let chain_id = match provider.get_chain_id().await {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to fetch chain id: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    let sqrt_price_x96 = adapter.poll_price(provider).await;
+    let price = match sqrt_price_x96 {
+        Some(sqrt_price_x96) => Some(info.price(sqrt_price_x96)?),
+        None => None,
+    };
+
+    Ok(Snapshot {
+        pool: info.pool,
+        chain_id,
+        protocol: adapter.protocol(),
+        symbol0: info.sym0,
+        symbol1: info.sym1,
+        fee: info.fee,
+        price,
+        sqrt_price_x96,
+        liquidity: adapter.poll_liquidity(provider).await,
+    })
+}
@@ -0,0 +1,342 @@
+use super::backfill::{chain_id, refresh_volatility, widen};
+use super::discovery::{TrackedPools, spawn_listener};
+use super::{ListenerSettings, PoolAdapter, V3Adapter, V3Flavor};
+use crate::alert::Alerter;
+use crate::config::{Protocol, SubgraphOrder, SubgraphRule};
+use crate::storage::{Storage, SwapRecord, unix_now};
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, B256, aliases::U160};
+use alloy::providers::Provider;
+use eyre::Result;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+use tracing::{error, info, warn};
+
+// swaps per page, the most a subgraph returns for one query
+const SWAP_PAGE: usize = 1_000;
+
+#[derive(Debug, Error)]
+pub enum SubgraphError {
+    #[error("Subgraph request failed: {0}")]
+    RequestFailed(String),
+
+    #[error("Subgraph returned errors: {0}")]
+    QueryFailed(String),
+
+    #[error("Subgraph discovery does not support {0:?} pools")]
+    UnsupportedProtocol(Protocol),
+}
+
+#[derive(Deserialize)]
+struct Response {
+    data: Option<Data>,
+    errors: Option<Vec<QueryError>>,
+}
+
+#[derive(Deserialize)]
+struct QueryError {
+    message: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Data {
+    pools: Vec<PoolRow>,
+    #[serde(rename = "poolDayDatas")]
+    pool_day_datas: Vec<DayRow>,
+    swaps: Vec<SwapRow>,
+}
+
+#[derive(Deserialize)]
+struct PoolRow {
+    id: Address,
+}
+
+#[derive(Deserialize)]
+struct DayRow {
+    pool: PoolRow,
+}
+
+// numbers are strings in the subgraph's JSON, amounts are signed and in whole tokens
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapRow {
+    // "<tx hash>#<log index>"
+    id: String,
+    transaction: TransactionRow,
+    timestamp: String,
+    amount0: String,
+    sqrt_price_x96: String,
+    tick: Option<String>,
+    log_index: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionRow {
+    id: B256,
+    block_number: String,
+}
+
+impl SwapRow {
+    // the subgraph does not keep the pool's liquidity at the time of each swap
+    fn record(&self, pool: Address, chain_id: u64) -> Option<SwapRecord> {
+        let log_index = match &self.log_index {
+            Some(index) => index.parse().ok()?,
+            None => self.id.rsplit_once('#')?.1.parse().ok()?,
+        };
+        Some(SwapRecord {
+            pool,
+            chain_id,
+            block: self.transaction.block_number.parse().ok()?,
+            log_index,
+            tx: Some(self.transaction.id),
+            timestamp: self.timestamp.parse().ok()?,
+            price: 0.0,
+            sqrt_price_x96: self.sqrt_price_x96.parse::<U160>().ok()?,
+            liquidity: 0,
+            tick: self
+                .tick
+                .as_deref()
+                .and_then(|t| t.parse().ok())
+                .unwrap_or_default(),
+            amount0: self.amount0.parse::<f64>().ok()?.abs(),
+        })
+    }
+}
+
+// track the top pools of a Uniswap V3 style subgraph by volume or TVL, refreshed
+// every `refresh_secs`: pools entering the top N are started, pools leaving it stopped
+pub async fn track_top_pools<P: Provider + Clone + 'static>(
+    rule: SubgraphRule,
+    provider: P,
+    tracked: TrackedPools,
+    alerter: Arc<Alerter>,
+    settings: Arc<ListenerSettings>,
+) -> Result<()> {
+    let flavor = match rule.protocol {
+        Protocol::UniswapV3 => V3Flavor::Uniswap,
+        Protocol::SushiswapV3 => V3Flavor::SushiSwap,
+        Protocol::PancakeswapV3 => V3Flavor::PancakeSwap,
+        protocol => {
+            error!("Cannot discover {:?} pools from a subgraph", protocol);
+            return Err(SubgraphError::UnsupportedProtocol(protocol).into());
+        }
+    };
+
+    let client = reqwest::Client::new();
+    // listeners started by this rule
+    let mut running: HashMap<Address, AbortHandle> = HashMap::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(rule.refresh_secs));
+
+    loop {
+        interval.tick().await;
+
+        let top = match top_pools(&client, &rule).await {
+            Ok(pools) => pools,
+            Err(e) => {
+                error!("Failed to fetch top pools from {}: {}", rule.url, e);
+                continue;
+            }
+        };
+
+        running.retain(|pool, handle| {
+            if top.contains(pool) {
+                return true;
+            }
+            info!(
+                "Pool {:?} left the top {}, no longer tracked",
+                pool, rule.top
+            );
+            handle.abort();
+            tracked.remove(*pool);
+            settings.state.write().unwrap().pools.remove(pool);
+            false
+        });
+
+        for pool in top {
+            // configured pools and pools of other rules keep their own listener
+            if running.contains_key(&pool) || !tracked.insert(pool) {
+                continue;
+            }
+            info!("Pool {:?} entered the top {}", pool, rule.top);
+            let handle = spawn_listener(
+                V3Adapter::new(pool, flavor, rule.factory),
+                provider.clone(),
+                alerter.clone(),
+                settings.clone(),
+            );
+            running.insert(pool, handle);
+        }
+    }
+}
+
+async fn top_pools(
+    client: &reqwest::Client,
+    rule: &SubgraphRule,
+) -> Result<HashSet<Address>, SubgraphError> {
+    let query = match rule.order_by {
+        SubgraphOrder::Tvl => {
+            let filter = token_filter(rule.token, "");
+            format!(
+                "{{ pools(first: {}, orderBy: totalValueLockedUSD, orderDirection: desc{}) {{ id }} }}",
+                rule.top, filter
+            )
+        }
+        SubgraphOrder::Volume => {
+            // the last complete UTC day, today's entry only covers the hours so far
+            let day = (unix_now() / 86_400 - 1) * 86_400;
+            let filter = token_filter(rule.token, &format!("date: {}, ", day));
+            format!(
+                "{{ poolDayDatas(first: {}, orderBy: volumeUSD, orderDirection: desc{}) {{ pool {{ id }} }} }}",
+                rule.top, filter
+            )
+        }
+    };
+
+    let data = post_query(client, &rule.url, query).await?;
+    Ok(data
+        .pools
+        .into_iter()
+        .chain(data.pool_day_datas.into_iter().map(|d| d.pool))
+        .map(|p| p.id)
+        .collect())
+}
+
+// store the pool's swaps with timestamps in `from..to` from a Uniswap V3 schema
+// subgraph, for history beyond what the node keeps or serves through eth_getLogs
+// prices come from each swap's sqrtPriceX96 and the token decimals read on-chain
+// returns the number of swaps stored
+pub async fn backfill_from_subgraph<A: PoolAdapter, P: Provider>(
+    mut adapter: A,
+    provider: P,
+    tokens: &TokenList,
+    storage: &Storage,
+    url: &str,
+    from: u64,
+    to: u64,
+) -> Result<usize> {
+    let info = adapter.load(&provider, tokens).await?;
+    info!(
+        "Backfilling pool {:?} ({}/{}) from {} between {} and {}",
+        info.pool, info.sym0, info.sym1, url, from, to
+    );
+
+    let chain_id = chain_id(&provider).await?;
+    let client = reqwest::Client::new();
+    let pool = info.pool.to_string().to_lowercase();
+    let mut stored = 0;
+    let mut span: Option<(u64, u64)> = None;
+    let mut cursor = from;
+    // swaps at `cursor` stored from the previous page
+    let mut seen: HashSet<String> = HashSet::new();
+
+    while cursor < to {
+        // pages overlap by their last timestamp
+        let query = format!(
+            "{{ swaps(first: {}, orderBy: timestamp, orderDirection: asc, where: {{ pool: \"{}\", timestamp_gte: {}, timestamp_lt: {} }}) {{ id transaction {{ id blockNumber }} timestamp amount0 sqrtPriceX96 tick logIndex }} }}",
+            SWAP_PAGE, pool, cursor, to
+        );
+        let rows = match post_query(&client, url, query).await {
+            Ok(data) => data.swaps,
+            Err(e) => {
+                error!("Failed to fetch swaps of pool {:?}: {}", info.pool, e);
+                return Err(e.into());
+            }
+        };
+
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows.iter().filter(|r| !seen.contains(&r.id)) {
+            let Some(mut record) = row.record(info.pool, chain_id) else {
+                warn!("Skipping malformed subgraph swap {}", row.id);
+                continue;
+            };
+            record.price = info.price(record.sqrt_price_x96)?;
+            span = Some(widen(span, record.timestamp));
+            records.push(record);
+        }
+
+        stored += records.len();
+        if !records.is_empty() {
+            storage.record_swaps(records).await?;
+        }
+        if rows.len() < SWAP_PAGE {
+            break;
+        }
+
+        let last = rows.last().map(|r| r.timestamp.clone()).unwrap_or_default();
+        match last.parse::<u64>() {
+            Ok(t) if t > cursor => {
+                cursor = t;
+                seen = rows
+                    .into_iter()
+                    .filter(|r| r.timestamp == last)
+                    .map(|r| r.id)
+                    .collect();
+            }
+            // a whole page within one second, the rest of that second is skipped
+            _ => {
+                warn!(
+                    "More than {} swaps of pool {:?} at {}, some are skipped",
+                    SWAP_PAGE, info.pool, cursor
+                );
+                cursor += 1;
+                seen.clear();
+            }
+        }
+        info!(
+            "Backfilled pool {:?} up to {}, {} swaps so far",
+            info.pool, cursor, stored
+        );
+    }
+
+    refresh_volatility(storage, info.pool, span).await;
+    Ok(stored)
+}
+
+async fn post_query(
+    client: &reqwest::Client,
+    url: &str,
+    query: String,
+) -> Result<Data, SubgraphError> {
+    let response = match client
+        .post(url)
+        .json(&json!({ "query": query }))
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => return Err(SubgraphError::RequestFailed(e.to_string())),
+    };
+    let body: Response = match response.json().await {
+        Ok(b) => b,
+        Err(e) => return Err(SubgraphError::RequestFailed(e.to_string())),
+    };
+
+    if let Some(errors) = body.errors {
+        let messages: Vec<String> = errors.into_iter().map(|e| e.message).collect();
+        return Err(SubgraphError::QueryFailed(messages.join("; ")));
+    }
+    Ok(body.data.unwrap_or_default())
+}
+
+// `where` argument keeping pools that contain `token`, with `extra` conditions
+// (ending in ", ") on every branch
+fn token_filter(token: Option<Address>, extra: &str) -> String {
+    // the subgraph stores addresses in lowercase
+    match token.map(|t| t.to_string().to_lowercase()) {
+        Some(t) if extra.is_empty() => {
+            format!(", where: {{ or: [{{ token0: \"{t}\" }}, {{ token1: \"{t}\" }}] }}")
+        }
+        Some(t) => format!(
+            ", where: {{ or: [{{ {extra}pool_: {{ token0: \"{t}\" }} }}, {{ {extra}pool_: {{ token1: \"{t}\" }} }}] }}"
+        ),
+        None if extra.is_empty() => String::new(),
+        None => format!(", where: {{ {} }}", extra.trim_end_matches(", ")),
+    }
+}
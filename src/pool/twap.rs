@@ -0,0 +1,103 @@
+use super::PoolAdapter;
+use crate::calc::price_from_tick;
+use crate::error::{PriceFailure, TrackerError};
+use crate::storage::{Storage, SwapRecord, unix_now};
+use crate::tokenlist::TokenList;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use eyre::Result;
+use serde::Serialize;
+use tracing::{error, warn};
+
+// time weighted average price of a pool over the last `window` seconds
+#[derive(Debug, Clone, Serialize)]
+pub struct Twap {
+    pub pool: Address,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub window: u64,
+    // token1 per token0
+    pub price: f64,
+    // "oracle" for the pool's observe(), "stored" for swaps in DATABASE_PATH
+    pub source: &'static str,
+}
+
+// the pool's own oracle when it has one and remembers far enough back, otherwise the
+// stored swaps of the window
+pub async fn twap<A: PoolAdapter, P: Provider>(
+    mut adapter: A,
+    provider: &P,
+    tokens: &TokenList,
+    window: u64,
+    storage: Option<&Storage>,
+) -> Result<Twap> {
+    let info = adapter.load(provider, tokens).await?;
+    let mut twap = Twap {
+        pool: info.pool,
+        symbol0: info.sym0.clone(),
+        symbol1: info.sym1.clone(),
+        window,
+        price: 0.0,
+        source: "oracle",
+    };
+
+    let tick = match u32::try_from(window) {
+        Ok(w) => adapter.observe_tick(provider, w).await,
+        Err(_) => None,
+    };
+    if let Some(tick) = tick {
+        twap.price = price_from_tick(tick, info.dec0, info.dec1);
+        return Ok(twap);
+    }
+
+    let Some(storage) = storage else {
+        error!(
+            "No oracle for pool {:?} and DATABASE_PATH is not set",
+            info.pool
+        );
+        return Err(TrackerError::new(PriceFailure::TwapFailed(window))
+            .with_pool(info.pool)
+            .into());
+    };
+    warn!("No oracle for pool {:?}, using stored swaps", info.pool);
+
+    let now = unix_now();
+    let swaps = storage
+        .swaps(vec![info.pool], now.saturating_sub(window), now)
+        .await?;
+    match stored_twap(&swaps, now) {
+        Some(price) => {
+            twap.price = price;
+            twap.source = "stored";
+            Ok(twap)
+        }
+        None => {
+            error!(
+                "No stored swaps for pool {:?} in the last {}s",
+                info.pool, window
+            );
+            Err(TrackerError::new(PriceFailure::TwapFailed(window))
+                .with_pool(info.pool)
+                .into())
+        }
+    }
+}
+
+// every swap price holds until the next swap, the last one until `now`; the price
+// before the first swap of the window is not stored, so the average starts there
+fn stored_twap(swaps: &[SwapRecord], now: u64) -> Option<f64> {
+    let first = swaps.first()?;
+    let span = now.saturating_sub(first.timestamp);
+    if span == 0 {
+        return swaps.last().map(|s| s.price);
+    }
+
+    let mut weighted = 0.0;
+    for (swap, next) in swaps
+        .iter()
+        .zip(swaps.iter().skip(1).map(|s| s.timestamp).chain([now]))
+    {
+        weighted += swap.price * next.saturating_sub(swap.timestamp) as f64;
+    }
+    Some(weighted / span as f64)
+}
@@ -0,0 +1,191 @@
+use super::adapter::{Decoded, Logs, PoolAdapter};
+use super::{PoolInfo, SwapUpdate, Trade};
+use crate::calc::{liquidity_from_reserves, sqrt_price_x96_from_amounts, tick_from_sqrt_price};
+use crate::error::{TokenFailure, TrackerError};
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, U256, aliases::U160};
+use alloy::{
+    providers::Provider,
+    rpc::types::{BlockId, Filter, Log},
+};
+use alloy_sol_types::{SolEvent, sol};
+use eyre::Result;
+use tracing::error;
+
+sol! {
+    #[sol(rpc)]
+    interface IUniswapV2Pair {
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function getReserves() external view returns (
+            uint112 reserve0,
+            uint112 reserve1,
+            uint32 blockTimestampLast
+        );
+    }
+
+    event Sync(uint112 reserve0, uint112 reserve1);
+
+    event Swap(
+        address indexed sender,
+        uint256 amount0In,
+        uint256 amount1In,
+        uint256 amount0Out,
+        uint256 amount1Out,
+        address indexed to
+    );
+}
+
+// a Uniswap V2 style pair: every swap emits Sync with the new reserves right
+// before Swap, the price is derived from those reserves
+pub struct V2Adapter {
+    pair: Address,
+    // the pair's swap fee in hundredths of a bip (3000 for Uniswap V2)
+    fee: u32,
+    // reserves from the latest Sync, consumed by the Swap that follows it
+    // (mints and burns emit Sync without a Swap)
+    reserves: Option<(U256, U256)>,
+}
+
+impl V2Adapter {
+    pub fn new(pair: Address, fee: u32) -> Self {
+        V2Adapter {
+            pair,
+            fee,
+            reserves: None,
+        }
+    }
+}
+
+impl PoolAdapter for V2Adapter {
+    fn protocol(&self) -> &'static str {
+        "V2"
+    }
+
+    async fn load<P: Provider>(&mut self, provider: &P, tokens: &TokenList) -> Result<PoolInfo> {
+        let pair_addr = self.pair;
+        let pair = IUniswapV2Pair::new(pair_addr, provider);
+
+        let token0 = match pair.token0().call().await {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!(
+                    "Failed to fetch token0 address for pair {:?}: {}",
+                    pair_addr, e
+                );
+                return Err(TrackerError::new(TokenFailure::TokenFetchFailed)
+                    .with_pool(pair_addr)
+                    .with_source(e)
+                    .into());
+            }
+        };
+        let token1 = match pair.token1().call().await {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!(
+                    "Failed to fetch token1 address for pair {:?}: {}",
+                    pair_addr, e
+                );
+                return Err(TrackerError::new(TokenFailure::TokenFetchFailed)
+                    .with_pool(pair_addr)
+                    .with_source(e)
+                    .into());
+            }
+        };
+
+        PoolInfo::load(pair_addr, token0, token1, self.fee, provider, tokens).await
+    }
+
+    fn filter(&self, _logs: Logs) -> Filter {
+        Filter::new()
+            .address(self.pair)
+            .events([Sync::SIGNATURE, Swap::SIGNATURE])
+    }
+
+    async fn decode<P: Provider>(&mut self, log: &Log, _provider: &P) -> Result<Decoded> {
+        if log.topic0() == Some(&Sync::SIGNATURE_HASH) {
+            match log.log_decode::<Sync>() {
+                Ok(decoded) => {
+                    let sync = decoded.inner.data;
+                    self.reserves = Some((U256::from(sync.reserve0), U256::from(sync.reserve1)));
+                }
+                Err(e) => error!("Failed to decode sync log: {}", e),
+            }
+            return Ok(Decoded::Skip);
+        }
+
+        let (amount0, trade) = match log.log_decode::<Swap>() {
+            Ok(decoded) => {
+                let swap = decoded.inner.data;
+                let trade = Trade {
+                    trader: Some(swap.to),
+                    sold0: swap.amount0In > swap.amount0Out,
+                    amount1: swap.amount1In + swap.amount1Out,
+                };
+                (swap.amount0In + swap.amount0Out, Some(trade))
+            }
+            Err(_) => (U256::ZERO, None),
+        };
+        let Some((reserve0, reserve1)) = self.reserves.take() else {
+            return Ok(Decoded::Skip);
+        };
+        let Some(sqrt_price_x96) = sqrt_price_x96_from_amounts(reserve0, reserve1) else {
+            return Ok(Decoded::Skip);
+        };
+
+        Ok(Decoded::Swap(SwapUpdate {
+            sqrt_price_x96,
+            // a V2 position is full range, √(x * y) is its V3 equivalent liquidity
+            liquidity: liquidity_from_reserves(reserve0, reserve1),
+            tick: tick_from_sqrt_price(sqrt_price_x96),
+            fee: None,
+            amount0,
+            tx: log.transaction_hash,
+            trade,
+        }))
+    }
+
+    async fn poll_price<P: Provider>(&self, provider: &P) -> Option<U160> {
+        let pair = IUniswapV2Pair::new(self.pair, provider);
+        match pair.getReserves().call().await {
+            Ok(r) => sqrt_price_x96_from_amounts(U256::from(r.reserve0), U256::from(r.reserve1)),
+            Err(e) => {
+                error!("Failed to poll reserves for pair {:?}: {}", self.pair, e);
+                None
+            }
+        }
+    }
+
+    async fn price_at_block<P: Provider>(&self, provider: &P, block: u64) -> Option<U160> {
+        let pair = IUniswapV2Pair::new(self.pair, provider);
+        match pair
+            .getReserves()
+            .block(BlockId::number(block))
+            .call()
+            .await
+        {
+            Ok(r) => sqrt_price_x96_from_amounts(U256::from(r.reserve0), U256::from(r.reserve1)),
+            Err(e) => {
+                error!(
+                    "Failed to fetch reserves of pair {:?} at block {}: {}",
+                    self.pair, block, e
+                );
+                None
+            }
+        }
+    }
+
+    async fn poll_liquidity<P: Provider>(&self, provider: &P) -> Option<u128> {
+        let pair = IUniswapV2Pair::new(self.pair, provider);
+        match pair.getReserves().call().await {
+            Ok(r) => Some(liquidity_from_reserves(
+                U256::from(r.reserve0),
+                U256::from(r.reserve1),
+            )),
+            Err(e) => {
+                error!("Failed to poll reserves for pair {:?}: {}", self.pair, e);
+                None
+            }
+        }
+    }
+}
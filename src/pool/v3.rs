@@ -0,0 +1,430 @@
+use super::adapter::{
+    Collected, Decoded, FlashLoan, Initialized, Logs, PoolAdapter, PositionChange,
+};
+use super::{
+    PoolInfo, SwapUpdate, Trade, algebra, call_word, call_word_at, is_not_a_contract, pancake,
+    slipstream, verify,
+};
+use crate::error::{LogFailure, PoolFailure, TokenFailure, TrackerError};
+use crate::tokenlist::TokenList;
+use alloy::primitives::{Address, U256, aliases::U160};
+use alloy::{
+    providers::Provider,
+    rpc::types::{BlockId, Filter, Log},
+};
+use alloy_sol_types::{SolCall, SolEvent, sol};
+use eyre::Result;
+use tracing::error;
+use uniswap_pool_core::swap::Swap;
+
+sol! {
+    #[sol(rpc)]
+    interface IUniswapV3Pool {
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function fee() external view returns (uint24);
+        function liquidity() external view returns (uint128);
+        function observe(uint32[] secondsAgos) external view returns (
+            int56[] tickCumulatives,
+            uint160[] secondsPerLiquidityCumulativeX128s
+        );
+        function slot0() external view returns (
+            uint160 sqrtPriceX96,
+            int24 tick,
+            uint16 observationIndex,
+            uint16 observationCardinality,
+            uint16 observationCardinalityNext,
+            uint8 feeProtocol,
+            bool unlocked
+        );
+    }
+
+    event Mint(
+        address sender,
+        address indexed owner,
+        int24 indexed tickLower,
+        int24 indexed tickUpper,
+        uint128 amount,
+        uint256 amount0,
+        uint256 amount1
+    );
+
+    event Burn(
+        address indexed owner,
+        int24 indexed tickLower,
+        int24 indexed tickUpper,
+        uint128 amount,
+        uint256 amount0,
+        uint256 amount1
+    );
+
+    event Collect(
+        address indexed owner,
+        address recipient,
+        int24 indexed tickLower,
+        int24 indexed tickUpper,
+        uint128 amount0,
+        uint128 amount1
+    );
+
+    event Flash(
+        address indexed sender,
+        address indexed recipient,
+        uint256 amount0,
+        uint256 amount1,
+        uint256 paid0,
+        uint256 paid1
+    );
+
+    event Initialize(uint160 sqrtPriceX96, int24 tick);
+}
+
+// pools that follow the Uniswap V3 design but differ from it in event or getter ABI
+#[derive(Debug, Clone, Copy)]
+pub enum V3Flavor {
+    Uniswap,
+    // identical ABI, different factories
+    SushiSwap,
+    PancakeSwap,
+    // Algebra V1/V1.9 (QuickSwap V3, Camelot V3) and Algebra Integral: dynamic fees
+    // and globalState() instead of fee() and slot0()
+    Algebra,
+    // Aerodrome / Velodrome Slipstream on Base and Optimism
+    Slipstream,
+}
+
+impl V3Flavor {
+    fn swap_signatures(self) -> Vec<&'static str> {
+        match self {
+            V3Flavor::Uniswap | V3Flavor::SushiSwap | V3Flavor::Slipstream => vec![Swap::SIGNATURE],
+            V3Flavor::PancakeSwap => vec![pancake::Swap::SIGNATURE],
+            V3Flavor::Algebra => vec![Swap::SIGNATURE, algebra::SWAP_SIGNATURE],
+        }
+    }
+
+    // price, liquidity, tick and the token0 amount of a swap
+    fn decode_swap(self, log: &Log) -> Option<(U160, u128, i32, U256, Trade)> {
+        match self {
+            V3Flavor::PancakeSwap => pancake::decode_swap(log),
+            V3Flavor::Algebra if log.topic0() != Some(&Swap::SIGNATURE_HASH) => {
+                algebra::decode_swap(log)
+            }
+            V3Flavor::Uniswap | V3Flavor::SushiSwap | V3Flavor::Algebra | V3Flavor::Slipstream => {
+                let swap = log.log_decode::<Swap>().ok()?.inner.data;
+                Some((
+                    swap.sqrtPriceX96,
+                    swap.liquidity,
+                    swap.tick.as_i32(),
+                    swap.amount0.unsigned_abs(),
+                    Trade::from_deltas(swap.recipient, swap.amount0, swap.amount1),
+                ))
+            }
+        }
+    }
+
+    // getter whose first return value is the pool's sqrtPriceX96
+    fn price_selector(self) -> [u8; 4] {
+        match self {
+            V3Flavor::Uniswap
+            | V3Flavor::SushiSwap
+            | V3Flavor::PancakeSwap
+            | V3Flavor::Slipstream => IUniswapV3Pool::slot0Call::SELECTOR,
+            V3Flavor::Algebra => algebra::IAlgebraPool::globalStateCall::SELECTOR,
+        }
+    }
+
+    // fee in hundredths of a bip; Algebra fees are dynamic, this is the current one
+    async fn fetch_fee(self, provider: impl Provider, pool: Address) -> Option<u32> {
+        match self {
+            V3Flavor::Uniswap
+            | V3Flavor::SushiSwap
+            | V3Flavor::PancakeSwap
+            | V3Flavor::Slipstream => {
+                match IUniswapV3Pool::new(pool, &provider).fee().call().await {
+                    Ok(f) => Some(f.to::<u32>()),
+                    Err(e) => {
+                        error!("Failed to fetch fee for pool {:?}: {}", pool, e);
+                        None
+                    }
+                }
+            }
+            V3Flavor::Algebra => {
+                let selector = algebra::IAlgebraPool::globalStateCall::SELECTOR;
+                let fee = call_word(&provider, pool, selector, 2).await?;
+                Some(fee.to::<u16>() as u32)
+            }
+        }
+    }
+}
+
+// a Uniswap V3 pool or one of its forks
+pub struct V3Adapter {
+    pool: Address,
+    flavor: V3Flavor,
+    // factory the pool must come from, instead of the flavor's known ones
+    factory: Option<Address>,
+}
+
+impl V3Adapter {
+    pub fn new(pool: Address, flavor: V3Flavor, factory: Option<Address>) -> Self {
+        V3Adapter {
+            pool,
+            flavor,
+            factory,
+        }
+    }
+}
+
+impl PoolAdapter for V3Adapter {
+    fn protocol(&self) -> &'static str {
+        match self.flavor {
+            V3Flavor::Uniswap => "Uniswap V3",
+            V3Flavor::SushiSwap => "SushiSwap V3",
+            V3Flavor::PancakeSwap => "PancakeSwap V3",
+            V3Flavor::Algebra => "Algebra",
+            V3Flavor::Slipstream => "Slipstream",
+        }
+    }
+
+    async fn load<P: Provider>(&mut self, provider: &P, tokens: &TokenList) -> Result<PoolInfo> {
+        let pool_addr = self.pool;
+        let pool = IUniswapV3Pool::new(pool_addr, provider);
+
+        // fetch token0 address from the pool contract
+        // returns an Ethereum address for token0
+        let token0 = match pool.token0().call().await {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!(
+                    "Failed to fetch token0 address for pool {}: {}",
+                    pool_addr, e
+                );
+                return Err(token_fetch_error(pool_addr, e).into());
+            }
+        };
+
+        // fetch token1 address from the pool contract
+        // returns an Ethereum address for token1
+        let token1 = match pool.token1().call().await {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!(
+                    "Failed to fetch token1 address for pool {:?}: {:?}",
+                    pool_addr, e
+                );
+                return Err(token_fetch_error(pool_addr, e).into());
+            }
+        };
+
+        // fee tier in hundredths of a bip, e.g. 500 = 0.05%
+        // the tokens were just read, so a missing fee means a different kind of
+        // contract with token0()/token1(), e.g. a V2 pair
+        let Some(fee) = self.flavor.fetch_fee(provider, pool_addr).await else {
+            return Err(TrackerError::new(PoolFailure::Unsupported("V3"))
+                .with_pool(pool_addr)
+                .into());
+        };
+
+        if let Err(e) = verify::verify_pool(
+            provider,
+            pool_addr,
+            self.flavor,
+            self.factory,
+            (token0, token1),
+            fee,
+        )
+        .await
+        {
+            error!("Refusing to track pool {:?}: {}", pool_addr, e);
+            return Err(e.into());
+        }
+
+        if let V3Flavor::Slipstream = self.flavor {
+            slipstream::describe(provider, pool_addr).await;
+        }
+
+        PoolInfo::load(pool_addr, token0, token1, fee, provider, tokens).await
+    }
+
+    //filter to listen only for swap events from this pool, plus mints when a rule needs them
+    //and the position, fee and flash events when every event is wanted
+    fn filter(&self, logs: Logs) -> Filter {
+        let mut events = self.flavor.swap_signatures();
+        if logs != Logs::Swaps {
+            events.push(Mint::SIGNATURE);
+        }
+        if logs == Logs::All {
+            events.extend([
+                Burn::SIGNATURE,
+                Collect::SIGNATURE,
+                Flash::SIGNATURE,
+                Initialize::SIGNATURE,
+            ]);
+        }
+        Filter::new().address(self.pool).events(events)
+    }
+
+    async fn decode<P: Provider>(&mut self, log: &Log, provider: &P) -> Result<Decoded> {
+        match log.topic0() {
+            Some(&Mint::SIGNATURE_HASH) => {
+                let Some(mint) = decode_event::<Mint>(log) else {
+                    return Ok(Decoded::Skip);
+                };
+                let Some(pool_liquidity) = self.poll_liquidity(provider).await else {
+                    return Ok(Decoded::Skip);
+                };
+                return Ok(Decoded::Mint(PositionChange {
+                    owner: mint.owner,
+                    tick_lower: mint.tickLower.as_i32(),
+                    tick_upper: mint.tickUpper.as_i32(),
+                    amount: mint.amount,
+                    amount0: mint.amount0,
+                    amount1: mint.amount1,
+                    pool_liquidity,
+                }));
+            }
+            Some(&Burn::SIGNATURE_HASH) => {
+                let Some(burn) = decode_event::<Burn>(log) else {
+                    return Ok(Decoded::Skip);
+                };
+                let Some(pool_liquidity) = self.poll_liquidity(provider).await else {
+                    return Ok(Decoded::Skip);
+                };
+                return Ok(Decoded::Burn(PositionChange {
+                    owner: burn.owner,
+                    tick_lower: burn.tickLower.as_i32(),
+                    tick_upper: burn.tickUpper.as_i32(),
+                    amount: burn.amount,
+                    amount0: burn.amount0,
+                    amount1: burn.amount1,
+                    pool_liquidity,
+                }));
+            }
+            Some(&Collect::SIGNATURE_HASH) => {
+                return Ok(
+                    decode_event::<Collect>(log).map_or(Decoded::Skip, |collect| {
+                        Decoded::Collect(Collected {
+                            owner: collect.owner,
+                            recipient: collect.recipient,
+                            tick_lower: collect.tickLower.as_i32(),
+                            tick_upper: collect.tickUpper.as_i32(),
+                            amount0: collect.amount0,
+                            amount1: collect.amount1,
+                        })
+                    }),
+                );
+            }
+            Some(&Flash::SIGNATURE_HASH) => {
+                return Ok(decode_event::<Flash>(log).map_or(Decoded::Skip, |flash| {
+                    Decoded::Flash(FlashLoan {
+                        sender: flash.sender,
+                        recipient: flash.recipient,
+                        amount0: flash.amount0,
+                        amount1: flash.amount1,
+                        paid0: flash.paid0,
+                        paid1: flash.paid1,
+                    })
+                }));
+            }
+            Some(&Initialize::SIGNATURE_HASH) => {
+                return Ok(
+                    decode_event::<Initialize>(log).map_or(Decoded::Skip, |init| {
+                        Decoded::Initialize(Initialized {
+                            sqrt_price_x96: init.sqrtPriceX96,
+                            tick: init.tick.as_i32(),
+                        })
+                    }),
+                );
+            }
+            _ => {}
+        }
+
+        let Some((sqrt_price_x96, liquidity, tick, amount0, trade)) = self.flavor.decode_swap(log)
+        else {
+            tracing::error!("Failed to decode swap log {:?}", log.transaction_hash);
+            return Err(TrackerError::new(LogFailure::SqrtPriceFetchFailed)
+                .with_pool(self.pool)
+                .into());
+        };
+
+        Ok(Decoded::Swap(SwapUpdate {
+            sqrt_price_x96,
+            liquidity,
+            tick,
+            fee: None,
+            amount0,
+            tx: log.transaction_hash,
+            trade: Some(trade),
+        }))
+    }
+
+    async fn poll_price<P: Provider>(&self, provider: &P) -> Option<U160> {
+        call_word(provider, self.pool, self.flavor.price_selector(), 0)
+            .await
+            .map(|price| price.to::<U160>())
+    }
+
+    async fn price_at_block<P: Provider>(&self, provider: &P, block: u64) -> Option<U160> {
+        call_word_at(
+            provider,
+            self.pool,
+            self.flavor.price_selector(),
+            0,
+            BlockId::number(block),
+        )
+        .await
+        .map(|price| price.to::<U160>())
+    }
+
+    async fn poll_liquidity<P: Provider>(&self, provider: &P) -> Option<u128> {
+        let pool = IUniswapV3Pool::new(self.pool, provider);
+        match pool.liquidity().call().await {
+            Ok(l) => Some(l),
+            Err(e) => {
+                error!("Failed to fetch liquidity for pool {:?}: {}", self.pool, e);
+                None
+            }
+        }
+    }
+
+    async fn observe_tick<P: Provider>(&self, provider: &P, window: u32) -> Option<f64> {
+        // Algebra keeps its oracle behind getTimepoints() in a separate plugin
+        if matches!(self.flavor, V3Flavor::Algebra) {
+            return None;
+        }
+        let pool = IUniswapV3Pool::new(self.pool, provider);
+        let observed = match pool.observe(vec![window, 0]).call().await {
+            Ok(o) => o,
+            Err(e) => {
+                error!("Failed to observe pool {:?}: {}", self.pool, e);
+                return None;
+            }
+        };
+        let [then, now] = observed.tickCumulatives[..] else {
+            return None;
+        };
+        Some((now.as_i64() - then.as_i64()) as f64 / window as f64)
+    }
+}
+
+// the data of a log of event `E`, None (logged) when it does not decode
+fn decode_event<E: SolEvent>(log: &Log) -> Option<E> {
+    match log.log_decode::<E>() {
+        Ok(decoded) => Some(decoded.inner.data),
+        Err(e) => {
+            error!("Failed to decode {} log: {}", E::SIGNATURE, e);
+            None
+        }
+    }
+}
+
+// a token getter that reverts or returns nothing is not a V3 pool and will not become one,
+// anything else may be the endpoint and is worth retrying
+fn token_fetch_error(pool: Address, e: alloy::contract::Error) -> TrackerError {
+    let error = if is_not_a_contract(&e) {
+        TrackerError::new(PoolFailure::Unsupported("V3"))
+    } else {
+        TrackerError::new(TokenFailure::TokenFetchFailed)
+    };
+    error.with_pool(pool).with_source(e)
+}
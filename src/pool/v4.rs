@@ -0,0 +1,251 @@
+use super::adapter::{Decoded, Logs, PoolAdapter};
+use super::backfill::LogPages;
+use super::{PoolInfo, SwapUpdate, Trade};
+use crate::config::PoolEntry;
+use crate::error::{LogFailure, PoolFailure, TokenFailure, TrackerError};
+use crate::tokenlist::TokenList;
+use alloy::primitives::aliases::{I24, U24, U160};
+use alloy::primitives::{Address, B256, U256, address, keccak256};
+use alloy::{
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use alloy_sol_types::{SolEvent, SolValue, sol};
+use eyre::Result;
+use thiserror::Error;
+use tracing::{error, info};
+
+// Uniswap V4 singleton on Ethereum mainnet and the block it was deployed in
+pub const MAINNET_POOL_MANAGER: Address = address!("0x000000000004444c5dc75cB358380D2e3dE08A90");
+const MAINNET_DEPLOY_BLOCK: u64 = 21_688_329;
+
+// lp fee flag marking pools whose fee is set by their hook on every swap
+const DYNAMIC_FEE_FLAG: u32 = 0x800000;
+
+#[derive(Debug, Error)]
+pub enum PoolKeyError {
+    #[error("Pool {0} is missing pool_id or its currencies")]
+    Incomplete(String),
+
+    #[error("Pool key does not hash to pool id {0}")]
+    Mismatch(B256),
+
+    #[error("No Initialize event found for pool id {0}")]
+    NotFound(B256),
+}
+
+sol! {
+    struct PoolKey {
+        address currency0;
+        address currency1;
+        uint24 fee;
+        int24 tickSpacing;
+        address hooks;
+    }
+
+    event Initialize(
+        bytes32 indexed id,
+        address indexed currency0,
+        address indexed currency1,
+        uint24 fee,
+        int24 tickSpacing,
+        address hooks,
+        uint160 sqrtPriceX96,
+        int24 tick
+    );
+
+    event Swap(
+        bytes32 indexed id,
+        address indexed sender,
+        int128 amount0,
+        int128 amount1,
+        uint160 sqrtPriceX96,
+        uint128 liquidity,
+        int24 tick,
+        uint24 fee
+    );
+}
+
+// V4 pools have no contract of their own, alerts, rules and the API refer to
+// them by the first 20 bytes of their PoolId
+pub fn pool_address(id: B256) -> Address {
+    Address::from_slice(&id[..20])
+}
+
+// one pool's swaps on the V4 PoolManager
+// the pool key (currencies, fee, tick spacing, hooks) comes from the config when
+// given, otherwise from the pool's Initialize event
+pub struct V4Adapter {
+    entry: PoolEntry,
+    manager: Address,
+    // known once the pool is loaded
+    id: B256,
+}
+
+impl V4Adapter {
+    pub fn new(entry: PoolEntry) -> Self {
+        V4Adapter {
+            manager: entry.address.unwrap_or(MAINNET_POOL_MANAGER),
+            entry,
+            id: B256::ZERO,
+        }
+    }
+}
+
+impl PoolAdapter for V4Adapter {
+    fn protocol(&self) -> &'static str {
+        "V4"
+    }
+
+    async fn load<P: Provider>(&mut self, provider: &P, tokens: &TokenList) -> Result<PoolInfo> {
+        let (id, key) = match resolve_pool_key(&self.entry, self.manager, provider).await {
+            Ok(k) => k,
+            Err(e) => {
+                error!("Failed to resolve V4 pool key: {}", e);
+                // only a failed Initialize lookup may succeed on a retry
+                let error = match e {
+                    PoolKeyError::NotFound(_) => TrackerError::new(TokenFailure::TokenFetchFailed),
+                    PoolKeyError::Incomplete(_) | PoolKeyError::Mismatch(_) => {
+                        TrackerError::new(PoolFailure::InvalidKey)
+                    }
+                };
+                return Err(error.with_source(e).into());
+            }
+        };
+        self.id = id;
+        let pool_addr = pool_address(id);
+
+        info!(
+            "V4 pool {:?} is tracked as {:?} (hooks {:?})",
+            id, pool_addr, key.hooks
+        );
+
+        let fee = key.fee.to::<u32>();
+        PoolInfo::load(
+            pool_addr,
+            key.currency0,
+            key.currency1,
+            if fee == DYNAMIC_FEE_FLAG { 0 } else { fee },
+            provider,
+            tokens,
+        )
+        .await
+    }
+
+    fn filter(&self, _logs: Logs) -> Filter {
+        Filter::new()
+            .address(self.manager)
+            .event_signature(Swap::SIGNATURE_HASH)
+            .topic1(self.id)
+    }
+
+    async fn decode<P: Provider>(&mut self, log: &Log, _provider: &P) -> Result<Decoded> {
+        let swap = match log.log_decode::<Swap>() {
+            Ok(decoded) => decoded.inner.data,
+            Err(e) => {
+                tracing::error!("Failed to decode log: {}", e);
+                return Err(TrackerError::new(LogFailure::SqrtPriceFetchFailed)
+                    .with_pool(pool_address(self.id))
+                    .with_source(e)
+                    .into());
+            }
+        };
+
+        Ok(Decoded::Swap(SwapUpdate {
+            sqrt_price_x96: swap.sqrtPriceX96,
+            liquidity: swap.liquidity,
+            tick: swap.tick.as_i32(),
+            // the fee actually charged, which may change on every swap with dynamic fees
+            fee: Some(swap.fee.to::<u32>()),
+            amount0: U256::from(swap.amount0.unsigned_abs()),
+            tx: log.transaction_hash,
+            // V4 amounts are the swapper's balance changes, negative into the pool
+            trade: Some(Trade {
+                trader: Some(swap.sender),
+                sold0: swap.amount0 < 0,
+                amount1: U256::from(swap.amount1.unsigned_abs()),
+            }),
+        }))
+    }
+
+    // the PoolManager has no slot0 getter, so stale pools are never polled
+    async fn poll_price<P: Provider>(&self, _provider: &P) -> Option<U160> {
+        None
+    }
+}
+
+async fn resolve_pool_key(
+    entry: &PoolEntry,
+    manager: Address,
+    provider: impl Provider,
+) -> Result<(B256, PoolKey), PoolKeyError> {
+    if let (Some(currency0), Some(currency1)) = (entry.currency0, entry.currency1) {
+        let key = PoolKey {
+            currency0,
+            currency1,
+            fee: U24::from(entry.fee.unwrap_or(3000)),
+            tickSpacing: I24::try_from(entry.tick_spacing.unwrap_or(60)).unwrap_or(I24::ONE),
+            hooks: entry.hooks.unwrap_or_default(),
+        };
+        let id = keccak256(key.abi_encode());
+        return match entry.pool_id {
+            Some(expected) if expected != id => Err(PoolKeyError::Mismatch(expected)),
+            _ => Ok((id, key)),
+        };
+    }
+
+    let Some(id) = entry.pool_id else {
+        return Err(PoolKeyError::Incomplete(format!("{:?}", entry.address)));
+    };
+
+    let from_block = entry
+        .from_block
+        .unwrap_or(if manager == MAINNET_POOL_MANAGER {
+            MAINNET_DEPLOY_BLOCK
+        } else {
+            0
+        });
+    let latest = match provider.get_block_number().await {
+        Ok(b) => b,
+        Err(e) => {
+            error!("Failed to fetch the latest block: {}", e);
+            return Err(PoolKeyError::NotFound(id));
+        }
+    };
+    let filter = Filter::new()
+        .address(manager)
+        .event_signature(Initialize::SIGNATURE_HASH)
+        .topic1(id);
+
+    // paged like a backfill, providers reject a single query over the whole chain
+    let mut pages = LogPages::new(filter, from_block, latest);
+    let init = loop {
+        match pages.next(&provider).await {
+            Ok(Some((_, _, logs))) => {
+                if let Some(init) = logs
+                    .iter()
+                    .find_map(|log| log.log_decode::<Initialize>().ok())
+                {
+                    break init;
+                }
+            }
+            Ok(None) => return Err(PoolKeyError::NotFound(id)),
+            Err(e) => {
+                error!("Failed to fetch Initialize event of pool {:?}: {}", id, e);
+                return Err(PoolKeyError::NotFound(id));
+            }
+        }
+    };
+
+    let init = init.inner.data;
+    Ok((
+        id,
+        PoolKey {
+            currency0: init.currency0,
+            currency1: init.currency1,
+            fee: init.fee,
+            tickSpacing: init.tickSpacing,
+            hooks: init.hooks,
+        },
+    ))
+}
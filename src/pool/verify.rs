@@ -0,0 +1,316 @@
+use super::V3Flavor;
+use alloy::primitives::aliases::U24;
+use alloy::primitives::{Address, address};
+use alloy::providers::Provider;
+use alloy_sol_types::sol;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("Failed to query the factory of pool {0}")]
+    FactoryCallFailed(Address),
+
+    #[error("Pool {0} is not the pool factory {1} deployed for its tokens and fee")]
+    NotFromFactory(Address, Address),
+
+    #[error("Pool {0} was deployed by factory {1}, expected {2}")]
+    WrongFactory(Address, Address, Address),
+}
+
+sol! {
+    #[sol(rpc)]
+    interface IFactoryPool {
+        function factory() external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface IV3Factory {
+        function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address);
+    }
+
+    // Algebra factories keep one pool per pair
+    #[sol(rpc)]
+    interface IAlgebraFactory {
+        function poolByPair(address tokenA, address tokenB) external view returns (address);
+    }
+
+    // Slipstream factories keep one pool per pair and tick spacing
+    #[sol(rpc)]
+    interface ICLFactory {
+        function getPool(address tokenA, address tokenB, int24 tickSpacing) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface ICLPool {
+        function tickSpacing() external view returns (int24);
+    }
+}
+
+// factories of the Uniswap V3 style deployments we know of, per flavor
+const UNISWAP_FACTORIES: &[Address] = &[
+    // Ethereum, Arbitrum, Optimism, Polygon
+    address!("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+    // Base
+    address!("0x33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+    // BNB Chain
+    address!("0xdB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7"),
+];
+const SUSHISWAP_FACTORIES: &[Address] = &[
+    // Ethereum
+    address!("0xbACEB8eC6b9355Dfc0269C18bac9d6E2Bdc29C4F"),
+    // Arbitrum
+    address!("0x1af415a1EbA07a4986a52B6f2e7dE7003D82231e"),
+];
+const PANCAKESWAP_FACTORIES: &[Address] = &[
+    // BNB Chain, Ethereum
+    address!("0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865"),
+];
+
+pub fn known_factories(flavor: V3Flavor) -> &'static [Address] {
+    match flavor {
+        V3Flavor::Uniswap => UNISWAP_FACTORIES,
+        V3Flavor::SushiSwap => SUSHISWAP_FACTORIES,
+        V3Flavor::PancakeSwap => PANCAKESWAP_FACTORIES,
+        V3Flavor::Algebra | V3Flavor::Slipstream => &[],
+    }
+}
+
+// make sure a pool is what it claims to be before tracking it: it must have been
+// deployed by `expected` (or a known factory of its flavor) and that factory must
+// return it for its tokens and fee (Algebra: its tokens, Slipstream: its tokens and
+// tick spacing); Algebra and Slipstream have no known factories, their pools are only
+// verified when `factory` is pinned
+pub async fn verify_pool(
+    provider: impl Provider,
+    pool: Address,
+    flavor: V3Flavor,
+    expected: Option<Address>,
+    tokens: (Address, Address),
+    fee: u32,
+) -> Result<(), VerifyError> {
+    if known_factories(flavor).is_empty() && expected.is_none() {
+        return Ok(());
+    }
+
+    let factory = match IFactoryPool::new(pool, &provider).factory().call().await {
+        Ok(f) => f,
+        Err(e) => {
+            error!("Failed to fetch factory of pool {:?}: {}", pool, e);
+            return Err(VerifyError::FactoryCallFailed(pool));
+        }
+    };
+
+    match expected {
+        Some(expected) if expected != factory => {
+            return Err(VerifyError::WrongFactory(pool, factory, expected));
+        }
+        Some(_) => {}
+        // other chains and forks have factories we do not know, the getPool check still applies
+        None if !known_factories(flavor).contains(&factory) => {
+            warn!(
+                "Pool {:?} was deployed by unknown {:?} factory {:?}, set `factory` to pin it",
+                pool, flavor, factory
+            );
+        }
+        None => {}
+    }
+
+    let deployed = match deployed_pool(&provider, pool, flavor, factory, tokens, fee).await {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to query factory {:?}: {}", factory, e);
+            return Err(VerifyError::FactoryCallFailed(pool));
+        }
+    };
+    if deployed != pool {
+        return Err(VerifyError::NotFromFactory(pool, factory));
+    }
+
+    info!("Verified pool {:?} against factory {:?}", pool, factory);
+    Ok(())
+}
+
+// the pool `factory` has deployed for the tokens, asked the way its flavor indexes them
+async fn deployed_pool(
+    provider: impl Provider,
+    pool: Address,
+    flavor: V3Flavor,
+    factory: Address,
+    tokens: (Address, Address),
+    fee: u32,
+) -> Result<Address, alloy::contract::Error> {
+    match flavor {
+        V3Flavor::Uniswap | V3Flavor::SushiSwap | V3Flavor::PancakeSwap => {
+            IV3Factory::new(factory, &provider)
+                .getPool(tokens.0, tokens.1, U24::from(fee))
+                .call()
+                .await
+        }
+        V3Flavor::Algebra => {
+            IAlgebraFactory::new(factory, &provider)
+                .poolByPair(tokens.0, tokens.1)
+                .call()
+                .await
+        }
+        V3Flavor::Slipstream => {
+            let tick_spacing = ICLPool::new(pool, &provider).tickSpacing().call().await?;
+            ICLFactory::new(factory, &provider)
+                .getPool(tokens.0, tokens.1, tick_spacing)
+                .call()
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, U256};
+    use alloy::providers::ProviderBuilder;
+    use alloy::rpc::client::RpcClient;
+    use alloy::rpc::json_rpc::{
+        ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload,
+    };
+    use alloy::transports::{TransportError, TransportFut};
+    use alloy_sol_types::{SolCall, SolValue};
+    use serde_json::value::RawValue;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    const POOL: Address = address!("0x00000000000000000000000000000000000000aa");
+    const FACTORY: Address = address!("0x00000000000000000000000000000000000000fa");
+    const TOKENS: (Address, Address) = (
+        address!("0x0000000000000000000000000000000000000001"),
+        address!("0x0000000000000000000000000000000000000002"),
+    );
+
+    // answers eth_calls by function selector, calls to anything else revert
+    #[derive(Clone)]
+    struct Contracts(Arc<HashMap<[u8; 4], Bytes>>);
+
+    impl tower::Service<RequestPacket> for Contracts {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), TransportError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            let RequestPacket::Single(request) = request else {
+                panic!("unexpected batch request");
+            };
+            let params: serde_json::Value = request
+                .params()
+                .and_then(|p| serde_json::from_str(p.get()).ok())
+                .unwrap_or_default();
+            let input = params[0]["input"].as_str().or(params[0]["data"].as_str());
+            let selector = input
+                .and_then(|input| input.parse::<Bytes>().ok())
+                .and_then(|input| input.get(..4).and_then(|s| <[u8; 4]>::try_from(s).ok()));
+            let payload = match selector.and_then(|s| self.0.get(&s)) {
+                Some(value) => ResponsePayload::Success(
+                    RawValue::from_string(serde_json::to_string(value).unwrap()).unwrap(),
+                ),
+                None => ResponsePayload::Failure(ErrorPayload {
+                    code: 3,
+                    message: "execution reverted".into(),
+                    data: None,
+                }),
+            };
+            let response = Response {
+                id: request.meta().id.clone(),
+                payload,
+            };
+            Box::pin(async move { Ok(ResponsePacket::Single(response)) })
+        }
+    }
+
+    fn provider(returns: &[([u8; 4], Bytes)]) -> impl Provider + use<> {
+        let contracts = Contracts(Arc::new(returns.iter().cloned().collect()));
+        ProviderBuilder::new().connect_client(RpcClient::new(contracts, true))
+    }
+
+    fn word(value: impl SolValue) -> Bytes {
+        value.abi_encode().into()
+    }
+
+    const FACTORY_CALL: [u8; 4] = IFactoryPool::factoryCall::SELECTOR;
+
+    #[tokio::test]
+    async fn verifies_uniswap_pool_with_get_pool() {
+        let provider = provider(&[
+            (FACTORY_CALL, word(UNISWAP_FACTORIES[0])),
+            (IV3Factory::getPoolCall::SELECTOR, word(POOL)),
+        ]);
+        let result = verify_pool(provider, POOL, V3Flavor::Uniswap, None, TOKENS, 500).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn verifies_pinned_algebra_pool_with_pool_by_pair() {
+        let provider = provider(&[
+            (FACTORY_CALL, word(FACTORY)),
+            (IAlgebraFactory::poolByPairCall::SELECTOR, word(POOL)),
+        ]);
+        let result = verify_pool(provider, POOL, V3Flavor::Algebra, Some(FACTORY), TOKENS, 0).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn verifies_pinned_slipstream_pool_with_tick_spacing() {
+        let provider = provider(&[
+            (FACTORY_CALL, word(FACTORY)),
+            (ICLPool::tickSpacingCall::SELECTOR, word(U256::from(100))),
+            (ICLFactory::getPoolCall::SELECTOR, word(POOL)),
+        ]);
+        let result = verify_pool(
+            provider,
+            POOL,
+            V3Flavor::Slipstream,
+            Some(FACTORY),
+            TOKENS,
+            0,
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn skips_unpinned_algebra_and_slipstream_pools() {
+        for flavor in [V3Flavor::Algebra, V3Flavor::Slipstream] {
+            let result = verify_pool(provider(&[]), POOL, flavor, None, TOKENS, 0).await;
+            assert!(result.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_pool_the_factory_did_not_deploy() {
+        let other = address!("0x00000000000000000000000000000000000000bb");
+        let provider = provider(&[
+            (FACTORY_CALL, word(FACTORY)),
+            (IAlgebraFactory::poolByPairCall::SELECTOR, word(other)),
+        ]);
+        let result = verify_pool(provider, POOL, V3Flavor::Algebra, Some(FACTORY), TOKENS, 0).await;
+        assert!(matches!(result, Err(VerifyError::NotFromFactory(..))));
+    }
+
+    #[tokio::test]
+    async fn rejects_pool_of_another_factory() {
+        let pinned = address!("0x00000000000000000000000000000000000000fb");
+        let provider = provider(&[(FACTORY_CALL, word(FACTORY))]);
+        let result = verify_pool(
+            provider,
+            POOL,
+            V3Flavor::Slipstream,
+            Some(pinned),
+            TOKENS,
+            0,
+        )
+        .await;
+        assert!(matches!(result, Err(VerifyError::WrongFactory(..))));
+    }
+}
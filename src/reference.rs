@@ -0,0 +1,189 @@
+#[cfg(feature = "cex")]
+mod cex;
+
+use crate::config::Config;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use alloy_sol_types::sol;
+#[cfg(feature = "cex")]
+use cex::Exchange;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info};
+
+#[derive(Debug, Error)]
+pub enum ReferenceError {
+    #[error("Unknown reference price source: {0}")]
+    UnknownSource(String),
+
+    #[error("Failed to fetch reference price from {0}")]
+    FetchFailed(String),
+
+    #[error("Ticker stream {0} failed")]
+    StreamFailed(String),
+
+    #[error("Reference {0} needs the cex feature")]
+    NotBuilt(String),
+}
+
+sol! {
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (
+            uint80 roundId,
+            int256 answer,
+            uint256 startedAt,
+            uint256 updatedAt,
+            uint80 answeredInRound
+        );
+    }
+}
+
+// latest price of an external reference, e.g. a Chainlink feed
+#[derive(Debug, Clone, Copy)]
+pub struct ReferencePrice {
+    pub price: f64,
+    // unix timestamp the source last updated the price, a feed's round can be
+    // much older than the poll that read it
+    pub updated_at: u64,
+}
+
+// reference prices keyed by the source id used in the config, e.g. "chainlink:0x5f4e..."
+// or "binance:ETHUSDC", written by the feed tasks, read by alert rules on every swap
+pub type ReferencePrices = Arc<RwLock<HashMap<String, ReferencePrice>>>;
+
+pub enum ReferenceSource {
+    Chainlink(Address),
+    // mid price of a centralized exchange's ticker stream, by the exchange's symbol
+    #[cfg(feature = "cex")]
+    Cex(Exchange, String),
+}
+
+impl std::str::FromStr for ReferenceSource {
+    type Err = ReferenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("chainlink", addr)) => match addr.parse() {
+                Ok(a) => Ok(ReferenceSource::Chainlink(a)),
+                Err(_) => Err(ReferenceError::UnknownSource(s.to_string())),
+            },
+            #[cfg(feature = "cex")]
+            Some(("binance", symbol)) => Ok(ReferenceSource::Cex(
+                Exchange::Binance,
+                symbol.to_uppercase(),
+            )),
+            #[cfg(feature = "cex")]
+            Some(("coinbase", product)) => Ok(ReferenceSource::Cex(
+                Exchange::Coinbase,
+                product.to_uppercase(),
+            )),
+            #[cfg(not(feature = "cex"))]
+            Some(("binance" | "coinbase", _)) => Err(ReferenceError::NotBuilt(s.to_string())),
+            _ => Err(ReferenceError::UnknownSource(s.to_string())),
+        }
+    }
+}
+
+// spawn one task per distinct reference used by the config: on-chain feeds are
+// polled every `refresh`, exchange tickers are streamed
+pub fn spawn_feeds<P>(config: &Config, provider: P, refresh: Duration) -> ReferencePrices
+where
+    P: Provider + Clone + 'static,
+{
+    let prices: ReferencePrices = Arc::new(RwLock::new(HashMap::new()));
+
+    let mut ids: Vec<&String> = config
+        .deviation
+        .iter()
+        .map(|r| &r.reference)
+        .chain(config.basis.iter().map(|r| &r.reference))
+        .collect();
+    ids.sort();
+    ids.dedup();
+
+    for id in ids {
+        let source = match id.parse::<ReferenceSource>() {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Skipping reference {}: {}", id, e);
+                continue;
+            }
+        };
+
+        let id = id.clone();
+        let prices = prices.clone();
+        #[cfg(feature = "cex")]
+        if let ReferenceSource::Cex(exchange, symbol) = source {
+            tokio::spawn(cex::stream(exchange, symbol, id, prices));
+            continue;
+        }
+        let provider = provider.clone();
+        tokio::spawn(async move {
+            info!(
+                "Polling reference price {} every {}s",
+                id,
+                refresh.as_secs()
+            );
+            let mut interval = tokio::time::interval(refresh);
+            loop {
+                interval.tick().await;
+                match fetch(&source, &id, &provider).await {
+                    Ok(price) => {
+                        prices.write().unwrap().insert(id.clone(), price);
+                    }
+                    Err(e) => error!("{}", e),
+                }
+            }
+        });
+    }
+
+    prices
+}
+
+async fn fetch(
+    source: &ReferenceSource,
+    id: &str,
+    provider: &impl Provider,
+) -> Result<ReferencePrice, ReferenceError> {
+    match source {
+        ReferenceSource::Chainlink(feed) => {
+            let feed = AggregatorV3Interface::new(*feed, provider);
+
+            let decimals = match feed.decimals().call().await {
+                Ok(d) => d,
+                Err(e) => {
+                    error!("Failed to fetch decimals of feed {}: {}", id, e);
+                    return Err(ReferenceError::FetchFailed(id.to_string()));
+                }
+            };
+            let round = match feed.latestRoundData().call().await {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Failed to fetch latest round of feed {}: {}", id, e);
+                    return Err(ReferenceError::FetchFailed(id.to_string()));
+                }
+            };
+
+            // a feed without a valid round answers zero or less
+            let answer: f64 = match round.answer.to_string().parse() {
+                Ok(a) if a > 0.0 => a,
+                _ => {
+                    error!("Feed {} answered {}", id, round.answer);
+                    return Err(ReferenceError::FetchFailed(id.to_string()));
+                }
+            };
+
+            Ok(ReferencePrice {
+                price: answer / 10f64.powi(decimals as i32),
+                updated_at: round.updatedAt.saturating_to(),
+            })
+        }
+        // streamed by cex::stream, never polled
+        #[cfg(feature = "cex")]
+        ReferenceSource::Cex(..) => Err(ReferenceError::FetchFailed(id.to_string())),
+    }
+}
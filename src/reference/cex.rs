@@ -0,0 +1,138 @@
+use super::{ReferenceError, ReferencePrice, ReferencePrices};
+use crate::storage::unix_now;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{error, info, warn};
+
+const BINANCE_WS: &str = "wss://stream.binance.com:9443/ws";
+const COINBASE_WS: &str = "wss://ws-feed.exchange.coinbase.com";
+
+// wait before reconnecting a dropped ticker stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// centralized exchange whose ticker stream is used as a reference
+#[derive(Debug, Clone, Copy)]
+pub enum Exchange {
+    Binance,
+    Coinbase,
+}
+
+// https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams#individual-symbol-book-ticker-streams
+#[derive(Deserialize)]
+struct BinanceBookTicker {
+    #[serde(rename = "b")]
+    bid: String,
+    #[serde(rename = "a")]
+    ask: String,
+}
+
+// https://docs.cdp.coinbase.com/exchange/docs/websocket-channels#ticker-channel
+#[derive(Deserialize)]
+struct CoinbaseTicker {
+    #[serde(rename = "type")]
+    kind: String,
+    best_bid: Option<String>,
+    best_ask: Option<String>,
+}
+
+// keep the mid price of `symbol` on `exchange` in `prices` under `id`, reconnecting
+// whenever the stream drops
+pub async fn stream(exchange: Exchange, symbol: String, id: String, prices: ReferencePrices) {
+    loop {
+        if let Err(e) = stream_once(exchange, &symbol, &id, &prices).await {
+            error!("{}", e);
+        }
+        warn!(
+            "Ticker stream {} ended, reconnecting in {}s",
+            id,
+            RECONNECT_DELAY.as_secs()
+        );
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn stream_once(
+    exchange: Exchange,
+    symbol: &str,
+    id: &str,
+    prices: &ReferencePrices,
+) -> Result<(), ReferenceError> {
+    let url = match exchange {
+        Exchange::Binance => format!("{}/{}@bookTicker", BINANCE_WS, symbol.to_lowercase()),
+        Exchange::Coinbase => COINBASE_WS.to_string(),
+    };
+
+    let (mut ws, _) = match connect_async(url.as_str()).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to connect ticker stream {}: {}", url, e);
+            return Err(ReferenceError::StreamFailed(id.to_string()));
+        }
+    };
+
+    if let Exchange::Coinbase = exchange {
+        let subscribe = json!({
+            "type": "subscribe",
+            "product_ids": [symbol],
+            "channels": ["ticker"],
+        });
+        if let Err(e) = ws.send(Message::Text(subscribe.to_string().into())).await {
+            error!("Failed to subscribe to Coinbase ticker {}: {}", symbol, e);
+            return Err(ReferenceError::StreamFailed(id.to_string()));
+        }
+    }
+
+    info!("Streaming reference price {}", id);
+
+    while let Some(msg) = ws.next().await {
+        let text = match msg {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(e) => {
+                error!("Ticker stream {} failed: {}", id, e);
+                return Err(ReferenceError::StreamFailed(id.to_string()));
+            }
+        };
+
+        let Some(price) = mid_price(exchange, &text) else {
+            continue;
+        };
+        prices.write().unwrap().insert(
+            id.to_string(),
+            ReferencePrice {
+                price,
+                updated_at: unix_now(),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+// mid of best bid and ask, None for messages that are not ticker updates
+fn mid_price(exchange: Exchange, text: &str) -> Option<f64> {
+    let (bid, ask) = match exchange {
+        Exchange::Binance => {
+            let ticker: BinanceBookTicker = serde_json::from_str(text).ok()?;
+            (ticker.bid, ticker.ask)
+        }
+        Exchange::Coinbase => {
+            let ticker: CoinbaseTicker = serde_json::from_str(text).ok()?;
+            if ticker.kind != "ticker" {
+                return None;
+            }
+            (ticker.best_bid?, ticker.best_ask?)
+        }
+    };
+    let bid: f64 = bid.parse().ok()?;
+    let ask: f64 = ask.parse().ok()?;
+    if bid <= 0.0 || ask <= 0.0 {
+        return None;
+    }
+    Some((bid + ask) / 2.0)
+}
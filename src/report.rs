@@ -0,0 +1,146 @@
+mod compare;
+mod summary;
+
+use crate::pool::PoolInfo;
+use crate::state::PoolSnapshot;
+use crate::storage::{Storage, StorageError, Volume};
+use alloy::primitives::Address;
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub use compare::compare;
+pub use summary::run_summaries;
+
+// symbols valued at one dollar when reporting volume in USD
+pub const USD_STABLECOINS: &[&str] = &[
+    "USDC", "USDT", "DAI", "USDC.e", "USDbC", "USDT0", "USDS", "FRAX", "LUSD", "PYUSD", "GHO",
+];
+
+// the pool and its tokens, from a pool loaded for a command or the live state
+#[derive(Debug, Clone)]
+pub struct ReportedPool {
+    pub pool: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub symbol0: String,
+    pub symbol1: String,
+}
+
+impl From<&PoolInfo> for ReportedPool {
+    fn from(info: &PoolInfo) -> Self {
+        ReportedPool {
+            pool: info.pool,
+            token0: info.token0,
+            token1: info.token1,
+            symbol0: info.sym0.clone(),
+            symbol1: info.sym1.clone(),
+        }
+    }
+}
+
+impl From<&PoolSnapshot> for ReportedPool {
+    fn from(snapshot: &PoolSnapshot) -> Self {
+        ReportedPool {
+            pool: snapshot.pool,
+            token0: snapshot.token0,
+            token1: snapshot.token1,
+            symbol0: snapshot.symbol0.clone(),
+            symbol1: snapshot.symbol1.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolVolume {
+    pub pool: Address,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub swaps: u64,
+    pub volume0: f64,
+    pub volume1: f64,
+    // None when neither token is a stablecoin or priced by a stablecoin pool
+    pub volume_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeReport {
+    pub from: u64,
+    pub to: u64,
+    pub pools: Vec<PoolVolume>,
+    pub swaps: u64,
+    // pools without a USD value are left out
+    pub volume_usd: f64,
+}
+
+// traded volume of every pool from the swaps stored in `from..to`, busiest pool first
+pub async fn volume_report(
+    pools: &[ReportedPool],
+    storage: &Storage,
+    from: u64,
+    to: u64,
+) -> Result<VolumeReport, StorageError> {
+    let mut volumes = Vec::with_capacity(pools.len());
+    for info in pools {
+        volumes.push(storage.volume(info.pool, from, to).await?);
+    }
+    let usd = usd_prices(pools, &volumes);
+
+    let mut report = VolumeReport {
+        from,
+        to,
+        pools: Vec::with_capacity(pools.len()),
+        swaps: 0,
+        volume_usd: 0.0,
+    };
+    for (info, volume) in pools.iter().zip(volumes) {
+        let volume_usd = match (usd.get(&info.token0), usd.get(&info.token1)) {
+            (Some(p), _) => Some(volume.volume0 * p),
+            (None, Some(p)) => Some(volume.volume1 * p),
+            (None, None) => None,
+        };
+        report.swaps += volume.swaps;
+        report.volume_usd += volume_usd.unwrap_or(0.0);
+        report.pools.push(PoolVolume {
+            pool: info.pool,
+            symbol0: info.symbol0.clone(),
+            symbol1: info.symbol1.clone(),
+            swaps: volume.swaps,
+            volume0: volume.volume0,
+            volume1: volume.volume1,
+            volume_usd,
+        });
+    }
+    report.pools.sort_by(|a, b| {
+        b.volume_usd
+            .unwrap_or(0.0)
+            .total_cmp(&a.volume_usd.unwrap_or(0.0))
+    });
+    Ok(report)
+}
+
+// USD price of stablecoins and of tokens traded against one in a reported pool,
+// at the last price of the period
+fn usd_prices(pools: &[ReportedPool], volumes: &[Volume]) -> HashMap<Address, f64> {
+    let stable = |symbol: &str| USD_STABLECOINS.contains(&symbol);
+    let mut prices = HashMap::new();
+
+    for info in pools {
+        if stable(&info.symbol0) {
+            prices.insert(info.token0, 1.0);
+        }
+        if stable(&info.symbol1) {
+            prices.insert(info.token1, 1.0);
+        }
+    }
+    for (info, volume) in pools.iter().zip(volumes) {
+        let Some(price) = volume.last_price.filter(|p| *p > 0.0) else {
+            continue;
+        };
+        if stable(&info.symbol1) && !stable(&info.symbol0) {
+            prices.entry(info.token0).or_insert(price);
+        } else if stable(&info.symbol0) && !stable(&info.symbol1) {
+            prices.entry(info.token1).or_insert(1.0 / price);
+        }
+    }
+    prices
+}
@@ -0,0 +1,107 @@
+use crate::storage::{Storage, StorageError};
+use alloy::primitives::Address;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// a pool's close and the external series' last price in one interval
+#[derive(Debug, Clone, Serialize)]
+pub struct AlignedPrice {
+    pub time: u64,
+    pub pool: f64,
+    pub external: f64,
+    // pool over external price, in basis points
+    pub basis_bps: f64,
+}
+
+// how closely a pool followed an imported price series, basis statistics over the
+// aligned intervals and the tracking error of the pool's returns against the series'
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub pool: Address,
+    pub series: String,
+    pub interval: u64,
+    pub mean_basis_bps: f64,
+    pub mean_abs_basis_bps: f64,
+    pub stddev_basis_bps: f64,
+    pub min_basis_bps: f64,
+    pub max_basis_bps: f64,
+    // standard deviation of the difference of log returns between adjacent intervals
+    pub tracking_error_bps: Option<f64>,
+    pub intervals: Vec<AlignedPrice>,
+}
+
+// compare the pool's candles in `from..to` with the stored external series, `invert`
+// for series quoted in token0 per token1
+// None when no interval has both prices
+pub async fn compare(
+    storage: &Storage,
+    pool: Address,
+    series: &str,
+    interval: u64,
+    from: u64,
+    to: u64,
+    invert: bool,
+) -> Result<Option<Comparison>, StorageError> {
+    let candles = storage.candles(pool, interval, from, to).await?;
+    let external = storage
+        .external_prices(series.to_string(), from, to)
+        .await?;
+
+    // oldest first, the last price of each interval wins
+    let external: BTreeMap<u64, f64> = external
+        .into_iter()
+        .map(|(time, price)| (time / interval * interval, price))
+        .collect();
+
+    let intervals: Vec<AlignedPrice> = candles
+        .iter()
+        .filter(|c| c.close > 0.0)
+        .filter_map(|c| {
+            let external = *external.get(&c.time)?;
+            let pool = if invert { 1.0 / c.close } else { c.close };
+            Some(AlignedPrice {
+                time: c.time,
+                pool,
+                external,
+                basis_bps: (pool / external - 1.0) * 10_000.0,
+            })
+        })
+        .collect();
+    if intervals.is_empty() {
+        return Ok(None);
+    }
+
+    let basis: Vec<f64> = intervals.iter().map(|p| p.basis_bps).collect();
+    let differences: Vec<f64> = intervals
+        .windows(2)
+        .filter(|w| w[1].time - w[0].time == interval)
+        .map(|w| ((w[1].pool / w[0].pool).ln() - (w[1].external / w[0].external).ln()) * 10_000.0)
+        .collect();
+
+    Ok(Some(Comparison {
+        pool,
+        series: series.to_string(),
+        interval,
+        mean_basis_bps: mean(&basis),
+        mean_abs_basis_bps: basis.iter().map(|b| b.abs()).sum::<f64>() / basis.len() as f64,
+        stddev_basis_bps: stddev(&basis),
+        min_basis_bps: basis.iter().copied().fold(f64::MAX, f64::min),
+        max_basis_bps: basis.iter().copied().fold(f64::MIN, f64::max),
+        tracking_error_bps: (differences.len() > 1).then(|| stddev(&differences)),
+        intervals,
+    }))
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// sample standard deviation, 0 for a single value
+fn stddev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let sum: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+    (sum / (values.len() - 1) as f64).sqrt()
+}
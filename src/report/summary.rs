@@ -0,0 +1,178 @@
+use super::{ReportedPool, volume_report};
+use crate::alert::Alerter;
+use crate::config::{ReportPeriod, ReportRule};
+use crate::state::SharedState;
+use crate::storage::{Storage, StorageError, SwapRecord, unix_now};
+use chrono::{DateTime, Datelike, Days, FixedOffset, NaiveTime, TimeZone, Utc, Weekday};
+use std::sync::Arc;
+use tracing::{error, info};
+
+const DAY: u64 = 86_400;
+
+// when a report is due, at a time of day in a fixed utc offset, on every day or on
+// one day of the week
+struct Schedule {
+    at: NaiveTime,
+    weekday: Option<Weekday>,
+    offset: FixedOffset,
+}
+
+impl Schedule {
+    fn from_rule(rule: &ReportRule) -> Option<Self> {
+        let at = match NaiveTime::parse_from_str(&rule.at, "%H:%M") {
+            Ok(t) => t,
+            Err(e) => {
+                error!("Invalid report time {}: {}", rule.at, e);
+                return None;
+            }
+        };
+        let Some(offset) = FixedOffset::east_opt(rule.utc_offset_minutes * 60) else {
+            error!("Invalid utc_offset_minutes {}", rule.utc_offset_minutes);
+            return None;
+        };
+        let weekday = match (rule.period, &rule.weekday) {
+            (ReportPeriod::Daily, _) => None,
+            (ReportPeriod::Weekly, None) => Some(Weekday::Mon),
+            (ReportPeriod::Weekly, Some(day)) => match day.parse() {
+                Ok(w) => Some(w),
+                Err(_) => {
+                    error!("Invalid report weekday {}", day);
+                    return None;
+                }
+            },
+        };
+        Some(Schedule {
+            at,
+            weekday,
+            offset,
+        })
+    }
+
+    // the first time after `now` the report is due
+    fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(&self.offset).date_naive();
+        (0..=7)
+            .filter_map(|d| today.checked_add_days(Days::new(d)))
+            .filter(|date| self.weekday.is_none_or(|w| date.weekday() == w))
+            .filter_map(|date| {
+                self.offset
+                    .from_local_datetime(&date.and_time(self.at))
+                    .single()
+            })
+            .map(|time| time.with_timezone(&Utc))
+            .find(|time| *time > now)
+            .unwrap_or(now + chrono::Duration::days(1))
+    }
+}
+
+// send a summary of every tracked pool over the rule's period whenever it is due
+pub async fn run_summaries(
+    rule: ReportRule,
+    storage: Storage,
+    state: SharedState,
+    alerter: Arc<Alerter>,
+) {
+    let Some(schedule) = Schedule::from_rule(&rule) else {
+        return;
+    };
+    let (name, period) = match rule.period {
+        ReportPeriod::Daily => ("Daily", DAY),
+        ReportPeriod::Weekly => ("Weekly", 7 * DAY),
+    };
+
+    loop {
+        let next = schedule.next_after(Utc::now());
+        info!("Next {} report at {}", name.to_ascii_lowercase(), next);
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let to = unix_now();
+        let pools: Vec<ReportedPool> = state
+            .read()
+            .unwrap()
+            .pools
+            .values()
+            .map(ReportedPool::from)
+            .collect();
+        let from = to.saturating_sub(period);
+        match summarize(&pools, &storage, from, to, rule.biggest_swaps).await {
+            Ok(lines) => {
+                let summary = format!("{} report\n{}", name, lines.join("\n"));
+                let key = format!("report:{}:{}", name.to_ascii_lowercase(), to);
+                alerter
+                    .send_report(&rule.channels, rule.severity, &key, &summary)
+                    .await;
+            }
+            Err(e) => error!("Failed to compile the {} report: {}", name, e),
+        }
+    }
+}
+
+// a line per pool with its price change, range and volume, busiest pool first, each
+// followed by its `biggest` largest swaps, and the total volume
+async fn summarize(
+    pools: &[ReportedPool],
+    storage: &Storage,
+    from: u64,
+    to: u64,
+    biggest: usize,
+) -> Result<Vec<String>, StorageError> {
+    let volumes = volume_report(pools, storage, from, to).await?;
+    let swaps = storage
+        .swaps(pools.iter().map(|p| p.pool).collect(), from, to)
+        .await?;
+
+    let mut lines = Vec::new();
+    for volume in &volumes.pools {
+        let pair = format!("{}/{}", volume.symbol0, volume.symbol1);
+        let pool_swaps: Vec<&SwapRecord> = swaps.iter().filter(|s| s.pool == volume.pool).collect();
+        let (Some(first), Some(last)) = (pool_swaps.first(), pool_swaps.last()) else {
+            lines.push(format!("{}: no swaps", pair));
+            continue;
+        };
+
+        let high = pool_swaps.iter().map(|s| s.price).fold(f64::MIN, f64::max);
+        let low = pool_swaps.iter().map(|s| s.price).fold(f64::MAX, f64::min);
+        let change_pct = if first.price > 0.0 {
+            (last.price / first.price - 1.0) * 100.0
+        } else {
+            0.0
+        };
+        let usd = volume
+            .volume_usd
+            .map(|v| format!(" (${:.0})", v))
+            .unwrap_or_default();
+        lines.push(format!(
+            "{}: {} -> {} ({:+.2}%), high {}, low {}, {} swaps, volume {:.4} {}{}",
+            pair,
+            first.price,
+            last.price,
+            change_pct,
+            high,
+            low,
+            volume.swaps,
+            volume.volume0,
+            volume.symbol0,
+            usd
+        ));
+
+        let mut largest = pool_swaps;
+        largest.sort_by(|a, b| b.amount0.total_cmp(&a.amount0));
+        for swap in largest.into_iter().take(biggest) {
+            lines.push(format!(
+                "  {:.4} {} at {}{}",
+                swap.amount0,
+                volume.symbol0,
+                swap.price,
+                swap.tx
+                    .map(|tx| format!(" in {:?}", tx))
+                    .unwrap_or_default()
+            ));
+        }
+    }
+    lines.push(format!(
+        "Total: {} swaps, ${:.0}",
+        volumes.swaps, volumes.volume_usd
+    ));
+    Ok(lines)
+}
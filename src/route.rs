@@ -0,0 +1,304 @@
+use crate::config::RouteRule;
+use crate::state::{PoolSnapshot, SharedState};
+use crate::storage::unix_now;
+use alloy::primitives::Address;
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// an order is split into this many chunks, each going to the pool that pays most for it
+const SPLIT_STEPS: usize = 20;
+
+// cheapest way to sell `size` of the pair's first token across the tracked pools
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    pub pair: String,
+    pub size: f64,
+    // best single pool and what it pays
+    pub best_pool: Address,
+    pub best_out: f64,
+    // the order split between pools, only pools that get a share are listed
+    pub legs: Vec<Leg>,
+    pub split_out: f64,
+    // average price of the split order, second token per first token
+    pub price: f64,
+    // how much more the split pays than the best single pool, in basis points
+    pub improvement_bps: f64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Leg {
+    pub pool: Address,
+    pub amount_in: f64,
+    pub amount_out: f64,
+}
+
+// a tracked pool of the pair, with the swap direction that sells the first token
+struct Venue<'a> {
+    pool: &'a PoolSnapshot,
+    zero_for_one: bool,
+}
+
+// recompute the routes of `rule` from the latest pool states every `refresh_secs`
+pub async fn run(rule: RouteRule, default_chain_id: u64, state: SharedState) {
+    let Some((sell, buy)) = rule.pair.split_once('/') else {
+        warn!("Invalid route pair {}, expected e.g. WETH/USDC", rule.pair);
+        return;
+    };
+    let chain_id = rule.chain_id.unwrap_or(default_chain_id);
+    let mut interval = tokio::time::interval(Duration::from_secs(rule.refresh_secs));
+
+    loop {
+        interval.tick().await;
+
+        let routes: Vec<Route> = {
+            let state = state.read().unwrap();
+            let venues: Vec<Venue> = state
+                .pools
+                .values()
+                .filter(|p| p.chain_id == chain_id && p.liquidity > 0)
+                .filter_map(|pool| {
+                    let (s0, s1) = (&pool.symbol0, &pool.symbol1);
+                    if s0.eq_ignore_ascii_case(sell) && s1.eq_ignore_ascii_case(buy) {
+                        Some(Venue {
+                            pool,
+                            zero_for_one: true,
+                        })
+                    } else if s0.eq_ignore_ascii_case(buy) && s1.eq_ignore_ascii_case(sell) {
+                        Some(Venue {
+                            pool,
+                            zero_for_one: false,
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            rule.sizes
+                .iter()
+                .filter_map(|&size| best_route(&rule.pair, &venues, size))
+                .collect()
+        };
+
+        if routes.is_empty() {
+            continue;
+        }
+        for route in &routes {
+            let legs: Vec<String> = route
+                .legs
+                .iter()
+                .map(|l| format!("{:.6} via {:?}", l.amount_in, l.pool))
+                .collect();
+            info!(
+                "Best route for {} {}: {:.6} out ({:+.2} bps vs {:?} alone), {}",
+                route.size,
+                route.pair,
+                route.split_out,
+                route.improvement_bps,
+                route.best_pool,
+                legs.join(", ")
+            );
+        }
+        state
+            .write()
+            .unwrap()
+            .routes
+            .insert(rule.pair.clone(), routes);
+    }
+}
+
+fn best_route(pair: &str, venues: &[Venue], size: f64) -> Option<Route> {
+    // best single pool
+    let (best, best_out) = venues
+        .iter()
+        .map(|v| (v, venue_out(v, size)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // greedy split: every chunk goes to the pool with the best marginal output,
+    // which converges on equal marginal prices across pools
+    let chunk = size / SPLIT_STEPS as f64;
+    let mut allocated = vec![0.0; venues.len()];
+    for _ in 0..SPLIT_STEPS {
+        let (i, _) = venues
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let marginal = venue_out(v, allocated[i] + chunk) - venue_out(v, allocated[i]);
+                (i, marginal)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        allocated[i] += chunk;
+    }
+
+    let legs: Vec<Leg> = venues
+        .iter()
+        .zip(&allocated)
+        .filter(|(_, amount)| **amount > 0.0)
+        .map(|(v, &amount)| Leg {
+            pool: v.pool.pool,
+            amount_in: amount,
+            amount_out: venue_out(v, amount),
+        })
+        .collect();
+    let split_out: f64 = legs.iter().map(|l| l.amount_out).sum();
+
+    Some(Route {
+        pair: pair.to_string(),
+        size,
+        best_pool: best.pool.pool,
+        best_out,
+        legs,
+        split_out,
+        price: split_out / size,
+        improvement_bps: if best_out > 0.0 {
+            (split_out / best_out - 1.0) * 10_000.0
+        } else {
+            0.0
+        },
+        updated_at: unix_now(),
+    })
+}
+
+fn venue_out(venue: &Venue, amount_in: f64) -> f64 {
+    amount_out(venue.pool, venue.zero_for_one, amount_in)
+}
+
+// output for selling `amount_in` whole tokens into a pool, assuming its in-range
+// liquidity holds for the whole swap, so large orders in V3 pools are estimates
+pub fn amount_out(pool: &PoolSnapshot, zero_for_one: bool, amount_in: f64) -> f64 {
+    let l = pool.liquidity as f64;
+    let s = f64::from(pool.sqrt_price_x96) / 2f64.powi(96);
+    let fee = pool.fee as f64 / 1_000_000.0;
+    let scale0 = 10f64.powi(pool.decimals0 as i32);
+    let scale1 = 10f64.powi(pool.decimals1 as i32);
+    if s <= 0.0 || amount_in <= 0.0 {
+        return 0.0;
+    }
+
+    if zero_for_one {
+        let x = amount_in * scale0 * (1.0 - fee);
+        // 1/s' = 1/s + x/L
+        let s_next = l * s / (l + x * s);
+        l * (s - s_next) / scale1
+    } else {
+        let y = amount_in * scale1 * (1.0 - fee);
+        // s' = s + y/L
+        let s_next = s + y / l;
+        l * (1.0 / s - 1.0 / s_next) / scale0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+    use alloy::primitives::aliases::U160;
+
+    // a pool at sqrt price `sqrt_price` with no decimals, so amounts are raw units
+    fn pool(pool: Address, sqrt_price: u128, fee: u32) -> PoolSnapshot {
+        PoolSnapshot {
+            symbol0: "A".to_string(),
+            symbol1: "B".to_string(),
+            decimals0: 0,
+            decimals1: 0,
+            fee,
+            sqrt_price_x96: U160::from(sqrt_price) << 96,
+            liquidity: 1_000_000,
+            ..PoolSnapshot::at_price(
+                pool,
+                Address::ZERO,
+                Address::ZERO,
+                (sqrt_price * sqrt_price) as f64,
+            )
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn amount_out_sells_token0() {
+        // L = 1e6 at price 4: 1000 in moves sqrt price to 2e6 / 1002000
+        let pool = pool(Address::ZERO, 2, 0);
+        assert_close(amount_out(&pool, true, 1000.0), 3992.015968063872);
+    }
+
+    #[test]
+    fn amount_out_sells_token1() {
+        // 1000 in moves sqrt price from 2 to 2.001
+        let pool = pool(Address::ZERO, 2, 0);
+        assert_close(amount_out(&pool, false, 1000.0), 249.87506246876563);
+    }
+
+    #[test]
+    fn amount_out_takes_the_fee_from_the_input() {
+        let pool = pool(Address::ZERO, 2, 3000);
+        assert_close(amount_out(&pool, true, 1000.0), 3980.0637528768502);
+    }
+
+    #[test]
+    fn amount_out_is_zero_without_input() {
+        let pool = pool(Address::ZERO, 2, 0);
+        assert_eq!(amount_out(&pool, true, 0.0), 0.0);
+    }
+
+    #[test]
+    fn best_route_splits_between_equal_pools() {
+        let a = pool(address!("0x00000000000000000000000000000000000000aa"), 2, 0);
+        let b = pool(address!("0x00000000000000000000000000000000000000bb"), 2, 0);
+        let venues = [
+            Venue {
+                pool: &a,
+                zero_for_one: true,
+            },
+            Venue {
+                pool: &b,
+                zero_for_one: true,
+            },
+        ];
+        let route = best_route("A/B", &venues, 1000.0).unwrap();
+
+        assert_close(route.best_out, 3992.015968063872);
+        assert_eq!(route.legs.len(), 2);
+        assert_close(route.legs[0].amount_in, 500.0);
+        assert_close(route.legs[1].amount_in, 500.0);
+        // each pool takes half the order, so half the price impact
+        assert_close(route.split_out, 2.0 * amount_out(&a, true, 500.0));
+        assert!(route.improvement_bps > 0.0);
+    }
+
+    #[test]
+    fn best_route_uses_the_direction_of_each_pool() {
+        // the same market once as A/B and once as B/A at the inverse price
+        let ab = pool(address!("0x00000000000000000000000000000000000000aa"), 2, 0);
+        let mut ba = pool(address!("0x00000000000000000000000000000000000000bb"), 1, 0);
+        ba.sqrt_price_x96 = U160::from(1u128 << 95);
+        let venues = [
+            Venue {
+                pool: &ab,
+                zero_for_one: true,
+            },
+            Venue {
+                pool: &ba,
+                zero_for_one: false,
+            },
+        ];
+        let route = best_route("A/B", &venues, 1000.0).unwrap();
+
+        assert_close(route.best_out, 3992.015968063872);
+        assert_close(venue_out(&venues[1], 1000.0), 3992.015968063872);
+        assert_eq!(route.legs.len(), 2);
+    }
+
+    #[test]
+    fn best_route_needs_a_pool() {
+        assert!(best_route("A/B", &[], 1000.0).is_none());
+    }
+}
@@ -0,0 +1,24 @@
+use crate::error::{ProviderFailure, TrackerError};
+use crate::metrics;
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use alloy_rpc_client::ClientBuilder;
+use eyre::Result;
+
+// `use<>`: the provider does not borrow the url
+pub async fn connect(rpc_url: &str) -> Result<impl Provider + Clone + use<>> {
+    let ws = WsConnect::new(rpc_url);
+    // every request is counted by method for /metrics
+    let client = ClientBuilder::default()
+        .layer(metrics::RpcCountLayer)
+        .ws(ws)
+        .await;
+    match client.map(|client| ProviderBuilder::new().connect_client(client)) {
+        Ok(p) => Ok(p),
+        Err(e) => {
+            tracing::error!("Failed to connect WebSocket provider {}: {}", rpc_url, e);
+            Err(TrackerError::new(ProviderFailure::WSConnectionFailed)
+                .with_source(e)
+                .into())
+        }
+    }
+}
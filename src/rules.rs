@@ -0,0 +1,250 @@
+mod arbitrage;
+mod band;
+mod basis;
+mod change;
+mod cross;
+mod deviation;
+mod liquidity;
+mod mint;
+mod triangular;
+mod volatility;
+
+use crate::alert::Alerter;
+use crate::config::rules_for_pool;
+use crate::pool::ListenerSettings;
+use alloy::primitives::Address;
+use arbitrage::ArbitrageWatcher;
+use band::BandWatcher;
+use basis::BasisWatcher;
+use change::ChangeWatcher;
+use cross::CrossWatcher;
+use deviation::DeviationWatcher;
+use liquidity::LiquidityWatcher;
+use mint::MintWatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tracing::info;
+use triangular::TriangularWatcher;
+use volatility::{VolatilitySamples, VolatilityWatcher};
+
+// a price observation derived from a single swap
+pub struct Observation<'a> {
+    pub pool: Address,
+    pub pair: &'a str,
+    // token1 per token0
+    pub price: f64,
+    // in-range liquidity after the swap
+    pub liquidity: u128,
+    // current tick after the swap
+    pub tick: i32,
+    // unix timestamp of the swap's block, rule windows are measured in block time so
+    // replayed swaps behave like live ones
+    pub timestamp: u64,
+    pub tx_url: Option<String>,
+}
+
+// a position opened by a single Mint event
+pub struct MintObservation<'a> {
+    pub pool: Address,
+    pub pair: &'a str,
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    // liquidity added by the mint
+    pub amount: u128,
+    // token amounts deposited, in whole tokens
+    pub amount0: f64,
+    pub amount1: f64,
+    // in-range liquidity of the pool after the mint
+    pub pool_liquidity: u128,
+    pub tx_url: Option<String>,
+}
+
+// rolling windows of the rules of one pool, by rule kind in config order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RulesCheckpoint {
+    change: Vec<VecDeque<(u64, f64)>>,
+    volatility: Vec<VolatilitySamples>,
+    liquidity: Vec<VecDeque<(u64, u128)>>,
+}
+
+// alert rules evaluated on every swap (and mint) of one pool
+pub struct PoolRules {
+    bands: Vec<BandWatcher>,
+    change: Vec<ChangeWatcher>,
+    volatility: Vec<VolatilityWatcher>,
+    liquidity: Vec<LiquidityWatcher>,
+    deviation: Vec<DeviationWatcher>,
+    basis: Vec<BasisWatcher>,
+    arbitrage: Vec<ArbitrageWatcher>,
+    triangular: Option<TriangularWatcher>,
+    mints: Vec<MintWatcher>,
+    crosses: Vec<CrossWatcher>,
+}
+
+impl PoolRules {
+    pub fn for_pool(pool: Address, settings: &ListenerSettings) -> Self {
+        let config = &settings.config;
+
+        let bands = config
+            .price_bands
+            .iter()
+            .filter(|band| band.pool == pool)
+            .cloned()
+            .map(BandWatcher::new)
+            .collect();
+
+        let change = rules_for_pool(&config.price_change, pool)
+            .into_iter()
+            .map(ChangeWatcher::new)
+            .collect();
+
+        let volatility = rules_for_pool(&config.volatility, pool)
+            .into_iter()
+            .map(VolatilityWatcher::new)
+            .collect();
+
+        let liquidity = rules_for_pool(&config.liquidity_drop, pool)
+            .into_iter()
+            .map(LiquidityWatcher::new)
+            .collect();
+
+        let deviation = config
+            .deviation
+            .iter()
+            .filter(|rule| rule.pool == pool)
+            .cloned()
+            .map(|rule| DeviationWatcher::new(rule, settings.references.clone()))
+            .collect();
+
+        let basis = config
+            .basis
+            .iter()
+            .filter(|rule| rule.pool == pool)
+            .cloned()
+            .map(|rule| {
+                BasisWatcher::new(rule, settings.references.clone(), settings.state.clone())
+            })
+            .collect();
+
+        let arbitrage = config
+            .arbitrage
+            .iter()
+            .filter(|rule| rule.pools.contains(&pool))
+            .cloned()
+            .map(|rule| ArbitrageWatcher::new(rule, settings.state.clone()))
+            .collect();
+
+        let triangular = config
+            .triangular
+            .clone()
+            .map(|rule| TriangularWatcher::new(rule, settings.state.clone()));
+
+        let mints = rules_for_pool(&config.large_mint, pool)
+            .into_iter()
+            .map(MintWatcher::new)
+            .collect();
+
+        let crosses = config
+            .tick_cross
+            .iter()
+            .filter(|rule| rule.pool == pool)
+            .cloned()
+            .map(CrossWatcher::new)
+            .collect();
+
+        PoolRules {
+            bands,
+            change,
+            volatility,
+            liquidity,
+            deviation,
+            basis,
+            arbitrage,
+            triangular,
+            mints,
+            crosses,
+        }
+    }
+
+    pub fn checkpoint(&self) -> RulesCheckpoint {
+        RulesCheckpoint {
+            change: self.change.iter().map(|w| w.samples()).collect(),
+            volatility: self.volatility.iter().map(|w| w.samples()).collect(),
+            liquidity: self.liquidity.iter().map(|w| w.samples()).collect(),
+        }
+    }
+
+    // windows are matched to rules by position, a kind whose rules changed since the
+    // checkpoint starts empty
+    pub fn restore(&mut self, pool: Address, checkpoint: RulesCheckpoint) {
+        if checkpoint.change.len() == self.change.len() {
+            for (w, samples) in self.change.iter_mut().zip(checkpoint.change) {
+                w.restore(samples);
+            }
+        } else {
+            info!(
+                "price_change rules of {:?} changed, not restoring them",
+                pool
+            );
+        }
+        if checkpoint.volatility.len() == self.volatility.len() {
+            for (w, samples) in self.volatility.iter_mut().zip(checkpoint.volatility) {
+                w.restore(samples);
+            }
+        } else {
+            info!("volatility rules of {:?} changed, not restoring them", pool);
+        }
+        if checkpoint.liquidity.len() == self.liquidity.len() {
+            for (w, samples) in self.liquidity.iter_mut().zip(checkpoint.liquidity) {
+                w.restore(samples);
+            }
+        } else {
+            info!(
+                "liquidity_drop rules of {:?} changed, not restoring them",
+                pool
+            );
+        }
+    }
+
+    // Mint events are only subscribed to when a rule needs them
+    pub fn watches_mints(&self) -> bool {
+        !self.mints.is_empty()
+    }
+
+    pub async fn on_mint(&mut self, mint: &MintObservation<'_>, alerter: &Alerter) {
+        for watcher in &mut self.mints {
+            watcher.on_mint(mint, alerter).await;
+        }
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        for band in &mut self.bands {
+            band.on_swap(obs, alerter).await;
+        }
+        for watcher in &mut self.change {
+            watcher.on_swap(obs, alerter).await;
+        }
+        for watcher in &mut self.volatility {
+            watcher.on_swap(obs, alerter).await;
+        }
+        for watcher in &mut self.liquidity {
+            watcher.on_swap(obs, alerter).await;
+        }
+        for watcher in &mut self.deviation {
+            watcher.on_swap(obs, alerter).await;
+        }
+        for watcher in &mut self.basis {
+            watcher.on_swap(obs);
+        }
+        for watcher in &mut self.arbitrage {
+            watcher.on_swap(obs, alerter).await;
+        }
+        if let Some(watcher) = &mut self.triangular {
+            watcher.on_swap(obs, alerter).await;
+        }
+        for watcher in &mut self.crosses {
+            watcher.on_swap(obs, alerter).await;
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::ArbitrageRule;
+use crate::state::{PoolSnapshot, SharedState};
+use alloy::primitives::Address;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+const RULE: &str = "arbitrage";
+
+// 2^96 as f64, to turn sqrtPriceX96 into a plain sqrt price
+const Q96: f64 = 79228162514264337593543950336.0;
+
+// compares a pool against the other pools of its arbitrage group and fires when
+// the price gap covers both pools' fees plus the gas cost of the two swaps
+pub struct ArbitrageWatcher {
+    rule: ArbitrageRule,
+    state: SharedState,
+    // alerts of open opportunities by counterpart pool
+    firing: HashMap<Address, Alert>,
+}
+
+struct Opportunity {
+    cheap: Address,
+    rich: Address,
+    gap_bps: f64,
+    fees_bps: f64,
+    // token0 to buy in the cheap pool and sell in the rich one
+    size0: f64,
+    // expected profit in token1, after fees and gas
+    profit1: f64,
+    gas_cost1: Option<f64>,
+}
+
+impl ArbitrageWatcher {
+    pub fn new(rule: ArbitrageRule, state: SharedState) -> Self {
+        ArbitrageWatcher {
+            rule,
+            state,
+            firing: HashMap::new(),
+        }
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let (snapshots, gas_price_wei) = {
+            let state = self.state.read().unwrap();
+            let snapshots: Vec<PoolSnapshot> = self
+                .rule
+                .pools
+                .iter()
+                .filter_map(|p| state.pools.get(p).cloned())
+                .collect();
+            (snapshots, state.gas_price_wei)
+        };
+        let Some(this) = state_of(&snapshots, obs.pool) else {
+            return;
+        };
+
+        for other in self.rule.pools.clone() {
+            if other == obs.pool {
+                continue;
+            }
+            let Some(that) = state_of(&snapshots, other) else {
+                continue;
+            };
+            if this.token0 != that.token0 || this.token1 != that.token1 {
+                warn!(
+                    "Pools {:?} and {:?} do not trade the same pair, skipping arbitrage check",
+                    obs.pool, other
+                );
+                continue;
+            }
+
+            let opportunity = self.evaluate(obs.pool, this, other, that, gas_price_wei);
+            match opportunity {
+                Some(opp) if opp.profit1 > self.rule.min_profit => {
+                    if !self.firing.contains_key(&other) {
+                        let alert = self.alert(obs, &opp);
+                        alerter.fire(&alert).await;
+                        self.firing.insert(other, alert);
+                    }
+                }
+                _ => {
+                    if let Some(alert) = self.firing.remove(&other) {
+                        alerter.resolve(&alert).await;
+                    }
+                }
+            }
+        }
+    }
+
+    fn evaluate(
+        &self,
+        this_addr: Address,
+        this: &PoolSnapshot,
+        that_addr: Address,
+        that: &PoolSnapshot,
+        gas_price_wei: Option<u128>,
+    ) -> Option<Opportunity> {
+        let ((cheap_addr, cheap), (rich_addr, rich)) = if this.price < that.price {
+            ((this_addr, this), (that_addr, that))
+        } else {
+            ((that_addr, that), (this_addr, this))
+        };
+        if cheap.price <= 0.0 || cheap.liquidity == 0 || rich.liquidity == 0 {
+            return None;
+        }
+
+        let gap_bps = (rich.price / cheap.price - 1.0) * 10_000.0;
+        let fee_cheap = cheap.fee as f64 / 1_000_000.0;
+        let fee_rich = rich.fee as f64 / 1_000_000.0;
+        let fees_bps = (fee_cheap + fee_rich) * 10_000.0;
+        if gap_bps <= fees_bps {
+            return None;
+        }
+
+        // both pools move towards a common sqrt price s* while the token0 bought
+        // from the cheap pool equals the token0 sold into the rich one:
+        // L1 (1/s1 - 1/s*) = L2 (1/s* - 1/s2)  =>  s* = (L1 + L2) / (L1/s1 + L2/s2)
+        // only in-range liquidity is considered, so larger gaps are underestimated
+        let s1: f64 = cheap.sqrt_price_x96.to_string().parse::<f64>().ok()? / Q96;
+        let s2: f64 = rich.sqrt_price_x96.to_string().parse::<f64>().ok()? / Q96;
+        let l1 = cheap.liquidity as f64;
+        let l2 = rich.liquidity as f64;
+        let s_target = (l1 + l2) / (l1 / s1 + l2 / s2);
+
+        let raw0 = l1 * (1.0 / s1 - 1.0 / s_target);
+        let raw1_paid = l1 * (s_target - s1) * (1.0 + fee_cheap);
+        let raw1_received = l2 * (s2 - s_target) * (1.0 - fee_rich);
+
+        let scale0 = 10f64.powi(cheap.decimals0 as i32);
+        let scale1 = 10f64.powi(cheap.decimals1 as i32);
+        let size0 = raw0 / scale0;
+        let gross1 = (raw1_received - raw1_paid) / scale1;
+
+        let gas_cost1 = gas_price_wei.and_then(|gas_price| {
+            let gas_eth = self.rule.gas_units as f64 * gas_price as f64 / 1e18;
+            self.native_price(cheap).map(|p| gas_eth * p)
+        });
+
+        Some(Opportunity {
+            cheap: cheap_addr,
+            rich: rich_addr,
+            gap_bps,
+            fees_bps,
+            size0,
+            profit1: gross1 - gas_cost1.unwrap_or(0.0),
+            gas_cost1,
+        })
+    }
+
+    // price of the native gas token in token1, needed to express gas in token1
+    fn native_price(&self, pool: &PoolSnapshot) -> Option<f64> {
+        if let Some(price) = self.rule.native_price {
+            return Some(price);
+        }
+        match self.rule.wrapped_native {
+            Some(weth) if pool.token0 == weth => Some(pool.price),
+            Some(weth) if pool.token1 == weth => Some(1.0),
+            _ => None,
+        }
+    }
+
+    fn alert(&self, obs: &Observation<'_>, opp: &Opportunity) -> Alert {
+        // the same key for both directions of a pool pair, whichever listener fires first
+        let (a, b) = if opp.cheap < opp.rich {
+            (opp.cheap, opp.rich)
+        } else {
+            (opp.rich, opp.cheap)
+        };
+
+        let gas = match opp.gas_cost1 {
+            Some(g) => format!("{:.6}", g),
+            None => "unknown".to_string(),
+        };
+
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), obs.pair.to_string());
+        vars.insert("price".to_string(), obs.price.to_string());
+        vars.insert("buy_pool".to_string(), format!("{:?}", opp.cheap));
+        vars.insert("sell_pool".to_string(), format!("{:?}", opp.rich));
+        vars.insert("gap_bps".to_string(), format!("{:.2}", opp.gap_bps));
+        vars.insert("fees_bps".to_string(), format!("{:.2}", opp.fees_bps));
+        vars.insert("size".to_string(), format!("{:.6}", opp.size0));
+        vars.insert("profit".to_string(), format!("{:.6}", opp.profit1));
+        vars.insert("gas_cost".to_string(), gas.clone());
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        Alert {
+            rule: RULE.to_string(),
+            key: format!("{}:{:?}:{:?}", RULE, a, b),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} arbitrage: buy in {:?}, sell in {:?}, gap {:.2} bps vs {:.2} bps fees, size ~{:.6}, profit ~{:.6} after gas {}",
+                obs.pair,
+                opp.cheap,
+                opp.rich,
+                opp.gap_bps,
+                opp.fees_bps,
+                opp.size0,
+                opp.profit1,
+                gas
+            ),
+            vars,
+        }
+    }
+}
+
+fn state_of(snapshots: &[PoolSnapshot], pool: Address) -> Option<&PoolSnapshot> {
+    snapshots.iter().find(|s| s.pool == pool)
+}
@@ -0,0 +1,111 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::PriceBand;
+use crate::console::format_amount;
+use std::collections::BTreeMap;
+use tracing::info;
+
+const RULE_ENTRY: &str = "price_band_entry";
+const RULE_EXIT: &str = "price_band_exit";
+
+// tracks whether a pool's price is inside a configured band
+// and notifies on every transition in either direction
+pub struct BandWatcher {
+    band: PriceBand,
+    // None until the first swap has been seen
+    inside: Option<bool>,
+}
+
+impl BandWatcher {
+    pub fn new(band: PriceBand) -> Self {
+        BandWatcher { band, inside: None }
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let price = if self.band.inverse {
+            1.0 / obs.price
+        } else {
+            obs.price
+        };
+
+        let margin = self.band.hysteresis_bps / 10_000.0;
+        let within = price >= self.band.lower && price <= self.band.upper;
+        let outside =
+            price < self.band.lower * (1.0 - margin) || price > self.band.upper * (1.0 + margin);
+
+        let inside = match self.inside {
+            // the first observation only establishes the starting state
+            None => {
+                info!(
+                    "Price band {} for {}: price {} is {} the band",
+                    self.name(),
+                    obs.pair,
+                    price,
+                    if within { "inside" } else { "outside" }
+                );
+                self.inside = Some(within);
+                return;
+            }
+            Some(true) if outside => false,
+            Some(false) if within => true,
+            Some(state) => state,
+        };
+        if self.inside == Some(inside) {
+            return;
+        }
+        self.inside = Some(inside);
+
+        let entry = self.alert(obs, price, RULE_ENTRY);
+        let exit = self.alert(obs, price, RULE_EXIT);
+        if inside {
+            alerter.resolve(&exit).await;
+            alerter.fire(&entry).await;
+        } else {
+            alerter.resolve(&entry).await;
+            alerter.fire(&exit).await;
+        }
+    }
+
+    fn name(&self) -> String {
+        self.band
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}", self.band.lower, self.band.upper))
+    }
+
+    fn alert(&self, obs: &Observation<'_>, price: f64, rule: &str) -> Alert {
+        let name = self.name();
+        let direction = if rule == RULE_ENTRY {
+            "entered"
+        } else {
+            "left"
+        };
+
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), obs.pair.to_string());
+        vars.insert("price".to_string(), price.to_string());
+        vars.insert("band".to_string(), name.clone());
+        vars.insert("lower".to_string(), self.band.lower.to_string());
+        vars.insert("upper".to_string(), self.band.upper.to_string());
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        Alert {
+            rule: rule.to_string(),
+            key: format!("{}:{:?}:{}", rule, obs.pool, name),
+            pool: obs.pool,
+            severity: self.band.severity,
+            summary: format!(
+                "{} price {} {} band {} [{}, {}]",
+                obs.pair,
+                format_amount(price),
+                direction,
+                name,
+                format_amount(self.band.lower),
+                format_amount(self.band.upper)
+            ),
+            vars,
+        }
+    }
+}
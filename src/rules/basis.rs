@@ -0,0 +1,69 @@
+use super::Observation;
+use crate::config::BasisRule;
+use crate::reference::ReferencePrices;
+use crate::state::{Basis, SharedState};
+use crate::storage::unix_now;
+use tracing::{info, warn};
+
+// DEX-vs-CEX basis of a pool, published to the shared state after every swap
+pub struct BasisWatcher {
+    rule: BasisRule,
+    references: ReferencePrices,
+    state: SharedState,
+}
+
+impl BasisWatcher {
+    pub fn new(rule: BasisRule, references: ReferencePrices, state: SharedState) -> Self {
+        BasisWatcher {
+            rule,
+            references,
+            state,
+        }
+    }
+
+    pub fn on_swap(&mut self, obs: &Observation<'_>) {
+        let reference = self
+            .references
+            .read()
+            .unwrap()
+            .get(&self.rule.reference)
+            .copied();
+        let Some(reference) = reference else {
+            return;
+        };
+        if unix_now().saturating_sub(reference.updated_at) > self.rule.max_reference_age_secs {
+            warn!(
+                "Reference {} is outdated, skipping basis of {}",
+                self.rule.reference, obs.pair
+            );
+            return;
+        }
+        if reference.price <= 0.0 || obs.price <= 0.0 {
+            return;
+        }
+
+        let price = if self.rule.inverse {
+            1.0 / obs.price
+        } else {
+            obs.price
+        };
+        let basis_bps = (price / reference.price - 1.0) * 10_000.0;
+
+        info!(
+            "Basis of {} ({:?}) vs {}: {:.2} bps ({} vs {})",
+            obs.pair, obs.pool, self.rule.reference, basis_bps, price, reference.price
+        );
+
+        self.state.write().unwrap().basis.insert(
+            (obs.pool, self.rule.reference.clone()),
+            Basis {
+                pool: obs.pool,
+                reference: self.rule.reference.clone(),
+                price,
+                reference_price: reference.price,
+                basis_bps,
+                updated_at: unix_now(),
+            },
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::PriceChangeRule;
+use crate::console::format_amount;
+use std::collections::{BTreeMap, VecDeque};
+
+const RULE: &str = "price_change";
+
+// fires when the price moved more than `change_pct` percent within the window
+pub struct ChangeWatcher {
+    rule: PriceChangeRule,
+    prices: VecDeque<(u64, f64)>,
+    firing: bool,
+}
+
+impl ChangeWatcher {
+    pub fn new(rule: PriceChangeRule) -> Self {
+        ChangeWatcher {
+            rule,
+            prices: VecDeque::new(),
+            firing: false,
+        }
+    }
+
+    // prices inside the window, saved across restarts
+    pub fn samples(&self) -> VecDeque<(u64, f64)> {
+        self.prices.clone()
+    }
+
+    pub fn restore(&mut self, prices: VecDeque<(u64, f64)>) {
+        self.prices = prices;
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let now = obs.timestamp;
+        self.prices.push_back((now, obs.price));
+        while let Some((t, _)) = self.prices.front() {
+            if now.saturating_sub(*t) > self.rule.window_secs {
+                self.prices.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        // compare against the oldest price still inside the window
+        let Some(&(_, start)) = self.prices.front() else {
+            return;
+        };
+        if start <= 0.0 {
+            return;
+        }
+        let change_pct = (obs.price / start - 1.0) * 100.0;
+
+        // resolve once the move is back under half the threshold
+        if !self.firing && change_pct.abs() >= self.rule.change_pct {
+            self.firing = true;
+            alerter.fire(&self.alert(obs, start, change_pct)).await;
+        } else if self.firing && change_pct.abs() < self.rule.change_pct / 2.0 {
+            self.firing = false;
+            alerter.resolve(&self.alert(obs, start, change_pct)).await;
+        }
+    }
+
+    fn alert(&self, obs: &Observation<'_>, start: f64, change_pct: f64) -> Alert {
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), obs.pair.to_string());
+        vars.insert("price".to_string(), obs.price.to_string());
+        vars.insert("start_price".to_string(), start.to_string());
+        vars.insert("change_pct".to_string(), format!("{:.2}", change_pct));
+        vars.insert("window_secs".to_string(), self.rule.window_secs.to_string());
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        Alert {
+            rule: RULE.to_string(),
+            // several rules can watch one pool, each alerts on its own
+            key: format!(
+                "{}:{:?}:{}s:{}%",
+                RULE, obs.pool, self.rule.window_secs, self.rule.change_pct
+            ),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} price changed {:+.2}% within {}s ({} -> {})",
+                obs.pair,
+                change_pct,
+                self.rule.window_secs,
+                format_amount(start),
+                format_amount(obs.price)
+            ),
+            vars,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::TickCrossRule;
+use crate::console::format_amount;
+use std::collections::BTreeMap;
+
+const RULE: &str = "tick_cross";
+
+// a tick or price of interest and whether a swap has crossed it yet
+struct Level {
+    value: LevelValue,
+    crossed: bool,
+}
+
+#[derive(Clone, Copy)]
+enum LevelValue {
+    Tick(i32),
+    Price(f64),
+}
+
+// reports the first swap that crosses each registered tick or price, with direction
+pub struct CrossWatcher {
+    rule: TickCrossRule,
+    levels: Vec<Level>,
+    // tick and price after the previous swap, None until the first swap
+    previous: Option<(i32, f64)>,
+}
+
+impl CrossWatcher {
+    pub fn new(rule: TickCrossRule) -> Self {
+        let levels = rule
+            .ticks
+            .iter()
+            .map(|t| LevelValue::Tick(*t))
+            .chain(rule.prices.iter().map(|p| LevelValue::Price(*p)))
+            .map(|value| Level {
+                value,
+                crossed: false,
+            })
+            .collect();
+
+        CrossWatcher {
+            rule,
+            levels,
+            previous: None,
+        }
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let price = if self.rule.inverse {
+            1.0 / obs.price
+        } else {
+            obs.price
+        };
+
+        let Some((prev_tick, prev_price)) = self.previous.replace((obs.tick, price)) else {
+            return;
+        };
+
+        let mut crossed = Vec::new();
+        for level in self.levels.iter_mut().filter(|l| !l.crossed) {
+            let up = match level.value {
+                LevelValue::Tick(t) => crossing(prev_tick as f64, obs.tick as f64, t as f64),
+                LevelValue::Price(p) => crossing(prev_price, price, p),
+            };
+            let Some(up) = up else {
+                continue;
+            };
+            level.crossed = true;
+            crossed.push((level.value, up));
+        }
+
+        for (level, up) in crossed {
+            alerter.notify(&self.alert(obs, level, up, price)).await;
+        }
+    }
+
+    fn alert(&self, obs: &Observation<'_>, level: LevelValue, up: bool, price: f64) -> Alert {
+        let direction = if up { "up" } else { "down" };
+        let level = match level {
+            LevelValue::Tick(t) => format!("tick {}", t),
+            LevelValue::Price(p) => format!("price {}", p),
+        };
+
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), obs.pair.to_string());
+        vars.insert("price".to_string(), price.to_string());
+        vars.insert("tick".to_string(), obs.tick.to_string());
+        vars.insert("level".to_string(), level.clone());
+        vars.insert("direction".to_string(), direction.to_string());
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        let name = self.rule.name.as_deref().unwrap_or(obs.pair);
+        Alert {
+            rule: RULE.to_string(),
+            key: format!("{}:{:?}:{}", RULE, obs.pool, level),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} crossed {} {} (now tick {}, price {})",
+                name,
+                level,
+                direction,
+                obs.tick,
+                format_amount(price)
+            ),
+            vars,
+        }
+    }
+}
+
+// Some(true) when moving from `from` to `to` crosses `level` upwards,
+// Some(false) downwards, None when it was not crossed
+fn crossing(from: f64, to: f64, level: f64) -> Option<bool> {
+    if from < level && to >= level {
+        Some(true)
+    } else if from >= level && to < level {
+        Some(false)
+    } else {
+        None
+    }
+}
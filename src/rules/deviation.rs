@@ -0,0 +1,116 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::DeviationRule;
+use crate::console::format_amount;
+use crate::reference::ReferencePrices;
+use crate::storage::unix_now;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const RULE: &str = "deviation";
+
+// compares the pool price against an external reference price and fires when
+// the deviation stays above the threshold for longer than `for_secs`
+pub struct DeviationWatcher {
+    rule: DeviationRule,
+    references: ReferencePrices,
+    deviating_since: Option<Instant>,
+    firing: bool,
+}
+
+impl DeviationWatcher {
+    pub fn new(rule: DeviationRule, references: ReferencePrices) -> Self {
+        DeviationWatcher {
+            rule,
+            references,
+            deviating_since: None,
+            firing: false,
+        }
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let reference = self
+            .references
+            .read()
+            .unwrap()
+            .get(&self.rule.reference)
+            .copied();
+        let Some(reference) = reference else {
+            return;
+        };
+        if unix_now().saturating_sub(reference.updated_at) > self.rule.max_reference_age_secs {
+            warn!(
+                "Reference {} is outdated, skipping deviation check for {}",
+                self.rule.reference, obs.pair
+            );
+            return;
+        }
+
+        let price = if self.rule.inverse {
+            1.0 / obs.price
+        } else {
+            obs.price
+        };
+        if reference.price <= 0.0 {
+            return;
+        }
+        let deviation_bps = (price / reference.price - 1.0) * 10_000.0;
+
+        if deviation_bps.abs() <= self.rule.max_deviation_bps {
+            self.deviating_since = None;
+            if self.firing {
+                self.firing = false;
+                let alert = self.alert(obs, price, reference.price, deviation_bps);
+                alerter.resolve(&alert).await;
+            }
+            return;
+        }
+
+        let since = *self.deviating_since.get_or_insert_with(Instant::now);
+        if !self.firing && since.elapsed() >= Duration::from_secs(self.rule.for_secs) {
+            self.firing = true;
+            let alert = self.alert(obs, price, reference.price, deviation_bps);
+            alerter.fire(&alert).await;
+        }
+    }
+
+    fn alert(
+        &self,
+        obs: &Observation<'_>,
+        price: f64,
+        reference: f64,
+        deviation_bps: f64,
+    ) -> Alert {
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), obs.pair.to_string());
+        vars.insert("price".to_string(), price.to_string());
+        vars.insert("reference".to_string(), self.rule.reference.clone());
+        vars.insert("reference_price".to_string(), reference.to_string());
+        vars.insert("deviation_bps".to_string(), format!("{:.2}", deviation_bps));
+        vars.insert(
+            "change_pct".to_string(),
+            format!("{:.2}", deviation_bps / 100.0),
+        );
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        Alert {
+            rule: RULE.to_string(),
+            key: format!("{}:{:?}:{}", RULE, obs.pool, self.rule.reference),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} price {} deviates {:.2} bps from reference {} ({}) for more than {}s",
+                obs.pair,
+                format_amount(price),
+                deviation_bps,
+                self.rule.reference,
+                format_amount(reference),
+                self.rule.for_secs
+            ),
+            vars,
+        }
+    }
+}
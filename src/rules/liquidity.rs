@@ -0,0 +1,94 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::LiquidityDropRule;
+use crate::console::group_thousands;
+use std::collections::{BTreeMap, VecDeque};
+
+const RULE: &str = "liquidity_drop";
+
+// watches the in-range liquidity reported by each swap and compares
+// it against the highest value seen within the rolling window
+pub struct LiquidityWatcher {
+    rule: LiquidityDropRule,
+    samples: VecDeque<(u64, u128)>,
+    firing: bool,
+}
+
+impl LiquidityWatcher {
+    pub fn new(rule: LiquidityDropRule) -> Self {
+        LiquidityWatcher {
+            rule,
+            samples: VecDeque::new(),
+            firing: false,
+        }
+    }
+
+    // liquidity samples inside the window, saved across restarts
+    pub fn samples(&self) -> VecDeque<(u64, u128)> {
+        self.samples.clone()
+    }
+
+    pub fn restore(&mut self, samples: VecDeque<(u64, u128)>) {
+        self.samples = samples;
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let now = obs.timestamp;
+        self.samples.push_back((now, obs.liquidity));
+        while let Some((t, _)) = self.samples.front() {
+            if now.saturating_sub(*t) > self.rule.window_secs {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let peak = self.samples.iter().map(|(_, l)| *l).max().unwrap_or(0);
+        if peak == 0 {
+            return;
+        }
+        let drop_pct = (1.0 - obs.liquidity as f64 / peak as f64) * 100.0;
+
+        // resolve once less than half of the configured drop remains
+        if !self.firing && drop_pct >= self.rule.drop_pct {
+            self.firing = true;
+            alerter.fire(&self.alert(obs, peak, drop_pct)).await;
+        } else if self.firing && drop_pct < self.rule.drop_pct / 2.0 {
+            self.firing = false;
+            alerter.resolve(&self.alert(obs, peak, drop_pct)).await;
+        }
+    }
+
+    fn alert(&self, obs: &Observation<'_>, peak: u128, drop_pct: f64) -> Alert {
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), obs.pair.to_string());
+        vars.insert("price".to_string(), obs.price.to_string());
+        vars.insert("liquidity".to_string(), obs.liquidity.to_string());
+        vars.insert("peak_liquidity".to_string(), peak.to_string());
+        vars.insert("change_pct".to_string(), format!("{:.2}", -drop_pct));
+        vars.insert("window_secs".to_string(), self.rule.window_secs.to_string());
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        Alert {
+            rule: RULE.to_string(),
+            // several rules can watch one pool, each alerts on its own
+            key: format!(
+                "{}:{:?}:{}s:{}%",
+                RULE, obs.pool, self.rule.window_secs, self.rule.drop_pct
+            ),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} in-range liquidity dropped {:.2}% within {}s ({} -> {})",
+                obs.pair,
+                drop_pct,
+                self.rule.window_secs,
+                group_thousands(&peak.to_string()),
+                group_thousands(&obs.liquidity.to_string())
+            ),
+            vars,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use super::MintObservation;
+use crate::alert::{Alert, Alerter};
+use crate::config::LargeMintRule;
+use crate::console::group_thousands;
+use std::collections::BTreeMap;
+
+const RULE: &str = "large_mint";
+
+// flags single Mint events that add a large share of the pool's liquidity,
+// big new positions often precede informed flow or a listing
+pub struct MintWatcher {
+    rule: LargeMintRule,
+}
+
+impl MintWatcher {
+    pub fn new(rule: LargeMintRule) -> Self {
+        MintWatcher { rule }
+    }
+
+    pub async fn on_mint(&mut self, mint: &MintObservation<'_>, alerter: &Alerter) {
+        if mint.pool_liquidity == 0 {
+            return;
+        }
+        let share_pct = mint.amount as f64 / mint.pool_liquidity as f64 * 100.0;
+        if share_pct < self.rule.min_share_pct {
+            return;
+        }
+
+        let mut vars = BTreeMap::new();
+        vars.insert("pair".to_string(), mint.pair.to_string());
+        vars.insert("owner".to_string(), format!("{:?}", mint.owner));
+        vars.insert("tick_lower".to_string(), mint.tick_lower.to_string());
+        vars.insert("tick_upper".to_string(), mint.tick_upper.to_string());
+        vars.insert("liquidity".to_string(), mint.amount.to_string());
+        vars.insert(
+            "pool_liquidity".to_string(),
+            mint.pool_liquidity.to_string(),
+        );
+        vars.insert("share_pct".to_string(), format!("{:.2}", share_pct));
+        vars.insert("amount0".to_string(), mint.amount0.to_string());
+        vars.insert("amount1".to_string(), mint.amount1.to_string());
+        if let Some(url) = &mint.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        // keyed by position, repeated mints into the same range share the cooldown
+        let key = format!(
+            "{}:{:?}:{:?}:{}:{}",
+            RULE, mint.pool, mint.owner, mint.tick_lower, mint.tick_upper
+        );
+
+        let alert = Alert {
+            rule: RULE.to_string(),
+            key,
+            pool: mint.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} new position of {} liquidity ({:.2}% of the pool) in ticks [{}, {}] by {:?}",
+                mint.pair,
+                group_thousands(&mint.amount.to_string()),
+                share_pct,
+                mint.tick_lower,
+                mint.tick_upper,
+                mint.owner
+            ),
+            vars,
+        };
+        alerter.notify(&alert).await;
+    }
+}
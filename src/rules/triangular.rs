@@ -0,0 +1,253 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::TriangularRule;
+use crate::state::{PoolSnapshot, SharedState};
+use alloy::primitives::Address;
+use std::collections::{BTreeMap, HashMap};
+
+const RULE: &str = "triangular_arbitrage";
+
+// walks every cycle of three tracked pools through this pool, e.g.
+// WETH -> USDC -> WBTC -> WETH, and fires when going around it returns more
+// than it started with after the three pools' fees
+pub struct TriangularWatcher {
+    rule: TriangularRule,
+    state: SharedState,
+    // open alerts by cycle key, with the cycle's pools
+    firing: HashMap<String, (Alert, [Address; 3])>,
+}
+
+// a profitable way around three pools
+struct Cycle {
+    // tokens in trading order, the first one is also where the cycle ends
+    tokens: [Address; 3],
+    symbols: [String; 3],
+    pools: [Address; 3],
+    // tokens out per token in after fees, > 1 is a profit
+    product: f64,
+}
+
+impl TriangularWatcher {
+    pub fn new(rule: TriangularRule, state: SharedState) -> Self {
+        TriangularWatcher {
+            rule,
+            state,
+            firing: HashMap::new(),
+        }
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let cycles = {
+            let state = self.state.read().unwrap();
+            let Some(this) = state.pools.get(&obs.pool) else {
+                return;
+            };
+            // cycles never leave the chain
+            let pools: Vec<&PoolSnapshot> = state
+                .pools
+                .values()
+                .filter(|p| p.chain_id == this.chain_id && p.price > 0.0)
+                .collect();
+            cycles_through(this, &pools)
+        };
+
+        let threshold = 1.0 + self.rule.min_profit_bps / 10_000.0;
+        let mut profitable: HashMap<String, Cycle> = HashMap::new();
+        for cycle in cycles {
+            if cycle.product > threshold {
+                profitable.insert(cycle_key(&cycle), cycle);
+            }
+        }
+
+        // only cycles through this pool were priced again, the others keep their alerts
+        let closed: Vec<String> = self
+            .firing
+            .iter()
+            .filter(|(key, (_, pools))| pools.contains(&obs.pool) && !profitable.contains_key(*key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in closed {
+            if let Some((alert, _)) = self.firing.remove(&key) {
+                alerter.resolve(&alert).await;
+            }
+        }
+
+        for (key, cycle) in profitable {
+            if self.firing.contains_key(&key) {
+                continue;
+            }
+            let alert = self.alert(obs, &key, &cycle);
+            alerter.fire(&alert).await;
+            self.firing.insert(key, (alert, cycle.pools));
+        }
+    }
+
+    fn alert(&self, obs: &Observation<'_>, key: &str, cycle: &Cycle) -> Alert {
+        let profit_bps = (cycle.product - 1.0) * 10_000.0;
+        let route = format!(
+            "{} -> {} -> {} -> {}",
+            cycle.symbols[0], cycle.symbols[1], cycle.symbols[2], cycle.symbols[0]
+        );
+
+        let mut vars = BTreeMap::new();
+        vars.insert("route".to_string(), route.clone());
+        vars.insert("profit_bps".to_string(), format!("{:.2}", profit_bps));
+        for (i, pool) in cycle.pools.iter().enumerate() {
+            vars.insert(format!("pool{}", i + 1), format!("{:?}", pool));
+        }
+        if let Some(url) = &obs.tx_url {
+            vars.insert("tx_url".to_string(), url.clone());
+        }
+
+        Alert {
+            rule: RULE.to_string(),
+            key: key.to_string(),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "Triangular arbitrage {} via {:?}, {:?}, {:?}: {:.2} bps after fees",
+                route, cycle.pools[0], cycle.pools[1], cycle.pools[2], profit_bps
+            ),
+            vars,
+        }
+    }
+}
+
+// every cycle of three pools that starts in `this`, in both directions
+fn cycles_through(this: &PoolSnapshot, pools: &[&PoolSnapshot]) -> Vec<Cycle> {
+    let mut cycles = Vec::new();
+    for (a, b) in [(this.token0, this.token1), (this.token1, this.token0)] {
+        for second in pools.iter().filter(|p| p.pool != this.pool) {
+            let Some(c) = other_token(second, b) else {
+                continue;
+            };
+            if c == a {
+                continue;
+            }
+            for third in pools
+                .iter()
+                .filter(|p| p.pool != this.pool && p.pool != second.pool)
+            {
+                if other_token(third, c) != Some(a) {
+                    continue;
+                }
+                let product = rate(this, a) * rate(second, b) * rate(third, c);
+                cycles.push(Cycle {
+                    tokens: [a, b, c],
+                    symbols: [symbol(this, a), symbol(second, b), symbol(third, c)],
+                    pools: [this.pool, second.pool, third.pool],
+                    product,
+                });
+            }
+        }
+    }
+    cycles
+}
+
+// the token a pool trades against `token`, None when it does not contain it
+fn other_token(pool: &PoolSnapshot, token: Address) -> Option<Address> {
+    if pool.token0 == token {
+        Some(pool.token1)
+    } else if pool.token1 == token {
+        Some(pool.token0)
+    } else {
+        None
+    }
+}
+
+fn symbol(pool: &PoolSnapshot, token: Address) -> String {
+    if pool.token0 == token {
+        pool.symbol0.clone()
+    } else {
+        pool.symbol1.clone()
+    }
+}
+
+// other token received per `sold` token after the pool fee, at the current price
+fn rate(pool: &PoolSnapshot, sold: Address) -> f64 {
+    let fee = pool.fee as f64 / 1_000_000.0;
+    let price = if pool.token0 == sold {
+        pool.price
+    } else {
+        1.0 / pool.price
+    };
+    price * (1.0 - fee)
+}
+
+// the same cycle is found from each of its pools, all of them share one alert
+fn cycle_key(cycle: &Cycle) -> String {
+    // rotate so the smallest token leads, keeping the direction
+    let start = (0..3).min_by_key(|&i| cycle.tokens[i]).unwrap_or(0);
+    let tokens: Vec<String> = (0..3)
+        .map(|i| format!("{:?}", cycle.tokens[(start + i) % 3]))
+        .collect();
+    let mut pools = cycle.pools;
+    pools.sort();
+    format!(
+        "{}:{}:{:?}:{:?}:{:?}",
+        RULE,
+        tokens.join(">"),
+        pools[0],
+        pools[1],
+        pools[2]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    const A: Address = address!("0x000000000000000000000000000000000000000a");
+    const B: Address = address!("0x000000000000000000000000000000000000000b");
+    const C: Address = address!("0x000000000000000000000000000000000000000c");
+    const D: Address = address!("0x000000000000000000000000000000000000000d");
+
+    fn pool(pool: u8, token0: Address, token1: Address, price: f64) -> PoolSnapshot {
+        PoolSnapshot {
+            symbol0: format!("{:?}", token0),
+            symbol1: format!("{:?}", token1),
+            ..PoolSnapshot::at_price(Address::with_last_byte(pool), token0, token1, price)
+        }
+    }
+
+    #[test]
+    fn cycles_through_finds_both_directions() {
+        let ab = pool(1, A, B, 2.0);
+        let bc = pool(2, B, C, 3.0);
+        let ca = pool(3, C, A, 0.2);
+        let cycles = cycles_through(&ab, &[&ab, &bc, &ca]);
+
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].tokens, [A, B, C]);
+        assert_eq!(cycles[0].pools, [ab.pool, bc.pool, ca.pool]);
+        // 2 * 3 * 0.2
+        assert!((cycles[0].product - 1.2).abs() < 1e-9);
+        assert_eq!(cycles[1].tokens, [B, A, C]);
+        assert!((cycles[1].product - 1.0 / 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cycles_through_skips_pools_that_do_not_close_the_cycle() {
+        let ab = pool(1, A, B, 2.0);
+        let bc = pool(2, B, C, 3.0);
+        let cd = pool(3, C, D, 1.0);
+        assert!(cycles_through(&ab, &[&ab, &bc, &cd]).is_empty());
+    }
+
+    #[test]
+    fn cycle_key_is_the_same_from_every_pool() {
+        let ab = pool(1, A, B, 2.0);
+        let bc = pool(2, B, C, 3.0);
+        let ca = pool(3, C, A, 0.2);
+        let pools = [&ab, &bc, &ca];
+        let from_ab = cycles_through(&ab, &pools);
+        let from_bc = cycles_through(&bc, &pools);
+
+        // A > B > C found from the second pool starts at B
+        assert_eq!(from_bc[0].tokens, [B, C, A]);
+        assert_eq!(cycle_key(&from_ab[0]), cycle_key(&from_bc[0]));
+        // the opposite direction is another cycle
+        assert_ne!(cycle_key(&from_ab[0]), cycle_key(&from_ab[1]));
+    }
+}
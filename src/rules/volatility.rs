@@ -0,0 +1,157 @@
+use super::Observation;
+use crate::alert::{Alert, Alerter};
+use crate::config::VolatilityRule;
+use std::collections::{BTreeMap, VecDeque};
+
+const RULE_REALIZED: &str = "volatility";
+const RULE_STREAK: &str = "volatility_streak";
+
+// realized volatility has to fall below this share of the threshold before
+// the alert resolves, so volatility hovering around the threshold does not flap
+const RESOLVE_RATIO: f64 = 0.8;
+
+// last price and (time, squared log return) samples of a watcher
+pub type VolatilitySamples = (Option<f64>, VecDeque<(u64, f64)>);
+
+// watches price returns between consecutive swaps of one pool
+pub struct VolatilityWatcher {
+    rule: VolatilityRule,
+    last_price: Option<f64>,
+    // (time, squared log return) of swaps inside the rolling window
+    returns: VecDeque<(u64, f64)>,
+    streak: u32,
+    realized_firing: bool,
+    streak_firing: bool,
+}
+
+impl VolatilityWatcher {
+    pub fn new(rule: VolatilityRule) -> Self {
+        VolatilityWatcher {
+            rule,
+            last_price: None,
+            returns: VecDeque::new(),
+            streak: 0,
+            realized_firing: false,
+            streak_firing: false,
+        }
+    }
+
+    // last price and squared returns inside the window, saved across restarts
+    pub fn samples(&self) -> VolatilitySamples {
+        (self.last_price, self.returns.clone())
+    }
+
+    pub fn restore(&mut self, (last_price, returns): VolatilitySamples) {
+        self.last_price = last_price;
+        self.returns = returns;
+    }
+
+    pub async fn on_swap(&mut self, obs: &Observation<'_>, alerter: &Alerter) {
+        let Some(prev) = self.last_price.replace(obs.price) else {
+            return;
+        };
+        if prev <= 0.0 || obs.price <= 0.0 {
+            return;
+        }
+
+        let log_return = (obs.price / prev).ln();
+        let move_bps = log_return.abs() * 10_000.0;
+
+        if let Some(max_bps) = self.rule.max_volatility_bps {
+            let now = obs.timestamp;
+            self.returns.push_back((now, log_return * log_return));
+            while let Some((t, _)) = self.returns.front() {
+                if now.saturating_sub(*t) > self.rule.window_secs {
+                    self.returns.pop_front();
+                } else {
+                    break;
+                }
+            }
+
+            // realized volatility over the window: sqrt of the sum of squared log returns
+            let realized_bps = self.returns.iter().map(|(_, r)| r).sum::<f64>().sqrt() * 10_000.0;
+
+            if !self.realized_firing && realized_bps > max_bps {
+                self.realized_firing = true;
+                let alert = self.realized_alert(obs, realized_bps, max_bps);
+                alerter.fire(&alert).await;
+            } else if self.realized_firing && realized_bps < max_bps * RESOLVE_RATIO {
+                self.realized_firing = false;
+                let alert = self.realized_alert(obs, realized_bps, max_bps);
+                alerter.resolve(&alert).await;
+            }
+        }
+
+        if let (Some(swaps), Some(min_move_bps)) = (self.rule.consecutive_swaps, self.rule.move_bps)
+        {
+            if move_bps > min_move_bps {
+                self.streak += 1;
+            } else {
+                self.streak = 0;
+            }
+
+            if !self.streak_firing && self.streak >= swaps {
+                self.streak_firing = true;
+                let alert = self.streak_alert(obs, swaps, min_move_bps);
+                alerter.fire(&alert).await;
+            } else if self.streak_firing && self.streak == 0 {
+                self.streak_firing = false;
+                let alert = self.streak_alert(obs, swaps, min_move_bps);
+                alerter.resolve(&alert).await;
+            }
+        }
+    }
+
+    fn realized_alert(&self, obs: &Observation<'_>, realized_bps: f64, max_bps: f64) -> Alert {
+        let mut vars = base_vars(obs);
+        vars.insert("volatility_bps".to_string(), format!("{:.2}", realized_bps));
+        vars.insert("window_secs".to_string(), self.rule.window_secs.to_string());
+
+        Alert {
+            rule: RULE_REALIZED.to_string(),
+            // several rules can watch one pool, each alerts on its own
+            key: format!(
+                "{}:{:?}:{}s:{}bps",
+                RULE_REALIZED, obs.pool, self.rule.window_secs, max_bps
+            ),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} realized volatility {:.2} bps over the last {}s",
+                obs.pair, realized_bps, self.rule.window_secs
+            ),
+            vars,
+        }
+    }
+
+    fn streak_alert(&self, obs: &Observation<'_>, swaps: u32, min_move_bps: f64) -> Alert {
+        let mut vars = base_vars(obs);
+        vars.insert("swaps".to_string(), swaps.to_string());
+        vars.insert("move_bps".to_string(), min_move_bps.to_string());
+
+        Alert {
+            rule: RULE_STREAK.to_string(),
+            key: format!(
+                "{}:{:?}:{}x{}bps",
+                RULE_STREAK, obs.pool, swaps, min_move_bps
+            ),
+            pool: obs.pool,
+            severity: self.rule.severity,
+            summary: format!(
+                "{} moved more than {} bps on {} consecutive swaps",
+                obs.pair, min_move_bps, swaps
+            ),
+            vars,
+        }
+    }
+}
+
+fn base_vars(obs: &Observation<'_>) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    vars.insert("pair".to_string(), obs.pair.to_string());
+    vars.insert("price".to_string(), obs.price.to_string());
+    if let Some(url) = &obs.tx_url {
+        vars.insert("tx_url".to_string(), url.clone());
+    }
+    vars
+}
@@ -0,0 +1,61 @@
+use std::env;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Failed to read {0} from {1}: {2}")]
+    ReadFailed(String, String, String),
+
+    #[error("Both {0} and {0}_FILE are set, use only one")]
+    Conflicting(String),
+}
+
+// variables holding API keys or tokens, which can also be read from the file named by
+// <VAR>_FILE
+const SECRETS: &[&str] = &[
+    "RPC_URL",
+    "RPC_FALLBACK_URLS",
+    "API_ADMIN_TOKEN",
+    "TELEGRAM_BOT_TOKEN",
+    "TELEGRAM_CHAT_ID",
+    "PAGERDUTY_ROUTING_KEY",
+    "NTFY_TOKEN",
+    "PUSHOVER_TOKEN",
+    "PUSHOVER_USER",
+    "WEBHOOK_URL",
+    "WEBHOOK_SECRET",
+    "WEBHOOK_HEADERS",
+    "ZEROX_API_KEY",
+    "ONEINCH_API_KEY",
+];
+
+// RPC_URL_FILE=/run/secrets/rpc_url sets RPC_URL to the content of the file, so API
+// keys can come from mounted Docker or Kubernetes secrets
+// must run before the tokio runtime starts, setting variables is only sound while no
+// other thread reads them
+pub fn load_files() -> Result<(), SecretError> {
+    for name in SECRETS {
+        let Some(path) = env::var_os(format!("{}_FILE", name)) else {
+            continue;
+        };
+        let path = path.to_string_lossy().to_string();
+        if env::var_os(name).is_some() {
+            return Err(SecretError::Conflicting(name.to_string()));
+        }
+        let content = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(SecretError::ReadFailed(
+                    name.to_string(),
+                    path,
+                    e.to_string(),
+                ));
+            }
+        };
+        // secret files usually end with a newline the value must not include
+        let value = content.trim_end_matches(['\r', '\n']);
+        // SAFETY: called from main before any other thread is spawned
+        unsafe { env::set_var(name, value) };
+    }
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::pool::ListenerSettings;
+#[cfg(unix)]
+use crate::systemd;
+use std::time::Duration;
+use tokio::sync::Notify;
+#[cfg(unix)]
+use tracing::error;
+use tracing::info;
+
+// resolves on SIGINT or SIGTERM, or once `stop` is notified by the TUI quitting
+#[cfg(unix)]
+pub async fn requested(stop: &Notify) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            error!("Failed to listen for SIGTERM: {}", e);
+            None
+        }
+    };
+    let terminated = async {
+        match &mut terminate {
+            Some(s) => {
+                s.recv().await;
+            }
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Interrupted, shutting down"),
+        _ = terminated => info!("Terminated, shutting down"),
+        _ = stop.notified() => info!("Shutting down"),
+    }
+}
+
+// other platforms have no SIGTERM, only ctrl-c
+#[cfg(not(unix))]
+pub async fn requested(stop: &Notify) {
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Interrupted, shutting down"),
+        _ = stop.notified() => info!("Shutting down"),
+    }
+}
+
+// tell systemd the tracker is stopping, then write out what the sinks still hold
+pub async fn drain(settings: &ListenerSettings, timeout: Duration) {
+    #[cfg(unix)]
+    systemd::notify("STOPPING=1");
+    settings.drain(timeout).await;
+}
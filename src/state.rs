@@ -0,0 +1,242 @@
+use crate::aggregator::AggregatorQuote;
+use crate::config::AggregatorSource;
+use crate::feetier::FeeTierReport;
+use crate::health::EndpointHealth;
+use crate::report::USD_STABLECOINS;
+use crate::route::Route;
+use crate::summary::ConfigSummary;
+use alloy::primitives::Address;
+use alloy::primitives::aliases::U160;
+use alloy::providers::Provider;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::error;
+
+// latest known state of a tracked pool, updated after every swap
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolSnapshot {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub token0: Address,
+    pub token1: Address,
+    pub symbol0: String,
+    pub symbol1: String,
+    pub decimals0: u8,
+    pub decimals1: u8,
+    // fee tier in hundredths of a bip, e.g. 500 = 0.05%
+    pub fee: u32,
+    // token logo urls from the token list
+    pub logo0: Option<String>,
+    pub logo1: Option<String>,
+    // token1 per token0
+    pub price: f64,
+    pub sqrt_price_x96: U160,
+    pub liquidity: u128,
+    // swaps seen since the tracker started
+    pub swaps: u64,
+    // token0 traded in the last 24 hours, only counts swaps since the tracker started
+    // unless restored from CHECKPOINT_PATH
+    pub volume0_24h: f64,
+    // unix timestamp of the last swap
+    pub updated_at: u64,
+}
+
+impl PoolSnapshot {
+    pub fn pair(&self) -> String {
+        format!("{}/{}", self.symbol0, self.symbol1)
+    }
+}
+
+#[cfg(test)]
+impl PoolSnapshot {
+    // a pool of two 18 decimal tokens at `price` with everything else zeroed
+    pub(crate) fn at_price(pool: Address, token0: Address, token1: Address, price: f64) -> Self {
+        PoolSnapshot {
+            pool,
+            chain_id: 1,
+            token0,
+            token1,
+            symbol0: String::new(),
+            symbol1: String::new(),
+            decimals0: 18,
+            decimals1: 18,
+            fee: 0,
+            logo0: None,
+            logo1: None,
+            price,
+            sqrt_price_x96: U160::ZERO,
+            liquidity: 0,
+            swaps: 0,
+            volume0_24h: 0.0,
+            updated_at: 0,
+        }
+    }
+}
+
+// price difference between pools of the same pair on two chains
+#[derive(Debug, Clone, Serialize)]
+pub struct CrossChainSpread {
+    // symbols of `pool`, prices are quoted in its orientation
+    pub pair: String,
+    pub pool: Address,
+    pub chain_id: u64,
+    pub price: f64,
+    pub other_pool: Address,
+    pub other_chain_id: u64,
+    // other pool's price in `pair` orientation, inverted when its tokens are sorted
+    // the other way round
+    pub other_price: f64,
+    // how much more `other_pool` pays for token0, in basis points
+    pub spread_bps: f64,
+}
+
+// pool price against an external reference, see [[basis]]
+#[derive(Debug, Clone, Serialize)]
+pub struct Basis {
+    pub pool: Address,
+    pub reference: String,
+    // pool price in the orientation of the reference
+    pub price: f64,
+    pub reference_price: f64,
+    pub basis_bps: f64,
+    pub updated_at: u64,
+}
+
+// what a pool listener is doing, for GET /status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenerState {
+    #[default]
+    Starting,
+    Subscribed,
+    // the log subscription ended and is set up again after RESUBSCRIBE_DELAY
+    Resubscribing,
+    // the listener gave up after an error
+    Stopped,
+}
+
+// operational view of one pool listener
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ListenerStatus {
+    pub chain_id: u64,
+    pub pair: String,
+    // hundredths of a bip, as the pool reports it
+    pub fee: u32,
+    pub state: ListenerState,
+    // unix timestamps
+    pub subscribed_at: Option<u64>,
+    // of any log, swaps or not
+    pub last_event_at: Option<u64>,
+    // errors the listener recovered from and the one that stopped it
+    pub errors: u64,
+    pub last_error: Option<String>,
+    // subscriptions set up again after they ended
+    pub resubscriptions: u64,
+    // times the connection went silent while blocks were produced, each one also
+    // counts as a resubscription
+    pub stalls: u64,
+    // logs waiting for their confirmations, lost if the tracker stops now
+    pub unconfirmed: usize,
+}
+
+// live view shared by all pool listeners, lets rules compare pools with each other
+#[derive(Debug, Default)]
+pub struct TrackerState {
+    pub pools: HashMap<Address, PoolSnapshot>,
+    pub listeners: HashMap<Address, ListenerStatus>,
+    // health of every RPC endpoint by url
+    pub endpoints: HashMap<String, EndpointHealth>,
+    // resolved configuration, logged at startup
+    pub summary: Option<ConfigSummary>,
+    // latest basis by pool and reference
+    pub basis: HashMap<(Address, String), Basis>,
+    // best routes by [[routes]] pair
+    pub routes: HashMap<String, Vec<Route>>,
+    // latest [[aggregator]] quotes by pool and source, one per size
+    pub aggregator_quotes: HashMap<(Address, AggregatorSource), Vec<AggregatorQuote>>,
+    // latest [fee_tier_report]
+    pub fee_tiers: Vec<FeeTierReport>,
+    pub gas_price_wei: Option<u128>,
+}
+
+impl TrackerState {
+    // USD price of a token on `chain_id`, one for stablecoins, otherwise the price of a
+    // tracked pool against a stablecoin
+    pub fn usd_price(&self, chain_id: u64, token: Address) -> Option<f64> {
+        let stable = |symbol: &str| USD_STABLECOINS.contains(&symbol);
+        self.pools
+            .values()
+            .filter(|p| p.chain_id == chain_id && p.price > 0.0)
+            .find_map(|p| {
+                if (p.token0 == token && stable(&p.symbol0))
+                    || (p.token1 == token && stable(&p.symbol1))
+                {
+                    Some(1.0)
+                } else if p.token0 == token && stable(&p.symbol1) {
+                    Some(p.price)
+                } else if p.token1 == token && stable(&p.symbol0) {
+                    Some(1.0 / p.price)
+                } else {
+                    None
+                }
+            })
+    }
+
+    // spreads of `pool` against the pools of the same pair on other chains
+    // pairs are matched by symbol, token addresses differ between chains
+    pub fn cross_chain_spreads(&self, pool: Address) -> Vec<CrossChainSpread> {
+        let Some(this) = self.pools.get(&pool) else {
+            return Vec::new();
+        };
+        if this.price <= 0.0 {
+            return Vec::new();
+        }
+
+        self.pools
+            .values()
+            .filter(|other| other.chain_id != this.chain_id)
+            .filter_map(|other| {
+                let same = other.symbol0.eq_ignore_ascii_case(&this.symbol0)
+                    && other.symbol1.eq_ignore_ascii_case(&this.symbol1);
+                let flipped = other.symbol0.eq_ignore_ascii_case(&this.symbol1)
+                    && other.symbol1.eq_ignore_ascii_case(&this.symbol0);
+                let other_price = match (same, flipped) {
+                    (true, _) => other.price,
+                    (_, true) if other.price > 0.0 => 1.0 / other.price,
+                    _ => return None,
+                };
+                Some(CrossChainSpread {
+                    pair: this.pair(),
+                    pool: this.pool,
+                    chain_id: this.chain_id,
+                    price: this.price,
+                    other_pool: other.pool,
+                    other_chain_id: other.chain_id,
+                    other_price,
+                    spread_bps: (other_price / this.price - 1.0) * 10_000.0,
+                })
+            })
+            .collect()
+    }
+}
+
+pub type SharedState = Arc<RwLock<TrackerState>>;
+
+// keep gas_price_wei up to date for rules that account for transaction costs
+pub fn spawn_gas_price_poller<P>(state: SharedState, provider: P, refresh: Duration)
+where
+    P: Provider + 'static,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresh);
+        loop {
+            interval.tick().await;
+            match provider.get_gas_price().await {
+                Ok(price) => state.write().unwrap().gas_price_wei = Some(price),
+                Err(e) => error!("Failed to fetch gas price: {}", e),
+            }
+        }
+    });
+}
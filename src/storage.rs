@@ -0,0 +1,607 @@
+use alloy::primitives::aliases::U160;
+use alloy::primitives::{Address, B256};
+#[cfg(feature = "sqlite")]
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "sqlite")]
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+#[cfg(feature = "sqlite")]
+use tracing::{error, info};
+#[cfg(feature = "sqlite")]
+use uniswap_pool_core::candle::merge_candles;
+
+#[cfg(not(feature = "sqlite"))]
+mod disabled;
+#[cfg(feature = "sqlite")]
+mod external;
+#[cfg(feature = "sqlite")]
+mod migrations;
+#[cfg(feature = "sqlite")]
+mod retention;
+#[cfg(feature = "sqlite")]
+mod volatility;
+mod writer;
+
+#[cfg(not(feature = "sqlite"))]
+pub use disabled::{Storage, run_retention};
+#[cfg(feature = "sqlite")]
+pub use retention::run_retention;
+pub use uniswap_pool_core::candle::Candle;
+pub use writer::SwapWriter;
+
+const HOUR: u64 = 3_600;
+const DAY: u64 = 86_400;
+
+// intervals the volatility series are stored in
+pub const VOLATILITY_INTERVALS: [u64; 2] = [HOUR, DAY];
+
+// realized volatility of a pool's price within one interval, from the log returns
+// between consecutive stored swaps, intervals without a return are left out
+#[derive(Debug, Clone, Serialize)]
+pub struct VolatilityPoint {
+    // unix timestamp of the interval start
+    pub time: u64,
+    // square root of the summed squared log returns
+    pub realized: f64,
+    // realized scaled to a year of such intervals
+    pub annualized: f64,
+    pub returns: u64,
+}
+
+#[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
+pub enum StorageError {
+    #[error("Failed to open database {0}")]
+    OpenFailed(String),
+
+    #[error("Failed to write to database")]
+    WriteFailed,
+
+    #[error("Failed to read from database")]
+    ReadFailed,
+
+    #[error("Built without the sqlite feature, {0} is not opened")]
+    NotBuilt(String),
+}
+
+// outcome of delivering an alert to one notification channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub channel: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    // held back by quiet hours, sent later as part of a summary
+    #[serde(default)]
+    pub queued: bool,
+}
+
+// a fired or resolved alert as recorded in the audit log
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertRecord {
+    pub id: i64,
+    // unix timestamp in seconds
+    pub timestamp: u64,
+    // "fire", "resolve", "suppressed", "renotify", "escalate" or "ack"
+    pub action: String,
+    pub rule: String,
+    pub key: String,
+    pub pool: String,
+    pub severity: String,
+    pub summary: String,
+    pub deliveries: Vec<Delivery>,
+}
+
+// swaps of a pool over a period, amounts in whole tokens
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub swaps: u64,
+    pub volume0: f64,
+    // token0 amounts valued at each swap's price
+    pub volume1: f64,
+    pub last_price: Option<f64>,
+}
+
+// a decoded swap with the price derived from it, recorded by live listeners and backfills
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapRecord {
+    pub pool: Address,
+    pub chain_id: u64,
+    pub block: u64,
+    pub log_index: u64,
+    pub tx: Option<B256>,
+    // unix timestamp of the block
+    pub timestamp: u64,
+    // token1 per token0
+    pub price: f64,
+    pub sqrt_price_x96: U160,
+    pub liquidity: u128,
+    pub tick: i32,
+    // token0 traded, in whole tokens
+    pub amount0: f64,
+}
+
+// SQLite backed storage, shared by the alerter and the API server
+// every call runs on the blocking thread pool so the listeners are never stalled by disk IO
+#[cfg(feature = "sqlite")]
+#[derive(Clone)]
+pub struct Storage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage {
+    pub fn open(path: &str) -> Result<Storage, StorageError> {
+        let mut conn = match Connection::open(path) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("Failed to open database {}: {}", path, e);
+                return Err(StorageError::OpenFailed(path.to_string()));
+            }
+        };
+
+        if let Err(e) = migrations::migrate(&mut conn) {
+            error!("Failed to migrate database {}: {}", path, e);
+            return Err(StorageError::OpenFailed(path.to_string()));
+        }
+
+        info!("Using database {}", path);
+
+        Ok(Storage {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub async fn record_alert(&self, record: AlertRecord) -> Result<(), StorageError> {
+        let conn = self.conn.clone();
+        let deliveries = serde_json::to_string(&record.deliveries).unwrap_or_default();
+
+        let result = tokio::task::spawn_blocking(move || {
+            conn.lock().unwrap().execute(
+                "INSERT INTO alert_log (timestamp, action, rule, key, pool, severity, summary, deliveries)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    record.timestamp as i64,
+                    record.action,
+                    record.rule,
+                    record.key,
+                    record.pool,
+                    record.severity,
+                    record.summary,
+                    deliveries
+                ],
+            )
+        })
+        .await;
+
+        match result {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => {
+                error!("Failed to record alert: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+            Err(e) => {
+                error!("Alert log writer panicked: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+        }
+    }
+
+    // a swap is identified by its chain, transaction and log index, or by pool, block and
+    // log index when its transaction is unknown, so replays, backfills and redundant
+    // providers update the stored row instead of adding another one
+    pub async fn record_swaps(&self, records: Vec<SwapRecord>) -> Result<(), StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(&format!(
+                    "INSERT INTO swaps (pool, block, log_index, tx, timestamp, price, sqrt_price_x96, liquidity, tick, amount0, chain_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                     ON CONFLICT (chain_id, tx, log_index) DO UPDATE SET {SWAP_UPDATE}
+                     ON CONFLICT DO UPDATE SET {SWAP_UPDATE}"
+                ))?;
+                for r in &records {
+                    stmt.execute(params![
+                        format!("{:?}", r.pool),
+                        r.block as i64,
+                        r.log_index as i64,
+                        r.tx.map(|tx| format!("{:?}", tx)),
+                        r.timestamp as i64,
+                        r.price,
+                        r.sqrt_price_x96.to_string(),
+                        r.liquidity.to_string(),
+                        r.tick,
+                        r.amount0,
+                        r.chain_id as i64
+                    ])?;
+                }
+            }
+            tx.commit()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => {
+                error!("Failed to record swaps: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+            Err(e) => {
+                error!("Swap writer panicked: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+        }
+    }
+
+    // delete the rows of swaps whose logs a reorg removed, identified as in record_swaps
+    pub async fn delete_swaps(&self, records: Vec<SwapRecord>) -> Result<u64, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            let mut deleted = 0;
+            {
+                let mut stmt = tx.prepare(
+                    "DELETE FROM swaps WHERE log_index = ?3 AND chain_id = ?4
+                     AND (tx = ?5 OR (tx IS NULL AND pool = ?1 AND block = ?2))",
+                )?;
+                for r in &records {
+                    deleted += stmt.execute(params![
+                        format!("{:?}", r.pool),
+                        r.block as i64,
+                        r.log_index as i64,
+                        r.chain_id as i64,
+                        r.tx.map(|tx| format!("{:?}", tx))
+                    ])? as u64;
+                }
+            }
+            tx.commit().map(|_| deleted)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(deleted)) => Ok(deleted),
+            Ok(Err(e)) => {
+                error!("Failed to delete reorged swaps: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+            Err(e) => {
+                error!("Swap writer panicked: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+        }
+    }
+
+    // stored swaps of `pools` with timestamps in `from..to`, in the order they happened
+    pub async fn swaps(
+        &self,
+        pools: Vec<Address>,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<SwapRecord>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT pool, block, log_index, tx, timestamp, price, sqrt_price_x96, liquidity, tick, amount0, chain_id
+                 FROM swaps
+                 WHERE pool = ?1 AND timestamp >= ?2 AND timestamp < ?3",
+            )?;
+            let mut records = Vec::new();
+            for pool in &pools {
+                let rows = stmt.query_map(
+                    params![format!("{:?}", pool), from as i64, to.min(i64::MAX as u64) as i64],
+                    |row| swap_from_row(*pool, row),
+                )?;
+                for row in rows {
+                    records.push(row?);
+                }
+            }
+            records.sort_by_key(|r| (r.timestamp, r.block, r.log_index));
+            Ok::<_, rusqlite::Error>(records)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(records)) => Ok(records),
+            Ok(Err(e)) => {
+                error!("Failed to read swaps: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Swap reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+
+    // the last stored swap of a pool in or before `block`, its price is the pool's price
+    // at the end of that block as long as no swaps are missing in between
+    pub async fn swap_at_block(
+        &self,
+        pool: Address,
+        block: u64,
+    ) -> Result<Option<SwapRecord>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.query_row(
+                "SELECT pool, block, log_index, tx, timestamp, price, sqrt_price_x96, liquidity, tick, amount0, chain_id
+                 FROM swaps
+                 WHERE pool = ?1 AND block <= ?2
+                 ORDER BY block DESC, log_index DESC LIMIT 1",
+                params![format!("{:?}", pool), block.min(i64::MAX as u64) as i64],
+                |row| swap_from_row(pool, row),
+            )
+            .optional()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(record)) => Ok(record),
+            Ok(Err(e)) => {
+                error!("Failed to read swap at block {}: {}", block, e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Swap reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+
+    // price after the last stored swap of a pool at or before `timestamp`
+    pub async fn price_at(
+        &self,
+        pool: Address,
+        timestamp: u64,
+    ) -> Result<Option<f64>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.query_row(
+                "SELECT price FROM swaps WHERE pool = ?1 AND timestamp <= ?2
+                 ORDER BY timestamp DESC, block DESC, log_index DESC LIMIT 1",
+                params![format!("{:?}", pool), timestamp.min(i64::MAX as u64) as i64],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(price)) => Ok(price),
+            Ok(Err(e)) => {
+                error!("Failed to read price at {}: {}", timestamp, e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Price reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+
+    // number of stored swaps of a pool in `from..to`, the amounts traded and the
+    // price after the last of them
+    pub async fn volume(&self, pool: Address, from: u64, to: u64) -> Result<Volume, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.query_row(
+                "SELECT count(*), coalesce(sum(amount0), 0.0), coalesce(sum(amount0 * price), 0.0),
+                     (SELECT price FROM swaps WHERE pool = ?1 AND timestamp >= ?2 AND timestamp < ?3
+                      ORDER BY timestamp DESC, block DESC, log_index DESC LIMIT 1)
+                 FROM swaps
+                 WHERE pool = ?1 AND timestamp >= ?2 AND timestamp < ?3",
+                params![
+                    format!("{:?}", pool),
+                    from as i64,
+                    to.min(i64::MAX as u64) as i64
+                ],
+                |row| {
+                    Ok(Volume {
+                        swaps: row.get::<_, i64>(0)? as u64,
+                        volume0: row.get(1)?,
+                        volume1: row.get(2)?,
+                        last_price: row.get(3)?,
+                    })
+                },
+            )
+        })
+        .await;
+
+        match result {
+            Ok(Ok(volume)) => Ok(volume),
+            Ok(Err(e)) => {
+                error!("Failed to read volume: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Volume reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+
+    // candles of `interval` seconds from the stored swaps of a pool in `from..to`, and
+    // from the candles swaps were compacted into where the interval is a multiple of theirs
+    pub async fn candles(
+        &self,
+        pool: Address,
+        interval: u64,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<Candle>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let pool = format!("{:?}", pool);
+            let range = (from as i64, to.min(i64::MAX as u64) as i64);
+            let mut pieces = Vec::new();
+
+            let mut stmt = conn.prepare(
+                "SELECT time, open, high, low, close, volume0, swaps FROM candles
+                 WHERE pool = ?1 AND ?2 % interval = 0 AND time >= ?3 AND time < ?4
+                 ORDER BY time",
+            )?;
+            let rows = stmt.query_map(params![pool, interval as i64, range.0, range.1], |row| {
+                candle_from_row(row, 0)
+            })?;
+            for row in rows {
+                pieces.push(row?);
+            }
+
+            // every swap as a candle of its own
+            let mut stmt = conn.prepare(
+                "SELECT timestamp, price, price, price, price, amount0, 1 FROM swaps
+                 WHERE pool = ?1 AND timestamp >= ?2 AND timestamp < ?3
+                 ORDER BY timestamp, block, log_index",
+            )?;
+            let rows = stmt.query_map(params![pool, range.0, range.1], |row| {
+                candle_from_row(row, 0)
+            })?;
+            for row in rows {
+                pieces.push(row?);
+            }
+
+            // stable, swaps of the same second stay in block order
+            pieces.sort_by_key(|c| c.time);
+            Ok::<_, rusqlite::Error>(merge_candles(pieces, interval))
+        })
+        .await;
+
+        match result {
+            Ok(Ok(candles)) => Ok(candles),
+            Ok(Err(e)) => {
+                error!("Failed to read candles: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Candle reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+
+    // most recent alerts first, optionally limited to one pool
+    pub async fn recent_alerts(
+        &self,
+        pool: Option<Address>,
+        limit: u32,
+    ) -> Result<Vec<AlertRecord>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT id, timestamp, action, rule, key, pool, severity, summary, deliveries
+                 FROM alert_log
+                 WHERE ?1 IS NULL OR pool = ?1
+                 ORDER BY id DESC
+                 LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![pool.map(|p| format!("{:?}", p)), limit], |row| {
+                let deliveries: String = row.get(8)?;
+                Ok(AlertRecord {
+                    id: row.get(0)?,
+                    timestamp: row.get::<_, i64>(1)? as u64,
+                    action: row.get(2)?,
+                    rule: row.get(3)?,
+                    key: row.get(4)?,
+                    pool: row.get(5)?,
+                    severity: row.get(6)?,
+                    summary: row.get(7)?,
+                    deliveries: serde_json::from_str(&deliveries).unwrap_or_default(),
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(records)) => Ok(records),
+            Ok(Err(e)) => {
+                error!("Failed to read alert log: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Alert log reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+}
+
+// time, open, high, low, close, volume0 and swaps from column `first` on
+#[cfg(feature = "sqlite")]
+fn candle_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<Candle> {
+    Ok(Candle {
+        time: row.get::<_, i64>(first)? as u64,
+        open: row.get(first + 1)?,
+        high: row.get(first + 2)?,
+        low: row.get(first + 3)?,
+        close: row.get(first + 4)?,
+        volume0: row.get(first + 5)?,
+        swaps: row.get::<_, i64>(first + 6)? as u64,
+    })
+}
+
+// columns of a swap updated when it is recorded again, subgraph swaps carry no liquidity
+// and keep the one already stored
+#[cfg(feature = "sqlite")]
+const SWAP_UPDATE: &str = "pool = excluded.pool, block = excluded.block, tx = excluded.tx,
+    timestamp = excluded.timestamp, price = excluded.price, sqrt_price_x96 = excluded.sqrt_price_x96,
+    liquidity = CASE WHEN excluded.liquidity = '0' THEN liquidity ELSE excluded.liquidity END,
+    tick = excluded.tick, amount0 = excluded.amount0, chain_id = excluded.chain_id";
+
+// a row of the swaps table, selected in column order starting with the pool
+#[cfg(feature = "sqlite")]
+fn swap_from_row(pool: Address, row: &rusqlite::Row) -> rusqlite::Result<SwapRecord> {
+    let tx: Option<String> = row.get(3)?;
+    let sqrt_price_x96: String = row.get(6)?;
+    let liquidity: String = row.get(7)?;
+    Ok(SwapRecord {
+        pool,
+        chain_id: row.get::<_, i64>(10)? as u64,
+        block: row.get::<_, i64>(1)? as u64,
+        log_index: row.get::<_, i64>(2)? as u64,
+        tx: tx.and_then(|tx| tx.parse().ok()),
+        timestamp: row.get::<_, i64>(4)? as u64,
+        price: row.get(5)?,
+        sqrt_price_x96: sqrt_price_x96.parse().unwrap_or_default(),
+        liquidity: liquidity.parse().unwrap_or_default(),
+        tick: row.get(8)?,
+        amount0: row.get(9)?,
+    })
+}
+
+// candle interval like "5m" in seconds; s, m, h and d units
+pub fn parse_interval(value: &str) -> Option<u64> {
+    let unit = match value.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 3_600,
+        'd' => 86_400,
+        _ => return None,
+    };
+    let count: u64 = value[..value.len() - 1].parse().ok()?;
+    (count > 0).then_some(count * unit)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,126 @@
+use super::{AlertRecord, Candle, StorageError, SwapRecord, VolatilityPoint, Volume};
+use crate::config::RetentionConfig;
+use alloy::primitives::Address;
+use std::convert::Infallible;
+use tracing::error;
+
+// Storage of a build without the sqlite feature: opening fails, so there is never a
+// value to call the methods on
+#[derive(Clone)]
+pub struct Storage {
+    never: Infallible,
+}
+
+impl Storage {
+    pub fn open(path: &str) -> Result<Storage, StorageError> {
+        error!(
+            "Built without the sqlite feature, cannot open database {}",
+            path
+        );
+        Err(StorageError::NotBuilt(path.to_string()))
+    }
+
+    pub async fn record_alert(&self, _record: AlertRecord) -> Result<(), StorageError> {
+        match self.never {}
+    }
+
+    pub async fn record_swaps(&self, _records: Vec<SwapRecord>) -> Result<(), StorageError> {
+        match self.never {}
+    }
+
+    pub async fn delete_swaps(&self, _records: Vec<SwapRecord>) -> Result<u64, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn swaps(
+        &self,
+        _pools: Vec<Address>,
+        _from: u64,
+        _to: u64,
+    ) -> Result<Vec<SwapRecord>, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn swap_at_block(
+        &self,
+        _pool: Address,
+        _block: u64,
+    ) -> Result<Option<SwapRecord>, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn price_at(
+        &self,
+        _pool: Address,
+        _timestamp: u64,
+    ) -> Result<Option<f64>, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn volume(
+        &self,
+        _pool: Address,
+        _from: u64,
+        _to: u64,
+    ) -> Result<Volume, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn candles(
+        &self,
+        _pool: Address,
+        _interval: u64,
+        _from: u64,
+        _to: u64,
+    ) -> Result<Vec<Candle>, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn recent_alerts(
+        &self,
+        _pool: Option<Address>,
+        _limit: u32,
+    ) -> Result<Vec<AlertRecord>, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn record_external_prices(
+        &self,
+        _series: String,
+        _prices: Vec<(u64, f64)>,
+    ) -> Result<(), StorageError> {
+        match self.never {}
+    }
+
+    pub async fn external_prices(
+        &self,
+        _series: String,
+        _from: u64,
+        _to: u64,
+    ) -> Result<Vec<(u64, f64)>, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn update_volatility(
+        &self,
+        _pool: Address,
+        _from: u64,
+        _to: u64,
+    ) -> Result<usize, StorageError> {
+        match self.never {}
+    }
+
+    pub async fn volatility(
+        &self,
+        _pool: Address,
+        _interval: u64,
+        _from: u64,
+        _to: u64,
+    ) -> Result<Vec<VolatilityPoint>, StorageError> {
+        match self.never {}
+    }
+}
+
+pub async fn run_retention(storage: Storage, _config: RetentionConfig) {
+    match storage.never {}
+}
@@ -0,0 +1,79 @@
+use super::{Storage, StorageError};
+use rusqlite::params;
+use tracing::error;
+
+impl Storage {
+    // prices of an imported series as (unix timestamp, price), a price already stored
+    // for the same time is replaced
+    pub async fn record_external_prices(
+        &self,
+        series: String,
+        prices: Vec<(u64, f64)>,
+    ) -> Result<(), StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT OR REPLACE INTO external_prices (series, time, price) VALUES (?1, ?2, ?3)",
+                )?;
+                for (time, price) in &prices {
+                    stmt.execute(params![series, *time as i64, price])?;
+                }
+            }
+            tx.commit()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => {
+                error!("Failed to record external prices: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+            Err(e) => {
+                error!("External price writer panicked: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+        }
+    }
+
+    // prices of an imported series with timestamps in `from..to`, oldest first
+    pub async fn external_prices(
+        &self,
+        series: String,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<(u64, f64)>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT time, price FROM external_prices
+                 WHERE series = ?1 AND time >= ?2 AND time < ?3
+                 ORDER BY time",
+            )?;
+            let rows = stmt.query_map(
+                params![series, from as i64, to.min(i64::MAX as u64) as i64],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, f64>(1)?)),
+            )?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(prices)) => Ok(prices),
+            Ok(Err(e)) => {
+                error!("Failed to read external prices: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("External price reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+}
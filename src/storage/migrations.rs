@@ -0,0 +1,121 @@
+use rusqlite::{Connection, Transaction};
+use thiserror::Error;
+use tracing::{error, info};
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Database schema version {0} is newer than this build supports ({1})")]
+    UnknownVersion(u32, u32),
+
+    #[error("Failed to read the database schema version")]
+    VersionReadFailed,
+
+    #[error("Migration {0} ({1}) failed")]
+    MigrationFailed(u32, &'static str),
+}
+
+enum Step {
+    Sql(&'static str),
+    // for changes that depend on what the database already has
+    Code(fn(&Transaction) -> rusqlite::Result<()>),
+}
+
+struct Migration {
+    version: u32,
+    name: &'static str,
+    step: Step,
+}
+
+// in order, the version of the last one applied is kept in the database's user_version
+// new migrations are appended, applied ones are never changed
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        step: Step::Sql(include_str!("../../migrations/0001_initial.sql")),
+    },
+    Migration {
+        version: 2,
+        name: "volatility",
+        step: Step::Sql(include_str!("../../migrations/0002_volatility.sql")),
+    },
+    Migration {
+        version: 3,
+        name: "swap chain key",
+        step: Step::Code(add_swap_key),
+    },
+    Migration {
+        version: 4,
+        name: "external prices",
+        step: Step::Sql(include_str!("../../migrations/0004_external_prices.sql")),
+    },
+];
+
+// bring the schema up to date, each migration in a transaction of its own so a failed
+// one leaves the database at the previous version
+pub fn migrate(conn: &mut Connection) -> Result<(), MigrationError> {
+    let latest = MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+    let current: u32 = match conn.query_row("PRAGMA user_version", [], |row| row.get(0)) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed to read schema version: {}", e);
+            return Err(MigrationError::VersionReadFailed);
+        }
+    };
+    if current > latest {
+        error!(
+            "Database schema version {} is newer than {}, the tracker was downgraded",
+            current, latest
+        );
+        return Err(MigrationError::UnknownVersion(current, latest));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        info!(
+            "Migrating database to version {} ({})",
+            migration.version, migration.name
+        );
+        if let Err(e) = apply(conn, migration) {
+            error!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.name, e
+            );
+            return Err(MigrationError::MigrationFailed(
+                migration.version,
+                migration.name,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn apply(conn: &mut Connection, migration: &Migration) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    match migration.step {
+        Step::Sql(sql) => tx.execute_batch(sql)?,
+        Step::Code(step) => step(&tx)?,
+    }
+    tx.pragma_update(None, "user_version", migration.version)?;
+    tx.commit()
+}
+
+// swaps get a chain id, 0 for the rows already stored, and a unique key on chain,
+// transaction and log index, keeping the latest of rows that would break it
+// databases created just before migrations may have the column already
+fn add_swap_key(tx: &Transaction) -> rusqlite::Result<()> {
+    let has_chain_id: bool = tx.query_row(
+        "SELECT count(*) > 0 FROM pragma_table_info('swaps') WHERE name = 'chain_id'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_chain_id {
+        tx.execute_batch(
+            "ALTER TABLE swaps ADD COLUMN chain_id INTEGER NOT NULL DEFAULT 0;
+             DELETE FROM swaps WHERE tx IS NOT NULL AND rowid NOT IN
+                 (SELECT max(rowid) FROM swaps WHERE tx IS NOT NULL GROUP BY chain_id, tx, log_index);",
+        )?;
+    }
+    tx.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS swaps_chain_tx ON swaps (chain_id, tx, log_index);",
+    )
+}
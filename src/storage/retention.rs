@@ -0,0 +1,156 @@
+use super::{Candle, Storage, candle_from_row, merge_candles, unix_now};
+use crate::config::RetentionConfig;
+use rusqlite::{Connection, params};
+use std::time::Duration;
+use tracing::{error, info};
+
+const MINUTE: u64 = 60;
+const DAY: u64 = 86_400;
+
+// enforce the retention periods every `compact_interval_secs`
+pub async fn run_retention(storage: Storage, config: RetentionConfig) {
+    info!(
+        "Keeping swaps for {} and minute candles for {}",
+        days(config.swap_days),
+        days(config.minute_candle_days)
+    );
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(config.compact_interval_secs.max(60)));
+
+    loop {
+        interval.tick().await;
+
+        let now = unix_now();
+        // whole candles only, the rest stays until the next run
+        let swaps_before = config
+            .swap_days
+            .map(|d| now.saturating_sub(d * DAY) / MINUTE * MINUTE);
+        let minutes_before = config
+            .minute_candle_days
+            .map(|d| now.saturating_sub(d * DAY) / DAY * DAY);
+
+        let conn = storage.conn.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            compact(&mut conn.lock().unwrap(), swaps_before, minutes_before)
+        })
+        .await;
+
+        match result {
+            Ok(Ok((0, 0))) => {}
+            Ok(Ok((swaps, minutes))) => info!(
+                "Compacted {} swaps into minute candles and {} minute candles into daily ones",
+                swaps, minutes
+            ),
+            Ok(Err(e)) => error!("Failed to compact stored swaps: {}", e),
+            Err(e) => error!("Compaction panicked: {}", e),
+        }
+    }
+}
+
+fn days(period: Option<u64>) -> String {
+    match period {
+        Some(d) => format!("{} days", d),
+        None => "ever".to_string(),
+    }
+}
+
+// swaps before `swaps_before` become minute candles, minute candles before
+// `minutes_before` daily candles, in one transaction
+// returns the number of swaps and minute candles removed
+fn compact(
+    conn: &mut Connection,
+    swaps_before: Option<u64>,
+    minutes_before: Option<u64>,
+) -> rusqlite::Result<(usize, usize)> {
+    let tx = conn.transaction()?;
+    let mut removed = (0, 0);
+
+    if let Some(before) = swaps_before {
+        let pieces = read_pieces(
+            &tx,
+            "SELECT pool, timestamp, price, price, price, price, amount0, 1 FROM swaps
+             WHERE timestamp < ?1
+             ORDER BY pool, timestamp, block, log_index",
+            before,
+        )?;
+        write_candles(&tx, pieces, MINUTE)?;
+        removed.0 = tx.execute(
+            "DELETE FROM swaps WHERE timestamp < ?1",
+            params![before as i64],
+        )?;
+    }
+
+    if let Some(before) = minutes_before {
+        let pieces = read_pieces(
+            &tx,
+            "SELECT pool, time, open, high, low, close, volume0, swaps FROM candles
+             WHERE interval = 60 AND time < ?1
+             ORDER BY pool, time",
+            before,
+        )?;
+        write_candles(&tx, pieces, DAY)?;
+        removed.1 = tx.execute(
+            "DELETE FROM candles WHERE interval = 60 AND time < ?1",
+            params![before as i64],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(removed)
+}
+
+// time ordered candles per pool, the pool in the first column
+fn read_pieces(
+    conn: &Connection,
+    sql: &str,
+    before: u64,
+) -> rusqlite::Result<Vec<(String, Vec<Candle>)>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params![before as i64], |row| {
+        Ok((row.get::<_, String>(0)?, candle_from_row(row, 1)?))
+    })?;
+
+    let mut pools: Vec<(String, Vec<Candle>)> = Vec::new();
+    for row in rows {
+        let (pool, candle) = row?;
+        match pools.last_mut() {
+            Some((last, candles)) if *last == pool => candles.push(candle),
+            _ => pools.push((pool, vec![candle])),
+        }
+    }
+    Ok(pools)
+}
+
+// a candle that already exists, e.g. from swaps backfilled after a compaction, is
+// extended rather than replaced
+fn write_candles(
+    conn: &Connection,
+    pools: Vec<(String, Vec<Candle>)>,
+    interval: u64,
+) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO candles (pool, interval, time, open, high, low, close, volume0, swaps)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT (pool, interval, time) DO UPDATE SET
+             high = max(high, excluded.high),
+             low = min(low, excluded.low),
+             volume0 = volume0 + excluded.volume0,
+             swaps = swaps + excluded.swaps",
+    )?;
+    for (pool, pieces) in pools {
+        for c in merge_candles(pieces, interval) {
+            stmt.execute(params![
+                pool,
+                interval as i64,
+                c.time as i64,
+                c.open,
+                c.high,
+                c.low,
+                c.close,
+                c.volume0,
+                c.swaps as i64
+            ])?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use super::{DAY, Storage, StorageError, VOLATILITY_INTERVALS, VolatilityPoint};
+use alloy::primitives::Address;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::BTreeMap;
+use tracing::error;
+
+const YEAR: u64 = 365 * DAY;
+
+impl Storage {
+    // recompute the stored volatility series of a pool over every interval touching
+    // `from..to`, called after a backfill filled in its swaps
+    // returns the number of intervals written
+    pub async fn update_volatility(
+        &self,
+        pool: Address,
+        from: u64,
+        to: u64,
+    ) -> Result<usize, StorageError> {
+        let conn = self.conn.clone();
+
+        let result =
+            tokio::task::spawn_blocking(move || compute(&mut conn.lock().unwrap(), pool, from, to))
+                .await;
+
+        match result {
+            Ok(Ok(count)) => Ok(count),
+            Ok(Err(e)) => {
+                error!("Failed to compute volatility of pool {:?}: {}", pool, e);
+                Err(StorageError::WriteFailed)
+            }
+            Err(e) => {
+                error!("Volatility writer panicked: {}", e);
+                Err(StorageError::WriteFailed)
+            }
+        }
+    }
+
+    // stored volatility series of a pool in one of VOLATILITY_INTERVALS, intervals
+    // starting in `from..to`
+    pub async fn volatility(
+        &self,
+        pool: Address,
+        interval: u64,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<VolatilityPoint>, StorageError> {
+        let conn = self.conn.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT time, realized, returns FROM volatility
+                 WHERE pool = ?1 AND interval = ?2 AND time >= ?3 AND time < ?4
+                 ORDER BY time",
+            )?;
+            let scale = (YEAR as f64 / interval as f64).sqrt();
+            let rows = stmt.query_map(
+                params![
+                    format!("{:?}", pool),
+                    interval as i64,
+                    from as i64,
+                    to.min(i64::MAX as u64) as i64
+                ],
+                |row| {
+                    let realized: f64 = row.get(1)?;
+                    Ok(VolatilityPoint {
+                        time: row.get::<_, i64>(0)? as u64,
+                        realized,
+                        annualized: realized * scale,
+                        returns: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(points)) => Ok(points),
+            Ok(Err(e)) => {
+                error!("Failed to read volatility: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+            Err(e) => {
+                error!("Volatility reader panicked: {}", e);
+                Err(StorageError::ReadFailed)
+            }
+        }
+    }
+}
+
+// whole days around `from..to` are recomputed, they contain the hours as well
+fn compute(conn: &mut Connection, pool: Address, from: u64, to: u64) -> rusqlite::Result<usize> {
+    let pool = format!("{:?}", pool);
+    let start = from / DAY * DAY;
+    let end = to.div_ceil(DAY).saturating_mul(DAY).min(i64::MAX as u64);
+
+    // the last price before the range, for the return of its first swap
+    let previous: Option<f64> = conn
+        .query_row(
+            "SELECT price FROM swaps WHERE pool = ?1 AND timestamp < ?2
+             ORDER BY timestamp DESC, block DESC, log_index DESC LIMIT 1",
+            params![pool, start as i64],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let mut returns = Vec::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, price FROM swaps
+             WHERE pool = ?1 AND timestamp >= ?2 AND timestamp < ?3
+             ORDER BY timestamp, block, log_index",
+        )?;
+        let rows = stmt.query_map(params![pool, start as i64, end as i64], |row| {
+            Ok((row.get::<_, i64>(0)? as u64, row.get::<_, f64>(1)?))
+        })?;
+        let mut last = previous;
+        for row in rows {
+            let (timestamp, price) = row?;
+            if let Some(prev) = last
+                && prev > 0.0
+                && price > 0.0
+            {
+                returns.push((timestamp, (price / prev).ln()));
+            }
+            last = Some(price);
+        }
+    }
+
+    let tx = conn.transaction()?;
+    let mut written = 0;
+    for interval in VOLATILITY_INTERVALS {
+        // sum of squared returns and their count per interval start
+        let mut buckets: BTreeMap<u64, (f64, u64)> = BTreeMap::new();
+        for (timestamp, r) in &returns {
+            let bucket = buckets.entry(timestamp / interval * interval).or_default();
+            bucket.0 += r * r;
+            bucket.1 += 1;
+        }
+
+        tx.execute(
+            "DELETE FROM volatility WHERE pool = ?1 AND interval = ?2 AND time >= ?3 AND time < ?4",
+            params![pool, interval as i64, start as i64, end as i64],
+        )?;
+        let mut stmt = tx.prepare(
+            "INSERT INTO volatility (pool, interval, time, realized, returns)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (time, (sum, count)) in buckets {
+            stmt.execute(params![
+                pool,
+                interval as i64,
+                time as i64,
+                sum.sqrt(),
+                count as i64
+            ])?;
+            written += 1;
+        }
+    }
+    tx.commit()?;
+    Ok(written)
+}
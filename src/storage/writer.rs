@@ -0,0 +1,352 @@
+use super::{Storage, SwapRecord};
+use crate::config::{OverflowPolicy, WriterConfig};
+use crate::metrics;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+// overflows between two warnings
+const OVERFLOW_LOG_EVERY: u64 = 1_000;
+
+// handle the pool listeners queue their swaps through, a dedicated task inserts them
+// so a slow database does not hold up event processing
+#[derive(Clone)]
+pub struct SwapWriter {
+    queue: Arc<Queue>,
+}
+
+struct Queue {
+    // the spill file is only touched with this lock held
+    records: Mutex<VecDeque<SwapRecord>>,
+    // swaps whose logs a reorg removed after they left the queue
+    removals: Mutex<Vec<SwapRecord>>,
+    config: WriterConfig,
+    // a swap was queued
+    pushed: Notify,
+    // the writer took swaps off the queue
+    drained: Notify,
+    // the writer finished a batch
+    written: Notify,
+    overflows: AtomicU64,
+    // set by close, swaps pushed afterwards are dropped
+    closed: AtomicBool,
+    // swaps of the batch being inserted
+    in_flight: AtomicUsize,
+    // swaps inserted, spilled by the writer, and lost with rejected batches
+    inserted: AtomicU64,
+    spilled: AtomicU64,
+    lost: AtomicU64,
+}
+
+// what became of the swaps queued when the writer was closed
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    pub flushed: u64,
+    pub spilled: u64,
+    // rejected by the database, still queued at the deadline, or pushed after closing
+    pub dropped: u64,
+}
+
+impl SwapWriter {
+    // spawn the writer task, swaps spilled before a restart are inserted first
+    pub fn start(storage: Storage, config: WriterConfig) -> Self {
+        info!(
+            "Queueing up to {} swaps for the database, {:?} when full",
+            config.queue_size, config.overflow
+        );
+        let queue = Arc::new(Queue {
+            records: Mutex::new(VecDeque::with_capacity(config.queue_size)),
+            removals: Mutex::new(Vec::new()),
+            config,
+            pushed: Notify::new(),
+            drained: Notify::new(),
+            written: Notify::new(),
+            overflows: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            in_flight: AtomicUsize::new(0),
+            inserted: AtomicU64::new(0),
+            spilled: AtomicU64::new(0),
+            lost: AtomicU64::new(0),
+        });
+        tokio::spawn(run_writer(storage, queue.clone()));
+        SwapWriter { queue }
+    }
+
+    // queue a swap, waits for room only under the block policy
+    pub async fn push(&self, record: SwapRecord) {
+        let queue = &self.queue;
+        if queue.closed.load(Ordering::Relaxed) {
+            queue.lost.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let mut record = Some(record);
+        while let Some(r) = record.take() {
+            // registered before checking, a drain in between is not missed
+            let drained = queue.drained.notified();
+            {
+                let mut records = queue.records.lock().unwrap();
+                if records.len() < queue.config.queue_size.max(1) {
+                    records.push_back(r);
+                    metrics::WRITE_QUEUE.store(records.len() as u64, Ordering::Relaxed);
+                    queue.pushed.notify_one();
+                    return;
+                }
+                match queue.config.overflow {
+                    OverflowPolicy::Block => record = Some(r),
+                    OverflowPolicy::DropOldest => {
+                        records.pop_front();
+                        records.push_back(r);
+                        metrics::WRITE_QUEUE.store(records.len() as u64, Ordering::Relaxed);
+                        queue.pushed.notify_one();
+                        queue.overflowed("dropped the oldest queued swap");
+                        return;
+                    }
+                    OverflowPolicy::Spill => {
+                        if let Err(e) = append_spill(&queue.config.spill_path, &[r]) {
+                            error!(
+                                "Failed to spill a swap to {}: {}",
+                                queue.config.spill_path, e
+                            );
+                        }
+                        queue.overflowed("spilled a swap to disk");
+                        return;
+                    }
+                }
+            }
+            queue.overflowed("waiting for the database");
+            drained.await;
+        }
+    }
+
+    // take back a swap whose log a reorg removed: it is dropped while still queued and
+    // deleted from the database once inserted
+    pub fn remove(&self, record: SwapRecord) {
+        let queue = &self.queue;
+        {
+            let mut records = queue.records.lock().unwrap();
+            if let Some(i) = records.iter().position(|r| same_swap(r, &record)) {
+                records.remove(i);
+                metrics::WRITE_QUEUE.store(records.len() as u64, Ordering::Relaxed);
+                return;
+            }
+        }
+        queue.removals.lock().unwrap().push(record);
+        queue.pushed.notify_one();
+    }
+
+    // stop taking swaps and give the writer until `timeout` to insert the queued ones,
+    // swaps still queued then are spilled under the spill policy and dropped otherwise
+    pub async fn close(&self, timeout: Duration) -> FlushReport {
+        let queue = &self.queue;
+        queue.closed.store(true, Ordering::Relaxed);
+        let inserted = queue.inserted.load(Ordering::Relaxed);
+        let spilled = queue.spilled.load(Ordering::Relaxed);
+        let lost = queue.lost.load(Ordering::Relaxed);
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            // registered before checking, a batch finishing in between is not missed
+            let written = queue.written.notified();
+            let idle = queue.records.lock().unwrap().is_empty()
+                && queue.in_flight.load(Ordering::Relaxed) == 0;
+            if idle || tokio::time::timeout_at(deadline, written).await.is_err() {
+                break;
+            }
+        }
+
+        let left: Vec<SwapRecord> = {
+            let mut records = queue.records.lock().unwrap();
+            let left = records.drain(..).collect();
+            metrics::WRITE_QUEUE.store(0, Ordering::Relaxed);
+            left
+        };
+        if !left.is_empty() {
+            if queue.config.overflow == OverflowPolicy::Spill {
+                queue.spill(&left);
+            } else {
+                queue.lost.fetch_add(left.len() as u64, Ordering::Relaxed);
+            }
+        }
+        FlushReport {
+            flushed: queue.inserted.load(Ordering::Relaxed) - inserted,
+            spilled: queue.spilled.load(Ordering::Relaxed) - spilled,
+            // a batch still being inserted may land after all, it is not counted as such
+            dropped: queue.lost.load(Ordering::Relaxed) - lost
+                + queue.in_flight.load(Ordering::Relaxed) as u64,
+        }
+    }
+}
+
+impl Queue {
+    fn overflowed(&self, action: &str) {
+        let count = self.overflows.fetch_add(1, Ordering::Relaxed) + 1;
+        if count % OVERFLOW_LOG_EVERY == 1 {
+            warn!(
+                "Swap queue full ({} swaps), {} ({} overflows so far)",
+                self.config.queue_size, action, count
+            );
+        }
+    }
+
+    // up to a batch of queued swaps
+    fn take_batch(&self) -> Vec<SwapRecord> {
+        let mut records = self.records.lock().unwrap();
+        let count = records.len().min(self.config.batch_size.max(1));
+        let batch: Vec<SwapRecord> = records.drain(..count).collect();
+        // under the lock, close never sees the batch neither queued nor in flight
+        self.in_flight.store(batch.len(), Ordering::Relaxed);
+        metrics::WRITE_QUEUE.store(records.len() as u64, Ordering::Relaxed);
+        if !batch.is_empty() {
+            self.drained.notify_waiters();
+        }
+        batch
+    }
+
+    // everything spilled so far, the file is moved aside first so new spills go to a
+    // fresh one and a crash while inserting keeps the swaps for the next start
+    fn take_spilled(&self) -> Vec<SwapRecord> {
+        let loading = format!("{}.loading", self.config.spill_path);
+        {
+            let _records = self.records.lock().unwrap();
+            if !std::path::Path::new(&loading).exists()
+                && let Err(e) = std::fs::rename(&self.config.spill_path, &loading)
+            {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    error!(
+                        "Failed to read spilled swaps {}: {}",
+                        self.config.spill_path, e
+                    );
+                }
+                return Vec::new();
+            }
+        }
+
+        let file = match std::fs::File::open(&loading) {
+            Ok(f) => f,
+            Err(e) => {
+                error!("Failed to read spilled swaps {}: {}", loading, e);
+                return Vec::new();
+            }
+        };
+        let mut spilled = Vec::new();
+        for line in BufReader::new(file).lines() {
+            match line.map(|l| serde_json::from_str::<SwapRecord>(&l)) {
+                Ok(Ok(record)) => spilled.push(record),
+                Ok(Err(e)) => warn!("Skipping malformed spilled swap: {}", e),
+                Err(e) => {
+                    error!("Failed to read spilled swaps {}: {}", loading, e);
+                    break;
+                }
+            }
+        }
+        if spilled.is_empty() {
+            let _ = std::fs::remove_file(&loading);
+        }
+        spilled
+    }
+
+    fn spill(&self, records: &[SwapRecord]) {
+        let _records = self.records.lock().unwrap();
+        match append_spill(&self.config.spill_path, records) {
+            Ok(()) => {
+                self.spilled
+                    .fetch_add(records.len() as u64, Ordering::Relaxed);
+            }
+            Err(e) => {
+                error!(
+                    "Failed to spill {} swaps to {}: {}",
+                    records.len(),
+                    self.config.spill_path,
+                    e
+                );
+                self.lost.fetch_add(records.len() as u64, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// the identity record_swaps stores a swap under
+fn same_swap(a: &SwapRecord, b: &SwapRecord) -> bool {
+    a.chain_id == b.chain_id
+        && a.log_index == b.log_index
+        && match (a.tx, b.tx) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.pool == b.pool && a.block == b.block,
+        }
+}
+
+fn append_spill(path: &str, records: &[SwapRecord]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut lines = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut lines, record)?;
+        lines.push(b'\n');
+    }
+    file.write_all(&lines)
+}
+
+// insert queued swaps a batch at a time, and spilled ones whenever the queue is empty
+async fn run_writer(storage: Storage, queue: Arc<Queue>) {
+    let spill = queue.config.overflow == OverflowPolicy::Spill;
+    // left over from a previous run, whatever the policy is now
+    let mut check_spill = true;
+
+    loop {
+        // after the batch they were part of, so they are never inserted again
+        let removals = std::mem::take(&mut *queue.removals.lock().unwrap());
+        if !removals.is_empty()
+            && let Ok(deleted) = storage.delete_swaps(removals).await
+        {
+            info!("Deleted {} stored swaps removed by a reorg", deleted);
+        }
+
+        let batch = queue.take_batch();
+        if batch.is_empty() {
+            if check_spill {
+                check_spill = false;
+                let spilled = queue.take_spilled();
+                if !spilled.is_empty() {
+                    info!("Inserting {} spilled swaps", spilled.len());
+                    let mut ok = true;
+                    for chunk in spilled.chunks(queue.config.batch_size.max(1)) {
+                        ok &= storage.record_swaps(chunk.to_vec()).await.is_ok();
+                    }
+                    let loading = format!("{}.loading", queue.config.spill_path);
+                    if ok && let Err(e) = std::fs::remove_file(&loading) {
+                        error!("Failed to remove {}: {}", loading, e);
+                    }
+                    continue;
+                }
+            }
+            queue.pushed.notified().await;
+            continue;
+        }
+
+        // failures are logged by the storage, under the spill policy the batch is
+        // kept on disk for a later attempt
+        let started = Instant::now();
+        let result = storage.record_swaps(batch.clone()).await;
+        metrics::STORAGE_SINK.observe(started.elapsed());
+        match result {
+            Ok(()) => {
+                queue
+                    .inserted
+                    .fetch_add(batch.len() as u64, Ordering::Relaxed);
+            }
+            Err(_) if spill => queue.spill(&batch),
+            Err(_) => {
+                queue.lost.fetch_add(batch.len() as u64, Ordering::Relaxed);
+            }
+        }
+        queue.in_flight.store(0, Ordering::Relaxed);
+        queue.written.notify_waiters();
+        check_spill |= spill;
+    }
+}
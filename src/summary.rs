@@ -0,0 +1,175 @@
+use crate::chain::ChainPreset;
+use crate::config::{Config, PoolEntry, Protocol};
+use crate::pool::parse_pair_spec;
+use alloy::primitives::Address;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::info;
+
+// what the tracker runs with once the environment, the config file and overrides are
+// resolved, logged at startup and served by /status
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSummary {
+    pub chain: &'static str,
+    pub chain_id: u64,
+    // endpoints without path and credentials, which often carry API keys
+    pub rpc_endpoints: Vec<String>,
+    pub pools: Vec<PoolSummary>,
+    // [[pairs]], [[subgraph]] and [[discovery]] rules that add pools while running
+    pub pool_rules: BTreeMap<&'static str, usize>,
+    // where prices, swaps and reports go
+    pub sinks: Vec<String>,
+    // configured alert rules by kind, and the backends alerts are sent to
+    pub alert_rules: BTreeMap<&'static str, usize>,
+    pub alert_channels: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolSummary {
+    pub address: Option<Address>,
+    pub protocol: Protocol,
+    // the pair spec the pool was configured by, e.g. WETH/USDC@0.05%
+    pub label: Option<String>,
+    // hundredths of a bip, known up front for pair specs and pools with a `fee`, the
+    // listeners in /status report the on-chain one
+    pub fee: Option<u32>,
+    pub rpc_endpoint: String,
+}
+
+impl ConfigSummary {
+    pub fn new(
+        chain: &ChainPreset,
+        rpc_url: &str,
+        fallback_urls: &[String],
+        pools: &[PoolEntry],
+        config: &Config,
+        sinks: Vec<String>,
+        alert_channels: Vec<&'static str>,
+    ) -> Self {
+        let pools: Vec<PoolSummary> = pools
+            .iter()
+            .map(|entry| PoolSummary {
+                address: entry.address,
+                protocol: entry.protocol,
+                label: entry.pair.clone(),
+                fee: entry.fee.or_else(|| {
+                    entry
+                        .pair
+                        .as_deref()
+                        .and_then(parse_pair_spec)
+                        .map(|(_, _, fee)| fee)
+                }),
+                rpc_endpoint: redact(entry.rpc_url.as_deref().unwrap_or(rpc_url)),
+            })
+            .collect();
+        let mut rpc_endpoints = vec![redact(rpc_url)];
+        let fallbacks = fallback_urls.iter().map(|url| redact(url));
+        for endpoint in fallbacks.chain(pools.iter().map(|p| p.rpc_endpoint.clone())) {
+            if !rpc_endpoints.contains(&endpoint) {
+                rpc_endpoints.push(endpoint);
+            }
+        }
+
+        let pool_rules = counts([
+            ("pairs", config.pairs.len()),
+            ("subgraph", config.subgraph.len()),
+            ("discovery", config.discovery.len()),
+        ]);
+        let alert_rules = counts([
+            ("price_bands", config.price_bands.len()),
+            ("price_change", config.price_change.len()),
+            ("volatility", config.volatility.len()),
+            ("liquidity_drop", config.liquidity_drop.len()),
+            ("large_mint", config.large_mint.len()),
+            ("tick_cross", config.tick_cross.len()),
+            ("stale", config.stale.len()),
+            ("deviation", config.deviation.len()),
+            ("basis", config.basis.len()),
+            ("arbitrage", config.arbitrage.len()),
+            ("triangular", usize::from(config.triangular.is_some())),
+        ]);
+
+        ConfigSummary {
+            chain: chain.name,
+            chain_id: chain.chain_id,
+            rpc_endpoints,
+            pools,
+            pool_rules,
+            sinks,
+            alert_rules,
+            alert_channels,
+        }
+    }
+
+    // one line per setting, so operators can see what the process actually does
+    pub fn log(&self) {
+        info!("Chain: {} ({})", self.chain, self.chain_id);
+        info!("RPC endpoints: {}", self.rpc_endpoints.join(", "));
+        for pool in &self.pools {
+            let address = pool
+                .address
+                .map_or_else(|| "-".to_string(), |a| a.to_string());
+            let fee = pool
+                .fee
+                .map(|f| format!(" {}%", f as f64 / 10_000.0))
+                .unwrap_or_default();
+            info!(
+                "Pool {} {:?}{}{} via {}",
+                address,
+                pool.protocol,
+                pool.label
+                    .as_ref()
+                    .map(|l| format!(" {}", l))
+                    .unwrap_or_default(),
+                fee,
+                pool.rpc_endpoint
+            );
+        }
+        if !self.pool_rules.is_empty() {
+            info!("Pool rules: {}", join_counts(&self.pool_rules));
+        }
+        info!("Sinks: {}", self.sinks.join(", "));
+        info!(
+            "Alert rules: {}",
+            match self.alert_rules.is_empty() {
+                true => "none".to_string(),
+                false => join_counts(&self.alert_rules),
+            }
+        );
+        info!(
+            "Alert channels: {}",
+            match self.alert_channels.is_empty() {
+                true => "none".to_string(),
+                false => self.alert_channels.join(", "),
+            }
+        );
+    }
+}
+
+// kinds without rules are left out
+fn counts<const N: usize>(kinds: [(&'static str, usize); N]) -> BTreeMap<&'static str, usize> {
+    kinds.into_iter().filter(|(_, n)| *n > 0).collect()
+}
+
+fn join_counts(counts: &BTreeMap<&'static str, usize>) -> String {
+    counts
+        .iter()
+        .map(|(kind, n)| format!("{} {}", kind, n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// scheme and host of an endpoint, keys in the path, query or user info are cut off,
+// e.g. wss://eth-mainnet.g.alchemy.com/v2/<key> becomes wss://eth-mainnet.g.alchemy.com/…
+pub fn redact(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return "…".to_string();
+    };
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let cut = end < rest.len() || host.len() < authority.len();
+    format!("{}://{}{}", scheme, host, if cut { "/…" } else { "" })
+}
@@ -0,0 +1,67 @@
+use std::env;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// send a state like READY=1 to the service manager, nothing happens outside a
+// Type=notify unit
+pub fn notify(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let socket = match UnixDatagram::unbound() {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Failed to create the systemd notify socket: {}", e);
+            return;
+        }
+    };
+    let result = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => send_abstract(&socket, name, state),
+        None => socket.send_to(state.as_bytes(), &path).map(|_| ()),
+    };
+    if let Err(e) = result {
+        warn!("Failed to notify systemd of {}: {}", state, e);
+    }
+}
+
+// sockets starting with @ live in the abstract namespace, which only Linux has
+#[cfg(target_os = "linux")]
+fn send_abstract(socket: &UnixDatagram, name: &[u8], state: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+    socket.send_to_addr(state.as_bytes(), &addr).map(|_| ())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_abstract(_socket: &UnixDatagram, _name: &[u8], _state: &str) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+// ping the watchdog at half of WatchdogSec from a task, so systemd restarts the
+// tracker once the runtime stops scheduling tasks
+pub fn spawn_watchdog() {
+    let Some(timeout) = watchdog_timeout() else {
+        return;
+    };
+    info!("Pinging the systemd watchdog every {:?}", timeout / 2);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(timeout / 2);
+        loop {
+            ticker.tick().await;
+            notify("WATCHDOG=1");
+        }
+    });
+}
+
+fn watchdog_timeout() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    // set when the watchdog is meant for another process of the unit
+    if let Ok(pid) = env::var("WATCHDOG_PID")
+        && pid.parse::<u32>().ok() != Some(std::process::id())
+    {
+        return None;
+    }
+    Some(Duration::from_micros(usec))
+}
@@ -0,0 +1,320 @@
+use crate::alert::Alerter;
+use crate::state::{PoolSnapshot, SharedState};
+use crate::storage::unix_now;
+use alloy::primitives::Address;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Error)]
+pub enum TelegramError {
+    #[error("Failed to call Telegram method {0}")]
+    RequestFailed(&'static str),
+}
+
+// minimal Telegram Bot API client: https://core.telegram.org/bots/api
+#[derive(Clone)]
+pub struct TelegramClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdatesResponse {
+    ok: bool,
+    #[serde(default)]
+    result: Vec<Update>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+impl TelegramClient {
+    pub fn new(client: reqwest::Client, token: &str) -> Self {
+        TelegramClient {
+            client,
+            base_url: format!("https://api.telegram.org/bot{}", token),
+        }
+    }
+
+    pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<(), TelegramError> {
+        let body = json!({
+            "chat_id": chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        });
+
+        let res = match self
+            .client
+            .post(format!("{}/sendMessage", self.base_url))
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to reach Telegram: {}", e);
+                return Err(TelegramError::RequestFailed("sendMessage"));
+            }
+        };
+
+        if !res.status().is_success() {
+            error!("Telegram rejected message with status {}", res.status());
+            return Err(TelegramError::RequestFailed("sendMessage"));
+        }
+
+        Ok(())
+    }
+
+    // long poll for new updates after `offset`
+    async fn get_updates(&self, offset: i64) -> Result<Vec<Update>, TelegramError> {
+        let res = match self
+            .client
+            .get(format!("{}/getUpdates", self.base_url))
+            .query(&[("offset", offset), ("timeout", 30)])
+            .timeout(Duration::from_secs(40))
+            .send()
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Failed to poll Telegram updates: {}", e);
+                return Err(TelegramError::RequestFailed("getUpdates"));
+            }
+        };
+
+        match res.json::<UpdatesResponse>().await {
+            Ok(updates) if updates.ok => Ok(updates.result),
+            Ok(_) => Err(TelegramError::RequestFailed("getUpdates")),
+            Err(e) => {
+                error!("Failed to decode Telegram updates: {}", e);
+                Err(TelegramError::RequestFailed("getUpdates"))
+            }
+        }
+    }
+}
+
+// answer bot commands with the tracker's live state and acknowledge alerts
+// when `allowed_chat` is set, messages from any other chat are ignored,
+// without it anyone can talk to the bot so alert commands are refused
+pub async fn run_bot(
+    telegram: TelegramClient,
+    state: SharedState,
+    alerter: Arc<Alerter>,
+    allowed_chat: Option<String>,
+) {
+    info!("Telegram bot is answering commands");
+
+    let mut offset = 0;
+    loop {
+        let updates = match telegram.get_updates(offset).await {
+            Ok(u) => u,
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = update.update_id + 1;
+
+            let Some(message) = update.message else {
+                continue;
+            };
+            let Some(text) = message.text else {
+                continue;
+            };
+            let chat_id = message.chat.id.to_string();
+            if allowed_chat.as_ref().is_some_and(|c| *c != chat_id) {
+                warn!("Ignoring Telegram command from chat {}", chat_id);
+                continue;
+            }
+
+            let reply = match answer_alerts(&text, &alerter, &chat_id, allowed_chat.is_some()).await
+            {
+                Some(r) => r,
+                None => match answer(&text, &state) {
+                    Some(r) => r,
+                    None => continue,
+                },
+            };
+            if let Err(e) = telegram.send_message(&chat_id, &reply).await {
+                error!("Failed to answer Telegram command: {}", e);
+            }
+        }
+    }
+}
+
+// /ack and /unacked, the alerter's state is async so these are handled apart from answer()
+// `restricted` is false when any chat can send commands
+async fn answer_alerts(
+    text: &str,
+    alerter: &Alerter,
+    chat_id: &str,
+    restricted: bool,
+) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    let command = parts.next()?.split('@').next()?;
+    let arg = parts.next();
+
+    if !restricted && matches!(command, "/ack" | "/unacked") {
+        warn!(
+            "Refusing {} from chat {} without TELEGRAM_CHAT_ID",
+            command, chat_id
+        );
+        return Some("Set TELEGRAM_CHAT_ID to use /ack and /unacked".to_string());
+    }
+
+    let reply = match (command, arg) {
+        ("/ack", Some(key)) => {
+            if alerter
+                .ack(key, &format!("telegram chat {}", chat_id))
+                .await
+            {
+                format!("Acknowledged {}", key)
+            } else {
+                format!("No unacknowledged alert {}", key)
+            }
+        }
+        ("/ack", None) => "Usage: /ack <alert key>, see /unacked".to_string(),
+        ("/unacked", _) => {
+            let pending = alerter.unacknowledged();
+            if pending.is_empty() {
+                "No unacknowledged alerts".to_string()
+            } else {
+                pending
+                    .iter()
+                    .map(|a| {
+                        format!(
+                            "{} ({}m{}): {}",
+                            a.key,
+                            a.age_secs / 60,
+                            if a.escalated { ", escalated" } else { "" },
+                            a.summary
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        _ => return None,
+    };
+
+    Some(reply)
+}
+
+fn answer(text: &str, state: &SharedState) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    // commands may be addressed to the bot as /price@my_bot
+    let command = parts.next()?.split('@').next()?;
+    let arg = parts.next();
+
+    let state = state.read().unwrap();
+    let mut pools: Vec<&PoolSnapshot> = state.pools.values().collect();
+    pools.sort_by_key(|p| (p.pair(), p.fee));
+
+    let reply = match (command, arg) {
+        ("/start" | "/help", _) => "Commands:\n\
+             /pools - tracked pools with their last price\n\
+             /price <TOKEN0/TOKEN1> - price of a pair in every tracked pool\n\
+             /stats <pool address or pair> - details of a pool\n\
+             /unacked - alerts waiting for an acknowledgement\n\
+             /ack <alert key> - stop re-notifying and escalating an alert"
+            .to_string(),
+        ("/pools", _) => {
+            if pools.is_empty() {
+                "No swaps seen yet".to_string()
+            } else {
+                pools
+                    .iter()
+                    .map(|p| {
+                        format!(
+                            "{} {} {:?}: {}",
+                            p.pair(),
+                            fee_label(p.fee),
+                            p.pool,
+                            p.price
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        ("/price", Some(pair)) => {
+            let (base, quote) = pair.split_once('/')?;
+            let lines: Vec<String> = pools
+                .iter()
+                .filter_map(|p| {
+                    let price = if p.symbol0.eq_ignore_ascii_case(base)
+                        && p.symbol1.eq_ignore_ascii_case(quote)
+                    {
+                        p.price
+                    } else if p.symbol1.eq_ignore_ascii_case(base)
+                        && p.symbol0.eq_ignore_ascii_case(quote)
+                    {
+                        1.0 / p.price
+                    } else {
+                        return None;
+                    };
+                    Some(format!(
+                        "1 {} = {} {} ({})",
+                        base,
+                        price,
+                        quote,
+                        fee_label(p.fee)
+                    ))
+                })
+                .collect();
+            if lines.is_empty() {
+                format!("No tracked pool for {}", pair)
+            } else {
+                lines.join("\n")
+            }
+        }
+        ("/stats", Some(target)) => {
+            let pool = match target.parse::<Address>() {
+                Ok(addr) => pools.iter().find(|p| p.pool == addr),
+                Err(_) => pools.iter().find(|p| p.pair().eq_ignore_ascii_case(target)),
+            };
+            match pool {
+                Some(p) => format!(
+                    "{} {}\npool: {:?}\nprice: {}\ninverse: {}\nliquidity: {}\nswaps: {}\nlast swap: {}s ago",
+                    p.pair(),
+                    fee_label(p.fee),
+                    p.pool,
+                    p.price,
+                    1.0 / p.price,
+                    p.liquidity,
+                    p.swaps,
+                    unix_now().saturating_sub(p.updated_at)
+                ),
+                None => format!("No tracked pool matches {}", target),
+            }
+        }
+        ("/price" | "/stats", None) => format!("Usage: {} <argument>, see /help", command),
+        _ => return None,
+    };
+
+    Some(reply)
+}
+
+fn fee_label(fee: u32) -> String {
+    format!("{}%", fee as f64 / 10_000.0)
+}
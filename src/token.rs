@@ -1,17 +1,18 @@
+mod proxy;
+
+use crate::tokenlist::TokenList;
 use alloy::primitives::Address;
 use alloy::providers::Provider;
-use alloy_sol_types::sol;
+use alloy::rpc::types::TransactionRequest;
+use alloy_sol_types::{SolCall, sol};
 use eyre::Result;
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, info, warn};
 
 #[derive(Debug, Error)]
 pub enum TokenInfoError {
-    #[error("Failed to fetch token decimal")]
-    TokenDecimalFetchFailed,
-
-    #[error("Failed to fetch token symbol")]
-    TokenSymbolFetchFailed,
+    #[error("Failed to fetch decimals of token {0}, set them in a [[tokens]] entry")]
+    TokenDecimalFetchFailed(Address),
 }
 
 sol! {
@@ -22,24 +23,144 @@ sol! {
     }
 }
 
+// tokens claiming more decimals than this return garbage, 10^decimals would not fit
+// the price math
+const MAX_DECIMALS: u8 = 36;
+
+pub use proxy::ProxyWatcher;
+
+pub struct TokenInfo {
+    pub decimals: u8,
+    pub symbol: String,
+    pub logo_uri: Option<String>,
+    // implementation contract when the token is an upgradeable proxy
+    pub implementation: Option<Address>,
+}
+
 //call token contract, return token's decimal and symbol
-pub async fn load_token_info(token: Address, provider: impl Provider) -> Result<(u8, String)> {
-    let contract = IERC20::new(token, &provider);
+// [[tokens]] overrides from the config win over the chain, the token list fills in when
+// the token reverts or returns garbage, and provides the logo
+pub async fn load_token_info(
+    token: Address,
+    provider: impl Provider,
+    tokens: &TokenList,
+) -> Result<TokenInfo> {
+    // Uniswap V4 represents the chain's native currency as address(0)
+    if token == Address::ZERO {
+        return Ok(TokenInfo {
+            decimals: 18,
+            symbol: "ETH".to_string(),
+            logo_uri: None,
+            implementation: None,
+        });
+    }
 
-    let decimals = match contract.decimals().call().await {
-        Ok(dec) => dec,
+    let listed = match provider.get_chain_id().await {
+        Ok(chain_id) => tokens.token(token, chain_id),
         Err(e) => {
-            error!("Failed to fetch token decimal {}: {}", token, e);
-            return Err(TokenInfoError::TokenDecimalFetchFailed.into());
+            error!("Failed to fetch chain id: {}", e);
+            None
         }
     };
-    let symbol = match contract.symbol().call().await {
-        Ok(sym) => sym,
+
+    let overridden = tokens.override_for(token);
+    let contract = IERC20::new(token, &provider);
+
+    let decimals = match overridden.and_then(|o| o.decimals) {
+        Some(dec) => dec,
+        None => match (contract.decimals().call().await, listed) {
+            (Ok(dec), _) if dec <= MAX_DECIMALS => dec,
+            (Ok(dec), Some(listed)) => {
+                warn!(
+                    "Token {} reports {} decimals, using {} from the token list",
+                    token, dec, listed.decimals
+                );
+                listed.decimals
+            }
+            (Err(e), Some(listed)) => {
+                warn!(
+                    "Failed to fetch token decimal {}: {}, using {} from the token list",
+                    token, e, listed.decimals
+                );
+                listed.decimals
+            }
+            // prices cannot be scaled without decimals, there is no sane default
+            (Ok(dec), None) => {
+                error!("Token {} reports {} decimals", token, dec);
+                return Err(TokenInfoError::TokenDecimalFetchFailed(token).into());
+            }
+            (Err(e), None) => {
+                error!("Failed to fetch token decimal {}: {}", token, e);
+                return Err(TokenInfoError::TokenDecimalFetchFailed(token).into());
+            }
+        },
+    };
+    let symbol = match overridden.and_then(|o| o.symbol.clone()) {
+        Some(sym) => sym,
+        None => match (fetch_symbol(token, &provider).await, listed) {
+            (Some(sym), _) if is_printable_symbol(&sym) => sym,
+            (_, Some(listed)) => {
+                warn!(
+                    "Token {} has no usable symbol, using {} from the token list",
+                    token, listed.symbol
+                );
+                listed.symbol.clone()
+            }
+            // the symbol is only a label, the start of the address will do
+            (sym, None) => {
+                let label = token.to_string()[..8].to_string();
+                warn!(
+                    "Token {} returned symbol {:?}, showing it as {}",
+                    token, sym, label
+                );
+                label
+            }
+        },
+    };
+
+    let implementation = proxy::implementation(token, &provider).await;
+    if let Some(implementation) = implementation {
+        info!(
+            "Token {} ({}) is a proxy, implementation {:?}",
+            symbol, token, implementation
+        );
+    }
+
+    Ok(TokenInfo {
+        decimals,
+        symbol,
+        logo_uri: listed.and_then(|t| t.logo_uri.clone()),
+        implementation,
+    })
+}
+
+// symbol() as a string, or as bytes32 for tokens like MKR that predate the
+// ERC-20 metadata standard
+async fn fetch_symbol(token: Address, provider: impl Provider) -> Option<String> {
+    let tx = TransactionRequest::default()
+        .to(token)
+        .input(IERC20::symbolCall::SELECTOR.to_vec().into());
+    let out = match provider.call(tx).await {
+        Ok(out) => out,
         Err(e) => {
             error!("Failed to fetch token symbol {}: {}", token, e);
-            return Err(TokenInfoError::TokenSymbolFetchFailed.into());
+            return None;
         }
     };
 
-    Ok((decimals, symbol))
+    if let Ok(symbol) = IERC20::symbolCall::abi_decode_returns(&out) {
+        return Some(symbol);
+    }
+    if out.len() == 32 {
+        let end = out.iter().position(|b| *b == 0).unwrap_or(32);
+        return String::from_utf8(out[..end].to_vec()).ok();
+    }
+    None
+}
+
+// empty, overlong or control character symbols would break alerts and pair names
+fn is_printable_symbol(symbol: &str) -> bool {
+    !symbol.trim().is_empty()
+        && symbol.chars().count() <= 32
+        && !symbol.chars().any(char::is_control)
 }
@@ -0,0 +1,138 @@
+use crate::alert::{Alert, Alerter, Severity};
+use alloy::primitives::{Address, U256, b256};
+use alloy::{
+    providers::Provider,
+    rpc::types::{BlockNumberOrTag, Filter},
+};
+use alloy_sol_types::{SolEvent, sol};
+use futures_util::stream::StreamExt;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+// storage slots holding the implementation of upgradeable proxies: EIP-1967, and the
+// ZeppelinOS slot of older proxies like USDC's
+const IMPLEMENTATION_SLOTS: [U256; 2] = [
+    U256::from_be_bytes(
+        b256!("0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc").0,
+    ),
+    U256::from_be_bytes(
+        b256!("0x7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3").0,
+    ),
+];
+
+sol! {
+    // emitted by both proxy kinds, older ones do not index the implementation
+    event Upgraded(address indexed implementation);
+}
+
+// implementation behind an upgradeable proxy, None for plain contracts
+pub async fn implementation(token: Address, provider: impl Provider) -> Option<Address> {
+    for slot in IMPLEMENTATION_SLOTS {
+        match provider.get_storage_at(token, slot).await {
+            Ok(word) if !word.is_zero() => {
+                return Some(Address::from_slice(&word.to_be_bytes::<32>()[12..]));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to read proxy slot of token {:?}: {}", token, e);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+// watches every proxied token once, however many pools it is in
+#[derive(Default)]
+pub struct ProxyWatcher(Mutex<HashSet<Address>>);
+
+impl ProxyWatcher {
+    // warn when the implementation of `token` changes, an upgrade can alter decimals
+    // or transfer semantics under a running tracker
+    pub fn watch<P: Provider + Clone + 'static>(
+        &self,
+        token: Address,
+        symbol: String,
+        implementation: Address,
+        pool: Address,
+        provider: P,
+        alerter: Arc<Alerter>,
+    ) {
+        if !self.0.lock().unwrap().insert(token) {
+            return;
+        }
+        tokio::spawn(watch_upgrades(
+            token,
+            symbol,
+            implementation,
+            pool,
+            provider,
+            alerter,
+        ));
+    }
+}
+
+async fn watch_upgrades<P: Provider>(
+    token: Address,
+    symbol: String,
+    mut current: Address,
+    pool: Address,
+    provider: P,
+    alerter: Arc<Alerter>,
+) {
+    let filter = Filter::new()
+        .address(token)
+        .event_signature(Upgraded::SIGNATURE_HASH)
+        .from_block(BlockNumberOrTag::Latest);
+
+    let sub = match provider.subscribe_logs(&filter).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to subscribe logs with filter {:?}: {}", filter, e);
+            return;
+        }
+    };
+
+    let mut stream = sub.into_stream();
+
+    info!(
+        "Watching proxy token {} ({:?}) for upgrades, implementation {:?}",
+        symbol, token, current
+    );
+
+    while stream.next().await.is_some() {
+        // read the slot instead of decoding the event, its layout differs between proxies
+        let Some(upgraded) = implementation(token, &provider).await else {
+            continue;
+        };
+        if upgraded == current {
+            continue;
+        }
+
+        warn!(
+            "Token {} ({:?}) was upgraded from {:?} to {:?}",
+            symbol, token, current, upgraded
+        );
+
+        let mut vars = BTreeMap::new();
+        vars.insert("token".to_string(), format!("{:?}", token));
+        vars.insert("symbol".to_string(), symbol.clone());
+        vars.insert("old_implementation".to_string(), format!("{:?}", current));
+        vars.insert("implementation".to_string(), format!("{:?}", upgraded));
+
+        let alert = Alert {
+            rule: "token_upgraded".to_string(),
+            key: format!("token_upgraded:{:?}:{:?}", token, upgraded),
+            pool,
+            severity: Severity::Warning,
+            summary: format!(
+                "Token {} ({:?}) was upgraded to implementation {:?}, decimals or transfer behaviour may have changed",
+                symbol, token, upgraded
+            ),
+            vars,
+        };
+        alerter.notify(&alert).await;
+        current = upgraded;
+    }
+}
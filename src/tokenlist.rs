@@ -0,0 +1,149 @@
+use crate::config::TokenOverride;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+// common tokens of the chains with built-in factories, in the tokenlists.org format
+const BUNDLED_LIST: &str = include_str!("../assets/tokenlist.json");
+
+// a cached remote list younger than this is used without fetching it again
+const CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Error)]
+pub enum TokenListError {
+    #[error("Failed to fetch token list {0}: {1}")]
+    FetchFailed(String, String),
+
+    #[error("Failed to parse token list {0}: {1}")]
+    ParseFailed(String, String),
+
+    #[error("Failed to read token list cache {0}: {1}")]
+    CacheUnreadable(String, String),
+}
+
+// https://tokenlists.org token list, only the fields the tracker uses
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TokenList {
+    tokens: Vec<ListedToken>,
+    // [[tokens]] from the config, these win over the chain and the list
+    #[serde(skip)]
+    overrides: Vec<TokenOverride>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListedToken {
+    pub chain_id: u64,
+    pub address: Address,
+    pub symbol: String,
+    pub decimals: u8,
+    #[serde(rename = "logoURI", skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+}
+
+impl TokenList {
+    // the bundled list, extended by the list at `url` (e.g. TOKEN_LIST_URL) whose
+    // entries win over bundled ones with the same symbol
+    // the remote list is cached in `cache`, refreshed daily and used as long as
+    // the url cannot be fetched
+    pub async fn load(url: Option<&str>, cache: &Path) -> TokenList {
+        let mut list: TokenList = match serde_json::from_str(BUNDLED_LIST) {
+            Ok(l) => l,
+            Err(e) => {
+                error!("Failed to parse bundled token list: {}", e);
+                TokenList::default()
+            }
+        };
+
+        if let Some(url) = url {
+            match load_remote(url, cache).await {
+                Ok(remote) => {
+                    info!("Loaded {} tokens from {}", remote.tokens.len(), url);
+                    list.tokens.splice(0..0, remote.tokens);
+                }
+                Err(e) => error!("Using the bundled token list only: {}", e),
+            }
+        }
+
+        list
+    }
+
+    pub fn with_overrides(mut self, overrides: &[TokenOverride]) -> Self {
+        self.overrides = overrides.to_vec();
+        self
+    }
+
+    pub fn override_for(&self, address: Address) -> Option<&TokenOverride> {
+        self.overrides.iter().find(|o| o.address == address)
+    }
+
+    // metadata of the token at `address` on `chain_id`
+    pub fn token(&self, address: Address, chain_id: u64) -> Option<&ListedToken> {
+        self.tokens
+            .iter()
+            .find(|t| t.chain_id == chain_id && t.address == address)
+    }
+
+    // address of the token with this symbol on `chain_id`, case insensitive
+    pub fn address_of(&self, symbol: &str, chain_id: u64) -> Option<Address> {
+        self.tokens
+            .iter()
+            .find(|t| t.chain_id == chain_id && t.symbol.eq_ignore_ascii_case(symbol))
+            .map(|t| t.address)
+    }
+}
+
+async fn load_remote(url: &str, cache: &Path) -> Result<TokenList, TokenListError> {
+    let fresh = std::fs::metadata(cache)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < CACHE_MAX_AGE);
+    if fresh && let Ok(list) = read_cache(cache) {
+        return Ok(list);
+    }
+
+    match fetch(url).await {
+        Ok(list) => {
+            match serde_json::to_string(&list) {
+                Ok(json) => {
+                    if let Err(e) = std::fs::write(cache, json) {
+                        warn!("Failed to write token list cache {:?}: {}", cache, e);
+                    }
+                }
+                Err(e) => warn!("Failed to serialize token list: {}", e),
+            }
+            Ok(list)
+        }
+        Err(e) => {
+            warn!("{}, falling back to the cached token list", e);
+            read_cache(cache)
+        }
+    }
+}
+
+fn read_cache(cache: &Path) -> Result<TokenList, TokenListError> {
+    let name = cache.display().to_string();
+    let content = match std::fs::read_to_string(cache) {
+        Ok(c) => c,
+        Err(e) => return Err(TokenListError::CacheUnreadable(name, e.to_string())),
+    };
+    match serde_json::from_str(&content) {
+        Ok(list) => Ok(list),
+        Err(e) => Err(TokenListError::ParseFailed(name, e.to_string())),
+    }
+}
+
+async fn fetch(url: &str) -> Result<TokenList, TokenListError> {
+    let response = match reqwest::get(url).await {
+        Ok(r) => r,
+        Err(e) => return Err(TokenListError::FetchFailed(url.to_string(), e.to_string())),
+    };
+    match response.json().await {
+        Ok(list) => Ok(list),
+        Err(e) => Err(TokenListError::ParseFailed(url.to_string(), e.to_string())),
+    }
+}
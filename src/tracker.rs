@@ -0,0 +1,375 @@
+use crate::alert::Alerter;
+use crate::checkpoint::{Checkpoints, run_checkpoints};
+use crate::config::{Config, PoolEntry, WriterConfig};
+use crate::pool::{
+    EVENTS_CAPACITY, LiquidityUpdate, ListenerError, ListenerSettings, PoolEvent, PriceUpdate,
+    Snapshot, SwapEvent, V3Adapter, V3Flavor, snapshot, spawn_listener,
+};
+use crate::reference::ReferencePrices;
+use crate::rpc::connect;
+use crate::state::SharedState;
+use crate::storage::{Storage, SwapWriter};
+use crate::token::ProxyWatcher;
+use crate::tokenlist::TokenList;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use eyre::Result;
+use futures_util::{Stream, StreamExt};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+// time a stopping tracker gives the database writer, like SHUTDOWN_TIMEOUT_SECS
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// pools followed in the background, for applications embedding the tracker
+// alert rules are off and a quiet pool is only logged as stale, unless the builder is
+// given an Alerter
+pub struct PoolTracker {
+    settings: Arc<ListenerSettings>,
+    events: broadcast::Sender<PoolEvent>,
+    errors: broadcast::Sender<ListenerError>,
+    listeners: Vec<JoinHandle<Result<()>>>,
+    // background tasks of the sinks and handlers
+    tasks: Vec<JoinHandle<()>>,
+}
+
+// where a tracker writes besides its subscribers
+pub enum Sink {
+    // swaps stored in this SQLite database, like DATABASE_PATH
+    Database(String),
+    // 24h volume and swap counts kept in this file across restarts, like CHECKPOINT_PATH
+    Checkpoints(String),
+}
+
+// a TrackerBuilder before `rpc` is given, it cannot be built yet
+pub struct NoRpc;
+
+// assembles a PoolTracker; only the endpoint is required, `build` does not exist
+// before `rpc` is called
+pub struct TrackerBuilder<R = NoRpc> {
+    rpc: R,
+    pools: Vec<PoolEntry>,
+    sinks: Vec<Sink>,
+    confirmations: Option<u64>,
+    stall_timeout: Option<Duration>,
+    alerter: Option<Alerter>,
+}
+
+impl TrackerBuilder {
+    pub fn new() -> Self {
+        TrackerBuilder {
+            rpc: NoRpc,
+            pools: Vec::new(),
+            sinks: Vec::new(),
+            confirmations: None,
+            stall_timeout: None,
+            alerter: None,
+        }
+    }
+}
+
+impl Default for TrackerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> TrackerBuilder<R> {
+    // websocket endpoint of the chain the pools live on
+    pub fn rpc(self, url: impl Into<String>) -> TrackerBuilder<String> {
+        TrackerBuilder {
+            rpc: url.into(),
+            pools: self.pools,
+            sinks: self.sinks,
+            confirmations: self.confirmations,
+            stall_timeout: self.stall_timeout,
+            alerter: self.alerter,
+        }
+    }
+
+    // a Uniswap V3 pool
+    pub fn pool(mut self, address: Address) -> Self {
+        self.pools.push(PoolEntry {
+            address: Some(address),
+            ..Default::default()
+        });
+        self
+    }
+
+    // a pool of any protocol, as in the [[pools]] section of the config file; entries
+    // with their own rpc_url or a pair instead of an address are not supported
+    pub fn pool_entry(mut self, entry: PoolEntry) -> Self {
+        self.pools.push(entry);
+        self
+    }
+
+    pub fn with_sink(mut self, sink: Sink) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    // blocks on top of a swap's block before its price is emitted, 0 emits swaps as they
+    // arrive; the chain's usual reorg depth by default
+    pub fn confirmations(mut self, blocks: u64) -> Self {
+        self.confirmations = Some(blocks);
+        self
+    }
+
+    // a pool without swaps for this long is reported as stale, like STALL_TIMEOUT_SECS
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    // where stale pools are reported, e.g. Alerter::from_env() for the binary's
+    // backends; alerts are only logged by default
+    pub fn alerter(mut self, alerter: Alerter) -> Self {
+        self.alerter = Some(alerter);
+        self
+    }
+}
+
+impl TrackerBuilder<String> {
+    // connect and start following the pools
+    pub async fn build(self) -> Result<PoolTracker> {
+        let provider = connect(&self.rpc).await?;
+        let chain_id = provider.get_chain_id().await?;
+        let chain = match crate::chain::by_id(chain_id) {
+            Some(chain) => chain,
+            None => {
+                warn!(
+                    "Chain {} has no preset, using the defaults of ethereum",
+                    chain_id
+                );
+                &crate::chain::PRESETS[0]
+            }
+        };
+
+        let mut writer = None;
+        let mut checkpoints = None;
+        let mut tasks = Vec::new();
+        for sink in self.sinks {
+            match sink {
+                Sink::Database(path) => {
+                    let storage = Storage::open(&path)?;
+                    writer = Some(SwapWriter::start(storage, WriterConfig::default()));
+                }
+                Sink::Checkpoints(path) => {
+                    let store = Arc::new(Checkpoints::load(path, Duration::from_secs(60)));
+                    tasks.push(tokio::spawn(run_checkpoints(store.clone())));
+                    checkpoints = Some(store);
+                }
+            }
+        }
+
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+        let (errors, _) = broadcast::channel(EVENTS_CAPACITY);
+        let settings = Arc::new(ListenerSettings {
+            stall_timeout: self.stall_timeout,
+            ws_stall_timeout: None,
+            confirmations: self.confirmations.unwrap_or(chain.reorg_depth),
+            explorer_url: chain.explorer_url.to_string(),
+            chain,
+            config: Config::default(),
+            references: ReferencePrices::default(),
+            state: SharedState::default(),
+            tokens: TokenList::default(),
+            proxies: ProxyWatcher::default(),
+            writer,
+            checkpoints,
+            events: events.clone(),
+            errors: errors.clone(),
+            all_events: true,
+        });
+        let alerter = Arc::new(self.alerter.unwrap_or_else(Alerter::log_only));
+
+        let listeners = self
+            .pools
+            .into_iter()
+            .map(|entry| spawn_listener(entry, provider.clone(), alerter.clone(), settings.clone()))
+            .collect();
+        Ok(PoolTracker {
+            settings,
+            events,
+            errors,
+            listeners,
+            tasks,
+        })
+    }
+}
+
+impl PoolTracker {
+    // follow `pools` on the websocket endpoint `rpc_url` without sinks, see TrackerBuilder
+    pub async fn start(rpc_url: &str, pools: Vec<PoolEntry>) -> Result<Self> {
+        pools
+            .into_iter()
+            .fold(
+                TrackerBuilder::new().rpc(rpc_url),
+                TrackerBuilder::pool_entry,
+            )
+            .build()
+            .await
+    }
+
+    // price updates of every pool from now on, ends when the tracker stops; a subscriber
+    // more than EVENTS_CAPACITY events behind skips the oldest
+    pub fn subscribe(&self) -> impl Stream<Item = PriceUpdate> + use<> {
+        self.events().filter_map(|event| async move {
+            match event {
+                PoolEvent::PriceUpdate(update) => Some(update),
+                _ => None,
+            }
+        })
+    }
+
+    // every event of every pool from now on, like `subscribe`
+    pub fn events(&self) -> impl Stream<Item = PoolEvent> + use<> {
+        receive(self.events.subscribe())
+    }
+
+    // call `handler` with every decoded swap
+    pub fn on_swap<F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(SwapEvent) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on(
+            |event| match event {
+                PoolEvent::Swap(swap) => Some(swap),
+                _ => None,
+            },
+            handler,
+        )
+    }
+
+    // call `handler` with every swap's price
+    pub fn on_price<F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(PriceUpdate) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on(
+            |event| match event {
+                PoolEvent::PriceUpdate(update) => Some(update),
+                _ => None,
+            },
+            handler,
+        )
+    }
+
+    // call `handler` whenever a pool's in-range liquidity changes
+    pub fn on_liquidity_change<F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(LiquidityUpdate) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on(
+            |event| match event {
+                PoolEvent::LiquidityUpdate(update) => Some(update),
+                _ => None,
+            },
+            handler,
+        )
+    }
+
+    // call `handler` with every event of every pool, see `events`
+    pub fn on_event<F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(PoolEvent) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.handle(self.events(), handler)
+    }
+
+    // call `handler` with the errors of the listeners, those they recovered from too
+    pub fn on_error<F, Fut>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(ListenerError) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let errors = receive(self.errors.subscribe());
+        self.handle(errors, handler)
+    }
+
+    // run `handler` for the events `pick` takes, see `handle`
+    fn on<T, F, Fut>(&mut self, pick: fn(PoolEvent) -> Option<T>, handler: F) -> &mut Self
+    where
+        T: Send + 'static,
+        F: Fn(T) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let events = self
+            .events()
+            .filter_map(move |event| std::future::ready(pick(event)));
+        self.handle(events, handler)
+    }
+
+    // run `handler` on a task of its own for every item of `items`, one at a time in
+    // the order they happened; a slow handler skips items like a slow subscriber
+    fn handle<T, F, Fut>(
+        &mut self,
+        items: impl Stream<Item = T> + Send + 'static,
+        handler: F,
+    ) -> &mut Self
+    where
+        T: Send + 'static,
+        F: Fn(T) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut items = Box::pin(items);
+        self.tasks.push(tokio::spawn(async move {
+            while let Some(item) = items.next().await {
+                handler(item).await;
+            }
+        }));
+        self
+    }
+
+    // stop following the pools and write out what the sinks still hold
+    pub async fn stop(mut self) {
+        for listener in std::mem::take(&mut self.listeners) {
+            listener.abort();
+            if let Ok(Err(e)) = listener.await {
+                error!("Listener stopped with an error: {}", e);
+            }
+        }
+        self.settings.drain(DRAIN_TIMEOUT).await;
+    }
+}
+
+// the current state of a Uniswap V3 pool, read once without starting a tracker
+pub async fn pool_snapshot(rpc_url: &str, pool: Address) -> Result<Snapshot> {
+    let provider = connect(rpc_url).await?;
+    let adapter = V3Adapter::new(pool, V3Flavor::Uniswap, None);
+    snapshot(adapter, &provider, &TokenList::default()).await
+}
+
+// the items sent on a channel from now on, ends when the tracker stops
+fn receive<T: Clone + Send + 'static>(rx: broadcast::Receiver<T>) -> impl Stream<Item = T> {
+    futures_util::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(item) => return Some((item, rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Subscriber fell behind, skipped {} events", skipped)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+impl Drop for PoolTracker {
+    fn drop(&mut self) {
+        for listener in &self.listeners {
+            listener.abort();
+        }
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
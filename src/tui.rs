@@ -0,0 +1,355 @@
+use crate::chain;
+use crate::console::{format_amount, format_price, group_thousands};
+use crate::state::{PoolSnapshot, SharedState};
+use crate::storage::{Storage, unix_now};
+use alloy::primitives::Address;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, mpsc};
+use tracing::error;
+
+const REFRESH: Duration = Duration::from_millis(500);
+// prices sampled per pool for the sparklines, one per REFRESH
+const SAMPLES: usize = 120;
+// how often the 24 hour old prices are looked up in storage
+const CHANGE_REFRESH_SECS: u64 = 60;
+// width of the trend column of the table
+const TREND_WIDTH: usize = 30;
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+enum Key {
+    Up,
+    Down,
+    Enter,
+    Back,
+    Quit,
+}
+
+#[derive(Default)]
+struct View {
+    selected: usize,
+    detail: bool,
+    // price samples since the dashboard started
+    samples: HashMap<Address, VecDeque<f64>>,
+    // price of each pool 24 hours ago, from the stored swaps
+    day_ago: HashMap<Address, f64>,
+    day_ago_at: u64,
+}
+
+// live table of the tracked pools in place of the log lines, with a detail view per
+// pool; arrows or j/k move, enter opens a pool, esc goes back, q quits
+pub async fn run_tui(state: SharedState, storage: Option<Storage>, stop: Arc<Notify>) {
+    // raw mode on the alternate screen, restored by ratatui::restore and on panics
+    let mut terminal = match ratatui::try_init() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            error!("The terminal UI needs an interactive terminal: {}", e);
+            return;
+        }
+    };
+    let (keys, mut key_rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || read_keys(keys));
+
+    let mut view = View::default();
+    let mut ticker = tokio::time::interval(REFRESH);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            key = key_rx.recv() => match key {
+                Some(Key::Quit) | None => break,
+                Some(key) => view.on_key(key),
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+
+        let mut pools: Vec<PoolSnapshot> = state.read().unwrap().pools.values().cloned().collect();
+        pools.sort_by(|a, b| a.pair().cmp(&b.pair()).then(a.pool.cmp(&b.pool)));
+        view.sample(&pools);
+        if let Some(storage) = &storage {
+            view.refresh_day_ago(storage, &pools).await;
+        }
+        view.selected = view.selected.min(pools.len().saturating_sub(1));
+
+        if let Err(e) = draw(&mut terminal, &view, &pools) {
+            error!("Failed to draw the terminal UI: {}", e);
+            break;
+        }
+    }
+
+    ratatui::restore();
+    stop.notify_one();
+}
+
+fn draw(
+    terminal: &mut DefaultTerminal,
+    view: &View,
+    pools: &[PoolSnapshot],
+) -> std::io::Result<()> {
+    terminal.draw(|frame| match pools.get(view.selected) {
+        Some(pool) if view.detail => view.draw_detail(frame, pool),
+        _ => view.draw_table(frame, pools),
+    })?;
+    Ok(())
+}
+
+impl View {
+    fn on_key(&mut self, key: Key) {
+        match key {
+            Key::Up if !self.detail => self.selected = self.selected.saturating_sub(1),
+            Key::Down if !self.detail => self.selected += 1,
+            Key::Enter => self.detail = true,
+            Key::Back => self.detail = false,
+            _ => {}
+        }
+    }
+
+    fn sample(&mut self, pools: &[PoolSnapshot]) {
+        for pool in pools {
+            let samples = self.samples.entry(pool.pool).or_default();
+            if samples.len() == SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back(pool.price);
+        }
+    }
+
+    async fn refresh_day_ago(&mut self, storage: &Storage, pools: &[PoolSnapshot]) {
+        let now = unix_now();
+        if now < self.day_ago_at + CHANGE_REFRESH_SECS {
+            return;
+        }
+        self.day_ago_at = now;
+        for pool in pools {
+            if let Ok(Some(price)) = storage.price_at(pool.pool, now - 86_400).await {
+                self.day_ago.insert(pool.pool, price);
+            }
+        }
+    }
+
+    // percent change over 24 hours, or since the dashboard started without storage
+    fn change(&self, pool: &PoolSnapshot) -> Option<f64> {
+        let before = self
+            .day_ago
+            .get(&pool.pool)
+            .or_else(|| self.samples.get(&pool.pool)?.front())?;
+        (*before > 0.0).then(|| (pool.price / before - 1.0) * 100.0)
+    }
+
+    fn draw_table(&self, frame: &mut Frame, pools: &[PoolSnapshot]) {
+        let [title, body] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).areas(frame.area());
+        frame.render_widget(
+            title_line(
+                &format!("Pools ({})", pools.len()),
+                "↑/↓ select  enter details  q quit",
+            ),
+            title,
+        );
+        if pools.is_empty() {
+            frame.render_widget(Paragraph::new("Waiting for the pools to load..."), body);
+            return;
+        }
+
+        let rows = pools.iter().map(|pool| {
+            Row::new([
+                pool.pair(),
+                chain_name(pool.chain_id),
+                format_price(pool.price),
+                self.change(pool)
+                    .map_or("-".to_string(), |c| format!("{:+.2}%", c)),
+                age(pool.updated_at),
+                compact(pool.liquidity as f64),
+                pool.swaps.to_string(),
+                self.trend(pool.pool, TREND_WIDTH),
+            ])
+        });
+        let header = Row::new([
+            "pair",
+            "chain",
+            "price",
+            "24h",
+            "last swap",
+            "liquidity",
+            "swaps",
+            "trend",
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        let widths = [
+            Constraint::Length(18),
+            Constraint::Length(10),
+            Constraint::Length(18),
+            Constraint::Length(8),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Min(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = TableState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(table, body, &mut state);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, pool: &PoolSnapshot) {
+        let [title, fields, trend] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(14),
+            Constraint::Min(3),
+        ])
+        .areas(frame.area());
+        frame.render_widget(title_line(&pool.pair(), "esc back  q quit"), title);
+
+        let field = |name: &str, value: String| Line::from(format!("{:<16} {}", name, value));
+        let lines = vec![
+            field("pool", format!("{:?}", pool.pool)),
+            field(
+                "chain",
+                format!("{} ({})", chain_name(pool.chain_id), pool.chain_id),
+            ),
+            field("token0", format!("{} {:?}", pool.symbol0, pool.token0)),
+            field("token1", format!("{} {:?}", pool.symbol1, pool.token1)),
+            field("fee", format!("{:.2}%", pool.fee as f64 / 10_000.0)),
+            field(
+                "price",
+                format!("{} {}", format_price(pool.price), pool.symbol1),
+            ),
+            field(
+                "24h change",
+                self.change(pool)
+                    .map_or("-".to_string(), |c| format!("{:+.2}%", c)),
+            ),
+            field("sqrtPriceX96", pool.sqrt_price_x96.to_string()),
+            field("liquidity", group_thousands(&pool.liquidity.to_string())),
+            field("swaps", group_thousands(&pool.swaps.to_string())),
+            field(
+                "24h volume",
+                format!("{} {}", format_amount(pool.volume0_24h), pool.symbol0),
+            ),
+            field("last swap", age(pool.updated_at)),
+        ];
+        frame.render_widget(Paragraph::new(lines), fields);
+
+        let sparkline = Sparkline::default()
+            .block(Block::bordered().title("price"))
+            .data(self.scaled(pool.pool, trend));
+        frame.render_widget(sparkline, trend);
+    }
+
+    // the latest samples that fit in `width` columns, oldest first
+    fn shown(&self, pool: Address, width: usize) -> Vec<f64> {
+        let Some(samples) = self.samples.get(&pool) else {
+            return Vec::new();
+        };
+        samples
+            .iter()
+            .skip(samples.len().saturating_sub(width))
+            .copied()
+            .collect()
+    }
+
+    // samples as bar heights of the Sparkline widget, which starts its bars at zero
+    fn scaled(&self, pool: Address, area: Rect) -> Vec<u64> {
+        let shown = self.shown(pool, area.width.saturating_sub(2).into());
+        let (low, high) = range(&shown);
+        shown
+            .iter()
+            .map(|p| match high > low {
+                true => 1 + ((p - low) / (high - low) * 99.0).round() as u64,
+                false => 50,
+            })
+            .collect()
+    }
+
+    // a sparkline as text, for the trend column of the table
+    fn trend(&self, pool: Address, width: usize) -> String {
+        let shown = self.shown(pool, width);
+        let (low, high) = range(&shown);
+        shown
+            .iter()
+            .map(|p| {
+                if high > low {
+                    SPARKS[(((p - low) / (high - low)) * 7.0).round() as usize]
+                } else {
+                    SPARKS[3]
+                }
+            })
+            .collect()
+    }
+}
+
+fn range(values: &[f64]) -> (f64, f64) {
+    let low = values.iter().copied().fold(f64::MAX, f64::min);
+    let high = values.iter().copied().fold(f64::MIN, f64::max);
+    (low, high)
+}
+
+fn title_line(title: &str, help: &str) -> Paragraph<'static> {
+    Paragraph::new(Line::from(vec![
+        Span::styled(title.to_string(), Style::new().add_modifier(Modifier::BOLD)),
+        Span::raw(format!("   {}", help)),
+    ]))
+}
+
+// key presses read by crossterm on a thread of their own, ctrl-c included since raw
+// mode keeps it from raising SIGINT
+fn read_keys(keys: mpsc::UnboundedSender<Key>) {
+    loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(_) => return,
+        };
+        let key = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Quit,
+            KeyCode::Up | KeyCode::Char('k') => Key::Up,
+            KeyCode::Down | KeyCode::Char('j') => Key::Down,
+            KeyCode::Enter | KeyCode::Char('l') => Key::Enter,
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Char('h') => Key::Back,
+            KeyCode::Char('q') | KeyCode::Char('Q') => Key::Quit,
+            _ => continue,
+        };
+        if keys.send(key).is_err() {
+            return;
+        }
+    }
+}
+
+fn chain_name(chain_id: u64) -> String {
+    match chain::by_id(chain_id) {
+        Some(chain) => chain.name.to_string(),
+        None => chain_id.to_string(),
+    }
+}
+
+// 1.2K, 3.4M, ... for raw liquidity values
+fn compact(value: f64) -> String {
+    const UNITS: [&str; 7] = ["", "K", "M", "B", "T", "P", "E"];
+    let mut value = value;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+fn age(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "-".to_string();
+    }
+    let secs = unix_now().saturating_sub(timestamp);
+    match secs {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 3_600 => format!("{}m ago", s / 60),
+        s if s < 86_400 => format!("{}h ago", s / 3_600),
+        s => format!("{}d ago", s / 86_400),
+    }
+}